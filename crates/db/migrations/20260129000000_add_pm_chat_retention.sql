@@ -0,0 +1,8 @@
+-- Add PM chat retention support
+-- Messages older than chat_retention_days are pruned unless pinned
+
+-- Number of days to keep PM chat messages (NULL = keep forever)
+ALTER TABLE projects ADD COLUMN chat_retention_days INTEGER;
+
+-- Pinned messages are never pruned
+ALTER TABLE pm_conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
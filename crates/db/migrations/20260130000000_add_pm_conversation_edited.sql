@@ -0,0 +1,2 @@
+-- Track PM chat messages whose content was edited after sending
+ALTER TABLE pm_conversations ADD COLUMN edited INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Token usage reported by the agent CLI for assistant messages
+ALTER TABLE pm_conversations ADD COLUMN input_tokens INTEGER;
+ALTER TABLE pm_conversations ADD COLUMN output_tokens INTEGER;
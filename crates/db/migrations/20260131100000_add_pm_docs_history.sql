@@ -0,0 +1,11 @@
+-- Previous versions of a project's PM docs, saved before each overwrite
+CREATE TABLE pm_docs_history (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    -- What replaced this version
+    source TEXT NOT NULL CHECK (source IN ('user', 'agent', 'task_summary', 'restore')),
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pm_docs_history_project_id ON pm_docs_history(project_id, created_at);
@@ -0,0 +1,6 @@
+-- Soft delete for PM chat messages: clearing the chat archives messages
+-- instead of deleting them. Existing messages stay active.
+ALTER TABLE pm_conversations ADD COLUMN archived_at DATETIME;
+UPDATE pm_conversations SET archived_at = NULL;
+
+CREATE INDEX idx_pm_conversations_archived_at ON pm_conversations(project_id, archived_at);
@@ -0,0 +1,3 @@
+-- Pixel size of image attachments, NULL for other files
+ALTER TABLE pm_attachments ADD COLUMN width INTEGER;
+ALTER TABLE pm_attachments ADD COLUMN height INTEGER;
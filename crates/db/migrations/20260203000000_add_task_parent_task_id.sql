@@ -0,0 +1,5 @@
+-- Parent task for breaking work down into subtasks (epic -> subtasks).
+-- Unlike task_dependencies this is a hierarchy, not an ordering.
+ALTER TABLE tasks ADD COLUMN parent_task_id BLOB REFERENCES tasks(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_tasks_parent_task_id ON tasks(parent_task_id);
@@ -0,0 +1,6 @@
+-- Client-generated id of a sent message, so a retried send returns the
+-- message stored by the first attempt instead of creating a duplicate.
+ALTER TABLE pm_conversations ADD COLUMN client_message_id TEXT;
+
+CREATE UNIQUE INDEX idx_pm_conversations_client_message_id
+    ON pm_conversations(project_id, client_message_id);
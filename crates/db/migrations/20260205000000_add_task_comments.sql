@@ -0,0 +1,10 @@
+-- Notes left on a task by users and agents
+CREATE TABLE task_comments (
+    id TEXT PRIMARY KEY NOT NULL,
+    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    author TEXT NOT NULL CHECK (author IN ('user', 'agent')),
+    content TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_task_comments_task_id ON task_comments(task_id, created_at);
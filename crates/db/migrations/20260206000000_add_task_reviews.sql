@@ -0,0 +1,13 @@
+-- Review requests generated for a task by `request_pm_review`
+CREATE TABLE task_reviews (
+    id TEXT PRIMARY KEY NOT NULL,
+    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    prompt TEXT NOT NULL,
+    -- JSON array of checklist items
+    checklist TEXT NOT NULL DEFAULT '[]',
+    -- Who requested the review
+    source TEXT NOT NULL CHECK (source IN ('user', 'agent')),
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_task_reviews_task_id ON task_reviews(task_id, created_at);
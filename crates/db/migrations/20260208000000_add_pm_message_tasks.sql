@@ -0,0 +1,8 @@
+-- Tasks created from a PM chat message, linked to the message they came from
+CREATE TABLE pm_message_tasks (
+    task_id TEXT PRIMARY KEY NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    message_id TEXT NOT NULL REFERENCES pm_conversations(id) ON DELETE CASCADE,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pm_message_tasks_message_id ON pm_message_tasks(message_id);
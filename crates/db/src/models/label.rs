@@ -1,9 +1,45 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    str::FromStr,
+};
+
 use chrono::{DateTime, Utc};
+use executors::executors::{BaseCodingAgent, CodingAgent};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, SqlitePool};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool};
+use strum::VariantNames;
+use thiserror::Error;
 use ts_rs::TS;
 use uuid::Uuid;
 
+use super::task::{Task, TaskPriority, TaskStatus};
+
+/// Color of labels created without one (indigo)
+const DEFAULT_LABEL_COLOR: &str = "#6366f1";
+
+#[derive(Debug, Error)]
+pub enum LabelError {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error("Invalid label color {0:?}: expected a hex color like #rrggbb or #rgb")]
+    InvalidColor(String),
+    #[error("Unknown executor {0:?}: expected one of {}", known_executors())]
+    UnknownExecutor(String),
+    #[error("Labels are not in this project: {}", join_ids(.0))]
+    OutsideProject(Vec<Uuid>),
+    #[error("Cannot merge a label into itself")]
+    MergeIntoSource,
+}
+
+/// Outcome of merging labels into a target label
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct LabelMergeResult {
+    /// Tasks that gained the target label
+    pub reassigned: u64,
+    /// Source labels that were deleted
+    pub deleted: u64,
+}
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
 pub struct Label {
     pub id: Uuid,
@@ -30,6 +66,24 @@ pub struct UpdateLabel {
     pub executor: Option<String>,
 }
 
+/// Row of `Label::find_for_tasks`: a label with the task it is attached to
+#[derive(FromRow)]
+struct LabelForTask {
+    task_id: Uuid,
+    #[sqlx(flatten)]
+    label: Label,
+}
+
+/// A label with the number of tasks that carry it
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct LabelWithCount {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    #[sqlx(flatten)]
+    pub label: Label,
+    pub task_count: i64,
+}
+
 /// A label attached to a task
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
 pub struct TaskLabel {
@@ -67,10 +121,48 @@ impl Label {
         .await
     }
 
-    pub async fn create(pool: &SqlitePool, data: &CreateLabel) -> Result<Self, sqlx::Error> {
+    /// Check that `color` is a `#rrggbb` or `#rgb` hex color and return it in
+    /// lowercase `#rrggbb` form
+    pub fn validate_color(color: &str) -> Result<String, LabelError> {
+        let invalid = || LabelError::InvalidColor(color.to_string());
+        let hex = color.trim().strip_prefix('#').ok_or_else(invalid)?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let hex = hex.to_ascii_lowercase();
+        match hex.len() {
+            6 => Ok(format!("#{}", hex)),
+            3 => Ok(hex.chars().fold(String::from("#"), |mut out, c| {
+                out.push(c);
+                out.push(c);
+                out
+            })),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Check that `executor` names a known coding agent and return its canonical
+    /// name. Matching ignores case and accepts `-` for `_` (`claude-code` is
+    /// `CLAUDE_CODE`). A blank value means no executor.
+    pub fn validate_executor(executor: &str) -> Result<Option<String>, LabelError> {
+        let normalized = executor.trim().replace('-', "_").to_ascii_uppercase();
+        if normalized.is_empty() {
+            return Ok(None);
+        }
+        BaseCodingAgent::from_str(&normalized)
+            .map(|agent| Some(agent.to_string()))
+            .map_err(|_| LabelError::UnknownExecutor(executor.to_string()))
+    }
+
+    pub async fn create(pool: &SqlitePool, data: &CreateLabel) -> Result<Self, LabelError> {
         let id = Uuid::new_v4();
-        let color = data.color.as_deref().unwrap_or("#6366f1"); // Default indigo
-        sqlx::query_as!(
+        let color = Self::validate_color(data.color.as_deref().unwrap_or(DEFAULT_LABEL_COLOR))?;
+        let executor = match data.executor.as_deref() {
+            Some(executor) => Self::validate_executor(executor)?,
+            None => None,
+        };
+        let label = sqlx::query_as!(
             Label,
             r#"INSERT INTO labels (id, project_id, name, color, executor)
                VALUES ($1, $2, $3, $4, $5)
@@ -79,31 +171,34 @@ impl Label {
             data.project_id,
             data.name,
             color,
-            data.executor
+            executor
         )
         .fetch_one(pool)
-        .await
+        .await?;
+        Ok(label)
     }
 
     pub async fn update(
         pool: &SqlitePool,
         id: Uuid,
         data: &UpdateLabel,
-    ) -> Result<Self, sqlx::Error> {
+    ) -> Result<Self, LabelError> {
         let existing = Self::find_by_id(pool, id)
             .await?
             .ok_or(sqlx::Error::RowNotFound)?;
 
         let name = data.name.as_ref().unwrap_or(&existing.name);
-        let color = data.color.as_ref().unwrap_or(&existing.color);
-        // For executor, we need to handle Option<Option<String>> - None means don't update, Some(None) means set to null
-        let executor = if data.executor.is_some() {
-            data.executor.as_ref()
-        } else {
-            existing.executor.as_ref()
+        let color = match &data.color {
+            Some(color) => Self::validate_color(color)?,
+            None => existing.color.clone(),
+        };
+        // None keeps the executor; a blank string clears it
+        let executor = match data.executor.as_deref() {
+            Some(executor) => Self::validate_executor(executor)?,
+            None => existing.executor.clone(),
         };
 
-        sqlx::query_as!(
+        let label = sqlx::query_as!(
             Label,
             r#"UPDATE labels
                SET name = $2, color = $3, executor = $4, updated_at = datetime('now', 'subsec')
@@ -115,7 +210,8 @@ impl Label {
             executor
         )
         .fetch_one(pool)
-        .await
+        .await?;
+        Ok(label)
     }
 
     pub async fn delete(pool: &SqlitePool, id: Uuid) -> Result<u64, sqlx::Error> {
@@ -125,6 +221,103 @@ impl Label {
         Ok(result.rows_affected())
     }
 
+    /// Move the tasks of the `source_ids` labels to `target_id` and delete the
+    /// sources, in one transaction. Tasks that already carry the target label
+    /// keep a single `task_labels` row.
+    pub async fn merge(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        source_ids: &[Uuid],
+        target_id: Uuid,
+    ) -> Result<LabelMergeResult, LabelError> {
+        if source_ids.contains(&target_id) {
+            return Err(LabelError::MergeIntoSource);
+        }
+        let project_labels: HashSet<Uuid> = Self::find_by_project_id(pool, project_id)
+            .await?
+            .into_iter()
+            .map(|label| label.id)
+            .collect();
+        let outside_project: Vec<Uuid> = source_ids
+            .iter()
+            .chain(std::iter::once(&target_id))
+            .filter(|id| !project_labels.contains(id))
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if !outside_project.is_empty() {
+            return Err(LabelError::OutsideProject(outside_project));
+        }
+
+        let mut tx = pool.begin().await?;
+        let mut result = LabelMergeResult {
+            reassigned: 0,
+            deleted: 0,
+        };
+        for source_id in source_ids.iter().collect::<BTreeSet<_>>() {
+            result.reassigned += sqlx::query!(
+                r#"INSERT OR IGNORE INTO task_labels (task_id, label_id, created_at)
+                   SELECT task_id, $2, created_at FROM task_labels WHERE label_id = $1"#,
+                source_id,
+                target_id
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            sqlx::query!("DELETE FROM task_labels WHERE label_id = $1", source_id)
+                .execute(&mut *tx)
+                .await?;
+            result.deleted += sqlx::query!("DELETE FROM labels WHERE id = $1", source_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        Ok(result)
+    }
+
+    /// Get a project's labels with the number of tasks carrying each, in one
+    /// grouped query. Unused labels have a count of 0.
+    pub async fn find_by_project_id_with_counts(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<LabelWithCount>, sqlx::Error> {
+        sqlx::query_as::<_, LabelWithCount>(
+            "SELECT l.id, l.project_id, l.name, l.color, l.executor, l.created_at, l.updated_at,
+                    COUNT(tl.task_id) AS task_count
+             FROM labels l
+             LEFT JOIN task_labels tl ON tl.label_id = l.id
+             WHERE l.project_id = $1
+             GROUP BY l.id
+             ORDER BY l.name ASC",
+        )
+        .bind(project_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Number of tasks carrying a label
+    pub async fn count_tasks(pool: &SqlitePool, label_id: Uuid) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM task_labels WHERE label_id = $1"#,
+            label_id
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Ids of the tasks carrying a label
+    pub async fn find_tasks(pool: &SqlitePool, label_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT task_id as "task_id!: Uuid" FROM task_labels WHERE label_id = $1 ORDER BY created_at ASC"#,
+            label_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
     /// Get all labels for a task
     pub async fn find_by_task_id(
         pool: &SqlitePool,
@@ -143,6 +336,35 @@ impl Label {
         .await
     }
 
+    /// Get the labels of several tasks with one query per 500 tasks, keyed by task id.
+    /// Tasks without labels are missing from the map.
+    pub async fn find_for_tasks(
+        pool: &SqlitePool,
+        task_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Self>>, sqlx::Error> {
+        let mut labels: HashMap<Uuid, Vec<Self>> = HashMap::new();
+        // Keep well below SQLite's limit on bound parameters
+        for chunk in task_ids.chunks(500) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "SELECT tl.task_id, l.id, l.project_id, l.name, l.color, l.executor, l.created_at, l.updated_at
+                 FROM labels l
+                 INNER JOIN task_labels tl ON tl.label_id = l.id
+                 WHERE tl.task_id IN (",
+            );
+            let mut ids = query.separated(", ");
+            for task_id in chunk {
+                ids.push_bind(*task_id);
+            }
+            ids.push_unseparated(") ORDER BY l.name ASC");
+
+            let rows: Vec<LabelForTask> = query.build_query_as().fetch_all(pool).await?;
+            for row in rows {
+                labels.entry(row.task_id).or_default().push(row.label);
+            }
+        }
+        Ok(labels)
+    }
+
     /// Add a label to a task
     pub async fn add_to_task(
         pool: &SqlitePool,
@@ -201,6 +423,27 @@ impl Label {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum TaskDependencyError {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error("Task dependencies contain a cycle involving {} tasks", .0.len())]
+    Cycle(Vec<Uuid>),
+    #[error("Tasks are not in the same project: {}", join_ids(.0))]
+    OutsideProject(Vec<Uuid>),
+}
+
+fn known_executors() -> String {
+    CodingAgent::VARIANTS.join(", ")
+}
+
+fn join_ids(ids: &[Uuid]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Task dependency representation
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
 pub struct TaskDependency {
@@ -246,6 +489,49 @@ impl TaskDependency {
         Ok(records.into_iter().map(|r| r.task_id).collect())
     }
 
+    /// Get all dependencies between tasks of a project
+    pub async fn find_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            TaskDependency,
+            r#"SELECT
+                td.task_id as "task_id!: Uuid",
+                td.depends_on_task_id as "depends_on_task_id!: Uuid",
+                td.created_at as "created_at!: DateTime<Utc>"
+            FROM task_dependencies td
+            JOIN tasks t ON t.id = td.task_id
+            WHERE t.project_id = $1"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get all `(task_id, depends_on_task_id)` pairs between tasks of a project
+    pub async fn find_all_for_project(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<(Uuid, Uuid)>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"SELECT
+                td.task_id as "task_id!: Uuid",
+                td.depends_on_task_id as "depends_on_task_id!: Uuid"
+            FROM task_dependencies td
+            JOIN tasks t ON t.id = td.task_id
+            WHERE t.project_id = $1"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|r| (r.task_id, r.depends_on_task_id))
+            .collect())
+    }
+
     /// Add a dependency
     pub async fn create(
         pool: &SqlitePool,
@@ -303,6 +589,44 @@ impl TaskDependency {
         Ok(())
     }
 
+    /// Check that the dependencies `depends_on_task_ids` are tasks of `task`'s
+    /// project and that giving `task` exactly these dependencies would not create
+    /// a cycle among them
+    pub async fn ensure_acyclic(
+        pool: &SqlitePool,
+        task: &Task,
+        depends_on_task_ids: &[Uuid],
+    ) -> Result<(), TaskDependencyError> {
+        let tasks: Vec<Task> = Task::find_by_project_id_with_attempt_status(pool, task.project_id)
+            .await?
+            .into_iter()
+            .map(|t| t.task)
+            .collect();
+        let mut outside_project: Vec<Uuid> = depends_on_task_ids
+            .iter()
+            .filter(|id| !tasks.iter().any(|t| t.id == **id))
+            .copied()
+            .collect();
+        if !outside_project.is_empty() {
+            outside_project.sort();
+            outside_project.dedup();
+            return Err(TaskDependencyError::OutsideProject(outside_project));
+        }
+
+        let mut dependencies: Vec<TaskDependency> = Self::find_by_project_id(pool, task.project_id)
+            .await?
+            .into_iter()
+            .filter(|dep| dep.task_id != task.id)
+            .collect();
+        let now = Utc::now();
+        dependencies.extend(depends_on_task_ids.iter().map(|id| TaskDependency {
+            task_id: task.id,
+            depends_on_task_id: *id,
+            created_at: now,
+        }));
+        Self::order_tasks(&tasks, &dependencies).map(|_| ())
+    }
+
     /// Check if a task has all its dependencies completed
     pub async fn are_dependencies_met(
         pool: &SqlitePool,
@@ -320,4 +644,540 @@ impl TaskDependency {
 
         Ok(unmet.count == 0)
     }
+
+    /// Unfinished (todo or in progress) tasks of a project whose dependencies are
+    /// all done, most urgent first, then oldest first
+    pub async fn find_actionable(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<Task>, sqlx::Error> {
+        // Left-join only the unmet dependencies; actionable tasks have none
+        sqlx::query_as!(
+            Task,
+            r#"SELECT t.id as "id!: Uuid", t.project_id as "project_id!: Uuid", t.title, t.description, t.status as "status!: TaskStatus", t.priority as "priority!: TaskPriority", t.position as "position!: i32", t.parent_workspace_id as "parent_workspace_id: Uuid", t.parent_task_id as "parent_task_id: Uuid", t.created_at as "created_at!: DateTime<Utc>", t.updated_at as "updated_at!: DateTime<Utc>"
+               FROM tasks t
+               LEFT JOIN task_dependencies td ON td.task_id = t.id
+               LEFT JOIN tasks dep ON dep.id = td.depends_on_task_id AND dep.status != 'done'
+               WHERE t.project_id = $1 AND t.status IN ('todo', 'inprogress')
+               GROUP BY t.id
+               HAVING COUNT(dep.id) = 0
+               ORDER BY CASE t.priority
+                            WHEN 'urgent' THEN 0
+                            WHEN 'high' THEN 1
+                            WHEN 'medium' THEN 2
+                            ELSE 3
+                        END,
+                        t.created_at ASC
+               LIMIT $2"#,
+            project_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Kahn's algorithm over `dependencies`. Among tasks that are ready at the
+    /// same time, higher priority comes first, then older tasks.
+    /// Dependencies on tasks not in `tasks` are ignored.
+    pub fn order_tasks(
+        tasks: &[Task],
+        dependencies: &[TaskDependency],
+    ) -> Result<Vec<Uuid>, TaskDependencyError> {
+        let priority_rank = |priority: &TaskPriority| match priority {
+            TaskPriority::Urgent => 0,
+            TaskPriority::High => 1,
+            TaskPriority::Medium => 2,
+            TaskPriority::Low => 3,
+        };
+        let sort_keys: HashMap<Uuid, (u8, DateTime<Utc>, Uuid)> = tasks
+            .iter()
+            .map(|t| (t.id, (priority_rank(&t.priority), t.created_at, t.id)))
+            .collect();
+
+        let mut unmet: HashMap<Uuid, usize> = tasks.iter().map(|t| (t.id, 0)).collect();
+        let mut dependents: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let mut seen = HashSet::new();
+        for dep in dependencies {
+            if !sort_keys.contains_key(&dep.task_id)
+                || !sort_keys.contains_key(&dep.depends_on_task_id)
+                || !seen.insert((dep.task_id, dep.depends_on_task_id))
+            {
+                continue;
+            }
+            *unmet.entry(dep.task_id).or_default() += 1;
+            dependents
+                .entry(dep.depends_on_task_id)
+                .or_default()
+                .push(dep.task_id);
+        }
+
+        let mut ready: BTreeSet<(u8, DateTime<Utc>, Uuid)> = unmet
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| sort_keys[id])
+            .collect();
+        let mut order = Vec::with_capacity(tasks.len());
+
+        while let Some(key) = ready.pop_first() {
+            let task_id = key.2;
+            order.push(task_id);
+            for dependent in dependents.get(&task_id).into_iter().flatten() {
+                if let Some(count) = unmet.get_mut(dependent) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert(sort_keys[dependent]);
+                    }
+                }
+            }
+        }
+
+        if order.len() < unmet.len() {
+            let ordered: HashSet<Uuid> = order.iter().copied().collect();
+            let cyclic = tasks
+                .iter()
+                .map(|t| t.id)
+                .filter(|id| !ordered.contains(id))
+                .collect();
+            return Err(TaskDependencyError::Cycle(cyclic));
+        }
+
+        Ok(order)
+    }
+
+    /// Longest chain of not-done tasks of a project, each depending on the previous one
+    pub async fn critical_path(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<Uuid>, TaskDependencyError> {
+        let tasks: Vec<Task> = Task::find_by_project_id_with_attempt_status(pool, project_id)
+            .await?
+            .into_iter()
+            .map(|t| t.task)
+            .collect();
+        let dependencies = Self::find_by_project_id(pool, project_id).await?;
+        Self::longest_chain(&tasks, &dependencies)
+    }
+
+    /// Longest dependency chain among tasks that are not done, ordered from the
+    /// first task to work on to the last. Completed prerequisites are skipped.
+    /// Ties are broken by the ordering of [`TaskDependency::order_tasks`].
+    pub fn longest_chain(
+        tasks: &[Task],
+        dependencies: &[TaskDependency],
+    ) -> Result<Vec<Uuid>, TaskDependencyError> {
+        let open_tasks: Vec<Task> = tasks
+            .iter()
+            .filter(|t| t.status != TaskStatus::Done)
+            .cloned()
+            .collect();
+        let order = Self::order_tasks(&open_tasks, dependencies)?;
+
+        let mut prerequisites: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for dep in dependencies {
+            prerequisites
+                .entry(dep.task_id)
+                .or_default()
+                .push(dep.depends_on_task_id);
+        }
+
+        // Length of the longest chain ending at each task, and the previous task in it
+        let mut chains: HashMap<Uuid, (usize, Option<Uuid>)> = HashMap::new();
+        let mut end: Option<(usize, Uuid)> = None;
+        for task_id in &order {
+            let previous = prerequisites
+                .get(task_id)
+                .into_iter()
+                .flatten()
+                .filter_map(|dep| chains.get(dep).map(|(len, _)| (*len, *dep)))
+                .fold(None, |best: Option<(usize, Uuid)>, candidate| match best {
+                    Some(best) if best.0 >= candidate.0 => Some(best),
+                    _ => Some(candidate),
+                });
+            let length = previous.map_or(1, |(len, _)| len + 1);
+            chains.insert(*task_id, (length, previous.map(|(_, id)| id)));
+            if end.is_none_or(|(best, _)| length > best) {
+                end = Some((length, *task_id));
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut current = end.map(|(_, id)| id);
+        while let Some(task_id) = current {
+            path.push(task_id);
+            current = chains.get(&task_id).and_then(|(_, previous)| *previous);
+        }
+        path.reverse();
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_project, insert_task, task, test_pool};
+
+    #[test]
+    fn test_validate_color_normalizes_to_lowercase() {
+        assert_eq!(Label::validate_color("#6366F1").unwrap(), "#6366f1");
+        assert_eq!(Label::validate_color(" #abcdef ").unwrap(), "#abcdef");
+    }
+
+    #[test]
+    fn test_validate_color_expands_shorthand() {
+        assert_eq!(Label::validate_color("#F0a").unwrap(), "#ff00aa");
+    }
+
+    #[test]
+    fn test_validate_executor_normalizes_name() {
+        assert_eq!(
+            Label::validate_executor("claude-code").unwrap().as_deref(),
+            Some("CLAUDE_CODE")
+        );
+        assert_eq!(
+            Label::validate_executor("Cursor").unwrap().as_deref(),
+            Some("CURSOR_AGENT")
+        );
+        assert_eq!(Label::validate_executor("  ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_validate_executor_rejects_unknown_name() {
+        assert!(matches!(
+            Label::validate_executor("claud_code"),
+            Err(LabelError::UnknownExecutor(name)) if name == "claud_code"
+        ));
+    }
+
+    #[test]
+    fn test_validate_color_rejects_invalid_values() {
+        for color in ["blue", "6366f1", "#6366f", "#12345678", "#ggg", "#", ""] {
+            assert!(
+                matches!(
+                    Label::validate_color(color),
+                    Err(LabelError::InvalidColor(_))
+                ),
+                "{color} should be rejected"
+            );
+        }
+    }
+
+    async fn create_label(pool: &SqlitePool, project_id: Uuid, name: &str) -> Label {
+        Label::create(
+            pool,
+            &CreateLabel {
+                project_id,
+                name: name.to_string(),
+                color: None,
+                executor: None,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_merge_dedups_tasks_that_already_have_target() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let both = insert_task(&pool, project_id, "both").await;
+        let source_only = insert_task(&pool, project_id, "source only").await;
+        let target = create_label(&pool, project_id, "frontend").await;
+        let source = create_label(&pool, project_id, "front-end").await;
+        Label::add_to_task(&pool, both, target.id).await.unwrap();
+        Label::add_to_task(&pool, both, source.id).await.unwrap();
+        Label::add_to_task(&pool, source_only, source.id)
+            .await
+            .unwrap();
+
+        let result = Label::merge(&pool, project_id, &[source.id], target.id)
+            .await
+            .unwrap();
+
+        assert_eq!(result.reassigned, 1);
+        assert_eq!(result.deleted, 1);
+        let mut tasks = Label::find_tasks(&pool, target.id).await.unwrap();
+        tasks.sort();
+        let mut expected = vec![both, source_only];
+        expected.sort();
+        assert_eq!(tasks, expected);
+        assert!(Label::find_by_id(&pool, source.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_label_task_counts_and_lookup_by_label() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let api = insert_task(&pool, project_id, "api").await;
+        let ui = insert_task(&pool, project_id, "ui").await;
+        let backend = create_label(&pool, project_id, "backend").await;
+        let frontend = create_label(&pool, project_id, "frontend").await;
+        let unused = create_label(&pool, project_id, "unused").await;
+        Label::set_task_labels(&pool, api, &[backend.id])
+            .await
+            .unwrap();
+        Label::set_task_labels(&pool, ui, &[backend.id, frontend.id])
+            .await
+            .unwrap();
+        // Another project's labels are not listed
+        let other_project_id = insert_project(&pool).await;
+        let other = create_label(&pool, other_project_id, "backend").await;
+        let elsewhere = insert_task(&pool, other_project_id, "elsewhere").await;
+        Label::add_to_task(&pool, elsewhere, other.id)
+            .await
+            .unwrap();
+
+        let labels = Label::find_by_project_id_with_counts(&pool, project_id)
+            .await
+            .unwrap();
+        let counts: Vec<(&str, i64)> = labels
+            .iter()
+            .map(|l| (l.label.name.as_str(), l.task_count))
+            .collect();
+        assert_eq!(counts, vec![("backend", 2), ("frontend", 1), ("unused", 0)]);
+
+        assert_eq!(Label::count_tasks(&pool, backend.id).await.unwrap(), 2);
+        assert_eq!(Label::count_tasks(&pool, unused.id).await.unwrap(), 0);
+        let mut tasks = Label::find_tasks(&pool, backend.id).await.unwrap();
+        tasks.sort();
+        let mut expected = vec![api, ui];
+        expected.sort();
+        assert_eq!(tasks, expected);
+        let frontend_tasks = Label::find_tasks(&pool, frontend.id).await.unwrap();
+        assert_eq!(frontend_tasks, vec![ui]);
+        let unused_tasks = Label::find_tasks(&pool, unused.id).await.unwrap();
+        assert!(unused_tasks.is_empty());
+    }
+
+    async fn insert_task_with(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        title: &str,
+        status: &str,
+        priority: &str,
+    ) -> Uuid {
+        let id = insert_task(pool, project_id, title).await;
+        sqlx::query("UPDATE tasks SET status = $2, priority = $3 WHERE id = $1")
+            .bind(id)
+            .bind(status)
+            .bind(priority)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_find_actionable_skips_tasks_with_unmet_dependencies() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let done = insert_task_with(&pool, project_id, "done", "done", "urgent").await;
+        let open = insert_task_with(&pool, project_id, "open", "todo", "low").await;
+        let unblocked = insert_task_with(&pool, project_id, "unblocked", "todo", "medium").await;
+        let blocked = insert_task_with(&pool, project_id, "blocked", "todo", "urgent").await;
+        let urgent = insert_task_with(&pool, project_id, "urgent", "inprogress", "urgent").await;
+        insert_task_with(&pool, project_id, "review", "inreview", "urgent").await;
+        for (task_id, depends_on_task_id) in [(unblocked, done), (blocked, done), (blocked, open)] {
+            TaskDependency::create(&pool, task_id, depends_on_task_id)
+                .await
+                .unwrap();
+        }
+
+        let tasks = TaskDependency::find_actionable(&pool, project_id, 10)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![urgent, unblocked, open]);
+
+        let limited = TaskDependency::find_actionable(&pool, project_id, 1)
+            .await
+            .unwrap();
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].id, urgent);
+    }
+
+    fn planned_task(title: &str, priority: TaskPriority, created_minute: i64) -> Task {
+        let created_at = DateTime::from_timestamp(1_700_000_000 + created_minute * 60, 0).unwrap();
+        Task {
+            priority,
+            created_at,
+            updated_at: created_at,
+            ..task(title, TaskStatus::Todo)
+        }
+    }
+
+    fn depends_on(task: &Task, prerequisite: &Task) -> TaskDependency {
+        TaskDependency {
+            task_id: task.id,
+            depends_on_task_id: prerequisite.id,
+            created_at: task.created_at,
+        }
+    }
+
+    fn ids(tasks: &[&Task]) -> Vec<Uuid> {
+        tasks.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_order_tasks_follows_linear_chain() {
+        // Created and prioritized against the chain, which must still win
+        let third = planned_task("third", TaskPriority::Urgent, 0);
+        let second = planned_task("second", TaskPriority::High, 1);
+        let first = planned_task("first", TaskPriority::Low, 2);
+        let dependencies = [depends_on(&third, &second), depends_on(&second, &first)];
+
+        let order = TaskDependency::order_tasks(
+            &[third.clone(), second.clone(), first.clone()],
+            &dependencies,
+        )
+        .unwrap();
+
+        assert_eq!(order, ids(&[&first, &second, &third]));
+    }
+
+    #[test]
+    fn test_order_tasks_orders_diamond() {
+        let base = planned_task("base", TaskPriority::Medium, 0);
+        let left = planned_task("left", TaskPriority::Low, 1);
+        let right = planned_task("right", TaskPriority::High, 2);
+        let top = planned_task("top", TaskPriority::Urgent, 3);
+        let dependencies = [
+            depends_on(&left, &base),
+            depends_on(&right, &base),
+            depends_on(&top, &left),
+            depends_on(&top, &right),
+        ];
+
+        let order = TaskDependency::order_tasks(
+            &[top.clone(), right.clone(), left.clone(), base.clone()],
+            &dependencies,
+        )
+        .unwrap();
+
+        assert_eq!(order, ids(&[&base, &right, &left, &top]));
+    }
+
+    #[test]
+    fn test_order_tasks_breaks_ties_by_priority_then_age() {
+        let old_low = planned_task("old low", TaskPriority::Low, 0);
+        let new_high = planned_task("new high", TaskPriority::High, 2);
+        let old_high = planned_task("old high", TaskPriority::High, 1);
+        let urgent = planned_task("urgent", TaskPriority::Urgent, 3);
+        // Unblocked by `old_low`, so it only becomes ready after it
+        let blocked_urgent = planned_task("blocked urgent", TaskPriority::Urgent, 4);
+        let dependencies = [depends_on(&blocked_urgent, &old_low)];
+
+        let order = TaskDependency::order_tasks(
+            &[
+                old_low.clone(),
+                new_high.clone(),
+                old_high.clone(),
+                urgent.clone(),
+                blocked_urgent.clone(),
+            ],
+            &dependencies,
+        )
+        .unwrap();
+
+        assert_eq!(
+            order,
+            ids(&[&urgent, &old_high, &new_high, &old_low, &blocked_urgent])
+        );
+    }
+
+    #[test]
+    fn test_order_tasks_reports_cycle() {
+        let a = planned_task("a", TaskPriority::Medium, 0);
+        let b = planned_task("b", TaskPriority::Medium, 1);
+        let c = planned_task("c", TaskPriority::Medium, 2);
+        let free = planned_task("free", TaskPriority::Medium, 3);
+        let dependencies = [depends_on(&a, &b), depends_on(&b, &c), depends_on(&c, &a)];
+
+        let result = TaskDependency::order_tasks(
+            &[a.clone(), b.clone(), c.clone(), free.clone()],
+            &dependencies,
+        );
+
+        let Err(TaskDependencyError::Cycle(mut cyclic)) = result else {
+            panic!("expected a cycle, got {result:?}");
+        };
+        cyclic.sort();
+        let mut expected = ids(&[&a, &b, &c]);
+        expected.sort();
+        assert_eq!(cyclic, expected);
+    }
+
+    #[test]
+    fn test_longest_chain_follows_longest_branch() {
+        let mut done = planned_task("done", TaskPriority::Medium, 0);
+        done.status = TaskStatus::Done;
+        let root = planned_task("root", TaskPriority::Medium, 1);
+        let short = planned_task("short", TaskPriority::Urgent, 2);
+        let long_1 = planned_task("long 1", TaskPriority::Low, 3);
+        let long_2 = planned_task("long 2", TaskPriority::Low, 4);
+        let dependencies = [
+            depends_on(&root, &done),
+            depends_on(&short, &root),
+            depends_on(&long_1, &root),
+            depends_on(&long_2, &long_1),
+        ];
+
+        let path = TaskDependency::longest_chain(
+            &[done, root.clone(), short, long_1.clone(), long_2.clone()],
+            &dependencies,
+        )
+        .unwrap();
+
+        assert_eq!(path, ids(&[&root, &long_1, &long_2]));
+    }
+
+    #[test]
+    fn test_longest_chain_picks_longest_component() {
+        let x = planned_task("x", TaskPriority::Urgent, 0);
+        let y = planned_task("y", TaskPriority::Urgent, 1);
+        let p = planned_task("p", TaskPriority::Low, 2);
+        let q = planned_task("q", TaskPriority::Low, 3);
+        let r = planned_task("r", TaskPriority::Low, 4);
+        let dependencies = [depends_on(&y, &x), depends_on(&q, &p), depends_on(&r, &q)];
+
+        let path =
+            TaskDependency::longest_chain(&[x, y, p.clone(), q.clone(), r.clone()], &dependencies)
+                .unwrap();
+
+        assert_eq!(path, ids(&[&p, &q, &r]));
+    }
+
+    #[test]
+    fn test_longest_chain_of_single_task() {
+        let only = planned_task("only", TaskPriority::Medium, 0);
+
+        let path = TaskDependency::longest_chain(&[only.clone()], &[]).unwrap();
+
+        assert_eq!(path, ids(&[&only]));
+        assert!(TaskDependency::longest_chain(&[], &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_longest_chain_reports_cycle() {
+        let a = planned_task("a", TaskPriority::Medium, 0);
+        let b = planned_task("b", TaskPriority::Medium, 1);
+        let c = planned_task("c", TaskPriority::Medium, 2);
+        let dependencies = [depends_on(&a, &b), depends_on(&b, &a), depends_on(&c, &a)];
+
+        let result = TaskDependency::longest_chain(&[a, b, c], &dependencies);
+
+        assert!(matches!(result, Err(TaskDependencyError::Cycle(_))));
+    }
+
+    #[tokio::test]
+    async fn test_merge_rejects_target_among_sources() {
+        let pool = test_pool().await;
+        let label_id = Uuid::new_v4();
+
+        assert!(matches!(
+            Label::merge(&pool, Uuid::new_v4(), &[label_id], label_id).await,
+            Err(LabelError::MergeIntoSource)
+        ));
+    }
 }
@@ -6,13 +6,18 @@ pub mod image;
 pub mod label;
 pub mod merge;
 pub mod pm_conversation;
+pub mod pm_docs_history;
+pub mod pm_message_task;
 pub mod project;
 pub mod project_repo;
+pub mod project_webhook;
 pub mod repo;
 pub mod scratch;
 pub mod session;
 pub mod tag;
 pub mod task;
 pub mod task_attachment;
+pub mod task_comment;
+pub mod task_review;
 pub mod workspace;
 pub mod workspace_repo;
@@ -53,12 +53,32 @@ pub struct PmConversation {
     pub role: String, // Stored as string in DB, use PmMessageRole for type safety
     pub content: String,
     pub model: Option<String>,
+    /// Pinned messages are kept when old messages are pruned
+    pub pinned: bool,
+    /// Whether the content was edited after the message was sent
+    pub edited: bool,
+    /// Prompt tokens of the assistant turn, when the agent CLI reports them
+    pub input_tokens: Option<i64>,
+    /// Generated tokens of the assistant turn, when the agent CLI reports them
+    pub output_tokens: Option<i64>,
+    /// When the message was archived by clearing the chat; archived messages
+    /// are hidden from the conversation until restored
+    #[ts(type = "Date | null")]
+    pub archived_at: Option<DateTime<Utc>>,
     #[ts(type = "Date")]
     pub created_at: DateTime<Utc>,
     #[ts(type = "Date")]
     pub updated_at: DateTime<Utc>,
 }
 
+/// One page of PM conversation messages, newest first
+#[derive(Debug, Clone)]
+pub struct PmConversationPage {
+    pub messages: Vec<PmConversation>,
+    /// Pass as `before` to fetch the next (older) page; `None` when there are no older messages
+    pub next_cursor: Option<Uuid>,
+}
+
 /// Data for creating a new PM conversation message
 #[derive(Debug, Clone, Deserialize, TS)]
 pub struct CreatePmConversation {
@@ -66,6 +86,25 @@ pub struct CreatePmConversation {
     pub role: PmMessageRole,
     pub content: String,
     pub model: Option<String>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+}
+
+/// Token usage of the assistant messages of one model
+#[derive(Debug, Clone, FromRow, Serialize, TS)]
+pub struct PmModelUsage {
+    pub model: Option<String>,
+    /// Number of assistant messages with reported usage
+    pub messages: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+/// Number of a project's PM conversation messages with one role
+#[derive(Debug, Clone, FromRow, Serialize, TS)]
+pub struct PmRoleCount {
+    pub role: String,
+    pub count: i64,
 }
 
 /// File attachment for PM conversation
@@ -79,6 +118,10 @@ pub struct PmAttachment {
     pub mime_type: String,
     pub file_size: i64,
     pub sha256: Option<String>,
+    /// Pixel width of image attachments
+    pub width: Option<i64>,
+    /// Pixel height of image attachments
+    pub height: Option<i64>,
     #[ts(type = "Date")]
     pub created_at: DateTime<Utc>,
 }
@@ -93,10 +136,12 @@ pub struct CreatePmAttachment {
     pub mime_type: String,
     pub file_size: i64,
     pub sha256: Option<String>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
 }
 
 impl PmConversation {
-    /// Find all messages for a project, ordered by creation time
+    /// Find all active (not archived) messages for a project, ordered by creation time
     pub async fn find_by_project_id(
         pool: &SqlitePool,
         project_id: Uuid,
@@ -109,6 +154,161 @@ impl PmConversation {
                 role,
                 content,
                 model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NULL
+            ORDER BY created_at ASC"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Find up to `limit` active messages of a project, newest first, starting just
+    /// before the message `before` (or at the newest message when `None`)
+    pub async fn find_by_project_id_paginated(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        limit: i64,
+        before: Option<Uuid>,
+    ) -> Result<PmConversationPage, sqlx::Error> {
+        // Fetch one extra row to know whether an older page exists
+        let fetch_limit = limit + 1;
+        let mut messages = sqlx::query_as!(
+            PmConversation,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM pm_conversations
+            WHERE project_id = $1
+              AND archived_at IS NULL
+              AND (
+                $2 IS NULL
+                OR (created_at, id) < (SELECT created_at, id FROM pm_conversations WHERE id = $2)
+              )
+            ORDER BY created_at DESC, id DESC
+            LIMIT $3"#,
+            project_id,
+            before,
+            fetch_limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let has_more = messages.len() as i64 > limit;
+        messages.truncate(limit.max(0) as usize);
+        let next_cursor = messages.last().map(|m| m.id).filter(|_| has_more);
+
+        Ok(PmConversationPage {
+            messages,
+            next_cursor,
+        })
+    }
+
+    /// Find up to `limit` active messages of a project whose content contains `query`,
+    /// newest first. Matching is case-insensitive for ASCII (SQLite `LIKE`).
+    pub async fn search(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+
+        sqlx::query_as!(
+            PmConversation,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NULL AND content LIKE $2 ESCAPE '\'
+            ORDER BY created_at DESC
+            LIMIT $3"#,
+            project_id,
+            pattern,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Find the archived messages of a project, ordered by creation time
+    pub async fn find_archived_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmConversation,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NOT NULL
+            ORDER BY created_at ASC"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Find every message of a project, archived or not, ordered by creation time
+    pub async fn find_all_for_retention(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmConversation,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>"
             FROM pm_conversations
@@ -130,6 +330,11 @@ impl PmConversation {
                 role,
                 content,
                 model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>"
             FROM pm_conversations
@@ -151,9 +356,9 @@ impl PmConversation {
         sqlx::query_as!(
             PmConversation,
             r#"INSERT INTO pm_conversations (
-                id, project_id, role, content, model
+                id, project_id, role, content, model, input_tokens, output_tokens
             ) VALUES (
-                $1, $2, $3, $4, $5
+                $1, $2, $3, $4, $5, $6, $7
             )
             RETURNING
                 id as "id!: Uuid",
@@ -161,6 +366,11 @@ impl PmConversation {
                 role,
                 content,
                 model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>""#,
             id,
@@ -168,11 +378,260 @@ impl PmConversation {
             role,
             data.content,
             data.model,
+            data.input_tokens,
+            data.output_tokens,
         )
         .fetch_one(executor)
         .await
     }
 
+    /// Create a message sent with a client-generated `client_message_id`. If the
+    /// project already has a message with that id (a retried send), it is
+    /// returned instead and the flag is `false`.
+    pub async fn create_idempotent(
+        pool: &SqlitePool,
+        data: &CreatePmConversation,
+        client_message_id: &str,
+    ) -> Result<(Self, bool), sqlx::Error> {
+        let id = Uuid::new_v4();
+        let role = data.role.to_string();
+
+        let created = sqlx::query_as!(
+            PmConversation,
+            r#"INSERT INTO pm_conversations (
+                id, project_id, role, content, model, input_tokens, output_tokens,
+                client_message_id
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8
+            )
+            ON CONFLICT (project_id, client_message_id) DO NOTHING
+            RETURNING
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>""#,
+            id,
+            data.project_id,
+            role,
+            data.content,
+            data.model,
+            data.input_tokens,
+            data.output_tokens,
+            client_message_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match created {
+            Some(message) => Ok((message, true)),
+            None => Self::find_by_client_message_id(pool, data.project_id, client_message_id)
+                .await?
+                .map(|message| (message, false))
+                .ok_or(sqlx::Error::RowNotFound),
+        }
+    }
+
+    /// Find the message a client sent with `client_message_id`
+    pub async fn find_by_client_message_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        client_message_id: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmConversation,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM pm_conversations
+            WHERE project_id = $1 AND client_message_id = $2"#,
+            project_id,
+            client_message_id
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Pin or unpin a message. Pinned messages are excluded from retention pruning.
+    pub async fn set_pinned(
+        pool: &SqlitePool,
+        id: Uuid,
+        pinned: bool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmConversation,
+            r#"UPDATE pm_conversations
+            SET pinned = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>""#,
+            id,
+            pinned,
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Replace the content of a message and mark it as edited
+    pub async fn update_content(
+        pool: &SqlitePool,
+        id: Uuid,
+        content: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmConversation,
+            r#"UPDATE pm_conversations
+            SET content = $2, edited = 1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                role,
+                content,
+                model,
+                pinned as "pinned!: bool",
+                edited as "edited!: bool",
+                input_tokens,
+                output_tokens,
+                archived_at as "archived_at: DateTime<Utc>",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>""#,
+            id,
+            content,
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Append text to a message being streamed, without marking it as edited
+    pub async fn append_content(
+        pool: &SqlitePool,
+        id: Uuid,
+        text: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE pm_conversations
+            SET content = content || $2, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1"#,
+            id,
+            text
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Replace the content of a message being streamed, without marking it as edited
+    pub async fn set_content(
+        pool: &SqlitePool,
+        id: Uuid,
+        content: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE pm_conversations
+            SET content = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1"#,
+            id,
+            content
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Record the token usage of an assistant message once its turn is over
+    pub async fn set_usage(
+        pool: &SqlitePool,
+        id: Uuid,
+        input_tokens: Option<i64>,
+        output_tokens: Option<i64>,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE pm_conversations
+            SET input_tokens = $2, output_tokens = $3
+            WHERE id = $1"#,
+            id,
+            input_tokens,
+            output_tokens
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Archive a message by ID
+    pub async fn archive(pool: &SqlitePool, id: Uuid) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE pm_conversations
+            SET archived_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND archived_at IS NULL"#,
+            id
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Archive all active messages for a project
+    pub async fn archive_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE pm_conversations
+            SET archived_at = CURRENT_TIMESTAMP
+            WHERE project_id = $1 AND archived_at IS NULL"#,
+            project_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Restore all archived messages for a project
+    pub async fn restore_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE pm_conversations
+            SET archived_at = NULL
+            WHERE project_id = $1 AND archived_at IS NOT NULL"#,
+            project_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
     /// Delete a message by ID
     pub async fn delete(pool: &SqlitePool, id: Uuid) -> Result<u64, sqlx::Error> {
         let result = sqlx::query!("DELETE FROM pm_conversations WHERE id = $1", id)
@@ -195,18 +654,98 @@ impl PmConversation {
         Ok(result.rows_affected())
     }
 
-    /// Get message count for a project
+    /// Total token usage of a project's active assistant messages, per model.
+    /// Messages without reported usage are left out.
+    pub async fn usage_by_model(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<PmModelUsage>, sqlx::Error> {
+        sqlx::query_as!(
+            PmModelUsage,
+            r#"SELECT
+                model,
+                COUNT(*) as "messages!: i64",
+                COALESCE(SUM(input_tokens), 0) as "input_tokens!: i64",
+                COALESCE(SUM(output_tokens), 0) as "output_tokens!: i64"
+            FROM pm_conversations
+            WHERE project_id = $1
+              AND archived_at IS NULL
+              AND role = 'assistant'
+              AND (input_tokens IS NOT NULL OR output_tokens IS NOT NULL)
+            GROUP BY model
+            ORDER BY model"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get active message count for a project
     pub async fn count_by_project_id(
         pool: &SqlitePool,
         project_id: Uuid,
     ) -> Result<i64, sqlx::Error> {
         sqlx::query_scalar!(
-            r#"SELECT COUNT(*) as "count!: i64" FROM pm_conversations WHERE project_id = $1"#,
+            r#"SELECT COUNT(*) as "count!: i64"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NULL"#,
             project_id
         )
         .fetch_one(pool)
         .await
     }
+
+    /// Get active message counts for a project, per role
+    pub async fn count_by_role(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<PmRoleCount>, sqlx::Error> {
+        sqlx::query_as!(
+            PmRoleCount,
+            r#"SELECT role, COUNT(*) as "count!: i64"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NULL
+            GROUP BY role
+            ORDER BY role"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Timestamps of a project's first and last active message, `None` without any
+    pub async fn time_range(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"SELECT
+                MIN(created_at) as "first: DateTime<Utc>",
+                MAX(created_at) as "last: DateTime<Utc>"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NULL"#,
+            project_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(row.first.zip(row.last))
+    }
+
+    /// Distinct models that replied in a project's active conversation
+    pub async fn models_used(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT DISTINCT model as "model!: String"
+            FROM pm_conversations
+            WHERE project_id = $1 AND archived_at IS NULL AND model IS NOT NULL
+            ORDER BY model"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
 }
 
 impl PmAttachment {
@@ -226,6 +765,8 @@ impl PmAttachment {
                 mime_type,
                 file_size,
                 sha256,
+                width,
+                height,
                 created_at as "created_at!: DateTime<Utc>"
             FROM pm_attachments
             WHERE conversation_id = $1
@@ -252,6 +793,8 @@ impl PmAttachment {
                 mime_type,
                 file_size,
                 sha256,
+                width,
+                height,
                 created_at as "created_at!: DateTime<Utc>"
             FROM pm_attachments
             WHERE project_id = $1
@@ -262,6 +805,110 @@ impl PmAttachment {
         .await
     }
 
+    /// Find an attachment of a project with the given content hash
+    pub async fn find_by_sha256(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        sha256: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmAttachment,
+            r#"SELECT
+                id as "id!: Uuid",
+                conversation_id as "conversation_id!: Uuid",
+                project_id as "project_id!: Uuid",
+                file_name,
+                file_path,
+                mime_type,
+                file_size,
+                sha256,
+                width,
+                height,
+                created_at as "created_at!: DateTime<Utc>"
+            FROM pm_attachments
+            WHERE project_id = $1 AND sha256 = $2
+            ORDER BY created_at ASC
+            LIMIT 1"#,
+            project_id,
+            sha256
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Number and total size in bytes of a project's attachments
+    pub async fn totals_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<(i64, i64), sqlx::Error> {
+        let row = sqlx::query!(
+            r#"SELECT
+                COUNT(*) as "count!: i64",
+                COALESCE(SUM(file_size), 0) as "bytes!: i64"
+            FROM pm_attachments
+            WHERE project_id = $1"#,
+            project_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok((row.count, row.bytes))
+    }
+
+    /// Distinct files on disk referenced by any attachment
+    pub async fn all_file_paths(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar!(r#"SELECT DISTINCT file_path as "file_path!" FROM pm_attachments"#)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Distinct files still stored in the flat legacy layout (no subdirectory),
+    /// with their content hash. Files without a hash are not included.
+    pub async fn legacy_files(pool: &SqlitePool) -> Result<Vec<(String, String)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"SELECT DISTINCT file_path as "file_path!", sha256 as "sha256!"
+               FROM pm_attachments
+               WHERE sha256 IS NOT NULL AND instr(file_path, '/') = 0"#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows.into_iter().map(|r| (r.file_path, r.sha256)).collect())
+    }
+
+    /// Point every attachment stored at `old_file_path` to `new_file_path`
+    pub async fn update_file_path(
+        pool: &SqlitePool,
+        old_file_path: &str,
+        new_file_path: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE pm_attachments SET file_path = $1 WHERE file_path = $2",
+            new_file_path,
+            old_file_path
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Count attachments that may be stored in the file at `file_path` (files
+    /// are shared between attachments with identical content): those pointing
+    /// at it, and those with content hash `sha256` whose rows may still name a
+    /// legacy path while their file is being moved to its sharded location
+    pub async fn count_sharing_file(
+        pool: &SqlitePool,
+        file_path: &str,
+        sha256: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM pm_attachments
+               WHERE file_path = $1 OR sha256 = $2"#,
+            file_path,
+            sha256
+        )
+        .fetch_one(pool)
+        .await
+    }
+
     /// Find a specific attachment by ID
     pub async fn find_by_id(pool: &SqlitePool, id: Uuid) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as!(
@@ -275,6 +922,8 @@ impl PmAttachment {
                 mime_type,
                 file_size,
                 sha256,
+                width,
+                height,
                 created_at as "created_at!: DateTime<Utc>"
             FROM pm_attachments
             WHERE id = $1"#,
@@ -294,9 +943,10 @@ impl PmAttachment {
         sqlx::query_as!(
             PmAttachment,
             r#"INSERT INTO pm_attachments (
-                id, conversation_id, project_id, file_name, file_path, mime_type, file_size, sha256
+                id, conversation_id, project_id, file_name, file_path, mime_type, file_size, sha256,
+                width, height
             ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
             )
             RETURNING
                 id as "id!: Uuid",
@@ -307,6 +957,8 @@ impl PmAttachment {
                 mime_type,
                 file_size,
                 sha256,
+                width,
+                height,
                 created_at as "created_at!: DateTime<Utc>""#,
             id,
             data.conversation_id,
@@ -316,6 +968,8 @@ impl PmAttachment {
             data.mime_type,
             data.file_size,
             data.sha256,
+            data.width,
+            data.height,
         )
         .fetch_one(executor)
         .await
@@ -329,3 +983,243 @@ impl PmAttachment {
         Ok(result.rows_affected())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_project, test_pool};
+
+    fn user_message(project_id: Uuid, content: &str) -> CreatePmConversation {
+        CreatePmConversation {
+            project_id,
+            role: PmMessageRole::User,
+            content: content.to_string(),
+            model: None,
+            input_tokens: None,
+            output_tokens: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_idempotent_returns_existing_message_on_replay() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+
+        let (first, created) =
+            PmConversation::create_idempotent(&pool, &user_message(project_id, "Hi"), "client-1")
+                .await
+                .unwrap();
+        assert!(created);
+
+        let (replayed, created) =
+            PmConversation::create_idempotent(&pool, &user_message(project_id, "Hi"), "client-1")
+                .await
+                .unwrap();
+        assert!(!created);
+        assert_eq!(replayed.id, first.id);
+        assert_eq!(
+            PmConversation::find_by_project_id(&pool, project_id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stats_exclude_archived_messages() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let question = PmConversation::create(&pool, &user_message(project_id, "Hi"))
+            .await
+            .unwrap();
+        let reply = |model: &str, tokens: i64| CreatePmConversation {
+            project_id,
+            role: PmMessageRole::Assistant,
+            content: "Hello".to_string(),
+            model: Some(model.to_string()),
+            input_tokens: Some(tokens),
+            output_tokens: Some(tokens),
+        };
+        let archived = PmConversation::create(&pool, &reply("opus", 1000))
+            .await
+            .unwrap();
+        PmConversation::create(&pool, &reply("sonnet", 10))
+            .await
+            .unwrap();
+        PmConversation::archive(&pool, archived.id).await.unwrap();
+
+        assert_eq!(
+            PmConversation::count_by_project_id(&pool, project_id)
+                .await
+                .unwrap(),
+            2
+        );
+        let roles = PmConversation::count_by_role(&pool, project_id)
+            .await
+            .unwrap();
+        let counts: Vec<(&str, i64)> = roles.iter().map(|r| (r.role.as_str(), r.count)).collect();
+        assert_eq!(counts, vec![("assistant", 1), ("user", 1)]);
+
+        let usage = PmConversation::usage_by_model(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].model.as_deref(), Some("sonnet"));
+        assert_eq!(usage[0].input_tokens, 10);
+        assert_eq!(
+            PmConversation::models_used(&pool, project_id)
+                .await
+                .unwrap(),
+            vec!["sonnet".to_string()]
+        );
+
+        let (first, _) = PmConversation::time_range(&pool, project_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first, question.created_at);
+
+        PmConversation::archive_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            PmConversation::count_by_project_id(&pool, project_id)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(
+            PmConversation::time_range(&pool, project_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_idempotent_scopes_keys_to_project() {
+        let pool = test_pool().await;
+        let first_project = insert_project(&pool).await;
+        let second_project = insert_project(&pool).await;
+
+        let (first, _) =
+            PmConversation::create_idempotent(&pool, &user_message(first_project, "Hi"), "key")
+                .await
+                .unwrap();
+        let (second, created) =
+            PmConversation::create_idempotent(&pool, &user_message(second_project, "Hi"), "key")
+                .await
+                .unwrap();
+
+        assert!(created);
+        assert_ne!(first.id, second.id);
+    }
+
+    #[tokio::test]
+    async fn test_count_sharing_file_includes_legacy_rows_with_same_content() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let message = PmConversation::create(&pool, &user_message(project_id, "Files"))
+            .await
+            .unwrap();
+        let sha256 = "ab".repeat(32);
+        let content_path = format!("ab/ab/{}.png", sha256);
+        for (file_path, sha256) in [
+            (content_path.as_str(), Some(sha256.clone())),
+            ("1234_photo.png", Some(sha256.clone())),
+            ("5678_other.png", None),
+        ] {
+            PmAttachment::create(
+                &pool,
+                &CreatePmAttachment {
+                    conversation_id: message.id,
+                    project_id,
+                    file_name: "photo.png".to_string(),
+                    file_path: file_path.to_string(),
+                    mime_type: "image/png".to_string(),
+                    file_size: 3,
+                    sha256,
+                    width: None,
+                    height: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        // The legacy row may resolve to the content file until it is relocated
+        let count = PmAttachment::count_sharing_file(&pool, &content_path, Some(&sha256))
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        let count = PmAttachment::count_sharing_file(&pool, "5678_other.png", None)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    /// Ids of `messages`, sorted; messages created within the same second
+    /// have no defined order
+    fn sorted_ids(messages: Vec<PmConversation>) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = messages.into_iter().map(|m| m.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_restore_move_messages_out_of_and_back_into_chat() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let other_project = insert_project(&pool).await;
+        let first = PmConversation::create(&pool, &user_message(project_id, "First"))
+            .await
+            .unwrap();
+        let second = PmConversation::create(&pool, &user_message(project_id, "Second"))
+            .await
+            .unwrap();
+        PmConversation::create(&pool, &user_message(other_project, "Elsewhere"))
+            .await
+            .unwrap();
+        let both = sorted_ids(vec![first.clone(), second]);
+
+        assert_eq!(PmConversation::archive(&pool, first.id).await.unwrap(), 1);
+        // Archiving twice changes nothing
+        assert_eq!(PmConversation::archive(&pool, first.id).await.unwrap(), 0);
+        let archived = PmConversation::archive_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(archived, 1);
+
+        let active = PmConversation::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert!(active.is_empty());
+        let archived = PmConversation::find_archived_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(sorted_ids(archived), both);
+        // Retention still sees archived messages
+        let all = PmConversation::find_all_for_retention(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(sorted_ids(all), both);
+        let other = PmConversation::find_by_project_id(&pool, other_project)
+            .await
+            .unwrap();
+        assert_eq!(other.len(), 1);
+
+        let restored = PmConversation::restore_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(restored, 2);
+        let active = PmConversation::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(sorted_ids(active), both);
+        let archived = PmConversation::find_archived_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert!(archived.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool, Type};
+use strum_macros::{Display, EnumString};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// What overwrote a version of the PM docs
+#[derive(
+    Debug, Clone, Copy, Type, Serialize, Deserialize, PartialEq, TS, EnumString, Display, Default,
+)]
+#[sqlx(type_name = "pm_docs_source", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PmDocsSource {
+    /// Edited in the PM docs panel
+    #[default]
+    User,
+    /// Written by an agent through the `update_pm_docs` MCP tool
+    Agent,
+    /// Task summary synced into the docs
+    TaskSummary,
+    /// An older version was restored
+    Restore,
+}
+
+/// A previous version of a project's PM docs
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct PmDocsVersion {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub content: String,
+    /// What replaced this version
+    pub source: PmDocsSource,
+    /// When this version was replaced
+    #[ts(type = "Date")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl PmDocsVersion {
+    /// Find all saved versions of a project's PM docs, newest first
+    pub async fn find_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmDocsVersion,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                content,
+                source as "source!: PmDocsSource",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM pm_docs_history
+            WHERE project_id = $1
+            ORDER BY created_at DESC, rowid DESC"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Find a saved version by ID
+    pub async fn find_by_id(pool: &SqlitePool, id: Uuid) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmDocsVersion,
+            r#"SELECT
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                content,
+                source as "source!: PmDocsSource",
+                created_at as "created_at!: DateTime<Utc>"
+            FROM pm_docs_history
+            WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Save the docs content that `source` is about to replace
+    pub async fn create(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        content: &str,
+        source: PmDocsSource,
+    ) -> Result<Self, sqlx::Error> {
+        let id = Uuid::new_v4();
+
+        sqlx::query_as!(
+            PmDocsVersion,
+            r#"INSERT INTO pm_docs_history (id, project_id, content, source)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                id as "id!: Uuid",
+                project_id as "project_id!: Uuid",
+                content,
+                source as "source!: PmDocsSource",
+                created_at as "created_at!: DateTime<Utc>""#,
+            id,
+            project_id,
+            content,
+            source,
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Delete all but the newest `keep` versions of a project's PM docs
+    pub async fn prune(pool: &SqlitePool, project_id: Uuid, keep: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"DELETE FROM pm_docs_history
+            WHERE project_id = $1
+              AND id NOT IN (
+                SELECT id FROM pm_docs_history
+                WHERE project_id = $1
+                ORDER BY created_at DESC, rowid DESC
+                LIMIT $2
+              )"#,
+            project_id,
+            keep
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
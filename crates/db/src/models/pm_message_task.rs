@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Link from a task to the PM chat message it was created from
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct PmMessageTask {
+    pub task_id: Uuid,
+    pub message_id: Uuid,
+    #[ts(type = "Date")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl PmMessageTask {
+    /// Record that a task was created from a message
+    pub async fn create(
+        pool: &SqlitePool,
+        task_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            PmMessageTask,
+            r#"INSERT INTO pm_message_tasks (task_id, message_id)
+               VALUES ($1, $2)
+               RETURNING task_id as "task_id!: Uuid",
+                         message_id as "message_id!: Uuid",
+                         created_at as "created_at!: DateTime<Utc>""#,
+            task_id,
+            message_id,
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Find the tasks created from a message, oldest first
+    pub async fn find_by_message_id(
+        pool: &SqlitePool,
+        message_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PmMessageTask,
+            r#"SELECT task_id as "task_id!: Uuid",
+                      message_id as "message_id!: Uuid",
+                      created_at as "created_at!: DateTime<Utc>"
+               FROM pm_message_tasks
+               WHERE message_id = $1
+               ORDER BY created_at, rowid"#,
+            message_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::pm_conversation::{CreatePmConversation, PmConversation, PmMessageRole},
+        test_support::{insert_project, insert_task, test_pool},
+    };
+
+    async fn insert_message(pool: &SqlitePool, project_id: Uuid) -> Uuid {
+        let message = CreatePmConversation {
+            project_id,
+            role: PmMessageRole::Assistant,
+            content: "We should add a login page".to_string(),
+            model: None,
+            input_tokens: None,
+            output_tokens: None,
+        };
+        PmConversation::create(pool, &message).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn test_find_by_message_id_returns_linked_tasks() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let message_id = insert_message(&pool, project_id).await;
+        let other_message_id = insert_message(&pool, project_id).await;
+        let first = insert_task(&pool, project_id, "Task").await;
+        let second = insert_task(&pool, project_id, "Task").await;
+        let unrelated = insert_task(&pool, project_id, "Task").await;
+
+        PmMessageTask::create(&pool, first, message_id)
+            .await
+            .unwrap();
+        PmMessageTask::create(&pool, second, message_id)
+            .await
+            .unwrap();
+        PmMessageTask::create(&pool, unrelated, other_message_id)
+            .await
+            .unwrap();
+
+        let links = PmMessageTask::find_by_message_id(&pool, message_id)
+            .await
+            .unwrap();
+        let task_ids: Vec<_> = links.iter().map(|l| l.task_id).collect();
+        assert_eq!(task_ids, vec![first, second]);
+    }
+
+    #[tokio::test]
+    async fn test_link_is_deleted_with_its_task() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let message_id = insert_message(&pool, project_id).await;
+        let task_id = insert_task(&pool, project_id, "Task").await;
+        PmMessageTask::create(&pool, task_id, message_id)
+            .await
+            .unwrap();
+
+        sqlx::query("DELETE FROM tasks WHERE id = $1")
+            .bind(task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let links = PmMessageTask::find_by_message_id(&pool, message_id)
+            .await
+            .unwrap();
+        assert!(links.is_empty());
+    }
+}
@@ -28,6 +28,8 @@ pub struct Project {
     pub pm_task_id: Option<Uuid>,
     /// PM documentation/specifications in Markdown format, generated from PM chat
     pub pm_docs: Option<String>,
+    /// Number of days to keep PM chat messages (None = keep forever)
+    pub chat_retention_days: Option<i32>,
     #[ts(type = "Date")]
     pub created_at: DateTime<Utc>,
     #[ts(type = "Date")]
@@ -83,6 +85,7 @@ impl Project {
                       remote_project_id as "remote_project_id: Uuid",
                       pm_task_id as "pm_task_id: Uuid",
                       pm_docs,
+                      chat_retention_days as "chat_retention_days: i32",
                       created_at as "created_at!: DateTime<Utc>",
                       updated_at as "updated_at!: DateTime<Utc>"
                FROM projects
@@ -102,6 +105,7 @@ impl Project {
                    p.remote_project_id as "remote_project_id: Uuid",
                    p.pm_task_id as "pm_task_id: Uuid",
                    p.pm_docs,
+                   p.chat_retention_days as "chat_retention_days: i32",
                    p.created_at as "created_at!: DateTime<Utc>", p.updated_at as "updated_at!: DateTime<Utc>"
             FROM projects p
             WHERE p.id IN (
@@ -127,6 +131,7 @@ impl Project {
                       remote_project_id as "remote_project_id: Uuid",
                       pm_task_id as "pm_task_id: Uuid",
                       pm_docs,
+                      chat_retention_days as "chat_retention_days: i32",
                       created_at as "created_at!: DateTime<Utc>",
                       updated_at as "updated_at!: DateTime<Utc>"
                FROM projects
@@ -146,6 +151,7 @@ impl Project {
                       remote_project_id as "remote_project_id: Uuid",
                       pm_task_id as "pm_task_id: Uuid",
                       pm_docs,
+                      chat_retention_days as "chat_retention_days: i32",
                       created_at as "created_at!: DateTime<Utc>",
                       updated_at as "updated_at!: DateTime<Utc>"
                FROM projects
@@ -168,6 +174,7 @@ impl Project {
                       remote_project_id as "remote_project_id: Uuid",
                       pm_task_id as "pm_task_id: Uuid",
                       pm_docs,
+                      chat_retention_days as "chat_retention_days: i32",
                       created_at as "created_at!: DateTime<Utc>",
                       updated_at as "updated_at!: DateTime<Utc>"
                FROM projects
@@ -198,6 +205,7 @@ impl Project {
                           remote_project_id as "remote_project_id: Uuid",
                           pm_task_id as "pm_task_id: Uuid",
                           pm_docs,
+                          chat_retention_days as "chat_retention_days: i32",
                           created_at as "created_at!: DateTime<Utc>",
                           updated_at as "updated_at!: DateTime<Utc>""#,
             project_id,
@@ -241,6 +249,7 @@ impl Project {
                          remote_project_id as "remote_project_id: Uuid",
                          pm_task_id as "pm_task_id: Uuid",
                          pm_docs,
+                         chat_retention_days as "chat_retention_days: i32",
                          created_at as "created_at!: DateTime<Utc>",
                          updated_at as "updated_at!: DateTime<Utc>""#,
             id,
@@ -252,6 +261,79 @@ impl Project {
         .await
     }
 
+    /// Set the PM docs and bump `updated_at`; `None` keeps the current docs.
+    /// With `expected_updated_at`, the write only happens if the project has
+    /// not been updated since then, and `None` is returned otherwise.
+    pub async fn update_pm_docs(
+        pool: &SqlitePool,
+        id: Uuid,
+        pm_docs: Option<&str>,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        // Compare through datetime() so stored and bound timestamps share a format
+        sqlx::query_as!(
+            Project,
+            r#"UPDATE projects
+               SET pm_docs = COALESCE($2, pm_docs),
+                   updated_at = datetime('now', 'subsec')
+               WHERE id = $1
+                 AND ($3 IS NULL OR datetime(updated_at, 'subsec') = datetime($3, 'subsec'))
+               RETURNING id as "id!: Uuid",
+                         name,
+                         default_agent_working_dir,
+                         remote_project_id as "remote_project_id: Uuid",
+                         pm_task_id as "pm_task_id: Uuid",
+                         pm_docs,
+                         chat_retention_days as "chat_retention_days: i32",
+                         created_at as "created_at!: DateTime<Utc>",
+                         updated_at as "updated_at!: DateTime<Utc>""#,
+            id,
+            pm_docs,
+            expected_updated_at,
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Find all projects that have a PM chat retention window configured
+    pub async fn find_with_chat_retention(pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Project,
+            r#"SELECT id as "id!: Uuid",
+                      name,
+                      default_agent_working_dir,
+                      remote_project_id as "remote_project_id: Uuid",
+                      pm_task_id as "pm_task_id: Uuid",
+                      pm_docs,
+                      chat_retention_days as "chat_retention_days: i32",
+                      created_at as "created_at!: DateTime<Utc>",
+                      updated_at as "updated_at!: DateTime<Utc>"
+               FROM projects
+               WHERE chat_retention_days IS NOT NULL"#
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Set (or clear with `None`) the PM chat retention window in days
+    pub async fn set_chat_retention_days(
+        pool: &SqlitePool,
+        id: Uuid,
+        chat_retention_days: Option<i32>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE projects
+               SET chat_retention_days = $2
+               WHERE id = $1"#,
+            id,
+            chat_retention_days
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn set_remote_project_id(
         pool: &SqlitePool,
         id: Uuid,
@@ -299,3 +381,53 @@ impl Project {
         Ok(result.rows_affected())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_pool;
+
+    async fn create_project(pool: &SqlitePool) -> Project {
+        let data = CreateProject {
+            name: "Project".to_string(),
+            repositories: Vec::new(),
+        };
+        Project::create(pool, &data, Uuid::new_v4()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_update_pm_docs_checks_expected_updated_at() {
+        let pool = test_pool().await;
+        let project = create_project(&pool).await;
+
+        let saved =
+            Project::update_pm_docs(&pool, project.id, Some("v1"), Some(project.updated_at))
+                .await
+                .unwrap()
+                .expect("write based on the current version succeeds");
+        assert_eq!(saved.pm_docs.as_deref(), Some("v1"));
+        assert!(saved.updated_at >= project.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_update_pm_docs_rejects_conflicting_write() {
+        let pool = test_pool().await;
+        let project = create_project(&pool).await;
+        Project::update_pm_docs(&pool, project.id, Some("theirs"), None)
+            .await
+            .unwrap();
+
+        // A client still holding a version from before the other write
+        let stale = project.updated_at - chrono::Duration::seconds(1);
+        let conflict = Project::update_pm_docs(&pool, project.id, Some("mine"), Some(stale))
+            .await
+            .unwrap();
+        assert!(conflict.is_none());
+
+        let current = Project::find_by_id(&pool, project.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(current.pm_docs.as_deref(), Some("theirs"));
+    }
+}
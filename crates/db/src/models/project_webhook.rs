@@ -0,0 +1,226 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Outbound webhook notified of task lifecycle events in a project
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct ProjectWebhook {
+    pub project_id: Uuid,
+    pub url: String,
+    /// Key used to sign deliveries with HMAC-SHA256
+    pub secret: String,
+    #[ts(type = "Date")]
+    pub created_at: DateTime<Utc>,
+    #[ts(type = "Date")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A webhook delivery that still failed after all retries
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct WebhookDeadLetter {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub event_type: String,
+    pub url: String,
+    /// JSON body that could not be delivered
+    pub payload: String,
+    /// Error of the last attempt
+    pub error: String,
+    pub attempts: i64,
+    #[ts(type = "Date")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Data for recording a failed webhook delivery
+#[derive(Debug, Clone, Deserialize, TS)]
+pub struct CreateWebhookDeadLetter {
+    pub project_id: Uuid,
+    pub event_type: String,
+    pub url: String,
+    pub payload: String,
+    pub error: String,
+    pub attempts: i64,
+}
+
+impl ProjectWebhook {
+    pub async fn find_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            ProjectWebhook,
+            r#"SELECT project_id as "project_id!: Uuid",
+                      url,
+                      secret,
+                      created_at as "created_at!: DateTime<Utc>",
+                      updated_at as "updated_at!: DateTime<Utc>"
+               FROM project_webhooks
+               WHERE project_id = $1"#,
+            project_id
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Set the webhook of a project, replacing any existing one
+    pub async fn upsert(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        url: &str,
+        secret: &str,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            ProjectWebhook,
+            r#"INSERT INTO project_webhooks (project_id, url, secret)
+               VALUES ($1, $2, $3)
+               ON CONFLICT(project_id) DO UPDATE SET
+                   url = excluded.url,
+                   secret = excluded.secret,
+                   updated_at = CURRENT_TIMESTAMP
+               RETURNING project_id as "project_id!: Uuid",
+                         url,
+                         secret,
+                         created_at as "created_at!: DateTime<Utc>",
+                         updated_at as "updated_at!: DateTime<Utc>""#,
+            project_id,
+            url,
+            secret
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn delete(pool: &SqlitePool, project_id: Uuid) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM project_webhooks WHERE project_id = $1",
+            project_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+impl WebhookDeadLetter {
+    pub async fn create(
+        pool: &SqlitePool,
+        data: &CreateWebhookDeadLetter,
+    ) -> Result<Self, sqlx::Error> {
+        let id = Uuid::new_v4();
+        sqlx::query_as!(
+            WebhookDeadLetter,
+            r#"INSERT INTO webhook_dead_letters (id, project_id, event_type, url, payload, error, attempts)
+               VALUES ($1, $2, $3, $4, $5, $6, $7)
+               RETURNING id as "id!: Uuid",
+                         project_id as "project_id!: Uuid",
+                         event_type,
+                         url,
+                         payload,
+                         error,
+                         attempts as "attempts!: i64",
+                         created_at as "created_at!: DateTime<Utc>""#,
+            id,
+            data.project_id,
+            data.event_type,
+            data.url,
+            data.payload,
+            data.error,
+            data.attempts,
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Find the failed deliveries of a project, newest first
+    pub async fn find_by_project_id(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            WebhookDeadLetter,
+            r#"SELECT id as "id!: Uuid",
+                      project_id as "project_id!: Uuid",
+                      event_type,
+                      url,
+                      payload,
+                      error,
+                      attempts as "attempts!: i64",
+                      created_at as "created_at!: DateTime<Utc>"
+               FROM webhook_dead_letters
+               WHERE project_id = $1
+               ORDER BY created_at DESC, rowid DESC"#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_project, test_pool};
+
+    #[tokio::test]
+    async fn test_upsert_replaces_existing_webhook() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+
+        ProjectWebhook::upsert(&pool, project_id, "https://example.com/a", "one")
+            .await
+            .unwrap();
+        ProjectWebhook::upsert(&pool, project_id, "https://example.com/b", "two")
+            .await
+            .unwrap();
+
+        let webhook = ProjectWebhook::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(webhook.url, "https://example.com/b");
+        assert_eq!(webhook.secret, "two");
+
+        assert_eq!(ProjectWebhook::delete(&pool, project_id).await.unwrap(), 1);
+        assert!(
+            ProjectWebhook::find_by_project_id(&pool, project_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_are_listed_per_project() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let other_project_id = insert_project(&pool).await;
+
+        for (project, event_type) in [
+            (project_id, "task.created"),
+            (other_project_id, "task.created"),
+            (project_id, "task.status_changed"),
+        ] {
+            WebhookDeadLetter::create(
+                &pool,
+                &CreateWebhookDeadLetter {
+                    project_id: project,
+                    event_type: event_type.to_string(),
+                    url: "https://example.com/hook".to_string(),
+                    payload: "{}".to_string(),
+                    error: "connection refused".to_string(),
+                    attempts: 4,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let dead_letters = WebhookDeadLetter::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        let events: Vec<_> = dead_letters.iter().map(|d| d.event_type.as_str()).collect();
+        assert_eq!(events, vec!["task.status_changed", "task.created"]);
+    }
+}
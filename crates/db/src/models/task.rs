@@ -1,7 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Executor, FromRow, Sqlite, SqlitePool, Type};
+use sqlx::{Executor, FromRow, QueryBuilder, Sqlite, SqlitePool, Type};
 use strum_macros::{Display, EnumString};
+use thiserror::Error;
 use ts_rs::TS;
 use uuid::Uuid;
 
@@ -36,6 +37,20 @@ pub enum TaskPriority {
     Urgent,
 }
 
+#[derive(Debug, Error)]
+pub enum TaskParentError {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error("A task cannot be its own parent")]
+    SelfParent,
+    #[error("Parent task {0} not found")]
+    ParentNotFound(Uuid),
+    #[error("Parent task {0} is in a different project")]
+    OutsideProject(Uuid),
+    #[error("Task {0} is a subtask of this task and cannot become its parent")]
+    Cycle(Uuid),
+}
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
 pub struct Task {
     pub id: Uuid,
@@ -46,6 +61,8 @@ pub struct Task {
     pub priority: TaskPriority,
     pub position: i32,
     pub parent_workspace_id: Option<Uuid>, // Foreign key to parent Workspace
+    /// Task this is a subtask of
+    pub parent_task_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -58,6 +75,10 @@ pub struct TaskWithAttemptStatus {
     pub has_in_progress_attempt: bool,
     pub last_attempt_failed: bool,
     pub executor: String,
+    /// Number of direct subtasks
+    pub subtask_count: i64,
+    /// Number of direct subtasks that are done
+    pub subtasks_done: i64,
 }
 
 impl std::ops::Deref for TaskWithAttemptStatus {
@@ -89,6 +110,8 @@ pub struct CreateTask {
     pub priority: Option<TaskPriority>,
     pub position: Option<i32>,
     pub parent_workspace_id: Option<Uuid>,
+    /// Create the task as a subtask of this task
+    pub parent_task_id: Option<Uuid>,
     pub image_ids: Option<Vec<Uuid>>,
     pub label_ids: Option<Vec<Uuid>>,
 }
@@ -107,6 +130,7 @@ impl CreateTask {
             priority: None,
             position: None,
             parent_workspace_id: None,
+            parent_task_id: None,
             image_ids: None,
             label_ids: None,
         }
@@ -125,6 +149,32 @@ pub struct UpdateTask {
     pub label_ids: Option<Vec<Uuid>>,
 }
 
+/// Task counts for a project's progress rollup
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+pub struct ProgressSummary {
+    pub total_tasks: i64,
+    pub todo: i64,
+    pub in_progress: i64,
+    pub in_review: i64,
+    pub done: i64,
+    pub cancelled: i64,
+    /// Tasks that are not done and depend on at least one task that is not done
+    pub blocked: i64,
+    /// Share of tasks that are done (0-100)
+    pub progress_percent: f64,
+}
+
+impl ProgressSummary {
+    /// Percentage of `total_tasks` that are done; 0 for a project without tasks
+    pub fn percent(total_tasks: i64, done: i64) -> f64 {
+        if total_tasks > 0 {
+            done as f64 / total_tasks as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
 impl Task {
     pub fn to_prompt(&self) -> String {
         if let Some(description) = self.description.as_ref().filter(|d| !d.trim().is_empty()) {
@@ -152,6 +202,7 @@ impl Task {
   t.priority                      AS "priority!: TaskPriority",
   t.position                      AS "position!: i32",
   t.parent_workspace_id           AS "parent_workspace_id: Uuid",
+  t.parent_task_id                AS "parent_task_id: Uuid",
   t.created_at                    AS "created_at!: DateTime<Utc>",
   t.updated_at                    AS "updated_at!: DateTime<Utc>",
 
@@ -184,7 +235,13 @@ impl Task {
       WHERE w.task_id = t.id
      ORDER BY s.created_at DESC
       LIMIT 1
-    )                               AS "executor!: String"
+    )                               AS "executor!: String",
+
+  ( SELECT COUNT(*) FROM tasks c WHERE c.parent_task_id = t.id )
+                                  AS "subtask_count!: i64",
+
+  ( SELECT COUNT(*) FROM tasks c WHERE c.parent_task_id = t.id AND c.status = 'done' )
+                                  AS "subtasks_done!: i64"
 
 FROM tasks t
 WHERE t.project_id = $1
@@ -206,22 +263,79 @@ ORDER BY t.position ASC, t.created_at DESC"#,
                     priority: rec.priority,
                     position: rec.position,
                     parent_workspace_id: rec.parent_workspace_id,
+                    parent_task_id: rec.parent_task_id,
                     created_at: rec.created_at,
                     updated_at: rec.updated_at,
                 },
                 has_in_progress_attempt: rec.has_in_progress_attempt != 0,
                 last_attempt_failed: rec.last_attempt_failed != 0,
                 executor: rec.executor,
+                subtask_count: rec.subtask_count,
+                subtasks_done: rec.subtasks_done,
             })
             .collect();
 
         Ok(tasks)
     }
 
+    /// Find tasks of a project whose title or description contains `query`
+    /// (case-insensitive for ASCII). Optionally only tasks with `status` and with
+    /// every label in `label_ids`.
+    pub async fn search(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        query: &str,
+        status: Option<TaskStatus>,
+        label_ids: &[Uuid],
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let pattern = format!(
+            "%{}%",
+            query
+                .trim()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "SELECT t.id, t.project_id, t.title, t.description, t.status, t.priority, t.position, t.parent_workspace_id, t.parent_task_id, t.created_at, t.updated_at
+             FROM tasks t
+             WHERE t.project_id = ",
+        );
+        builder.push_bind(project_id);
+        builder.push(" AND (t.title LIKE ");
+        builder.push_bind(pattern.clone());
+        builder.push(" ESCAPE '\\' OR COALESCE(t.description, '') LIKE ");
+        builder.push_bind(pattern);
+        builder.push(" ESCAPE '\\')");
+        if let Some(status) = status {
+            builder.push(" AND t.status = ");
+            builder.push_bind(status);
+        }
+        if !label_ids.is_empty() {
+            builder.push(
+                " AND (SELECT COUNT(DISTINCT tl.label_id) FROM task_labels tl WHERE tl.task_id = t.id AND tl.label_id IN (",
+            );
+            let mut ids = builder.separated(", ");
+            for label_id in label_ids {
+                ids.push_bind(*label_id);
+            }
+            ids.push_unseparated(")) = ");
+            let distinct_labels = label_ids
+                .iter()
+                .collect::<std::collections::HashSet<_>>()
+                .len() as i64;
+            builder.push_bind(distinct_labels);
+        }
+        builder.push(" ORDER BY t.position ASC, t.created_at DESC");
+
+        builder.build_query_as::<Task>().fetch_all(pool).await
+    }
+
     pub async fn find_by_id(pool: &SqlitePool, id: Uuid) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as!(
             Task,
-            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
+            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
                FROM tasks
                WHERE id = $1"#,
             id
@@ -233,7 +347,7 @@ ORDER BY t.position ASC, t.created_at DESC"#,
     pub async fn find_by_rowid(pool: &SqlitePool, rowid: i64) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as!(
             Task,
-            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
+            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
                FROM tasks
                WHERE rowid = $1"#,
             rowid
@@ -252,9 +366,9 @@ ORDER BY t.position ASC, t.created_at DESC"#,
         let position = data.position.unwrap_or(0);
         sqlx::query_as!(
             Task,
-            r#"INSERT INTO tasks (id, project_id, title, description, status, priority, position, parent_workspace_id)
-               VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-               RETURNING id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>""#,
+            r#"INSERT INTO tasks (id, project_id, title, description, status, priority, position, parent_workspace_id, parent_task_id)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+               RETURNING id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>""#,
             task_id,
             data.project_id,
             data.title,
@@ -262,7 +376,8 @@ ORDER BY t.position ASC, t.created_at DESC"#,
             status,
             priority,
             position,
-            data.parent_workspace_id
+            data.parent_workspace_id,
+            data.parent_task_id
         )
         .fetch_one(pool)
         .await
@@ -285,7 +400,7 @@ ORDER BY t.position ASC, t.created_at DESC"#,
             r#"UPDATE tasks
                SET title = $3, description = $4, status = $5, priority = $6, position = $7, parent_workspace_id = $8
                WHERE id = $1 AND project_id = $2
-               RETURNING id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>""#,
+               RETURNING id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>""#,
             id,
             project_id,
             title,
@@ -408,7 +523,7 @@ ORDER BY t.position ASC, t.created_at DESC"#,
         // Find only child tasks that have this workspace as their parent
         sqlx::query_as!(
             Task,
-            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
+            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
                FROM tasks
                WHERE parent_workspace_id = $1
                ORDER BY position ASC, created_at DESC"#,
@@ -418,6 +533,136 @@ ORDER BY t.position ASC, t.created_at DESC"#,
         .await
     }
 
+    /// Direct subtasks of a task
+    pub async fn find_children(pool: &SqlitePool, task_id: Uuid) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Task,
+            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
+               FROM tasks
+               WHERE parent_task_id = $1
+               ORDER BY position ASC, created_at ASC"#,
+            task_id,
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Tasks of a project that are not a subtask of another task
+    pub async fn find_roots(pool: &SqlitePool, project_id: Uuid) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Task,
+            r#"SELECT id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>"
+               FROM tasks
+               WHERE project_id = $1 AND parent_task_id IS NULL
+               ORDER BY position ASC, created_at DESC"#,
+            project_id,
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Check that `parent_task_id` can become the parent of task `task_id` in
+    /// `project_id`: it must exist in the same project and must not be the task
+    /// itself or one of its subtasks (directly or further down).
+    pub async fn validate_parent(
+        pool: &SqlitePool,
+        task_id: Uuid,
+        project_id: Uuid,
+        parent_task_id: Uuid,
+    ) -> Result<(), TaskParentError> {
+        if parent_task_id == task_id {
+            return Err(TaskParentError::SelfParent);
+        }
+        let parent = Self::find_by_id(pool, parent_task_id)
+            .await?
+            .ok_or(TaskParentError::ParentNotFound(parent_task_id))?;
+        if parent.project_id != project_id {
+            return Err(TaskParentError::OutsideProject(parent_task_id));
+        }
+
+        // Walk up from the new parent; reaching the task means the parent is one of its subtasks
+        let is_descendant = sqlx::query_scalar!(
+            r#"WITH RECURSIVE ancestors(id) AS (
+                   SELECT parent_task_id FROM tasks WHERE id = $1
+                   UNION
+                   SELECT t.parent_task_id FROM tasks t JOIN ancestors a ON t.id = a.id
+               )
+               SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2) as "is_descendant!: bool""#,
+            parent_task_id,
+            task_id
+        )
+        .fetch_one(pool)
+        .await?;
+        if is_descendant {
+            return Err(TaskParentError::Cycle(parent_task_id));
+        }
+
+        Ok(())
+    }
+
+    /// Make a task a subtask of `parent_task_id`, or a top-level task with `None`
+    pub async fn set_parent(
+        pool: &SqlitePool,
+        task: &Task,
+        parent_task_id: Option<Uuid>,
+    ) -> Result<Self, TaskParentError> {
+        if let Some(parent_task_id) = parent_task_id {
+            Self::validate_parent(pool, task.id, task.project_id, parent_task_id).await?;
+        }
+
+        Ok(sqlx::query_as!(
+            Task,
+            r#"UPDATE tasks
+               SET parent_task_id = $2, updated_at = CURRENT_TIMESTAMP
+               WHERE id = $1
+               RETURNING id as "id!: Uuid", project_id as "project_id!: Uuid", title, description, status as "status!: TaskStatus", priority as "priority!: TaskPriority", position as "position!: i32", parent_workspace_id as "parent_workspace_id: Uuid", parent_task_id as "parent_task_id: Uuid", created_at as "created_at!: DateTime<Utc>", updated_at as "updated_at!: DateTime<Utc>""#,
+            task.id,
+            parent_task_id
+        )
+        .fetch_one(pool)
+        .await?)
+    }
+
+    /// Count a project's tasks by status, and the open ones blocked by unfinished
+    /// dependencies. Done and cancelled tasks are never blocked. A cancelled
+    /// dependency still blocks, as it does for `TaskDependency::are_dependencies_met`,
+    /// until the dependency is removed.
+    pub async fn progress_summary(
+        pool: &SqlitePool,
+        project_id: Uuid,
+    ) -> Result<ProgressSummary, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"SELECT COUNT(*) as "total_tasks!: i64",
+                      COALESCE(SUM(t.status = 'todo'), 0) as "todo!: i64",
+                      COALESCE(SUM(t.status = 'inprogress'), 0) as "in_progress!: i64",
+                      COALESCE(SUM(t.status = 'inreview'), 0) as "in_review!: i64",
+                      COALESCE(SUM(t.status = 'done'), 0) as "done!: i64",
+                      COALESCE(SUM(t.status = 'cancelled'), 0) as "cancelled!: i64",
+                      COALESCE(SUM(t.status NOT IN ('done', 'cancelled') AND EXISTS (
+                          SELECT 1
+                          FROM task_dependencies td
+                          JOIN tasks dep ON dep.id = td.depends_on_task_id
+                          WHERE td.task_id = t.id AND dep.status != 'done'
+                      )), 0) as "blocked!: i64"
+               FROM tasks t
+               WHERE t.project_id = $1"#,
+            project_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(ProgressSummary {
+            total_tasks: row.total_tasks,
+            todo: row.todo,
+            in_progress: row.in_progress,
+            in_review: row.in_review,
+            done: row.done,
+            cancelled: row.cancelled,
+            blocked: row.blocked,
+            progress_percent: ProgressSummary::percent(row.total_tasks, row.done),
+        })
+    }
+
     pub async fn find_relationships_for_workspace(
         pool: &SqlitePool,
         workspace: &Workspace,
@@ -452,3 +697,172 @@ ORDER BY t.position ASC, t.created_at DESC"#,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::label::TaskDependency,
+        test_support::{insert_project, test_pool},
+    };
+
+    async fn create_task(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        title: &str,
+        parent_task_id: Option<Uuid>,
+    ) -> Task {
+        let data = CreateTask {
+            parent_task_id,
+            ..CreateTask::from_title_description(project_id, title.to_string(), None)
+        };
+        Task::create(pool, &data, Uuid::new_v4()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_find_children_and_roots() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let epic = create_task(&pool, project_id, "Epic", None).await;
+        let first = create_task(&pool, project_id, "First", Some(epic.id)).await;
+        let second = create_task(&pool, project_id, "Second", Some(epic.id)).await;
+
+        let mut children: Vec<Uuid> = Task::find_children(&pool, epic.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        children.sort();
+        let mut expected = vec![first.id, second.id];
+        expected.sort();
+        assert_eq!(children, expected);
+
+        let roots = Task::find_roots(&pool, project_id).await.unwrap();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].id, epic.id);
+    }
+
+    #[tokio::test]
+    async fn test_set_parent_rejects_self_and_cycles() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let epic = create_task(&pool, project_id, "Epic", None).await;
+        let story = create_task(&pool, project_id, "Story", Some(epic.id)).await;
+        let subtask = create_task(&pool, project_id, "Subtask", Some(story.id)).await;
+
+        assert!(matches!(
+            Task::set_parent(&pool, &epic, Some(epic.id)).await,
+            Err(TaskParentError::SelfParent)
+        ));
+        assert!(matches!(
+            Task::set_parent(&pool, &epic, Some(subtask.id)).await,
+            Err(TaskParentError::Cycle(id)) if id == subtask.id
+        ));
+
+        // Moving a subtask up the hierarchy is fine
+        let moved = Task::set_parent(&pool, &subtask, Some(epic.id))
+            .await
+            .unwrap();
+        assert_eq!(moved.parent_task_id, Some(epic.id));
+    }
+
+    #[tokio::test]
+    async fn test_set_parent_rejects_task_of_other_project() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let other_project_id = insert_project(&pool).await;
+        let task = create_task(&pool, project_id, "Task", None).await;
+        let other = create_task(&pool, other_project_id, "Other", None).await;
+
+        assert!(matches!(
+            Task::set_parent(&pool, &task, Some(other.id)).await,
+            Err(TaskParentError::OutsideProject(id)) if id == other.id
+        ));
+        assert!(matches!(
+            Task::set_parent(&pool, &task, Some(Uuid::new_v4())).await,
+            Err(TaskParentError::ParentNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_progress_percent() {
+        assert_eq!(ProgressSummary::percent(0, 0), 0.0);
+        assert_eq!(ProgressSummary::percent(10, 0), 0.0);
+        assert_eq!(ProgressSummary::percent(4, 1), 25.0);
+        assert_eq!(ProgressSummary::percent(10, 10), 100.0);
+        let third = ProgressSummary::percent(3, 1);
+        assert!(
+            (third - 33.333333).abs() < 0.001,
+            "Expected ~33.33, got {}",
+            third
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_summary_counts_statuses_and_blocked_tasks() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let done = create_task(&pool, project_id, "Done", None).await;
+        let todo = create_task(&pool, project_id, "Todo", None).await;
+        let blocked = create_task(&pool, project_id, "Blocked", None).await;
+        let blocked_in_progress = create_task(&pool, project_id, "Blocked in progress", None).await;
+        let in_review = create_task(&pool, project_id, "In review", None).await;
+        let done_with_open_dep = create_task(&pool, project_id, "Done early", None).await;
+        let cancelled_with_open_dep = create_task(&pool, project_id, "Dropped", None).await;
+        let after_cancelled = create_task(&pool, project_id, "After dropped", None).await;
+        for (task, status) in [
+            (&done, TaskStatus::Done),
+            (&blocked_in_progress, TaskStatus::InProgress),
+            (&in_review, TaskStatus::InReview),
+            (&done_with_open_dep, TaskStatus::Done),
+            (&cancelled_with_open_dep, TaskStatus::Cancelled),
+        ] {
+            Task::update_status(&pool, task.id, status).await.unwrap();
+        }
+        for (task, depends_on) in [
+            (&todo, &done),
+            (&blocked, &done),
+            (&blocked, &todo),
+            (&blocked_in_progress, &blocked),
+            (&in_review, &done),
+            (&done_with_open_dep, &todo),
+            (&cancelled_with_open_dep, &todo),
+            // Blocked until the dependency on the cancelled task is removed
+            (&after_cancelled, &cancelled_with_open_dep),
+        ] {
+            TaskDependency::create(&pool, task.id, depends_on.id)
+                .await
+                .unwrap();
+        }
+        // Another project's tasks are not counted
+        let other_project_id = insert_project(&pool).await;
+        create_task(&pool, other_project_id, "Elsewhere", None).await;
+
+        let summary = Task::progress_summary(&pool, project_id).await.unwrap();
+
+        assert_eq!(
+            summary,
+            ProgressSummary {
+                total_tasks: 8,
+                todo: 3,
+                in_progress: 1,
+                in_review: 1,
+                done: 2,
+                cancelled: 1,
+                blocked: 3,
+                progress_percent: ProgressSummary::percent(8, 2),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_summary_of_empty_project() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+
+        let summary = Task::progress_summary(&pool, project_id).await.unwrap();
+
+        assert_eq!(summary, ProgressSummary::default());
+    }
+}
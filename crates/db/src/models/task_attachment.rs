@@ -118,6 +118,55 @@ impl TaskAttachment {
         .await
     }
 
+    /// Number of attachments sharing a stored file (uploads are deduplicated by hash)
+    pub async fn count_by_file_path(
+        pool: &SqlitePool,
+        file_path: &str,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM task_attachments WHERE file_path = $1"#,
+            file_path
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Distinct files still stored in the flat legacy layout (no subdirectory),
+    /// with their content hash. Files without a hash are not included.
+    pub async fn legacy_files(pool: &SqlitePool) -> Result<Vec<(String, String)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"SELECT DISTINCT file_path as "file_path!", sha256 as "sha256!"
+               FROM task_attachments
+               WHERE sha256 IS NOT NULL AND instr(file_path, '/') = 0"#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows.into_iter().map(|r| (r.file_path, r.sha256)).collect())
+    }
+
+    /// Point every attachment stored at `old_file_path` to `new_file_path`
+    pub async fn update_file_path(
+        pool: &SqlitePool,
+        old_file_path: &str,
+        new_file_path: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE task_attachments SET file_path = $1 WHERE file_path = $2",
+            new_file_path,
+            old_file_path
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Distinct files on disk referenced by any attachment
+    pub async fn all_file_paths(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar!(r#"SELECT DISTINCT file_path as "file_path!" FROM task_attachments"#)
+            .fetch_all(pool)
+            .await
+    }
+
     /// Delete an attachment by ID
     pub async fn delete(pool: &SqlitePool, id: Uuid) -> Result<(), sqlx::Error> {
         sqlx::query!(r#"DELETE FROM task_attachments WHERE id = $1"#, id)
@@ -134,3 +183,128 @@ impl TaskAttachment {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, insert_project, test_pool};
+
+    async fn insert_task(pool: &SqlitePool) -> Uuid {
+        let project_id = insert_project(pool).await;
+        test_support::insert_task(pool, project_id, "Task").await
+    }
+
+    fn attachment(task_id: Uuid, file_name: &str, file_path: &str) -> CreateTaskAttachment {
+        CreateTaskAttachment {
+            task_id,
+            file_name: file_name.to_string(),
+            file_path: file_path.to_string(),
+            mime_type: "text/plain".to_string(),
+            file_size: 5,
+            sha256: Some("abc123".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_by_sha256_returns_stored_file_for_reuse() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+        TaskAttachment::create(&pool, &attachment(task_id, "notes.txt", "stored.txt"))
+            .await
+            .unwrap();
+
+        let existing = TaskAttachment::find_by_sha256(&pool, "abc123")
+            .await
+            .unwrap()
+            .unwrap();
+        let reused = TaskAttachment::create(
+            &pool,
+            &attachment(insert_task(&pool).await, "copy.txt", &existing.file_path),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reused.file_path, "stored.txt");
+        assert_eq!(reused.file_name, "copy.txt");
+        assert!(
+            TaskAttachment::find_by_sha256(&pool, "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_legacy_files_are_relocated_by_path() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+        for name in ["a.txt", "b.txt"] {
+            TaskAttachment::create(&pool, &attachment(task_id, name, "legacy.txt"))
+                .await
+                .unwrap();
+        }
+        TaskAttachment::create(&pool, &attachment(task_id, "c.txt", "ab/c1/abc123.txt"))
+            .await
+            .unwrap();
+        let mut unhashed = attachment(task_id, "d.txt", "unhashed.txt");
+        unhashed.sha256 = None;
+        TaskAttachment::create(&pool, &unhashed).await.unwrap();
+
+        assert_eq!(
+            TaskAttachment::legacy_files(&pool).await.unwrap(),
+            vec![("legacy.txt".to_string(), "abc123".to_string())]
+        );
+
+        let updated = TaskAttachment::update_file_path(&pool, "legacy.txt", "ab/c1/abc123.txt")
+            .await
+            .unwrap();
+        assert_eq!(updated, 2);
+        assert!(
+            TaskAttachment::legacy_files(&pool)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            TaskAttachment::count_by_file_path(&pool, "ab/c1/abc123.txt")
+                .await
+                .unwrap(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_by_file_path_tracks_remaining_references() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+        let first = TaskAttachment::create(&pool, &attachment(task_id, "a.txt", "stored.txt"))
+            .await
+            .unwrap();
+        let second = TaskAttachment::create(&pool, &attachment(task_id, "b.txt", "stored.txt"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            TaskAttachment::count_by_file_path(&pool, "stored.txt")
+                .await
+                .unwrap(),
+            2
+        );
+
+        TaskAttachment::delete(&pool, first.id).await.unwrap();
+        assert_eq!(
+            TaskAttachment::count_by_file_path(&pool, "stored.txt")
+                .await
+                .unwrap(),
+            1
+        );
+
+        TaskAttachment::delete(&pool, second.id).await.unwrap();
+        assert_eq!(
+            TaskAttachment::count_by_file_path(&pool, "stored.txt")
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool, Type};
+use strum_macros::{Display, EnumString};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Who wrote a task comment
+#[derive(
+    Debug, Clone, Copy, Type, Serialize, Deserialize, PartialEq, TS, EnumString, Display, Default,
+)]
+#[sqlx(type_name = "task_comment_author", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TaskCommentAuthor {
+    /// Written in the UI
+    #[default]
+    User,
+    /// Written by an agent through the `add_task_comment` MCP tool
+    Agent,
+}
+
+/// A note left on a task
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct TaskComment {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub author: TaskCommentAuthor,
+    pub content: String,
+    #[ts(type = "Date")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Data for creating a new task comment
+#[derive(Debug, Clone, Deserialize, TS)]
+pub struct CreateTaskComment {
+    pub task_id: Uuid,
+    pub author: TaskCommentAuthor,
+    pub content: String,
+}
+
+impl TaskComment {
+    /// Create a new task comment
+    pub async fn create(pool: &SqlitePool, data: &CreateTaskComment) -> Result<Self, sqlx::Error> {
+        let id = Uuid::new_v4();
+        sqlx::query_as!(
+            TaskComment,
+            r#"INSERT INTO task_comments (id, task_id, author, content)
+               VALUES ($1, $2, $3, $4)
+               RETURNING id as "id!: Uuid",
+                         task_id as "task_id!: Uuid",
+                         author as "author!: TaskCommentAuthor",
+                         content,
+                         created_at as "created_at!: DateTime<Utc>""#,
+            id,
+            data.task_id,
+            data.author,
+            data.content,
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Find all comments on a task, oldest first
+    pub async fn find_by_task_id(
+        pool: &SqlitePool,
+        task_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            TaskComment,
+            r#"SELECT id as "id!: Uuid",
+                      task_id as "task_id!: Uuid",
+                      author as "author!: TaskCommentAuthor",
+                      content,
+                      created_at as "created_at!: DateTime<Utc>"
+               FROM task_comments
+               WHERE task_id = $1
+               ORDER BY created_at, rowid"#,
+            task_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, insert_project, test_pool};
+
+    async fn insert_task(pool: &SqlitePool) -> Uuid {
+        let project_id = insert_project(pool).await;
+        test_support::insert_task(pool, project_id, "Task").await
+    }
+
+    #[tokio::test]
+    async fn test_find_by_task_id_returns_comments_in_order() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+        let other_task_id = insert_task(&pool).await;
+
+        for (task, author, content) in [
+            (task_id, TaskCommentAuthor::User, "First"),
+            (other_task_id, TaskCommentAuthor::User, "Elsewhere"),
+            (task_id, TaskCommentAuthor::Agent, "Second"),
+        ] {
+            TaskComment::create(
+                &pool,
+                &CreateTaskComment {
+                    task_id: task,
+                    author,
+                    content: content.to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let comments = TaskComment::find_by_task_id(&pool, task_id).await.unwrap();
+        let contents: Vec<_> = comments.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(contents, vec!["First", "Second"]);
+        assert_eq!(comments[1].author, TaskCommentAuthor::Agent);
+    }
+
+    #[tokio::test]
+    async fn test_comments_are_deleted_with_their_task() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+        TaskComment::create(
+            &pool,
+            &CreateTaskComment {
+                task_id,
+                author: TaskCommentAuthor::User,
+                content: "Note".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        sqlx::query("DELETE FROM tasks WHERE id = $1")
+            .bind(task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let comments = TaskComment::find_by_task_id(&pool, task_id).await.unwrap();
+        assert!(comments.is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool, Type, types::Json};
+use strum_macros::{Display, EnumString};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Who requested a task review
+#[derive(
+    Debug, Clone, Copy, Type, Serialize, Deserialize, PartialEq, TS, EnumString, Display, Default,
+)]
+#[sqlx(type_name = "task_review_source", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TaskReviewSource {
+    /// Requested from the UI
+    #[default]
+    User,
+    /// Requested by an agent through the `request_pm_review` MCP tool
+    Agent,
+}
+
+/// A review requested for a task, with the prompt and checklist it was given
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, TS)]
+pub struct TaskReview {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub prompt: String,
+    #[ts(type = "Array<string>")]
+    pub checklist: Json<Vec<String>>,
+    pub source: TaskReviewSource,
+    #[ts(type = "Date")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Data for recording a new task review
+#[derive(Debug, Clone, Deserialize, TS)]
+pub struct CreateTaskReview {
+    pub task_id: Uuid,
+    pub prompt: String,
+    pub checklist: Vec<String>,
+    pub source: TaskReviewSource,
+}
+
+impl TaskReview {
+    /// Record a new task review
+    pub async fn create(pool: &SqlitePool, data: &CreateTaskReview) -> Result<Self, sqlx::Error> {
+        let id = Uuid::new_v4();
+        let checklist = Json(&data.checklist);
+        sqlx::query_as!(
+            TaskReview,
+            r#"INSERT INTO task_reviews (id, task_id, prompt, checklist, source)
+               VALUES ($1, $2, $3, $4, $5)
+               RETURNING id as "id!: Uuid",
+                         task_id as "task_id!: Uuid",
+                         prompt,
+                         checklist as "checklist!: Json<Vec<String>>",
+                         source as "source!: TaskReviewSource",
+                         created_at as "created_at!: DateTime<Utc>""#,
+            id,
+            data.task_id,
+            data.prompt,
+            checklist,
+            data.source,
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Find all reviews of a task, newest first
+    pub async fn find_by_task_id(
+        pool: &SqlitePool,
+        task_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            TaskReview,
+            r#"SELECT id as "id!: Uuid",
+                      task_id as "task_id!: Uuid",
+                      prompt,
+                      checklist as "checklist!: Json<Vec<String>>",
+                      source as "source!: TaskReviewSource",
+                      created_at as "created_at!: DateTime<Utc>"
+               FROM task_reviews
+               WHERE task_id = $1
+               ORDER BY created_at DESC, rowid DESC"#,
+            task_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, insert_project, test_pool};
+
+    async fn insert_task(pool: &SqlitePool) -> Uuid {
+        let project_id = insert_project(pool).await;
+        test_support::insert_task(pool, project_id, "Task").await
+    }
+
+    fn review(task_id: Uuid, prompt: &str) -> CreateTaskReview {
+        CreateTaskReview {
+            task_id,
+            prompt: prompt.to_string(),
+            checklist: vec!["Test coverage".to_string(), "Edge cases".to_string()],
+            source: TaskReviewSource::Agent,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_round_trips_checklist() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+
+        let created = TaskReview::create(&pool, &review(task_id, "Review it"))
+            .await
+            .unwrap();
+
+        let reviews = TaskReview::find_by_task_id(&pool, task_id).await.unwrap();
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].id, created.id);
+        assert_eq!(reviews[0].prompt, "Review it");
+        assert_eq!(reviews[0].checklist.0, vec!["Test coverage", "Edge cases"]);
+        assert_eq!(reviews[0].source, TaskReviewSource::Agent);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_task_id_returns_newest_first() {
+        let pool = test_pool().await;
+        let task_id = insert_task(&pool).await;
+        let other_task_id = insert_task(&pool).await;
+
+        for (task, prompt) in [
+            (task_id, "First"),
+            (other_task_id, "Elsewhere"),
+            (task_id, "Second"),
+        ] {
+            TaskReview::create(&pool, &review(task, prompt))
+                .await
+                .unwrap();
+        }
+
+        let reviews = TaskReview::find_by_task_id(&pool, task_id).await.unwrap();
+        let prompts: Vec<_> = reviews.iter().map(|r| r.prompt.as_str()).collect();
+        assert_eq!(prompts, vec!["Second", "First"]);
+    }
+}
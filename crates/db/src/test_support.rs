@@ -0,0 +1,80 @@
+//! Fixtures for tests that need a migrated database. Available to this
+//! crate's tests and, through the `test-support` feature, to other crates'.
+
+use chrono::Utc;
+use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use uuid::Uuid;
+
+use crate::models::{
+    label::Label,
+    task::{Task, TaskPriority, TaskStatus},
+};
+
+/// A fresh in-memory database with every migration applied
+pub async fn test_pool() -> SqlitePool {
+    // One connection, as each connection to `sqlite::memory:` is its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
+
+/// Insert a project named "Project", returning its id
+pub async fn insert_project(pool: &SqlitePool) -> Uuid {
+    let project_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO projects (id, name) VALUES ($1, 'Project')")
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    project_id
+}
+
+/// Insert a task titled `title` into the project, returning its id
+pub async fn insert_task(pool: &SqlitePool, project_id: Uuid, title: &str) -> Uuid {
+    let task_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO tasks (id, project_id, title) VALUES ($1, $2, $3)")
+        .bind(task_id)
+        .bind(project_id)
+        .bind(title)
+        .execute(pool)
+        .await
+        .unwrap();
+    task_id
+}
+
+/// An unsaved medium-priority task, for tests of code that works on loaded
+/// tasks. Override other fields with struct update syntax.
+pub fn task(title: &str, status: TaskStatus) -> Task {
+    let now = Utc::now();
+    Task {
+        id: Uuid::new_v4(),
+        project_id: Uuid::nil(),
+        title: title.to_string(),
+        description: None,
+        status,
+        priority: TaskPriority::Medium,
+        position: 0,
+        parent_workspace_id: None,
+        parent_task_id: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// An unsaved label named `name`
+pub fn label(name: &str) -> Label {
+    let now = Utc::now();
+    Label {
+        id: Uuid::new_v4(),
+        project_id: Uuid::nil(),
+        name: name.to_string(),
+        color: "#6366f1".to_string(),
+        executor: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
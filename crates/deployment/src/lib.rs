@@ -27,6 +27,7 @@ use services::services::{
     filesystem_watcher::FilesystemWatcherError,
     git::{GitService, GitServiceError},
     image::{ImageError, ImageService},
+    pm_chat_retention::PmChatRetentionService,
     pr_monitor::PrMonitorService,
     project::ProjectService,
     queued_message::QueuedMessageService,
@@ -132,6 +133,10 @@ pub trait Deployment: Clone + Send + Sync + 'static {
         PrMonitorService::spawn(db, analytics).await
     }
 
+    async fn spawn_pm_chat_retention_service(&self) -> tokio::task::JoinHandle<()> {
+        PmChatRetentionService::spawn(self.db().clone()).await
+    }
+
     async fn track_if_analytics_allowed(&self, event_name: &str, properties: Value) {
         let analytics_enabled = self.config().read().await.analytics_enabled;
         // Track events unless user has explicitly opted out
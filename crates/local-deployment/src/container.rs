@@ -20,7 +20,7 @@ use db::{
         repo::Repo,
         scratch::{DraftFollowUpData, Scratch, ScratchType},
         session::{Session, SessionError},
-        task::{Task, TaskStatus},
+        task::TaskStatus,
         workspace::Workspace,
         workspace_repo::WorkspaceRepo,
     },
@@ -49,6 +49,7 @@ use services::services::{
     image::ImageService,
     notification::NotificationService,
     queued_message::QueuedMessageService,
+    webhooks,
     workspace_manager::{RepoWorkspaceInput, WorkspaceManager},
 };
 use tokio::{sync::RwLock, task::JoinHandle};
@@ -925,6 +926,10 @@ impl ContainerService for LocalContainerService {
         self.config.read().await.git_branch_prefix.clone()
     }
 
+    async fn docs_scan_ignore(&self) -> Vec<String> {
+        self.config.read().await.docs_scan_ignore.clone()
+    }
+
     fn workspace_to_current_dir(&self, workspace: &Workspace) -> PathBuf {
         PathBuf::from(workspace.container_ref.clone().unwrap_or_default())
     }
@@ -1229,7 +1234,7 @@ impl ContainerService for LocalContainerService {
                 ExecutionProcessRunReason::DevServer
             )
             && let Err(e) =
-                Task::update_status(&self.db.pool, ctx.task.id, TaskStatus::InReview).await
+                webhooks::update_task_status(&self.db.pool, ctx.task.id, TaskStatus::InReview).await
         {
             tracing::error!("Failed to update task status to InReview: {e}");
         }
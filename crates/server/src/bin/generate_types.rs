@@ -16,6 +16,8 @@ fn generate_types_content() -> String {
         db::models::project::UpdateProject::decl(),
         db::models::project::SearchResult::decl(),
         db::models::project::SearchMatchType::decl(),
+        db::models::project_webhook::ProjectWebhook::decl(),
+        db::models::project_webhook::WebhookDeadLetter::decl(),
         db::models::repo::Repo::decl(),
         db::models::repo::UpdateRepo::decl(),
         db::models::project_repo::ProjectRepo::decl(),
@@ -30,12 +32,15 @@ fn generate_types_content() -> String {
         db::models::task::TaskPriority::decl(),
         db::models::task::Task::decl(),
         db::models::task::TaskWithAttemptStatus::decl(),
+        db::models::task::ProgressSummary::decl(),
         db::models::task::TaskRelationships::decl(),
         db::models::task::CreateTask::decl(),
         db::models::task::UpdateTask::decl(),
         db::models::label::Label::decl(),
         db::models::label::CreateLabel::decl(),
         db::models::label::UpdateLabel::decl(),
+        db::models::label::LabelWithCount::decl(),
+        db::models::label::LabelMergeResult::decl(),
         db::models::label::TaskLabel::decl(),
         db::models::label::TaskDependency::decl(),
         db::models::label::CreateTaskDependency::decl(),
@@ -44,13 +49,47 @@ fn generate_types_content() -> String {
         db::models::pm_conversation::PmMessageRole::decl(),
         db::models::pm_conversation::CreatePmConversation::decl(),
         db::models::pm_conversation::CreatePmAttachment::decl(),
+        db::models::pm_conversation::PmModelUsage::decl(),
+        db::models::pm_conversation::PmRoleCount::decl(),
+        db::models::pm_docs_history::PmDocsSource::decl(),
+        db::models::pm_docs_history::PmDocsVersion::decl(),
+        db::models::pm_message_task::PmMessageTask::decl(),
+        db::models::task_comment::TaskCommentAuthor::decl(),
+        db::models::task_comment::TaskComment::decl(),
+        db::models::task_review::TaskReviewSource::decl(),
+        db::models::task_review::TaskReview::decl(),
         server::routes::pm_chat::SendMessageRequest::decl(),
+        server::routes::pm_chat::PmChatMessage::decl(),
         server::routes::pm_chat::PmChatResponse::decl(),
         server::routes::pm_chat::UpdatePmDocsRequest::decl(),
+        server::routes::pm_chat::PmDocsConflict::decl(),
+        server::routes::pm_chat::UpdatePmDocsResponse::decl(),
+        server::routes::pm_chat::RenderedPmDocs::decl(),
         server::routes::pm_chat::PmChatAgent::decl(),
         server::routes::pm_chat::AiChatRequest::decl(),
+        server::routes::pm_chat::RegenerateAiChatRequest::decl(),
         server::routes::pm_chat::AvailablePmChatAgentsResponse::decl(),
         server::routes::pm_chat::PmChatAgentInfo::decl(),
+        server::routes::pm_chat::McpConfigMode::decl(),
+        server::routes::pm_chat::ModelInfo::decl(),
+        server::routes::pm_chat::AvailableModelsResponse::decl(),
+        server::routes::pm_chat::UpdateMessageRequest::decl(),
+        server::routes::pm_chat::SetMessagePinnedRequest::decl(),
+        server::routes::pm_chat::MessageToTaskRequest::decl(),
+        server::routes::pm_chat::UpdateChatRetentionRequest::decl(),
+        server::routes::pm_chat::PruneChatResponse::decl(),
+        server::routes::pm_chat::RestoreChatResponse::decl(),
+        server::routes::pm_chat::PurgeChatResponse::decl(),
+        server::routes::pm_chat::TextRange::decl(),
+        server::routes::pm_chat::PmChatSearchResult::decl(),
+        server::routes::pm_chat::PmChatUsageResponse::decl(),
+        server::routes::pm_chat::PmChatStats::decl(),
+        server::routes::pm_chat::PmChatContextPreview::decl(),
+        server::routes::pm_chat::AgentSelftestReport::decl(),
+        server::routes::pm_chat::CancelAiChatRequest::decl(),
+        server::routes::pm_chat::CancelAiChatResponse::decl(),
+        server::routes::pm_chat::AiChatSession::decl(),
+        server::routes::pm_chat::TaskSummaryLocale::decl(),
         db::models::scratch::DraftFollowUpData::decl(),
         db::models::scratch::DraftWorkspaceData::decl(),
         db::models::scratch::DraftWorkspaceRepo::decl(),
@@ -109,6 +148,7 @@ fn generate_types_content() -> String {
         utils::api::projects::RemoteProject::decl(),
         utils::api::projects::ListProjectsResponse::decl(),
         utils::api::projects::RemoteProjectMembersResponse::decl(),
+        server::routes::labels::MergeLabelsRequest::decl(),
         server::routes::projects::CreateRemoteProjectRequest::decl(),
         server::routes::projects::LinkToExistingRequest::decl(),
         server::routes::repo::RegisterRepoRequest::decl(),
@@ -136,6 +176,21 @@ fn generate_types_content() -> String {
         server::routes::task_attempts::OpenEditorRequest::decl(),
         server::routes::task_attempts::OpenEditorResponse::decl(),
         server::routes::tasks::CreateAndStartTaskRequest::decl(),
+        server::routes::tasks::QuickTaskRequest::decl(),
+        server::routes::tasks::QuickTaskResponse::decl(),
+        services::services::task_inference::LabelSuggestion::decl(),
+        services::services::task_inference::ExecutorSuggestion::decl(),
+        services::services::attachment_gc::AttachmentGcReport::decl(),
+        services::services::webhooks::WebhookEventType::decl(),
+        services::services::webhooks::WebhookEvent::decl(),
+        services::services::events::BoardEvent::decl(),
+        server::routes::webhooks::SetProjectWebhookRequest::decl(),
+        server::routes::tasks::SearchTasksResponse::decl(),
+        server::routes::tasks::OrderedTaskSummary::decl(),
+        server::routes::tasks::CriticalPathResponse::decl(),
+        server::routes::tasks::SetTaskParentRequest::decl(),
+        server::routes::tasks::AddTaskCommentRequest::decl(),
+        server::routes::tasks::CreateTaskReviewRequest::decl(),
         server::routes::tasks::TaskPositionUpdate::decl(),
         server::routes::tasks::BatchUpdatePositionsRequest::decl(),
         server::routes::task_attempts::pr::CreatePrApiRequest::decl(),
@@ -179,6 +234,8 @@ fn generate_types_content() -> String {
         services::services::config::UiLanguage::decl(),
         services::services::config::ShowcaseState::decl(),
         services::services::config::SendMessageShortcut::decl(),
+        services::services::config::PmAttachmentPolicy::decl(),
+        services::services::config::PmChatSettings::decl(),
         services::services::git::GitBranch::decl(),
         services::services::queued_message::QueuedMessage::decl(),
         services::services::queued_message::QueueStatus::decl(),
@@ -62,15 +62,23 @@ fn main() -> anyhow::Result<()> {
                 url
             };
 
-            let service = TaskServer::new(&base_url)
-                .init()
-                .await
-                .serve(stdio())
-                .await
-                .map_err(|e| {
-                    tracing::error!("serving error: {:?}", e);
-                    e
+            // Task links point at the backend, which also serves the UI, unless overridden
+            let mut server = TaskServer::new(&base_url);
+            if let Ok(app_url) = std::env::var("VIBE_APP_URL") {
+                tracing::info!("[MCP] Using app URL from VIBE_APP_URL: {}", app_url);
+                server = server.with_app_url(&app_url);
+            }
+            if let Ok(ttl) = std::env::var("VIBE_MCP_TAG_CACHE_TTL_SECS") {
+                let secs = ttl.parse::<u64>().map_err(|e| {
+                    anyhow::anyhow!("Invalid VIBE_MCP_TAG_CACHE_TTL_SECS value '{}': {}", ttl, e)
                 })?;
+                server = server.with_tag_cache_ttl(std::time::Duration::from_secs(secs));
+            }
+
+            let service = server.init().await.serve(stdio()).await.map_err(|e| {
+                tracing::error!("serving error: {:?}", e);
+                e
+            })?;
 
             service.waiting().await?;
             Ok(())
@@ -72,10 +72,16 @@ pub enum ApiError {
     Unauthorized,
     #[error("Bad request: {0}")]
     BadRequest(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
     #[error("Conflict: {0}")]
     Conflict(String),
     #[error("Forbidden: {0}")]
     Forbidden(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
     #[error(transparent)]
     CommandBuilder(#[from] CommandBuildError),
     #[error(transparent)]
@@ -129,6 +135,9 @@ impl IntoResponse for ApiError {
             ApiError::Container(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ContainerError"),
             ApiError::Executor(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ExecutorError"),
             ApiError::CommandBuilder(_) => (StatusCode::INTERNAL_SERVER_ERROR, "CommandBuildError"),
+            ApiError::Database(sqlx::Error::RowNotFound) => {
+                (StatusCode::NOT_FOUND, "NotFoundError")
+            }
             ApiError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "DatabaseError"),
             ApiError::Worktree(_) => (StatusCode::INTERNAL_SERVER_ERROR, "WorktreeError"),
             ApiError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ConfigError"),
@@ -180,8 +189,11 @@ impl IntoResponse for ApiError {
             },
             ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
             ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, "BadRequest"),
+            ApiError::NotFound(_) => (StatusCode::NOT_FOUND, "NotFoundError"),
             ApiError::Conflict(_) => (StatusCode::CONFLICT, "ConflictError"),
             ApiError::Forbidden(_) => (StatusCode::FORBIDDEN, "ForbiddenError"),
+            ApiError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "PayloadTooLargeError"),
+            ApiError::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, "TooManyRequestsError"),
             ApiError::Pty(err) => match err {
                 PtyError::SessionNotFound(_) => (StatusCode::NOT_FOUND, "PtyError"),
                 PtyError::SessionClosed => (StatusCode::GONE, "PtyError"),
@@ -262,12 +274,17 @@ impl IntoResponse for ApiError {
                 RemoteClientError::Url(_) => "Remote service URL is invalid.".to_string(),
             },
             ApiError::Unauthorized => "Unauthorized. Please sign in again.".to_string(),
-            ApiError::BadRequest(msg) => msg.clone(),
-            ApiError::Conflict(msg) => msg.clone(),
-            ApiError::Forbidden(msg) => msg.clone(),
+            ApiError::Database(sqlx::Error::RowNotFound) => "Not found".to_string(),
+            ApiError::BadRequest(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::PayloadTooLarge(msg)
+            | ApiError::TooManyRequests(msg) => msg.clone(),
             _ => format!("{}: {}", error_type, self),
         };
-        let response = ApiResponse::<()>::error(&error_message);
+        // `error_type` doubles as the stable code clients can match on
+        let response = ApiResponse::<()>::error_with_code(&error_message, error_type);
         (status_code, Json(response)).into_response()
     }
 }
@@ -347,3 +364,66 @@ impl From<ProjectRepoError> for ApiError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn response_parts(error: ApiError) -> (StatusCode, ApiResponse<()>) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_response_has_status_and_code() {
+        let cases = [
+            (
+                ApiError::NotFound("Attachment not found".to_string()),
+                StatusCode::NOT_FOUND,
+                "NotFoundError",
+            ),
+            (
+                ApiError::Database(sqlx::Error::RowNotFound),
+                StatusCode::NOT_FOUND,
+                "NotFoundError",
+            ),
+            (
+                ApiError::Forbidden("Attachment does not belong to this project".to_string()),
+                StatusCode::FORBIDDEN,
+                "ForbiddenError",
+            ),
+            (
+                ApiError::PayloadTooLarge("File too large".to_string()),
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "PayloadTooLargeError",
+            ),
+            (
+                ApiError::TooManyRequests("Wait for a response to finish".to_string()),
+                StatusCode::TOO_MANY_REQUESTS,
+                "TooManyRequestsError",
+            ),
+            (
+                ApiError::BadRequest("No file provided".to_string()),
+                StatusCode::BAD_REQUEST,
+                "BadRequest",
+            ),
+        ];
+
+        for (error, status, code) in cases {
+            let (actual_status, body) = response_parts(error).await;
+            assert_eq!(actual_status, status);
+            assert_eq!(body.code(), Some(code));
+            assert!(!body.is_success());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_response_keeps_message() {
+        let (_, body) = response_parts(ApiError::Forbidden("Not yours".to_string())).await;
+        assert_eq!(body.message(), Some("Not yours"));
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::{self, Error as AnyhowError};
 use deployment::{Deployment, DeploymentError};
 use server::{DeploymentImpl, routes};
-use services::services::container::ContainerService;
+use services::services::{attachment_layout, container::ContainerService};
 use sqlx::Error as SqlxError;
 use strip_ansi_escapes::strip;
 use thiserror::Error;
@@ -68,6 +68,16 @@ async fn main() -> Result<(), VibeKanbanError> {
         .await
         .map_err(DeploymentError::from)?;
     deployment.spawn_pr_monitor_service().await;
+    deployment.spawn_pm_chat_retention_service().await;
+    // Move attachment files still in the flat legacy layout into shard directories
+    let deployment_for_attachments = deployment.clone();
+    tokio::spawn(async move {
+        if let Err(e) =
+            attachment_layout::relocate_legacy_files(&deployment_for_attachments.db().pool).await
+        {
+            tracing::warn!("Failed to relocate legacy attachment files: {}", e);
+        }
+    });
     deployment
         .track_if_analytics_allowed("session_start", serde_json::json!({}))
         .await;
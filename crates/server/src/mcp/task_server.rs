@@ -1,13 +1,29 @@
-use std::{future::Future, str::FromStr};
+use std::{
+    collections::HashSet,
+    future::Future,
+    str::FromStr,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Utc};
 use db::models::{
+    label::Label,
     project::Project,
     repo::Repo,
     tag::Tag,
-    task::{CreateTask, Task, TaskStatus, TaskWithAttemptStatus, UpdateTask},
+    task::{
+        CreateTask, ProgressSummary, Task, TaskPriority, TaskStatus, TaskWithAttemptStatus,
+        UpdateTask,
+    },
+    task_comment::{TaskComment, TaskCommentAuthor},
+    task_review::{TaskReview, TaskReviewSource},
     workspace::{Workspace, WorkspaceContext},
 };
-use executors::{executors::BaseCodingAgent, profile::ExecutorProfileId};
+use executors::{
+    executors::{BaseCodingAgent, CodingAgent},
+    profile::ExecutorProfileId,
+};
 use regex::Regex;
 use rmcp::{
     ErrorData, ServerHandler,
@@ -19,11 +35,16 @@ use rmcp::{
 };
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json;
+use services::services::task_inference::{ExecutorSuggestion, LabelSuggestion};
+use strum::VariantNames;
+use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::routes::{
     containers::ContainerQuery,
+    pm_chat::{pm_docs_diff, replace_docs_section},
     task_attempts::{CreateTaskAttemptBody, WorkspaceRepoInput},
+    tasks::{CriticalPathResponse, OrderedTaskSummary},
 };
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -40,17 +61,117 @@ pub struct CreateTaskRequest {
     pub depends_on: Option<Vec<String>>,
     #[schemars(description = "If true, check for duplicate tasks before creating. Returns existing task if found.")]
     pub check_duplicate: Option<bool>,
+    #[schemars(description = "Title similarity (0.0-1.0) at which an existing task counts as a duplicate when check_duplicate is true. Defaults to 0.8.")]
+    pub similarity_threshold: Option<f64>,
     #[schemars(description = "Optional list of label IDs to attach to the task")]
     pub label_ids: Option<Vec<String>>,
+    #[schemars(description = "If true, validate and preview the task without creating it. Use it to confirm the plan with the user first.")]
+    pub dry_run: Option<bool>,
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
 pub struct CreateTaskResponse {
+    #[schemars(description = "The ID of the task; empty for a dry run that would create a new task")]
     pub task_id: String,
     #[schemars(description = "True if this is a new task, false if an existing duplicate was found")]
     pub is_new: bool,
     #[schemars(description = "Message about the task creation result")]
     pub message: Option<String>,
+    #[schemars(description = "The title of the task as stored")]
+    pub title: String,
+    #[schemars(description = "The current status of the task")]
+    pub status: String,
+    #[schemars(description = "The priority of the task")]
+    pub priority: String,
+    #[schemars(description = "Names of the labels attached to the task")]
+    pub labels: Vec<String>,
+    #[schemars(description = "Link to the task in the Vibe Kanban UI")]
+    pub url: String,
+    #[schemars(description = "The task that would be created, only set for a dry run")]
+    pub preview: Option<CreateTaskPreview>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct CreateTaskPreview {
+    #[schemars(description = "The title with @tag references expanded")]
+    pub title: String,
+    #[schemars(description = "The description with @tag references expanded")]
+    pub description: Option<String>,
+    #[schemars(description = "The resolved priority")]
+    pub priority: String,
+    #[schemars(description = "IDs of the tasks the task would depend on")]
+    pub depends_on: Vec<String>,
+    #[schemars(description = "Names of the labels that would be attached")]
+    pub labels: Vec<String>,
+}
+
+impl CreateTaskPreview {
+    fn new(
+        title: &str,
+        description: Option<&str>,
+        priority: Option<TaskPriority>,
+        depends_on: &[String],
+        label_ids: &[String],
+        project_labels: &[Label],
+    ) -> Self {
+        Self {
+            title: title.to_string(),
+            description: description.map(str::to_string),
+            priority: priority.unwrap_or_default().to_string(),
+            depends_on: depends_on.to_vec(),
+            labels: label_ids
+                .iter()
+                .filter_map(|id| project_labels.iter().find(|l| l.id.to_string() == *id))
+                .map(|l| l.name.clone())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct CreateTasksRequest {
+    #[schemars(description = "The ID of the project to create the tasks in. This is required!")]
+    pub project_id: Uuid,
+    #[schemars(description = "The tasks to create")]
+    pub tasks: Vec<BatchTaskInput>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct BatchTaskInput {
+    #[schemars(
+        description = "A temporary ID of your choice (e.g. 't1'), unique within the batch. Other tasks of the batch use it in 'depends_on'"
+    )]
+    pub temp_id: String,
+    #[schemars(description = "The title of the task")]
+    pub title: String,
+    #[schemars(description = "Optional description of the task")]
+    pub description: Option<String>,
+    #[schemars(
+        description = "Task priority: 'urgent', 'high', 'medium', or 'low'. Defaults to 'medium' if not specified."
+    )]
+    pub priority: Option<String>,
+    #[schemars(description = "Optional list of label IDs to attach to the task")]
+    pub label_ids: Option<Vec<Uuid>>,
+    #[schemars(
+        description = "Tasks this task depends on: temp_ids of tasks in this batch, or IDs of existing tasks"
+    )]
+    pub depends_on: Option<Vec<String>>,
+}
+
+/// A dependency of a task in a `create_tasks` batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchDependency {
+    /// Index of another task in the batch
+    Batch(usize),
+    Existing(Uuid),
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct CreateTasksResponse {
+    pub project_id: String,
+    #[schemars(description = "The real task ID created for each temp_id")]
+    pub task_ids: std::collections::BTreeMap<String, String>,
+    pub count: usize,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -62,17 +183,113 @@ pub struct GetProjectProgressRequest {
 #[derive(Debug, Serialize, schemars::JsonSchema)]
 pub struct GetProjectProgressResponse {
     #[schemars(description = "Total number of tasks in the project")]
-    pub total_tasks: i32,
+    pub total_tasks: i64,
     #[schemars(description = "Number of completed (done) tasks")]
-    pub completed_tasks: i32,
+    pub completed_tasks: i64,
     #[schemars(description = "Number of in-progress tasks")]
-    pub in_progress_tasks: i32,
+    pub in_progress_tasks: i64,
     #[schemars(description = "Number of blocked tasks (has incomplete dependencies)")]
-    pub blocked_tasks: i32,
+    pub blocked_tasks: i64,
     #[schemars(description = "Completion percentage (0-100)")]
-    pub progress_percent: f32,
+    pub progress_percent: f64,
     #[schemars(description = "Summary by status")]
-    pub status_summary: std::collections::HashMap<String, i32>,
+    pub status_summary: std::collections::HashMap<String, i64>,
+}
+
+impl GetProjectProgressResponse {
+    fn from_summary(summary: ProgressSummary) -> Self {
+        let status_summary = std::collections::HashMap::from([
+            (TaskStatus::Todo.to_string(), summary.todo),
+            (TaskStatus::InProgress.to_string(), summary.in_progress),
+            (TaskStatus::InReview.to_string(), summary.in_review),
+            (TaskStatus::Done.to_string(), summary.done),
+            (TaskStatus::Cancelled.to_string(), summary.cancelled),
+            ("blocked".to_string(), summary.blocked),
+        ]);
+        Self {
+            total_tasks: summary.total_tasks,
+            completed_tasks: summary.done,
+            in_progress_tasks: summary.in_progress,
+            blocked_tasks: summary.blocked,
+            progress_percent: summary.progress_percent,
+            status_summary,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetTaskOrderRequest {
+    #[schemars(description = "The ID of the project to order tasks for")]
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct OrderedTask {
+    #[schemars(description = "Position in the suggested execution order (1-based)")]
+    pub position: usize,
+    #[schemars(description = "The unique identifier of the task")]
+    pub id: String,
+    #[schemars(description = "The title of the task")]
+    pub title: String,
+    #[schemars(description = "Current status of the task")]
+    pub status: String,
+    #[schemars(description = "Task priority")]
+    pub priority: String,
+    #[schemars(description = "IDs of tasks that must be completed before this one")]
+    pub depends_on: Vec<String>,
+}
+
+impl OrderedTask {
+    fn from_summary(position: usize, task: OrderedTaskSummary) -> Self {
+        Self {
+            position,
+            id: task.id.to_string(),
+            title: task.title,
+            status: task.status.to_string(),
+            priority: task.priority.to_string(),
+            depends_on: task.depends_on.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct GetTaskOrderResponse {
+    #[schemars(description = "Tasks in dependency-safe order")]
+    pub tasks: Vec<OrderedTask>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetActionableTasksRequest {
+    #[schemars(description = "The ID of the project to find actionable tasks in")]
+    pub project_id: Uuid,
+    #[schemars(description = "Maximum number of tasks to return (default: 10, max: 100)")]
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct GetActionableTasksResponse {
+    #[schemars(
+        description = "Todo and in-progress tasks whose dependencies are all done, most urgent and oldest first"
+    )]
+    pub tasks: Vec<TaskSummary>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetCriticalPathRequest {
+    #[schemars(description = "The ID of the project to compute the critical path for")]
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct GetCriticalPathResponse {
+    #[schemars(
+        description = "Unfinished tasks on the longest dependency chain, in the order they must be done"
+    )]
+    pub tasks: Vec<OrderedTask>,
+    #[schemars(description = "Number of tasks on the critical path")]
+    pub length: usize,
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
@@ -104,18 +321,49 @@ pub struct McpRepoSummary {
     pub id: String,
     #[schemars(description = "The name of the repository")]
     pub name: String,
+    #[schemars(
+        description = "Filesystem path of the repository; null unless include_path was set"
+    )]
+    pub path: Option<String>,
+}
+
+/// Filesystem path of `repo`, only when the caller asked for it
+fn repo_path(repo: &Repo, include_path: Option<bool>) -> Option<String> {
+    include_path
+        .unwrap_or(false)
+        .then(|| repo.path.to_string_lossy().into_owned())
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct ListReposRequest {
     #[schemars(description = "The ID of the project to list repositories from")]
     pub project_id: Uuid,
+    #[schemars(
+        description = "Include each repository's filesystem path (default: false). Only request paths when you need them"
+    )]
+    pub include_path: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct GetRepoRequest {
     #[schemars(description = "The ID of the repository to retrieve")]
     pub repo_id: Uuid,
+    #[schemars(
+        description = "Include the repository's filesystem path (default: false). Only request it when you need it"
+    )]
+    pub include_path: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetReposRequest {
+    #[schemars(description = "The ID of the project the repositories belong to")]
+    pub project_id: Uuid,
+    #[schemars(description = "IDs of the repositories to retrieve")]
+    pub repo_ids: Vec<Uuid>,
+    #[schemars(
+        description = "Include each repository's filesystem path (default: false). Only request paths when you need them"
+    )]
+    pub include_path: Option<bool>,
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
@@ -126,6 +374,10 @@ pub struct RepoDetails {
     pub name: String,
     #[schemars(description = "The display name of the repository")]
     pub display_name: String,
+    #[schemars(
+        description = "Filesystem path of the repository; null unless include_path was set"
+    )]
+    pub path: Option<String>,
     #[schemars(description = "The setup script that runs when initializing a workspace")]
     pub setup_script: Option<String>,
     #[schemars(description = "The cleanup script that runs when tearing down a workspace")]
@@ -134,6 +386,28 @@ pub struct RepoDetails {
     pub dev_server_script: Option<String>,
 }
 
+impl RepoDetails {
+    fn from_repo(repo: Repo, include_path: Option<bool>) -> Self {
+        Self {
+            path: repo_path(&repo, include_path),
+            id: repo.id.to_string(),
+            name: repo.name,
+            display_name: repo.display_name,
+            setup_script: repo.setup_script,
+            cleanup_script: repo.cleanup_script,
+            dev_server_script: repo.dev_server_script,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct GetReposResponse {
+    pub repos: Vec<RepoDetails>,
+    pub count: usize,
+    #[schemars(description = "Requested IDs that are not repositories of the project")]
+    pub not_found: Vec<String>,
+}
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct UpdateSetupScriptRequest {
     #[schemars(description = "The ID of the repository to update")]
@@ -191,6 +465,30 @@ pub struct ListTasksRequest {
     pub status: Option<String>,
     #[schemars(description = "Maximum number of tasks to return (default: 50)")]
     pub limit: Option<i32>,
+    #[schemars(
+        description = "Only tasks created at or after this RFC3339 time, e.g. '2025-03-10T00:00:00Z'"
+    )]
+    pub created_after: Option<String>,
+    #[schemars(description = "Only tasks created before this RFC3339 time")]
+    pub created_before: Option<String>,
+    #[schemars(description = "Only tasks updated at or after this RFC3339 time")]
+    pub updated_after: Option<String>,
+}
+
+/// Parse an optional RFC3339 time filter of `list_tasks`
+fn parse_time_filter(name: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value.trim())
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|e| {
+                    format!(
+                        "Invalid {} '{}': expected an RFC3339 time ({})",
+                        name, value, e
+                    )
+                })
+        })
+        .transpose()
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
@@ -201,6 +499,12 @@ pub struct TaskSummary {
     pub title: String,
     #[schemars(description = "Current status of the task")]
     pub status: String,
+    #[schemars(description = "Priority of the task: 'urgent', 'high', 'medium', or 'low'")]
+    pub priority: String,
+    #[schemars(
+        description = "Order of the task in the project: lower positions come first. Change it with 'move_task'"
+    )]
+    pub position: i32,
     #[schemars(description = "When the task was created")]
     pub created_at: String,
     #[schemars(description = "When the task was last updated")]
@@ -209,22 +513,161 @@ pub struct TaskSummary {
     pub has_in_progress_attempt: Option<bool>,
     #[schemars(description = "Whether the last execution attempt failed")]
     pub last_attempt_failed: Option<bool>,
+    #[schemars(
+        description = "Completion of the task's subtasks, e.g. '3/5 subtasks done'. Absent if it has none"
+    )]
+    pub subtasks: Option<String>,
+}
+
+/// Subtask completion rollup shown in task summaries, `None` without subtasks
+fn subtask_progress(done: i64, total: i64) -> Option<String> {
+    (total > 0).then(|| format!("{}/{} subtasks done", done, total))
 }
 
 impl TaskSummary {
+    fn from_task(task: Task) -> Self {
+        Self {
+            id: task.id.to_string(),
+            title: task.title,
+            status: task.status.to_string(),
+            priority: task.priority.to_string(),
+            position: task.position,
+            created_at: task.created_at.to_rfc3339(),
+            updated_at: task.updated_at.to_rfc3339(),
+            has_in_progress_attempt: None,
+            last_attempt_failed: None,
+            subtasks: None,
+        }
+    }
+
     fn from_task_with_status(task: TaskWithAttemptStatus) -> Self {
         Self {
             id: task.id.to_string(),
             title: task.title.to_string(),
             status: task.status.to_string(),
+            priority: task.priority.to_string(),
+            position: task.position,
             created_at: task.created_at.to_rfc3339(),
             updated_at: task.updated_at.to_rfc3339(),
             has_in_progress_attempt: Some(task.has_in_progress_attempt),
             last_attempt_failed: Some(task.last_attempt_failed),
+            subtasks: subtask_progress(task.subtasks_done, task.subtask_count),
         }
     }
 }
 
+/// Spacing between task positions, leaving room to move tasks in between
+const TASK_POSITION_GAP: i32 = 1024;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct MoveTaskRequest {
+    #[schemars(description = "The ID of the task to move")]
+    pub task_id: Uuid,
+    #[schemars(
+        description = "Place the task directly before this task of the same project. Omit to move it to the end"
+    )]
+    pub before_task_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct MoveTaskResponse {
+    pub task_id: String,
+    #[schemars(description = "The new position of the task")]
+    pub position: i32,
+    #[schemars(
+        description = "Number of other tasks renumbered to make room; 0 unless there was no gap left"
+    )]
+    pub renumbered: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetTaskDependenciesRequest {
+    #[schemars(description = "The ID of the task to get dependencies for")]
+    pub task_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SetTaskDependenciesRequest {
+    #[schemars(description = "The ID of the task to set dependencies for")]
+    pub task_id: Uuid,
+    #[schemars(
+        description = "IDs of the tasks this task depends on, replacing its current dependencies. An empty list removes all dependencies"
+    )]
+    pub depends_on_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct DependencyTaskRef {
+    #[schemars(description = "The unique identifier of the task")]
+    pub id: String,
+    #[schemars(description = "The title of the task, if it still exists")]
+    pub title: Option<String>,
+    #[schemars(description = "Current status of the task, if it still exists")]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct TaskDependenciesResponse {
+    pub task_id: String,
+    #[schemars(description = "Tasks this task depends on")]
+    pub depends_on: Vec<DependencyTaskRef>,
+    #[schemars(description = "Tasks that depend on this task")]
+    pub depended_by: Vec<DependencyTaskRef>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct CreateSubtaskRequest {
+    #[schemars(description = "The ID of the task to create the subtask under")]
+    pub parent_task_id: Uuid,
+    #[schemars(description = "The title of the subtask")]
+    pub title: String,
+    #[schemars(description = "Optional description of the subtask")]
+    pub description: Option<String>,
+    #[schemars(
+        description = "Subtask priority: 'urgent', 'high', 'medium', or 'low'. Defaults to 'medium' if not specified."
+    )]
+    pub priority: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListSubtasksRequest {
+    #[schemars(description = "The ID of the task to list subtasks of")]
+    pub task_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct ListSubtasksResponse {
+    pub task_id: String,
+    pub subtasks: Vec<TaskSummary>,
+    #[schemars(description = "Completion of the subtasks, e.g. '3/5 subtasks done'")]
+    pub progress: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SearchTasksRequest {
+    #[schemars(description = "The ID of the project to search tasks in")]
+    pub project_id: Uuid,
+    #[schemars(description = "Text to find in task titles and descriptions (case-insensitive)")]
+    pub query: String,
+    #[schemars(
+        description = "Optional status filter: 'todo', 'inprogress', 'inreview', 'done', 'cancelled'"
+    )]
+    pub status: Option<String>,
+    #[schemars(description = "Optional label IDs; only tasks with all of these labels match")]
+    pub label_ids: Option<Vec<Uuid>>,
+    #[schemars(description = "Maximum number of tasks to return (default: 50)")]
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct SearchTasksResponse {
+    pub tasks: Vec<TaskSummary>,
+    pub count: usize,
+    #[schemars(description = "Total number of matching tasks, including those past the limit")]
+    pub total: usize,
+    pub project_id: String,
+}
+
 #[derive(Debug, Serialize, schemars::JsonSchema)]
 pub struct TaskDetails {
     #[schemars(description = "The unique identifier of the task")]
@@ -235,6 +678,12 @@ pub struct TaskDetails {
     pub description: Option<String>,
     #[schemars(description = "Current status of the task")]
     pub status: String,
+    #[schemars(description = "Priority of the task: 'urgent', 'high', 'medium', or 'low'")]
+    pub priority: String,
+    #[schemars(
+        description = "Order of the task in the project: lower positions come first. Change it with 'move_task'"
+    )]
+    pub position: i32,
     #[schemars(description = "When the task was created")]
     pub created_at: String,
     #[schemars(description = "When the task was last updated")]
@@ -252,6 +701,8 @@ impl TaskDetails {
             title: task.title,
             description: task.description,
             status: task.status.to_string(),
+            priority: task.priority.to_string(),
+            position: task.position,
             created_at: task.created_at.to_rfc3339(),
             updated_at: task.updated_at.to_rfc3339(),
             has_in_progress_attempt: None,
@@ -272,6 +723,9 @@ pub struct ListTasksResponse {
 pub struct ListTasksFilters {
     pub status: Option<String>,
     pub limit: i32,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub updated_after: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -284,6 +738,8 @@ pub struct UpdateTaskRequest {
     pub description: Option<String>,
     #[schemars(description = "New status: 'todo', 'inprogress', 'inreview', 'done', 'cancelled'")]
     pub status: Option<String>,
+    #[schemars(description = "New priority: 'urgent', 'high', 'medium', or 'low'")]
+    pub priority: Option<String>,
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
@@ -291,6 +747,25 @@ pub struct UpdateTaskResponse {
     pub task: TaskDetails,
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct MoveTaskRequest {
+    #[schemars(description = "The ID of the task to move")]
+    pub task_id: Uuid,
+    #[schemars(
+        description = "Move the task even if some of its dependencies are not done. Only use this when the user explicitly asks for it"
+    )]
+    pub force: Option<bool>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct IncompleteDependenciesResponse {
+    pub success: bool,
+    pub error: String,
+    pub task_id: String,
+    #[schemars(description = "Dependencies of the task that are not done yet")]
+    pub incomplete_dependencies: Vec<DependencyTaskRef>,
+}
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct DeleteTaskRequest {
     #[schemars(description = "The ID of the task to delete")]
@@ -310,7 +785,7 @@ pub struct StartWorkspaceSessionRequest {
     #[schemars(description = "The ID of the task to start")]
     pub task_id: Uuid,
     #[schemars(
-        description = "The coding agent executor to run ('CLAUDE_CODE', 'AMP', 'GEMINI', 'CODEX', 'OPENCODE', 'CURSOR_AGENT', 'QWEN_CODE', 'COPILOT', 'DROID')"
+        description = "The coding agent executor to run ('CLAUDE_CODE', 'AMP', 'GEMINI', 'CODEX', 'OPENCODE', 'CURSOR_AGENT', 'QWEN_CODE', 'COPILOT', 'DROID'). Common names like 'claude' or 'openai' are accepted too"
     )]
     pub executor: String,
     #[schemars(description = "Optional executor variant, if needed")]
@@ -325,6 +800,67 @@ pub struct StartWorkspaceSessionResponse {
     pub workspace_id: String,
 }
 
+/// Check that workspace repos are listed once each and belong to the task's
+/// project, naming the first offending repo id
+fn check_workspace_repos(requested: &[Uuid], project_repos: &[Uuid]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for repo_id in requested {
+        if !seen.insert(repo_id) {
+            return Err(format!("Repository {} is listed more than once.", repo_id));
+        }
+    }
+    match requested.iter().find(|id| !project_repos.contains(id)) {
+        Some(repo_id) => Err(format!(
+            "Repository {} does not belong to the task's project. Use 'list_repos' to find its repositories.",
+            repo_id
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Common names agents use for executors, after lowercasing and replacing
+/// `-` and spaces with `_`
+const EXECUTOR_ALIASES: &[(&str, BaseCodingAgent)] = &[
+    ("claude", BaseCodingAgent::ClaudeCode),
+    ("claude_cli", BaseCodingAgent::ClaudeCode),
+    ("anthropic", BaseCodingAgent::ClaudeCode),
+    ("gpt", BaseCodingAgent::Codex),
+    ("openai", BaseCodingAgent::Codex),
+    ("chatgpt", BaseCodingAgent::Codex),
+    ("codex_cli", BaseCodingAgent::Codex),
+    ("gemini_cli", BaseCodingAgent::Gemini),
+    ("google", BaseCodingAgent::Gemini),
+    ("sourcegraph", BaseCodingAgent::Amp),
+    ("open_code", BaseCodingAgent::Opencode),
+    ("cursor_cli", BaseCodingAgent::CursorAgent),
+    ("qwen", BaseCodingAgent::QwenCode),
+    ("github_copilot", BaseCodingAgent::Copilot),
+    ("gh_copilot", BaseCodingAgent::Copilot),
+    ("factory", BaseCodingAgent::Droid),
+    ("factory_droid", BaseCodingAgent::Droid),
+];
+
+/// Resolve an executor name, accepting the exact executor names in any case
+/// and the common aliases in [`EXECUTOR_ALIASES`]
+fn resolve_executor(executor: &str) -> Result<BaseCodingAgent, String> {
+    let normalized = executor.trim().replace(['-', ' '], "_");
+    if let Ok(exec) = BaseCodingAgent::from_str(&normalized.to_ascii_uppercase()) {
+        return Ok(exec);
+    }
+    let alias = normalized.to_ascii_lowercase();
+    EXECUTOR_ALIASES
+        .iter()
+        .find(|(name, _)| *name == alias)
+        .map(|(_, exec)| *exec)
+        .ok_or_else(|| {
+            format!(
+                "Unknown executor '{}'. Valid executors: {}",
+                executor.trim(),
+                CodingAgent::VARIANTS.join(", ")
+            )
+        })
+}
+
 #[derive(Debug, Serialize, schemars::JsonSchema)]
 pub struct DeleteTaskResponse {
     pub deleted_task_id: Option<String>,
@@ -358,6 +894,10 @@ pub struct RequestPmReviewRequest {
     pub task_id: Uuid,
     #[schemars(description = "Additional review instructions to include alongside the PM specs")]
     pub additional_instructions: Option<String>,
+    #[schemars(
+        description = "If true, also post the review checklist as a comment on the task. Defaults to false."
+    )]
+    pub save_as_comment: Option<bool>,
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
@@ -370,6 +910,19 @@ pub struct RequestPmReviewResponse {
     pub review_prompt: String,
     #[schemars(description = "Summary of what the review should check")]
     pub review_checklist: Vec<String>,
+    #[schemars(description = "ID of the stored review, listed by GET /api/tasks/{id}/reviews")]
+    pub review_id: String,
+    #[schemars(description = "ID of the checklist comment, when save_as_comment was set")]
+    pub comment_id: Option<String>,
+}
+
+/// Render a review checklist as markdown task list items
+fn review_checklist_markdown(checklist: &[String]) -> String {
+    checklist
+        .iter()
+        .map(|item| format!("- [ ] {}", item))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -379,9 +932,17 @@ pub struct UpdatePmDocsRequest {
     #[schemars(description = "The new PM documentation content in markdown format")]
     pub content: String,
     #[schemars(
-        description = "Mode: 'replace' to replace all docs, 'append' to add to existing docs. Defaults to 'append'."
+        description = "Mode: 'replace' to replace all docs, 'append' to add to the end of existing docs, 'prepend' to add to the beginning. Defaults to 'append'. Ignored when 'section' is set."
     )]
     pub mode: Option<String>,
+    #[schemars(
+        description = "Optional markdown section title (without '## '). If set, 'content' replaces the body of the '## <section>' section up to the next '## ' heading, and the section is added at the end if missing."
+    )]
+    pub section: Option<String>,
+    #[schemars(
+        description = "If true, include a unified diff between the old and new docs in the response"
+    )]
+    pub return_diff: Option<bool>,
 }
 
 #[derive(Debug, Serialize, schemars::JsonSchema)]
@@ -392,15 +953,45 @@ pub struct UpdatePmDocsResponse {
     pub success: bool,
     #[schemars(description = "The updated PM docs content")]
     pub pm_docs: Option<String>,
+    #[schemars(description = "Unified diff between the old and new docs, if requested")]
+    pub diff: Option<String>,
 }
 
-#[derive(Debug, Serialize, schemars::JsonSchema)]
-pub struct GetTaskResponse {
-    pub task: TaskDetails,
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetPmDocsRequest {
+    #[schemars(description = "The ID of the project to get PM docs for")]
+    pub project_id: Uuid,
+    #[schemars(
+        description = "Maximum number of characters to return (default: 20000). Use with `offset` to page through large docs."
+    )]
+    pub max_chars: Option<usize>,
+    #[schemars(description = "Character offset to start reading from (default: 0)")]
+    pub offset: Option<usize>,
 }
 
-#[derive(Debug, Deserialize, schemars::JsonSchema)]
-pub struct ListTaskAttachmentsRequest {
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct GetPmDocsResponse {
+    #[schemars(description = "The project ID")]
+    pub project_id: String,
+    #[schemars(description = "The requested slice of the PM docs")]
+    pub content: String,
+    #[schemars(description = "Character offset this slice starts at")]
+    pub offset: usize,
+    #[schemars(description = "Total length of the PM docs in characters")]
+    pub total_chars: usize,
+    #[schemars(description = "Whether more content is available after this slice")]
+    pub has_more: bool,
+    #[schemars(description = "Offset to pass to fetch the next slice, if any")]
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct GetTaskResponse {
+    pub task: TaskDetails,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListTaskAttachmentsRequest {
     #[schemars(description = "The ID of the task to list attachments for")]
     pub task_id: Uuid,
 }
@@ -452,6 +1043,294 @@ pub struct AttachFileToTaskResponse {
     pub download_url: String,
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct AddTaskCommentRequest {
+    #[schemars(description = "The ID of the task to comment on")]
+    pub task_id: Uuid,
+    #[schemars(description = "The comment text (markdown)")]
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListTaskCommentsRequest {
+    #[schemars(description = "The ID of the task to list comments for")]
+    pub task_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct TaskCommentInfo {
+    #[schemars(description = "The unique identifier of the comment")]
+    pub id: String,
+    #[schemars(description = "Who wrote the comment: 'user' or 'agent'")]
+    pub author: String,
+    #[schemars(description = "The comment text")]
+    pub content: String,
+    #[schemars(description = "When the comment was written")]
+    pub created_at: String,
+}
+
+impl TaskCommentInfo {
+    fn from_comment(comment: TaskComment) -> Self {
+        Self {
+            id: comment.id.to_string(),
+            author: comment.author.to_string(),
+            content: comment.content,
+            created_at: comment.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct ListTaskCommentsResponse {
+    pub task_id: String,
+    pub comments: Vec<TaskCommentInfo>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListLabelsRequest {
+    #[schemars(description = "The ID of the project to list labels for")]
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct LabelInfo {
+    #[schemars(
+        description = "The unique identifier of the label. Pass it in 'label_ids' of 'create_task'"
+    )]
+    pub id: String,
+    #[schemars(description = "The name of the label")]
+    pub name: String,
+    #[schemars(description = "The label color as a hex code")]
+    pub color: String,
+    #[schemars(description = "The executor/agent preferred for tasks with this label, if any")]
+    pub executor: Option<String>,
+}
+
+impl LabelInfo {
+    fn from_label(label: Label) -> Self {
+        Self {
+            id: label.id.to_string(),
+            name: label.name,
+            color: label.color,
+            executor: label.executor,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct ListLabelsResponse {
+    pub project_id: String,
+    pub labels: Vec<LabelInfo>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetTaskLabelsRequest {
+    #[schemars(description = "The ID of the task to get labels for")]
+    pub task_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SetTaskLabelsRequest {
+    #[schemars(description = "The ID of the task to set labels on")]
+    pub task_id: Uuid,
+    #[schemars(
+        description = "IDs of the labels the task should have, replacing its current labels. Must be labels of the task's project (see 'list_labels'); an empty list removes all labels"
+    )]
+    pub label_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct TaskLabelRequest {
+    #[schemars(description = "The ID of the task")]
+    pub task_id: Uuid,
+    #[schemars(description = "The ID of the label, from 'list_labels'")]
+    pub label_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct TaskLabelsResponse {
+    pub task_id: String,
+    pub labels: Vec<LabelInfo>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SuggestLabelsRequest {
+    #[schemars(description = "The ID of the task to suggest labels for")]
+    pub task_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct LabelSuggestionInfo {
+    #[schemars(
+        description = "The ID of the suggested label, for 'set_task_labels' or 'add_task_label'"
+    )]
+    pub label_id: String,
+    pub name: String,
+    #[schemars(description = "Match strength; higher is better")]
+    pub score: u32,
+    #[schemars(description = "Keywords in the task title or description that matched the label")]
+    pub matched_keywords: Vec<String>,
+}
+
+impl LabelSuggestionInfo {
+    fn from_suggestion(suggestion: LabelSuggestion) -> Self {
+        Self {
+            label_id: suggestion.label_id.to_string(),
+            name: suggestion.name,
+            score: suggestion.score,
+            matched_keywords: suggestion.matched_keywords,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct SuggestLabelsResponse {
+    pub task_id: String,
+    #[schemars(description = "Suggested labels, strongest match first")]
+    pub suggestions: Vec<LabelSuggestionInfo>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SuggestExecutorRequest {
+    #[schemars(description = "The ID of the task to suggest a coding agent for")]
+    pub task_id: Uuid,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct SuggestExecutorResponse {
+    pub task_id: String,
+    #[schemars(
+        description = "Recommended coding agent, for 'start_workspace_session' executor; null when no label of the task specifies one"
+    )]
+    pub executor: Option<String>,
+    #[schemars(description = "Names of the task's labels that specify the recommended executor")]
+    pub labels: Vec<String>,
+    #[schemars(description = "Other executors specified by the task's labels, best first")]
+    pub alternatives: Vec<String>,
+}
+
+impl SuggestExecutorResponse {
+    fn new(task_id: Uuid, suggestion: Option<ExecutorSuggestion>) -> Self {
+        match suggestion {
+            Some(suggestion) => Self {
+                task_id: task_id.to_string(),
+                executor: Some(suggestion.executor.to_string()),
+                labels: suggestion.label_names,
+                alternatives: suggestion
+                    .alternatives
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            },
+            None => Self {
+                task_id: task_id.to_string(),
+                executor: None,
+                labels: Vec::new(),
+                alternatives: Vec::new(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct CloseStaleReviewsRequest {
+    #[schemars(description = "The ID of the project to check for stale in-review tasks")]
+    pub project_id: Uuid,
+    #[schemars(
+        description = "Tasks in 'inreview' not updated within this many hours are considered stale. Defaults to 72."
+    )]
+    pub stale_after_hours: Option<u32>,
+    #[schemars(
+        description = "Mode: 'report' to only list stale tasks, 'apply' to move them back to 'todo' with a note. Defaults to 'report'."
+    )]
+    pub mode: Option<String>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct StaleReviewTask {
+    #[schemars(description = "The unique identifier of the task")]
+    pub id: String,
+    #[schemars(description = "The title of the task")]
+    pub title: String,
+    #[schemars(description = "When the task was last updated")]
+    pub updated_at: String,
+    #[schemars(description = "How many hours the task has been in review without updates")]
+    pub hours_stale: i64,
+    #[schemars(description = "Whether the task was moved back to 'todo' (only in 'apply' mode)")]
+    pub moved_to_todo: bool,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct CloseStaleReviewsResponse {
+    pub project_id: String,
+    #[schemars(description = "The mode that was applied: 'report' or 'apply'")]
+    pub mode: String,
+    pub stale_after_hours: u32,
+    pub tasks: Vec<StaleReviewTask>,
+    pub count: usize,
+}
+
+/// How `update_pm_docs` combines new content with the existing docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PmDocsUpdateMode {
+    Replace,
+    Append,
+    Prepend,
+}
+
+impl FromStr for PmDocsUpdateMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "replace" => Ok(PmDocsUpdateMode::Replace),
+            "append" => Ok(PmDocsUpdateMode::Append),
+            "prepend" => Ok(PmDocsUpdateMode::Prepend),
+            _ => Err(format!("Invalid mode: {}", s)),
+        }
+    }
+}
+
+impl PmDocsUpdateMode {
+    /// Whether an update can be re-applied on top of docs someone else changed
+    /// meanwhile without losing their edit: only plain appends can
+    pub fn retries_on_conflict(self, section: Option<&str>) -> bool {
+        self == PmDocsUpdateMode::Append && section.is_none()
+    }
+}
+
+/// How `close_stale_reviews` treats the stale tasks it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleReviewMode {
+    Report,
+    Apply,
+}
+
+impl FromStr for StaleReviewMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "report" => Ok(StaleReviewMode::Report),
+            "apply" => Ok(StaleReviewMode::Apply),
+            _ => Err(format!("Invalid mode: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for StaleReviewMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StaleReviewMode::Report => write!(f, "report"),
+            StaleReviewMode::Apply => write!(f, "apply"),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct DeleteTaskAttachmentRequest {
     #[schemars(description = "The ID of the task")]
@@ -460,10 +1339,44 @@ pub struct DeleteTaskAttachmentRequest {
     pub attachment_id: Uuid,
 }
 
+/// Default page size for `get_pm_docs`
+const DEFAULT_PM_DOCS_PAGE_CHARS: usize = 20_000;
+
+/// Default title similarity at which `create_task` treats an existing task as a duplicate
+const DEFAULT_DUPLICATE_SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// How many levels of tags referenced from tag content `expand_tags` follows
+const MAX_TAG_EXPANSION_DEPTH: usize = 5;
+
+/// How long `expand_tags` reuses the fetched tag list
+const DEFAULT_TAG_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Matches @tagname where tagname is non-whitespace, non-@ characters
+static TAG_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@([^\s@]+)").unwrap());
+
+/// Words ignored when comparing task titles
+const TITLE_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "be", "by", "for", "from", "in", "into", "is", "of", "on", "or",
+    "the", "this", "that", "to", "with",
+];
+
+/// A character-based slice of a larger text
+#[derive(Debug, PartialEq, Eq)]
+pub struct CharPage<'a> {
+    pub content: &'a str,
+    pub total_chars: usize,
+    pub next_offset: Option<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TaskServer {
     client: reqwest::Client,
     base_url: String,
+    /// Base URL of the web UI, used for links to tasks
+    app_url: String,
+    /// Tags fetched by `expand_tags` and when they were fetched, shared by clones
+    tag_cache: Arc<Mutex<Option<(Instant, Vec<Tag>)>>>,
+    tag_cache_ttl: Duration,
     tool_router: ToolRouter<TaskServer>,
     context: Option<McpContext>,
 }
@@ -508,11 +1421,27 @@ impl TaskServer {
         Self {
             client: reqwest::Client::new(),
             base_url: base_url.to_string(),
+            app_url: base_url.to_string(),
+            tag_cache: Arc::new(Mutex::new(None)),
+            tag_cache_ttl: DEFAULT_TAG_CACHE_TTL,
             tool_router: Self::tool_router(),
             context: None,
         }
     }
 
+    /// Use `app_url` instead of the backend URL for links to the web UI
+    pub fn with_app_url(mut self, app_url: &str) -> Self {
+        self.app_url = app_url.to_string();
+        self
+    }
+
+    /// How long fetched tags are reused before `expand_tags` fetches them again.
+    /// A zero TTL disables the cache.
+    pub fn with_tag_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.tag_cache_ttl = ttl;
+        self
+    }
+
     pub async fn init(mut self) -> Self {
         let context = self.fetch_context_at_startup().await;
 
@@ -647,10 +1576,184 @@ impl TaskServer {
         Self::err_value(v)
     }
 
+    /// Look up the dependents of a task and the titles of all related tasks
+    async fn task_dependencies_result(
+        &self,
+        task_id: Uuid,
+        depends_on: Vec<Uuid>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/dependents", task_id));
+        let depended_by: Vec<Uuid> = match self.send_json(self.client.get(&url)).await {
+            Ok(ids) => ids,
+            Err(e) => return Ok(e),
+        };
+
+        let url = self.url(&format!("/api/tasks/{}", task_id));
+        let task: Task = match self.send_json(self.client.get(&url)).await {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
+        let url = self.url(&format!("/api/tasks?project_id={}", task.project_id));
+        let project_tasks: Vec<TaskWithAttemptStatus> =
+            match self.send_json(self.client.get(&url)).await {
+                Ok(t) => t,
+                Err(e) => return Ok(e),
+            };
+
+        let tasks_by_id: std::collections::HashMap<Uuid, &Task> =
+            project_tasks.iter().map(|t| (t.id, &t.task)).collect();
+        let to_refs = |ids: Vec<Uuid>| -> Vec<DependencyTaskRef> {
+            ids.into_iter()
+                .map(|id| DependencyTaskRef {
+                    id: id.to_string(),
+                    title: tasks_by_id.get(&id).map(|t| t.title.clone()),
+                    status: tasks_by_id.get(&id).map(|t| t.status.to_string()),
+                })
+                .collect()
+        };
+
+        TaskServer::success(&TaskDependenciesResponse {
+            task_id: task_id.to_string(),
+            depends_on: to_refs(depends_on),
+            depended_by: to_refs(depended_by),
+        })
+    }
+
+    /// Change the status of a task, refusing while its dependencies are not done
+    /// unless `force` is set
+    async fn move_task_status(
+        &self,
+        task_id: Uuid,
+        status: TaskStatus,
+        force: bool,
+    ) -> Result<CallToolResult, ErrorData> {
+        if !force {
+            let url = self.url(&format!("/api/tasks/{}/dependencies", task_id));
+            let depends_on: Vec<Uuid> = match self.send_json(self.client.get(&url)).await {
+                Ok(ids) => ids,
+                Err(e) => return Ok(e),
+            };
+            if !depends_on.is_empty() {
+                let url = self.url(&format!("/api/tasks/{}", task_id));
+                let task: Task = match self.send_json(self.client.get(&url)).await {
+                    Ok(t) => t,
+                    Err(e) => return Ok(e),
+                };
+                let url = self.url(&format!("/api/tasks?project_id={}", task.project_id));
+                let project_tasks: Vec<TaskWithAttemptStatus> =
+                    match self.send_json(self.client.get(&url)).await {
+                        Ok(t) => t,
+                        Err(e) => return Ok(e),
+                    };
+                let project_tasks: Vec<Task> = project_tasks.into_iter().map(|t| t.task).collect();
+
+                let incomplete = Self::incomplete_dependencies(&depends_on, &project_tasks);
+                if !incomplete.is_empty() {
+                    let titles: Vec<&str> = incomplete
+                        .iter()
+                        .filter_map(|dep| dep.title.as_deref())
+                        .collect();
+                    let response = IncompleteDependenciesResponse {
+                        success: false,
+                        error: format!(
+                            "Cannot move the task to '{}' while {} of its dependencies are not done: {}. Finish them first, or pass force=true if the user explicitly asked for it.",
+                            status,
+                            incomplete.len(),
+                            titles.join(", ")
+                        ),
+                        task_id: task_id.to_string(),
+                        incomplete_dependencies: incomplete,
+                    };
+                    return Self::err_value(serde_json::to_value(response).unwrap_or_default());
+                }
+            }
+        }
+
+        let payload = UpdateTask {
+            title: None,
+            description: None,
+            status: Some(status),
+            priority: None,
+            position: None,
+            parent_workspace_id: None,
+            image_ids: None,
+            label_ids: None,
+        };
+        let url = self.url(&format!("/api/tasks/{}", task_id));
+        let updated_task: Task = match self.send_json(self.client.put(&url).json(&payload)).await {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
+
+        TaskServer::success(&UpdateTaskResponse {
+            task: TaskDetails::from_task(updated_task),
+        })
+    }
+
+    async fn post_task_comment(
+        &self,
+        task_id: Uuid,
+        content: String,
+    ) -> Result<TaskComment, CallToolResult> {
+        let url = self.url(&format!("/api/tasks/{}/comments", task_id));
+        let body = serde_json::json!({
+            "content": content,
+            "author": TaskCommentAuthor::Agent,
+        });
+        self.send_json(self.client.post(&url).json(&body)).await
+    }
+
+    /// Store a `request_pm_review` result and, when requested, post its checklist
+    /// as a task comment. Returns the review ID and the comment ID.
+    async fn record_review(
+        &self,
+        task_id: Uuid,
+        prompt: &str,
+        checklist: &[String],
+        save_as_comment: Option<bool>,
+    ) -> Result<(String, Option<String>), CallToolResult> {
+        let url = self.url(&format!("/api/tasks/{}/reviews", task_id));
+        let body = serde_json::json!({
+            "prompt": prompt,
+            "checklist": checklist,
+            "source": TaskReviewSource::Agent,
+        });
+        let review: TaskReview = self.send_json(self.client.post(&url).json(&body)).await?;
+
+        if !save_as_comment.unwrap_or(false) {
+            return Ok((review.id.to_string(), None));
+        }
+        let content = format!(
+            "PM review checklist:\n{}",
+            review_checklist_markdown(checklist)
+        );
+        let comment = self.post_task_comment(task_id, content).await?;
+        Ok((review.id.to_string(), Some(comment.id.to_string())))
+    }
+
+    fn task_labels_result(task_id: Uuid, labels: Vec<Label>) -> Result<CallToolResult, ErrorData> {
+        let labels: Vec<LabelInfo> = labels.into_iter().map(LabelInfo::from_label).collect();
+        TaskServer::success(&TaskLabelsResponse {
+            task_id: task_id.to_string(),
+            count: labels.len(),
+            labels,
+        })
+    }
+
     async fn send_json<T: DeserializeOwned>(
         &self,
         rb: reqwest::RequestBuilder,
     ) -> Result<T, CallToolResult> {
+        self.send_optional_json(rb)
+            .await?
+            .ok_or_else(|| Self::err("VK API response missing data field", None).unwrap())
+    }
+
+    /// Like `send_json`, for endpoints whose data may be `null`
+    async fn send_optional_json<T: DeserializeOwned>(
+        &self,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<Option<T>, CallToolResult> {
         let resp = rb
             .send()
             .await
@@ -658,8 +1761,14 @@ impl TaskServer {
 
         if !resp.status().is_success() {
             let status = resp.status();
+            // Error responses carry the reason (e.g. a dependency cycle) in `message`
+            let message = resp
+                .json::<ApiResponseEnvelope<serde_json::Value>>()
+                .await
+                .ok()
+                .and_then(|r| r.message);
             return Err(
-                Self::err(format!("VK API returned error status: {}", status), None).unwrap(),
+                Self::err(format!("VK API returned error status: {}", status), message).unwrap(),
             );
         }
 
@@ -672,9 +1781,7 @@ impl TaskServer {
             return Err(Self::err("VK API returned error", Some(msg)).unwrap());
         }
 
-        api_response
-            .data
-            .ok_or_else(|| Self::err("VK API response missing data field", None).unwrap())
+        Ok(api_response.data)
     }
 
     async fn send_empty_json(&self, rb: reqwest::RequestBuilder) -> Result<(), CallToolResult> {
@@ -716,56 +1823,125 @@ impl TaskServer {
         )
     }
 
+    /// Link to a task in the web UI
+    fn task_link(&self, project_id: Uuid, task_id: Uuid) -> String {
+        format!(
+            "{}/projects/{}/tasks/{}",
+            self.app_url.trim_end_matches('/'),
+            project_id,
+            task_id
+        )
+    }
+
+    fn create_task_response(
+        &self,
+        task: &Task,
+        is_new: bool,
+        message: String,
+        labels: Vec<Label>,
+    ) -> CreateTaskResponse {
+        CreateTaskResponse {
+            task_id: task.id.to_string(),
+            is_new,
+            message: Some(message),
+            title: task.title.clone(),
+            status: task.status.to_string(),
+            priority: task.priority.to_string(),
+            labels: labels.into_iter().map(|l| l.name).collect(),
+            url: self.task_link(task.project_id, task.id),
+            preview: None,
+        }
+    }
+
     /// Expands @tagname references in text by replacing them with tag content.
+    /// Tags referenced from tag content are expanded too, see [`Self::expand_tag_references`].
     /// Returns the original text if expansion fails (e.g., network error).
     /// Unknown tags are left as-is (not expanded, not an error).
     async fn expand_tags(&self, text: &str) -> String {
-        // Pattern matches @tagname where tagname is non-whitespace, non-@ characters
-        let tag_pattern = match Regex::new(r"@([^\s@]+)") {
-            Ok(re) => re,
-            Err(_) => return text.to_string(),
-        };
-
-        // Find all unique tag names referenced in the text
-        let tag_names: Vec<String> = tag_pattern
-            .captures_iter(text)
-            .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect();
+        if !TAG_PATTERN.is_match(text) {
+            return text.to_string();
+        }
 
-        if tag_names.is_empty() {
+        // Fetch the tags once; nested references reuse the same list
+        let Some(tags) = self.tags().await else {
             return text.to_string();
+        };
+
+        // Build a map of tag_name -> content for quick lookup
+        let tag_map: std::collections::HashMap<&str, &str> = tags
+            .iter()
+            .map(|t| (t.tag_name.as_str(), t.content.as_str()))
+            .collect();
+
+        Self::expand_tag_references(text, &tag_map, MAX_TAG_EXPANSION_DEPTH)
+    }
+
+    /// All tags, reusing the last fetched list while it is younger than the TTL.
+    /// The lock is held while fetching so concurrent tool calls share one request.
+    async fn tags(&self) -> Option<Vec<Tag>> {
+        let mut cache = self.tag_cache.lock().await;
+        if let Some(tags) = Self::fresh_tags(cache.as_ref(), self.tag_cache_ttl, Instant::now()) {
+            return Some(tags.to_vec());
         }
 
-        // Fetch all tags from the API
         let url = self.url("/api/tags");
         let tags: Vec<Tag> = match self.client.get(&url).send().await {
             Ok(resp) if resp.status().is_success() => {
                 match resp.json::<ApiResponseEnvelope<Vec<Tag>>>().await {
                     Ok(envelope) if envelope.success => envelope.data.unwrap_or_default(),
-                    _ => return text.to_string(),
+                    _ => return None,
                 }
             }
-            _ => return text.to_string(),
+            _ => return None,
         };
+        *cache = Some((Instant::now(), tags.clone()));
+        Some(tags)
+    }
 
-        // Build a map of tag_name -> content for quick lookup
-        let tag_map: std::collections::HashMap<&str, &str> = tags
-            .iter()
-            .map(|t| (t.tag_name.as_str(), t.content.as_str()))
-            .collect();
+    /// The cached tags, if they were fetched less than `ttl` before `now`
+    fn fresh_tags(
+        cache: Option<&(Instant, Vec<Tag>)>,
+        ttl: Duration,
+        now: Instant,
+    ) -> Option<&[Tag]> {
+        cache
+            .filter(|(fetched_at, _)| now.saturating_duration_since(*fetched_at) < ttl)
+            .map(|(_, tags)| tags.as_slice())
+    }
 
-        // Replace each @tagname with its content (if found)
-        let result = tag_pattern.replace_all(text, |caps: &regex::Captures| {
-            let tag_name = caps.get(1).map(|m| m.as_str()).unwrap_or("");
-            match tag_map.get(tag_name) {
-                Some(content) => (*content).to_string(),
-                None => caps.get(0).map(|m| m.as_str()).unwrap_or("").to_string(),
-            }
-        });
+    /// Replace each @tagname in `text` with its content from `tags`, expanding
+    /// references inside tag content up to `max_depth` levels deep. A reference
+    /// to a tag that is already being expanded (e.g. `@a` -> `@b` -> `@a`) is
+    /// left as-is, as are unknown tags.
+    pub fn expand_tag_references(
+        text: &str,
+        tags: &std::collections::HashMap<&str, &str>,
+        max_depth: usize,
+    ) -> String {
+        fn expand<'a>(
+            text: &str,
+            tags: &std::collections::HashMap<&'a str, &'a str>,
+            expanding: &mut Vec<&'a str>,
+            depth_left: usize,
+        ) -> String {
+            TAG_PATTERN
+                .replace_all(text, |caps: &regex::Captures| {
+                    let reference = caps.get(0).map(|m| m.as_str()).unwrap_or("");
+                    let tag_name = caps.get(1).map(|m| m.as_str()).unwrap_or("");
+                    match tags.get_key_value(tag_name) {
+                        Some((name, content)) if depth_left > 0 && !expanding.contains(name) => {
+                            expanding.push(*name);
+                            let expanded = expand(content, tags, expanding, depth_left - 1);
+                            expanding.pop();
+                            expanded
+                        }
+                        _ => reference.to_string(),
+                    }
+                })
+                .into_owned()
+        }
 
-        result.into_owned()
+        expand(text, tags, &mut Vec::new(), max_depth)
     }
 }
 
@@ -782,7 +1958,7 @@ impl TaskServer {
     }
 
     #[tool(
-        description = "Create a new task/ticket in a project. Always pass the `project_id` of the project you want to create the task in - it is required! Use check_duplicate=true to avoid creating duplicate tasks. Use depends_on to set task dependencies. Use label_ids to attach labels. Use priority to set task priority (urgent/high/medium/low)."
+        description = "Create a new task/ticket in a project. Always pass the `project_id` of the project you want to create the task in - it is required! Use check_duplicate=true to avoid creating duplicate tasks (similarity_threshold tunes how close a title must be). Use depends_on to set task dependencies. Use label_ids to attach labels. Use priority to set task priority (urgent/high/medium/low). Use dry_run=true to preview the task without creating it."
     )]
     async fn create_task(
         &self,
@@ -793,32 +1969,48 @@ impl TaskServer {
             priority,
             depends_on,
             check_duplicate,
+            similarity_threshold,
             label_ids,
+            dry_run,
         }): Parameters<CreateTaskRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        // Check for duplicate tasks if requested
-        if check_duplicate.unwrap_or(false) {
-            let list_url = self.url(&format!("/api/projects/{}/tasks", project_id));
-            let existing_tasks: Vec<Task> = match self.send_json(self.client.get(&list_url)).await {
+        let dry_run = dry_run.unwrap_or(false);
+        let mut project_labels = Vec::new();
+        // Reject references to tasks or labels of other projects before creating anything
+        if let Some(dep_ids) = depends_on.as_ref().filter(|ids| !ids.is_empty()) {
+            let url = self.url(&format!("/api/projects/{}/tasks", project_id));
+            let project_tasks: Vec<Task> = match self.send_json(self.client.get(&url)).await {
                 Ok(tasks) => tasks,
-                Err(_) => vec![], // If we can't get tasks, proceed with creation
+                Err(e) => return Ok(e),
             };
-
-            // Check for similar titles using extracted helper
-            for existing in &existing_tasks {
-                if Self::is_duplicate_title(&title, &existing.title) {
-                    return TaskServer::success(&CreateTaskResponse {
-                        task_id: existing.id.to_string(),
-                        is_new: false,
-                        message: Some(format!(
-                            "Found existing similar task: '{}'. Returning existing task instead of creating duplicate.",
-                            existing.title
-                        )),
-                    });
-                }
+            let known: HashSet<Uuid> = project_tasks.iter().map(|t| t.id).collect();
+            let foreign = Self::ids_outside_project(dep_ids, &known);
+            if !foreign.is_empty() {
+                return Self::err(
+                    "depends_on contains tasks that are not in this project".to_string(),
+                    Some(Self::join_ids(&foreign)),
+                );
+            }
+        }
+        if let Some(lbl_ids) = label_ids.as_ref().filter(|ids| !ids.is_empty()) {
+            let url = self.url(&format!("/api/projects/{}/labels", project_id));
+            project_labels = match self.send_json::<Vec<Label>>(self.client.get(&url)).await {
+                Ok(labels) => labels,
+                Err(e) => return Ok(e),
+            };
+            let known: HashSet<Uuid> = project_labels.iter().map(|l| l.id).collect();
+            let foreign = Self::ids_outside_project(lbl_ids, &known);
+            if !foreign.is_empty() {
+                return Self::err(
+                    "label_ids contains labels that are not in this project. Use 'list_labels' to find the project's labels".to_string(),
+                    Some(Self::join_ids(&foreign)),
+                );
             }
         }
 
+        // Expand @tagname references in the title before comparing it to existing tasks
+        let title = self.expand_tags(&title).await;
+
         // Expand @tagname references in description
         let expanded_description = match description {
             Some(desc) => Some(self.expand_tags(&desc).await),
@@ -836,6 +2028,69 @@ impl TaskServer {
             }
         });
 
+        let preview = dry_run.then(|| {
+            CreateTaskPreview::new(
+                &title,
+                expanded_description.as_deref(),
+                task_priority.clone(),
+                depends_on.as_deref().unwrap_or_default(),
+                label_ids.as_deref().unwrap_or_default(),
+                &project_labels,
+            )
+        });
+
+        // Check for duplicate tasks if requested
+        if check_duplicate.unwrap_or(false) {
+            let list_url = self.url(&format!("/api/projects/{}/tasks", project_id));
+            let existing_tasks: Vec<Task> = match self.send_json(self.client.get(&list_url)).await {
+                Ok(tasks) => tasks,
+                Err(_) => vec![], // If we can't get tasks, proceed with creation
+            };
+
+            let threshold = similarity_threshold
+                .unwrap_or(DEFAULT_DUPLICATE_SIMILARITY_THRESHOLD)
+                .clamp(0.0, 1.0);
+
+            // Check for similar titles using extracted helper
+            for existing in &existing_tasks {
+                if Self::is_duplicate_title(&title, &existing.title, threshold) {
+                    let labels_url = self.url(&format!("/api/tasks/{}/labels", existing.id));
+                    let labels: Vec<Label> = self
+                        .send_json(self.client.get(&labels_url))
+                        .await
+                        .unwrap_or_default();
+                    let mut response = self.create_task_response(
+                        existing,
+                        false,
+                        format!(
+                            "Found existing similar task: '{}'. Returning existing task instead of creating duplicate.",
+                            existing.title
+                        ),
+                        labels,
+                    );
+                    response.preview = preview;
+                    return TaskServer::success(&response);
+                }
+            }
+        }
+
+        if let Some(preview) = preview {
+            return TaskServer::success(&CreateTaskResponse {
+                task_id: String::new(),
+                is_new: true,
+                message: Some(format!(
+                    "Dry run: would create new task '{}'. Nothing was created.",
+                    title
+                )),
+                title: preview.title.clone(),
+                status: TaskStatus::Todo.to_string(),
+                priority: preview.priority.clone(),
+                labels: preview.labels.clone(),
+                url: String::new(),
+                preview: Some(preview),
+            });
+        }
+
         let url = self.url("/api/tasks");
 
         let create_task_data = CreateTask {
@@ -846,6 +2101,7 @@ impl TaskServer {
             priority: task_priority,
             position: None,
             parent_workspace_id: None,
+            parent_task_id: None,
             image_ids: None,
             label_ids: None, // Labels are set separately after task creation
         };
@@ -891,86 +2147,239 @@ impl TaskServer {
         }
 
         // Set labels if provided
+        let mut labels = Vec::new();
         if let Some(lbl_ids) = label_ids {
             if !lbl_ids.is_empty() {
-                // Update task with label_ids via the update endpoint
-                let update_url = self.url(&format!("/api/tasks/{}", task.id));
+                let labels_url = self.url(&format!("/api/tasks/{}/labels", task.id));
                 match self
-                    .client
-                    .put(&update_url)
-                    .json(&serde_json::json!({ "label_ids": lbl_ids }))
-                    .send()
+                    .send_json::<Vec<Label>>(
+                        self.client
+                            .put(&labels_url)
+                            .json(&serde_json::json!({ "label_ids": lbl_ids })),
+                    )
                     .await
                 {
-                    Ok(resp) if resp.status().is_success() => {
+                    Ok(attached) => {
                         tracing::debug!("Labels attached successfully for task {}", task.id);
+                        labels = attached;
                     }
-                    Ok(resp) => {
-                        tracing::warn!(
-                            "Failed to attach labels for task {}: {}",
-                            task.id,
-                            resp.status()
-                        );
-                    }
-                    Err(e) => {
-                        tracing::error!("Error attaching labels for task {}: {}", task.id, e);
+                    Err(_) => {
+                        tracing::warn!("Failed to attach labels for task {}", task.id);
                     }
                 }
             }
         }
 
-        TaskServer::success(&CreateTaskResponse {
-            task_id: task.id.to_string(),
-            is_new: true,
-            message: Some(format!("Created new task: '{}'", title)),
+        TaskServer::success(&self.create_task_response(
+            &task,
+            true,
+            format!("Created new task: '{}'", title),
+            labels,
+        ))
+    }
+
+    #[tool(
+        description = "Create several tasks in one call. Give each task a temp_id and reference other tasks of the batch by temp_id (or existing tasks by ID) in depends_on; dependencies are wired after all tasks are created. Rejects batches whose dependencies form a cycle. Returns the real task ID for each temp_id. `project_id` is required!"
+    )]
+    async fn create_tasks(
+        &self,
+        Parameters(CreateTasksRequest { project_id, tasks }): Parameters<CreateTasksRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let dependencies = match Self::resolve_batch_dependencies(&tasks) {
+            Ok(deps) => deps,
+            Err(e) => return Self::err("Invalid task batch".to_string(), Some(e)),
+        };
+        let mut priorities = Vec::with_capacity(tasks.len());
+        for task in &tasks {
+            match task
+                .priority
+                .as_deref()
+                .map(|p| TaskPriority::from_str(&p.to_lowercase()))
+            {
+                None => priorities.push(None),
+                Some(Ok(p)) => priorities.push(Some(p)),
+                Some(Err(_)) => {
+                    return Self::err(
+                        "Invalid priority. Valid values: 'urgent', 'high', 'medium', 'low'"
+                            .to_string(),
+                        task.priority.clone(),
+                    );
+                }
+            }
+        }
+
+        // First pass: create every task
+        let url = self.url("/api/tasks");
+        let mut created: Vec<Uuid> = Vec::with_capacity(tasks.len());
+        for (task, priority) in tasks.iter().zip(priorities) {
+            let description = match &task.description {
+                Some(desc) => Some(self.expand_tags(desc).await),
+                None => None,
+            };
+            let create_task_data = CreateTask {
+                project_id,
+                title: self.expand_tags(&task.title).await,
+                description,
+                status: None,
+                priority,
+                position: None,
+                parent_workspace_id: None,
+                parent_task_id: None,
+                image_ids: None,
+                label_ids: task.label_ids.clone(),
+            };
+            match self
+                .send_json::<Task>(self.client.post(&url).json(&create_task_data))
+                .await
+            {
+                Ok(t) => created.push(t.id),
+                // Nothing to report besides the failure itself
+                Err(e) if created.is_empty() => return Ok(e),
+                Err(_) => {
+                    return Self::err(
+                        format!(
+                            "Failed to create task '{}'; {} tasks of the batch were already created",
+                            task.temp_id,
+                            created.len()
+                        ),
+                        Some(Self::batch_task_ids(&tasks, &created)),
+                    );
+                }
+            }
+        }
+
+        // Second pass: wire dependencies now that every temp_id has a real ID
+        for (index, deps) in dependencies.iter().enumerate() {
+            if deps.is_empty() {
+                continue;
+            }
+            let dep_ids: Vec<Uuid> = deps
+                .iter()
+                .map(|dep| match dep {
+                    BatchDependency::Batch(i) => created[*i],
+                    BatchDependency::Existing(id) => *id,
+                })
+                .collect();
+            let deps_url = self.url(&format!("/api/tasks/{}/dependencies", created[index]));
+            let body = serde_json::json!({ "dependency_ids": dep_ids });
+            if self
+                .send_json::<Vec<Uuid>>(self.client.put(&deps_url).json(&body))
+                .await
+                .is_err()
+            {
+                return Self::err(
+                    format!(
+                        "Created all tasks but failed to set the dependencies of '{}'",
+                        tasks[index].temp_id
+                    ),
+                    Some(Self::batch_task_ids(&tasks, &created)),
+                );
+            }
+        }
+
+        let task_ids = tasks
+            .iter()
+            .zip(&created)
+            .map(|(task, id)| (task.temp_id.clone(), id.to_string()))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        TaskServer::success(&CreateTasksResponse {
+            project_id: project_id.to_string(),
+            count: task_ids.len(),
+            task_ids,
         })
     }
 
     #[tool(
-        description = "Get the progress/completion status of a project. Returns the number of tasks by status and completion percentage."
+        description = "Get a suggested execution order for a project's tasks. Every task comes after the tasks it depends on; among ready tasks, higher priority and older tasks come first. Fails if the dependencies contain a cycle. `project_id` is required!"
     )]
-    async fn get_project_progress(
+    async fn get_task_order(
         &self,
-        Parameters(GetProjectProgressRequest { project_id }): Parameters<GetProjectProgressRequest>,
+        Parameters(GetTaskOrderRequest { project_id }): Parameters<GetTaskOrderRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        let list_url = self.url(&format!("/api/projects/{}/tasks", project_id));
-        let tasks: Vec<Task> = match self.send_json(self.client.get(&list_url)).await {
+        let url = self.url(&format!("/api/projects/{}/task-order", project_id));
+        let ordered: Vec<OrderedTaskSummary> = match self.send_json(self.client.get(&url)).await {
             Ok(tasks) => tasks,
             Err(e) => return Ok(e),
         };
 
-        let total_tasks = tasks.len() as i32;
-        let mut status_summary = std::collections::HashMap::new();
-        let mut completed_tasks = 0;
-        let mut in_progress_tasks = 0;
+        let tasks: Vec<OrderedTask> = ordered
+            .into_iter()
+            .enumerate()
+            .map(|(i, task)| OrderedTask::from_summary(i + 1, task))
+            .collect();
 
-        for task in &tasks {
-            let status_str = format!("{:?}", task.status).to_lowercase();
-            *status_summary.entry(status_str.clone()).or_insert(0) += 1;
+        TaskServer::success(&GetTaskOrderResponse {
+            count: tasks.len(),
+            tasks,
+        })
+    }
 
-            if task.status == TaskStatus::Done {
-                completed_tasks += 1;
-            } else if task.status == TaskStatus::InProgress {
-                in_progress_tasks += 1;
-            }
+    #[tool(
+        description = "Get the tasks that can be worked on next: todo and in-progress tasks whose dependencies are all done, sorted by priority and then by creation time. `project_id` is required!"
+    )]
+    async fn get_actionable_tasks(
+        &self,
+        Parameters(GetActionableTasksRequest { project_id, limit }): Parameters<
+            GetActionableTasksRequest,
+        >,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/projects/{}/actionable-tasks", project_id));
+        let mut request = self.client.get(&url);
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
         }
+        let tasks: Vec<Task> = match self.send_json(request).await {
+            Ok(tasks) => tasks,
+            Err(e) => return Ok(e),
+        };
 
-        // Calculate blocked tasks (those with incomplete dependencies)
-        // This is a simplified check - ideally we'd query dependencies
-        let blocked_tasks = 0; // Would need dependency info from API
+        let tasks: Vec<TaskSummary> = tasks.into_iter().map(TaskSummary::from_task).collect();
+        TaskServer::success(&GetActionableTasksResponse {
+            count: tasks.len(),
+            tasks,
+        })
+    }
 
-        let progress_percent = Self::calculate_progress(total_tasks, completed_tasks);
+    #[tool(
+        description = "Get the critical path of a project: the longest chain of unfinished tasks where each depends on the previous one. Completed prerequisites are skipped. `project_id` is required!"
+    )]
+    async fn get_critical_path(
+        &self,
+        Parameters(GetCriticalPathRequest { project_id }): Parameters<GetCriticalPathRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/projects/{}/critical-path", project_id));
+        let path: CriticalPathResponse = match self.send_json(self.client.get(&url)).await {
+            Ok(path) => path,
+            Err(e) => return Ok(e),
+        };
 
-        TaskServer::success(&GetProjectProgressResponse {
-            total_tasks,
-            completed_tasks,
-            in_progress_tasks,
-            blocked_tasks,
-            progress_percent,
-            status_summary,
+        TaskServer::success(&GetCriticalPathResponse {
+            tasks: path
+                .tasks
+                .into_iter()
+                .enumerate()
+                .map(|(i, task)| OrderedTask::from_summary(i + 1, task))
+                .collect(),
+            length: path.length,
         })
     }
 
+    #[tool(
+        description = "Get the progress/completion status of a project. Returns the number of tasks by status, how many are blocked by unfinished dependencies, and the completion percentage."
+    )]
+    async fn get_project_progress(
+        &self,
+        Parameters(GetProjectProgressRequest { project_id }): Parameters<GetProjectProgressRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/projects/{}/progress", project_id));
+        let summary: ProgressSummary = match self.send_json(self.client.get(&url)).await {
+            Ok(summary) => summary,
+            Err(e) => return Ok(e),
+        };
+
+        TaskServer::success(&GetProjectProgressResponse::from_summary(summary))
+    }
+
     #[tool(description = "List all the available projects")]
     async fn list_projects(&self) -> Result<CallToolResult, ErrorData> {
         let url = self.url("/api/projects");
@@ -992,10 +2401,15 @@ impl TaskServer {
         TaskServer::success(&response)
     }
 
-    #[tool(description = "List all repositories for a project. `project_id` is required!")]
+    #[tool(
+        description = "List all repositories for a project. Paths are only included with `include_path: true`. `project_id` is required!"
+    )]
     async fn list_repos(
         &self,
-        Parameters(ListReposRequest { project_id }): Parameters<ListReposRequest>,
+        Parameters(ListReposRequest {
+            project_id,
+            include_path,
+        }): Parameters<ListReposRequest>,
     ) -> Result<CallToolResult, ErrorData> {
         let url = self.url(&format!("/api/projects/{}/repositories", project_id));
         let repos: Vec<Repo> = match self.send_json(self.client.get(&url)).await {
@@ -1006,6 +2420,7 @@ impl TaskServer {
         let repo_summaries: Vec<McpRepoSummary> = repos
             .into_iter()
             .map(|r| McpRepoSummary {
+                path: repo_path(&r, include_path),
                 id: r.id.to_string(),
                 name: r.name,
             })
@@ -1021,24 +2436,55 @@ impl TaskServer {
     }
 
     #[tool(
-        description = "Get detailed information about a repository including its scripts. Use `list_repos` to find available repo IDs."
+        description = "Get detailed information about a repository including its scripts. The path is only included with `include_path: true`. Use `list_repos` to find available repo IDs."
     )]
     async fn get_repo(
         &self,
-        Parameters(GetRepoRequest { repo_id }): Parameters<GetRepoRequest>,
+        Parameters(GetRepoRequest {
+            repo_id,
+            include_path,
+        }): Parameters<GetRepoRequest>,
     ) -> Result<CallToolResult, ErrorData> {
         let url = self.url(&format!("/api/repos/{}", repo_id));
         let repo: Repo = match self.send_json(self.client.get(&url)).await {
             Ok(r) => r,
             Err(e) => return Ok(e),
         };
-        TaskServer::success(&RepoDetails {
-            id: repo.id.to_string(),
-            name: repo.name,
-            display_name: repo.display_name,
-            setup_script: repo.setup_script,
-            cleanup_script: repo.cleanup_script,
-            dev_server_script: repo.dev_server_script,
+        TaskServer::success(&RepoDetails::from_repo(repo, include_path))
+    }
+
+    #[tool(
+        description = "Get detailed information, including scripts, about several repositories of a project in one call. Paths are only included with `include_path: true`. `project_id` and `repo_ids` are required!"
+    )]
+    async fn get_repos(
+        &self,
+        Parameters(GetReposRequest {
+            project_id,
+            repo_ids,
+            include_path,
+        }): Parameters<GetReposRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/projects/{}/repositories", project_id));
+        let mut repos: Vec<Repo> = match self.send_json(self.client.get(&url)).await {
+            Ok(rs) => rs,
+            Err(e) => return Ok(e),
+        };
+        repos.retain(|repo| repo_ids.contains(&repo.id));
+
+        let not_found: Vec<String> = repo_ids
+            .iter()
+            .filter(|id| !repos.iter().any(|repo| repo.id == **id))
+            .map(|id| id.to_string())
+            .collect();
+        let repos: Vec<RepoDetails> = repos
+            .into_iter()
+            .map(|repo| RepoDetails::from_repo(repo, include_path))
+            .collect();
+
+        TaskServer::success(&GetReposResponse {
+            count: repos.len(),
+            repos,
+            not_found,
         })
     }
 
@@ -1130,7 +2576,7 @@ impl TaskServer {
     }
 
     #[tool(
-        description = "List all the task/tickets in a project with optional filtering and execution status. `project_id` is required!"
+        description = "List all the task/tickets in a project with optional filtering and execution status. Filter by status and by creation or update time (RFC3339), e.g. created_after for tasks created this week or updated_after for tasks updated since yesterday. `project_id` is required!"
     )]
     async fn list_tasks(
         &self,
@@ -1138,6 +2584,9 @@ impl TaskServer {
             project_id,
             status,
             limit,
+            created_after,
+            created_before,
+            updated_after,
         }): Parameters<ListTasksRequest>,
     ) -> Result<CallToolResult, ErrorData> {
         let status_filter = if let Some(ref status_str) = status {
@@ -1154,22 +2603,38 @@ impl TaskServer {
             None
         };
 
-        let url = self.url(&format!("/api/tasks?project_id={}", project_id));
-        let all_tasks: Vec<TaskWithAttemptStatus> =
-            match self.send_json(self.client.get(&url)).await {
+        let mut params = vec![("project_id", project_id.to_string())];
+        if let Some(status) = status_filter {
+            params.push(("status", status.to_string()));
+        }
+        let mut times = Vec::new();
+        for (name, value) in [
+            ("created_after", created_after),
+            ("created_before", created_before),
+            ("updated_after", updated_after),
+        ] {
+            let time = match parse_time_filter(name, value.as_deref()) {
+                Ok(time) => time.map(|time| time.to_rfc3339()),
+                Err(e) => return Self::err(e, None),
+            };
+            if let Some(time) = &time {
+                params.push((name, time.clone()));
+            }
+            times.push(time);
+        }
+        let [created_after, created_before, updated_after]: [Option<String>; 3] =
+            times.try_into().expect("one entry per time filter");
+
+        // Filtering happens server-side
+        let url = self.url("/api/tasks");
+        let tasks: Vec<TaskWithAttemptStatus> =
+            match self.send_json(self.client.get(&url).query(&params)).await {
                 Ok(t) => t,
                 Err(e) => return Ok(e),
             };
 
         let task_limit = limit.unwrap_or(50).max(0) as usize;
-        let filtered = all_tasks.into_iter().filter(|t| {
-            if let Some(ref want) = status_filter {
-                &t.status == want
-            } else {
-                true
-            }
-        });
-        let limited: Vec<TaskWithAttemptStatus> = filtered.take(task_limit).collect();
+        let limited: Vec<TaskWithAttemptStatus> = tasks.into_iter().take(task_limit).collect();
 
         let task_summaries: Vec<TaskSummary> = limited
             .into_iter()
@@ -1183,6 +2648,9 @@ impl TaskServer {
             applied_filters: ListTasksFilters {
                 status: status.clone(),
                 limit: task_limit as i32,
+                created_after,
+                created_before,
+                updated_after,
             },
         };
 
@@ -1190,68 +2658,318 @@ impl TaskServer {
     }
 
     #[tool(
-        description = "Start working on a task by creating and launching a new workspace session."
+        description = "Move a task to just before `before_task_id`, or to the end of the project's order when it is omitted. Only the moved task's position changes unless the neighbouring positions leave no room. `task_id` is required!"
     )]
-    async fn start_workspace_session(
+    async fn move_task(
         &self,
-        Parameters(StartWorkspaceSessionRequest {
+        Parameters(MoveTaskRequest {
             task_id,
-            executor,
-            variant,
-            repos,
-        }): Parameters<StartWorkspaceSessionRequest>,
+            before_task_id,
+        }): Parameters<MoveTaskRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        if repos.is_empty() {
-            return Self::err(
-                "At least one repository must be specified.",
-                None::<&str>,
-            );
-        }
+        let task_url = self.url(&format!("/api/tasks/{}", task_id));
+        let task: Task = match self.send_json(self.client.get(&task_url)).await {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
 
-        let executor_trimmed = executor.trim();
-        if executor_trimmed.is_empty() {
-            return Self::err("Executor must not be empty.", None::<&str>);
-        }
+        let list_url = self.url(&format!("/api/tasks?project_id={}", task.project_id));
+        let tasks: Vec<TaskWithAttemptStatus> =
+            match self.send_json(self.client.get(&list_url)).await {
+                Ok(t) => t,
+                Err(e) => return Ok(e),
+            };
+        let mut order: Vec<(Uuid, i32)> = tasks.iter().map(|t| (t.id, t.position)).collect();
+        order.sort_by_key(|(_, position)| *position);
 
-        let normalized_executor = executor_trimmed.replace('-', "_").to_ascii_uppercase();
-        let base_executor = match BaseCodingAgent::from_str(&normalized_executor) {
-            Ok(exec) => exec,
-            Err(_) => {
-                return Self::err(format!("Unknown executor '{executor_trimmed}'."), None);
-            }
+        let updates = match Self::plan_task_move(&order, task_id, before_task_id) {
+            Ok(updates) => updates,
+            Err(msg) => return Self::err(msg, before_task_id.map(|id| id.to_string())),
         };
-
-        let variant = variant.and_then(|v| {
-            let trimmed = v.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.to_string())
+        let position = updates
+            .iter()
+            .find(|(id, _)| *id == task_id)
+            .map_or(task.position, |(_, position)| *position);
+
+        if let [(_, position)] = updates.as_slice() {
+            let payload = serde_json::json!({ "position": position });
+            if let Err(e) = self
+                .send_json::<Task>(self.client.put(&task_url).json(&payload))
+                .await
+            {
+                return Ok(e);
             }
-        });
+        } else if !updates.is_empty() {
+            let url = self.url("/api/tasks/batch-update-positions");
+            let updates: Vec<_> = updates
+                .iter()
+                .map(|(id, position)| serde_json::json!({ "task_id": id, "position": position }))
+                .collect();
+            let payload = serde_json::json!({ "updates": updates });
+            if let Err(e) = self
+                .send_empty_json(self.client.post(&url).json(&payload))
+                .await
+            {
+                return Ok(e);
+            }
+        }
 
-        let executor_profile_id = ExecutorProfileId {
-            executor: base_executor,
-            variant,
+        TaskServer::success(&MoveTaskResponse {
+            task_id: task_id.to_string(),
+            position,
+            renumbered: updates.iter().filter(|(id, _)| *id != task_id).count(),
+        })
+    }
+
+    #[tool(
+        description = "Get the dependencies of a task: the tasks it depends on and the tasks that depend on it, with their titles and statuses. `task_id` is required."
+    )]
+    async fn get_task_dependencies(
+        &self,
+        Parameters(GetTaskDependenciesRequest { task_id }): Parameters<GetTaskDependenciesRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/dependencies", task_id));
+        let depends_on: Vec<Uuid> = match self.send_json(self.client.get(&url)).await {
+            Ok(ids) => ids,
+            Err(e) => return Ok(e),
         };
 
-        let workspace_repos: Vec<WorkspaceRepoInput> = repos
-            .into_iter()
-            .map(|r| WorkspaceRepoInput {
-                repo_id: r.repo_id,
-                target_branch: r.base_branch,
-            })
-            .collect();
+        self.task_dependencies_result(task_id, depends_on).await
+    }
 
-        let payload = CreateTaskAttemptBody {
+    #[tool(
+        description = "Replace the dependencies of a task with `depends_on_ids`. Fails if the change would create a dependency cycle. Returns the task's dependencies afterwards. `task_id` and `depends_on_ids` are required."
+    )]
+    async fn set_task_dependencies(
+        &self,
+        Parameters(SetTaskDependenciesRequest {
             task_id,
-            executor_profile_id,
-            repos: workspace_repos,
+            depends_on_ids,
+        }): Parameters<SetTaskDependenciesRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/dependencies", task_id));
+        let body = serde_json::json!({ "dependency_ids": depends_on_ids });
+        let depends_on: Vec<Uuid> = match self.send_json(self.client.put(&url).json(&body)).await {
+            Ok(ids) => ids,
+            Err(e) => return Ok(e),
         };
 
-        let url = self.url("/api/task-attempts");
-        let workspace: Workspace = match self.send_json(self.client.post(&url).json(&payload)).await
-        {
+        self.task_dependencies_result(task_id, depends_on).await
+    }
+
+    #[tool(
+        description = "Create a subtask under an existing task, in the same project. Subtasks break a task down hierarchically (epic -> subtasks); use dependencies instead to express ordering. `parent_task_id` and `title` are required."
+    )]
+    async fn create_subtask(
+        &self,
+        Parameters(CreateSubtaskRequest {
+            parent_task_id,
+            title,
+            description,
+            priority,
+        }): Parameters<CreateSubtaskRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let priority = match priority
+            .as_deref()
+            .map(|p| TaskPriority::from_str(&p.to_lowercase()))
+        {
+            None => None,
+            Some(Ok(p)) => Some(p),
+            Some(Err(_)) => {
+                return Self::err(
+                    "Invalid priority. Valid values: 'urgent', 'high', 'medium', 'low'".to_string(),
+                    priority,
+                );
+            }
+        };
+
+        let parent_url = self.url(&format!("/api/tasks/{}", parent_task_id));
+        let parent: Task = match self.send_json(self.client.get(&parent_url)).await {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
+
+        let description = match description {
+            Some(desc) => Some(self.expand_tags(&desc).await),
+            None => None,
+        };
+        let create_task_data = CreateTask {
+            project_id: parent.project_id,
+            title: self.expand_tags(&title).await,
+            description,
+            status: None,
+            priority,
+            position: None,
+            parent_workspace_id: None,
+            parent_task_id: Some(parent_task_id),
+            image_ids: None,
+            label_ids: None,
+        };
+        let url = self.url("/api/tasks");
+        let task: Task = match self
+            .send_json(self.client.post(&url).json(&create_task_data))
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
+
+        TaskServer::success(&TaskSummary::from_task(task))
+    }
+
+    #[tool(
+        description = "List the direct subtasks of a task with their statuses and how many are done. `task_id` is required."
+    )]
+    async fn list_subtasks(
+        &self,
+        Parameters(ListSubtasksRequest { task_id }): Parameters<ListSubtasksRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/subtasks", task_id));
+        let subtasks: Vec<Task> = match self.send_json(self.client.get(&url)).await {
+            Ok(tasks) => tasks,
+            Err(e) => return Ok(e),
+        };
+
+        let done = subtasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Done)
+            .count() as i64;
+        let progress = subtask_progress(done, subtasks.len() as i64);
+        TaskServer::success(&ListSubtasksResponse {
+            task_id: task_id.to_string(),
+            subtasks: subtasks.into_iter().map(TaskSummary::from_task).collect(),
+            progress,
+        })
+    }
+
+    #[tool(
+        description = "Search the tasks of a project by text in their title or description, optionally filtered by status and labels. Cheaper than 'list_tasks' when looking for specific tasks. `project_id` and `query` are required."
+    )]
+    async fn search_tasks(
+        &self,
+        Parameters(SearchTasksRequest {
+            project_id,
+            query,
+            status,
+            label_ids,
+            limit,
+        }): Parameters<SearchTasksRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Some(ref status_str) = status
+            && TaskStatus::from_str(status_str).is_err()
+        {
+            return Self::err(
+                "Invalid status filter. Valid values: 'todo', 'inprogress', 'inreview', 'done', 'cancelled'".to_string(),
+                Some(status_str.to_string()),
+            );
+        }
+
+        let task_limit = limit.unwrap_or(50).max(0);
+        let mut params = vec![("query", query), ("limit", task_limit.to_string())];
+        if let Some(status) = status {
+            params.push(("status", status));
+        }
+        if let Some(label_ids) = label_ids.filter(|ids| !ids.is_empty()) {
+            let ids: Vec<String> = label_ids.iter().map(|id| id.to_string()).collect();
+            params.push(("label_ids", ids.join(",")));
+        }
+
+        let url = self.url(&format!("/api/projects/{}/tasks/search", project_id));
+        let result: crate::routes::tasks::SearchTasksResponse =
+            match self.send_json(self.client.get(&url).query(&params)).await {
+                Ok(r) => r,
+                Err(e) => return Ok(e),
+            };
+
+        let tasks: Vec<TaskSummary> = result
+            .tasks
+            .into_iter()
+            .map(TaskSummary::from_task)
+            .collect();
+        TaskServer::success(&SearchTasksResponse {
+            count: tasks.len(),
+            tasks,
+            total: result.total,
+            project_id: project_id.to_string(),
+        })
+    }
+
+    #[tool(
+        description = "Start working on a task by creating and launching a new workspace session."
+    )]
+    async fn start_workspace_session(
+        &self,
+        Parameters(StartWorkspaceSessionRequest {
+            task_id,
+            executor,
+            variant,
+            repos,
+        }): Parameters<StartWorkspaceSessionRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if repos.is_empty() {
+            return Self::err(
+                "At least one repository must be specified.",
+                None::<&str>,
+            );
+        }
+
+        let executor_trimmed = executor.trim();
+        if executor_trimmed.is_empty() {
+            return Self::err("Executor must not be empty.", None::<&str>);
+        }
+
+        let base_executor = match resolve_executor(executor_trimmed) {
+            Ok(exec) => exec,
+            Err(msg) => return Self::err(msg, None::<&str>),
+        };
+
+        let task_url = self.url(&format!("/api/tasks/{}", task_id));
+        let task: Task = match self.send_json(self.client.get(&task_url)).await {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
+        let repos_url = self.url(&format!("/api/projects/{}/repositories", task.project_id));
+        let project_repos: Vec<Repo> = match self.send_json(self.client.get(&repos_url)).await {
+            Ok(rs) => rs,
+            Err(e) => return Ok(e),
+        };
+        let requested_repos: Vec<Uuid> = repos.iter().map(|r| r.repo_id).collect();
+        let project_repo_ids: Vec<Uuid> = project_repos.iter().map(|r| r.id).collect();
+        if let Err(msg) = check_workspace_repos(&requested_repos, &project_repo_ids) {
+            return Self::err(msg, Some(task.project_id.to_string()));
+        }
+
+        let variant = variant.and_then(|v| {
+            let trimmed = v.trim();
+            if trimmed.is_empty() {
+                None
+            } else {
+                Some(trimmed.to_string())
+            }
+        });
+
+        let executor_profile_id = ExecutorProfileId {
+            executor: base_executor,
+            variant,
+        };
+
+        let workspace_repos: Vec<WorkspaceRepoInput> = repos
+            .into_iter()
+            .map(|r| WorkspaceRepoInput {
+                repo_id: r.repo_id,
+                target_branch: r.base_branch,
+            })
+            .collect();
+
+        let payload = CreateTaskAttemptBody {
+            task_id,
+            executor_profile_id,
+            repos: workspace_repos,
+        };
+
+        let url = self.url("/api/task-attempts");
+        let workspace: Workspace = match self.send_json(self.client.post(&url).json(&payload)).await
+        {
             Ok(workspace) => workspace,
             Err(e) => return Ok(e),
         };
@@ -1265,7 +2983,7 @@ impl TaskServer {
     }
 
     #[tool(
-        description = "Update an existing task/ticket's title, description, or status. `task_id` is required. `title`, `description`, and `status` are optional."
+        description = "Update an existing task/ticket's title, description, status, or priority. `task_id` is required. `title`, `description`, `status`, and `priority` are optional."
     )]
     async fn update_task(
         &self,
@@ -1274,6 +2992,7 @@ impl TaskServer {
             title,
             description,
             status,
+            priority,
         }): Parameters<UpdateTaskRequest>,
     ) -> Result<CallToolResult, ErrorData> {
         let status = if let Some(ref status_str) = status {
@@ -1290,7 +3009,25 @@ impl TaskServer {
             None
         };
 
-        // Expand @tagname references in description
+        let priority = match priority
+            .as_deref()
+            .map(|p| TaskPriority::from_str(&p.to_lowercase()))
+        {
+            None => None,
+            Some(Ok(p)) => Some(p),
+            Some(Err(_)) => {
+                return Self::err(
+                    "Invalid priority. Valid values: 'urgent', 'high', 'medium', 'low'".to_string(),
+                    priority,
+                );
+            }
+        };
+
+        // Expand @tagname references in title and description
+        let title = match title {
+            Some(title) => Some(self.expand_tags(&title).await),
+            None => None,
+        };
         let expanded_description = match description {
             Some(desc) => Some(self.expand_tags(&desc).await),
             None => None,
@@ -1300,7 +3037,7 @@ impl TaskServer {
             title,
             description: expanded_description,
             status,
-            priority: None,
+            priority,
             position: None,
             parent_workspace_id: None,
             image_ids: None,
@@ -1317,6 +3054,28 @@ impl TaskServer {
         TaskServer::success(&response)
     }
 
+    #[tool(
+        description = "Mark a task as done. Refuses if any task it depends on is not done yet and lists those dependencies, unless `force` is true. `task_id` is required."
+    )]
+    async fn complete_task(
+        &self,
+        Parameters(MoveTaskRequest { task_id, force }): Parameters<MoveTaskRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.move_task_status(task_id, TaskStatus::Done, force.unwrap_or(false))
+            .await
+    }
+
+    #[tool(
+        description = "Move a task to 'inprogress'. Refuses if any task it depends on is not done yet and lists those dependencies, unless `force` is true. `task_id` is required."
+    )]
+    async fn start_task(
+        &self,
+        Parameters(MoveTaskRequest { task_id, force }): Parameters<MoveTaskRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.move_task_status(task_id, TaskStatus::InProgress, force.unwrap_or(false))
+            .await
+    }
+
     #[tool(description = "Delete a task/ticket. `task_id` is required.")]
     async fn delete_task(
         &self,
@@ -1396,6 +3155,38 @@ impl TaskServer {
         })
     }
 
+    #[tool(
+        description = "Read the PM docs for a project in pages. Returns at most `max_chars` characters starting at `offset`, plus `has_more` and `next_offset` for fetching the rest. Prefer this over loading the full docs when they may be large."
+    )]
+    async fn get_pm_docs(
+        &self,
+        Parameters(GetPmDocsRequest {
+            project_id,
+            max_chars,
+            offset,
+        }): Parameters<GetPmDocsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/projects/{}", project_id));
+        let project: Project = match self.send_json(self.client.get(&url)).await {
+            Ok(p) => p,
+            Err(e) => return Ok(e),
+        };
+
+        let docs = project.pm_docs.unwrap_or_default();
+        let offset = offset.unwrap_or(0);
+        let max_chars = max_chars.unwrap_or(DEFAULT_PM_DOCS_PAGE_CHARS).max(1);
+        let page = Self::paginate_chars(&docs, offset, max_chars);
+
+        TaskServer::success(&GetPmDocsResponse {
+            project_id: project_id.to_string(),
+            content: page.content.to_string(),
+            offset,
+            total_chars: page.total_chars,
+            has_more: page.next_offset.is_some(),
+            next_offset: page.next_offset,
+        })
+    }
+
     #[tool(
         description = "Request a PM-based review for a task. This generates a review prompt based on the project's PM specifications. Use this when a task is ready for review (status: inreview) to verify the implementation matches the project requirements."
     )]
@@ -1404,6 +3195,7 @@ impl TaskServer {
         Parameters(RequestPmReviewRequest {
             task_id,
             additional_instructions,
+            save_as_comment,
         }): Parameters<RequestPmReviewRequest>,
     ) -> Result<CallToolResult, ErrorData> {
         // First, get the task to find the project_id
@@ -1437,16 +3229,27 @@ impl TaskServer {
                     .unwrap_or_default()
             );
 
+            let review_checklist = vec![
+                "Code quality and best practices".to_string(),
+                "Potential bugs or edge cases".to_string(),
+                "Security considerations".to_string(),
+                "Test coverage".to_string(),
+            ];
+            let (review_id, comment_id) = match self
+                .record_review(task_id, &basic_prompt, &review_checklist, save_as_comment)
+                .await
+            {
+                Ok(ids) => ids,
+                Err(e) => return Ok(e),
+            };
+
             return TaskServer::success(&RequestPmReviewResponse {
                 task_id: task_id.to_string(),
                 has_pm_task: false,
                 review_prompt: basic_prompt,
-                review_checklist: vec![
-                    "Code quality and best practices".to_string(),
-                    "Potential bugs or edge cases".to_string(),
-                    "Security considerations".to_string(),
-                    "Test coverage".to_string(),
-                ],
+                review_checklist,
+                review_id,
+                comment_id,
             });
         };
 
@@ -1492,100 +3295,483 @@ impl TaskServer {
             "Edge cases and error handling".to_string(),
             "Test coverage".to_string(),
         ];
+        let (review_id, comment_id) = match self
+            .record_review(task_id, &review_prompt, &review_checklist, save_as_comment)
+            .await
+        {
+            Ok(ids) => ids,
+            Err(e) => return Ok(e),
+        };
 
         TaskServer::success(&RequestPmReviewResponse {
             task_id: task_id.to_string(),
             has_pm_task: true,
             review_prompt,
             review_checklist,
+            review_id,
+            comment_id,
         })
     }
 
-    /// Check if two task titles are similar enough to be considered duplicates.
-    /// Returns true if titles are duplicates (case-insensitive exact match or containment).
-    pub fn is_duplicate_title(new_title: &str, existing_title: &str) -> bool {
-        let new_lower = new_title.to_lowercase();
-        let existing_lower = existing_title.to_lowercase();
-        existing_lower == new_lower
-            || existing_lower.contains(&new_lower)
-            || new_lower.contains(&existing_lower)
-    }
-
-    /// Calculate project progress from task status counts.
-    pub fn calculate_progress(total_tasks: i32, completed_tasks: i32) -> f32 {
-        if total_tasks > 0 {
-            (completed_tasks as f32 / total_tasks as f32) * 100.0
-        } else {
-            0.0
-        }
-    }
-
     #[tool(
-        description = "List all file attachments for a task. Returns file names, sizes, MIME types, and download URLs."
+        description = "Add a comment to a task, e.g. a progress note, a decision, or review findings. Comments are kept in order and shown with the task. `task_id` and `content` are required!"
     )]
-    async fn list_task_attachments(
+    async fn add_task_comment(
         &self,
-        Parameters(ListTaskAttachmentsRequest { task_id }): Parameters<ListTaskAttachmentsRequest>,
+        Parameters(AddTaskCommentRequest { task_id, content }): Parameters<AddTaskCommentRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        let url = self.url(&format!("/api/tasks/{}/attachments", task_id));
+        let comment = match self.post_task_comment(task_id, content).await {
+            Ok(c) => c,
+            Err(e) => return Ok(e),
+        };
 
-        #[derive(Deserialize)]
-        struct AttachmentResponse {
-            id: Uuid,
-            file_name: String,
-            mime_type: String,
-            file_size: i64,
-            download_url: String,
-            created_at: String,
-        }
+        TaskServer::success(&TaskCommentInfo::from_comment(comment))
+    }
 
-        let attachments: Vec<AttachmentResponse> = match self.send_json(self.client.get(&url)).await {
-            Ok(a) => a,
+    #[tool(description = "List the comments on a task, oldest first. `task_id` is required!")]
+    async fn list_task_comments(
+        &self,
+        Parameters(ListTaskCommentsRequest { task_id }): Parameters<ListTaskCommentsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/comments", task_id));
+        let comments: Vec<TaskComment> = match self.send_json(self.client.get(&url)).await {
+            Ok(c) => c,
             Err(e) => return Ok(e),
         };
 
-        let attachment_infos: Vec<TaskAttachmentInfo> = attachments
+        let comments: Vec<TaskCommentInfo> = comments
             .into_iter()
-            .map(|a| TaskAttachmentInfo {
-                id: a.id.to_string(),
-                file_name: a.file_name,
-                mime_type: a.mime_type,
-                file_size: a.file_size,
-                download_url: a.download_url,
-                created_at: a.created_at,
-            })
+            .map(TaskCommentInfo::from_comment)
             .collect();
-
-        let count = attachment_infos.len();
-        TaskServer::success(&ListTaskAttachmentsResponse {
+        TaskServer::success(&ListTaskCommentsResponse {
             task_id: task_id.to_string(),
-            attachments: attachment_infos,
-            count,
+            count: comments.len(),
+            comments,
         })
     }
 
-    #[tool(
-        description = "Attach a file from a local path to a task. The file will be uploaded and stored. Use this to attach reference documents, design files, screenshots, etc. to a task."
-    )]
-    async fn attach_file_to_task(
-        &self,
-        Parameters(AttachFileToTaskRequest { task_id, file_path, file_name }): Parameters<AttachFileToTaskRequest>,
-    ) -> Result<CallToolResult, ErrorData> {
-        use std::path::Path;
+    /// Describe the tasks of a `create_tasks` batch created so far, as `temp_id=task_id` pairs
+    fn batch_task_ids(tasks: &[BatchTaskInput], created: &[Uuid]) -> String {
+        tasks
+            .iter()
+            .zip(created)
+            .map(|(task, id)| format!("{}={}", task.temp_id, id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-        // Read the file
-        let path = Path::new(&file_path);
-        if !path.exists() {
-            return Self::err("File not found", Some(&file_path));
+    /// Resolve the `depends_on` entries of a `create_tasks` batch to batch indexes
+    /// or existing task IDs. Fails on duplicate or unknown temp_ids and on cycles
+    /// within the batch.
+    pub fn resolve_batch_dependencies(
+        tasks: &[BatchTaskInput],
+    ) -> Result<Vec<Vec<BatchDependency>>, String> {
+        let mut indexes = std::collections::HashMap::new();
+        for (index, task) in tasks.iter().enumerate() {
+            let temp_id = task.temp_id.trim();
+            if temp_id.is_empty() {
+                return Err(format!("Task '{}' has an empty temp_id", task.title));
+            }
+            if indexes.insert(temp_id, index).is_some() {
+                return Err(format!("Duplicate temp_id '{}'", temp_id));
+            }
         }
 
-        let file_data = match tokio::fs::read(&path).await {
-            Ok(data) => data,
-            Err(e) => return Self::err("Failed to read file", Some(&e.to_string())),
-        };
-
-        let actual_file_name = file_name.unwrap_or_else(|| {
-            path.file_name()
+        let mut dependencies = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let mut deps = Vec::new();
+            for reference in task.depends_on.iter().flatten() {
+                let reference = reference.trim();
+                let dep = match indexes.get(reference) {
+                    Some(index) => BatchDependency::Batch(*index),
+                    None => match Uuid::parse_str(reference) {
+                        Ok(id) => BatchDependency::Existing(id),
+                        Err(_) => {
+                            return Err(format!(
+                                "Task '{}' depends on unknown temp_id '{}'",
+                                task.temp_id, reference
+                            ));
+                        }
+                    },
+                };
+                if !deps.contains(&dep) {
+                    deps.push(dep);
+                }
+            }
+            dependencies.push(deps);
+        }
+
+        // Kahn's algorithm over the dependencies within the batch
+        let mut unmet: Vec<usize> = dependencies
+            .iter()
+            .map(|deps| {
+                deps.iter()
+                    .filter(|dep| matches!(dep, BatchDependency::Batch(_)))
+                    .count()
+            })
+            .collect();
+        let mut ready: Vec<usize> = (0..tasks.len()).filter(|i| unmet[*i] == 0).collect();
+        let mut resolved = 0;
+        while let Some(done) = ready.pop() {
+            resolved += 1;
+            for (index, deps) in dependencies.iter().enumerate() {
+                if deps.contains(&BatchDependency::Batch(done)) {
+                    unmet[index] -= 1;
+                    if unmet[index] == 0 {
+                        ready.push(index);
+                    }
+                }
+            }
+        }
+        if resolved < tasks.len() {
+            let cyclic: Vec<&str> = tasks
+                .iter()
+                .zip(&unmet)
+                .filter(|(_, count)| **count > 0)
+                .map(|(task, _)| task.temp_id.as_str())
+                .collect();
+            return Err(format!(
+                "Dependencies form a cycle involving: {}",
+                cyclic.join(", ")
+            ));
+        }
+
+        Ok(dependencies)
+    }
+
+    /// Check if two task titles are similar enough to be considered duplicates.
+    /// Returns true for a case-insensitive exact match, or when the
+    /// [`title_similarity`](Self::title_similarity) reaches `threshold`.
+    pub fn is_duplicate_title(new_title: &str, existing_title: &str, threshold: f64) -> bool {
+        if new_title.trim().to_lowercase() == existing_title.trim().to_lowercase() {
+            return true;
+        }
+        Self::title_similarity(new_title, existing_title) >= threshold
+    }
+
+    /// Jaccard similarity (0.0-1.0) of the significant words of two titles.
+    /// Words are lowercased and stop words ignored; titles without any
+    /// significant words have a similarity of 0.0.
+    pub fn title_similarity(a: &str, b: &str) -> f64 {
+        let tokens = |title: &str| -> HashSet<String> {
+            title
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty() && !TITLE_STOP_WORDS.contains(word))
+                .map(|word| word.to_string())
+                .collect()
+        };
+        let (a, b) = (tokens(a), tokens(b));
+        let union = a.union(&b).count();
+        if union == 0 {
+            return 0.0;
+        }
+        a.intersection(&b).count() as f64 / union as f64
+    }
+
+    /// The dependencies in `depends_on` that are not done. Like
+    /// `TaskDependency::are_dependencies_met`, dependencies on tasks missing from
+    /// `tasks` are ignored.
+    pub fn incomplete_dependencies(depends_on: &[Uuid], tasks: &[Task]) -> Vec<DependencyTaskRef> {
+        depends_on
+            .iter()
+            .filter_map(|id| tasks.iter().find(|t| t.id == *id))
+            .filter(|t| t.status != TaskStatus::Done)
+            .map(|t| DependencyTaskRef {
+                id: t.id.to_string(),
+                title: Some(t.title.clone()),
+                status: Some(t.status.to_string()),
+            })
+            .collect()
+    }
+
+    /// The IDs in `ids` that are not in `project_ids`, without duplicates
+    pub fn ids_outside_project(ids: &[Uuid], project_ids: &HashSet<Uuid>) -> Vec<Uuid> {
+        let mut seen = HashSet::new();
+        ids.iter()
+            .filter(|id| !project_ids.contains(id) && seen.insert(**id))
+            .copied()
+            .collect()
+    }
+
+    /// Position updates that move `task_id` directly before `before_task_id`, or
+    /// to the end when it is `None`. `order` lists the project's tasks in their
+    /// current order. The moved task takes the midpoint between its new
+    /// neighbours; only when they leave no room is the whole project renumbered
+    /// `TASK_POSITION_GAP` apart, returning an update for every changed task.
+    pub fn plan_task_move(
+        order: &[(Uuid, i32)],
+        task_id: Uuid,
+        before_task_id: Option<Uuid>,
+    ) -> Result<Vec<(Uuid, i32)>, String> {
+        if !order.iter().any(|(id, _)| *id == task_id) {
+            return Err(format!("Task {} not found in its project", task_id));
+        }
+        if before_task_id == Some(task_id) {
+            return Err("A task cannot be moved before itself".to_string());
+        }
+        let mut others: Vec<(Uuid, i32)> = order
+            .iter()
+            .filter(|(id, _)| *id != task_id)
+            .copied()
+            .collect();
+        let index = match before_task_id {
+            Some(before) => others
+                .iter()
+                .position(|(id, _)| *id == before)
+                .ok_or_else(|| format!("Task {} is not in the same project", before))?,
+            None => others.len(),
+        };
+
+        let prev = index.checked_sub(1).map(|i| others[i].1);
+        let next = others.get(index).map(|(_, position)| *position);
+        let position = match (prev, next) {
+            (Some(prev), Some(next)) if next - prev >= 2 => Some(prev + (next - prev) / 2),
+            (Some(_), Some(_)) => None,
+            (None, Some(next)) => next.checked_sub(TASK_POSITION_GAP),
+            (Some(prev), None) => prev.checked_add(TASK_POSITION_GAP),
+            (None, None) => Some(0),
+        };
+        if let Some(position) = position {
+            return Ok(vec![(task_id, position)]);
+        }
+
+        // No room between the neighbours: spread the whole order out again
+        others.insert(index, (task_id, i32::MIN));
+        Ok(others
+            .iter()
+            .zip(1..)
+            .map(|((id, old), i)| (*id, *old, i * TASK_POSITION_GAP))
+            .filter(|(_, old, new)| old != new)
+            .map(|(id, _, new)| (id, new))
+            .collect())
+    }
+
+    fn join_ids(ids: &[Uuid]) -> String {
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Slice `text` by character (not byte) offset so multibyte content is never split.
+    pub fn paginate_chars(text: &str, offset: usize, max_chars: usize) -> CharPage<'_> {
+        let total_chars = text.chars().count();
+        let byte_at = |char_idx: usize| {
+            text.char_indices()
+                .nth(char_idx)
+                .map(|(i, _)| i)
+                .unwrap_or(text.len())
+        };
+
+        let start = byte_at(offset);
+        let end_char = offset.saturating_add(max_chars);
+        let end = byte_at(end_char);
+
+        CharPage {
+            content: &text[start..end],
+            total_chars,
+            next_offset: (end_char < total_chars).then_some(end_char),
+        }
+    }
+
+    /// Find tasks that have been sitting in review without updates for at least `threshold`.
+    /// Returns the stale tasks paired with how long they have been idle.
+    pub fn find_stale_reviews<'a>(
+        tasks: &'a [Task],
+        now: DateTime<Utc>,
+        threshold: chrono::Duration,
+    ) -> Vec<(&'a Task, chrono::Duration)> {
+        tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::InReview)
+            .map(|t| (t, now - t.updated_at))
+            .filter(|(_, idle)| *idle >= threshold)
+            .collect()
+    }
+
+    /// Build the description for a stale review task that is being moved back to todo.
+    pub fn stale_review_note(description: Option<&str>, hours_stale: i64) -> String {
+        let note = format!(
+            "> Moved back to todo: no review activity for {} hours.",
+            hours_stale
+        );
+        match description.filter(|d| !d.trim().is_empty()) {
+            Some(existing) => format!("{}\n\n{}", existing, note),
+            None => note,
+        }
+    }
+
+    /// Combine new `content` with the existing PM docs. With a `section`, only the
+    /// body of that section is replaced and `mode` is ignored.
+    pub fn updated_pm_docs(
+        existing: Option<&str>,
+        content: String,
+        mode: PmDocsUpdateMode,
+        section: Option<&str>,
+    ) -> String {
+        if let Some(section) = section.filter(|s| !s.trim().is_empty()) {
+            return replace_docs_section(existing, section, &content);
+        }
+        match (mode, existing.filter(|e| !e.is_empty())) {
+            (PmDocsUpdateMode::Replace, _) | (_, None) => content,
+            (PmDocsUpdateMode::Append, Some(existing)) => format!("{}\n\n{}", existing, content),
+            (PmDocsUpdateMode::Prepend, Some(existing)) => format!("{}\n\n{}", content, existing),
+        }
+    }
+
+    #[tool(
+        description = "Find tasks stuck in review. Lists 'inreview' tasks that have not been updated within `stale_after_hours` (default 72). With mode='apply', moves them back to 'todo' and appends a note to the description. Defaults to mode='report', which changes nothing."
+    )]
+    async fn close_stale_reviews(
+        &self,
+        Parameters(CloseStaleReviewsRequest {
+            project_id,
+            stale_after_hours,
+            mode,
+        }): Parameters<CloseStaleReviewsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let mode = match mode.as_deref().map(StaleReviewMode::from_str) {
+            None => StaleReviewMode::Report,
+            Some(Ok(m)) => m,
+            Some(Err(e)) => {
+                return Self::err(
+                    "Invalid mode. Valid values: 'report', 'apply'".to_string(),
+                    Some(e),
+                );
+            }
+        };
+        let stale_after_hours = stale_after_hours.unwrap_or(72);
+
+        let url = self.url(&format!("/api/tasks?project_id={}", project_id));
+        let all_tasks: Vec<TaskWithAttemptStatus> =
+            match self.send_json(self.client.get(&url)).await {
+                Ok(t) => t,
+                Err(e) => return Ok(e),
+            };
+        let tasks: Vec<Task> = all_tasks.into_iter().map(|t| t.task).collect();
+
+        let stale = Self::find_stale_reviews(
+            &tasks,
+            Utc::now(),
+            chrono::Duration::hours(stale_after_hours as i64),
+        );
+
+        let mut results = Vec::with_capacity(stale.len());
+        for (task, idle) in stale {
+            let hours_stale = idle.num_hours();
+            let mut moved_to_todo = false;
+
+            if mode == StaleReviewMode::Apply {
+                let payload = UpdateTask {
+                    title: None,
+                    description: Some(Self::stale_review_note(
+                        task.description.as_deref(),
+                        hours_stale,
+                    )),
+                    status: Some(TaskStatus::Todo),
+                    priority: None,
+                    position: None,
+                    parent_workspace_id: None,
+                    image_ids: None,
+                    label_ids: None,
+                };
+                let update_url = self.url(&format!("/api/tasks/{}", task.id));
+                match self
+                    .send_json::<Task>(self.client.put(&update_url).json(&payload))
+                    .await
+                {
+                    Ok(_) => moved_to_todo = true,
+                    Err(_) => {
+                        tracing::warn!("Failed to move stale review task {} to todo", task.id);
+                    }
+                }
+            }
+
+            results.push(StaleReviewTask {
+                id: task.id.to_string(),
+                title: task.title.clone(),
+                updated_at: task.updated_at.to_rfc3339(),
+                hours_stale,
+                moved_to_todo,
+            });
+        }
+
+        TaskServer::success(&CloseStaleReviewsResponse {
+            project_id: project_id.to_string(),
+            mode: mode.to_string(),
+            stale_after_hours,
+            count: results.len(),
+            tasks: results,
+        })
+    }
+
+    #[tool(
+        description = "List all file attachments for a task. Returns file names, sizes, MIME types, and download URLs."
+    )]
+    async fn list_task_attachments(
+        &self,
+        Parameters(ListTaskAttachmentsRequest { task_id }): Parameters<ListTaskAttachmentsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/attachments", task_id));
+
+        #[derive(Deserialize)]
+        struct AttachmentResponse {
+            id: Uuid,
+            file_name: String,
+            mime_type: String,
+            file_size: i64,
+            download_url: String,
+            created_at: String,
+        }
+
+        let attachments: Vec<AttachmentResponse> = match self.send_json(self.client.get(&url)).await {
+            Ok(a) => a,
+            Err(e) => return Ok(e),
+        };
+
+        let attachment_infos: Vec<TaskAttachmentInfo> = attachments
+            .into_iter()
+            .map(|a| TaskAttachmentInfo {
+                id: a.id.to_string(),
+                file_name: a.file_name,
+                mime_type: a.mime_type,
+                file_size: a.file_size,
+                download_url: a.download_url,
+                created_at: a.created_at,
+            })
+            .collect();
+
+        let count = attachment_infos.len();
+        TaskServer::success(&ListTaskAttachmentsResponse {
+            task_id: task_id.to_string(),
+            attachments: attachment_infos,
+            count,
+        })
+    }
+
+    #[tool(
+        description = "Attach a file from a local path to a task. The file will be uploaded and stored. Use this to attach reference documents, design files, screenshots, etc. to a task."
+    )]
+    async fn attach_file_to_task(
+        &self,
+        Parameters(AttachFileToTaskRequest { task_id, file_path, file_name }): Parameters<AttachFileToTaskRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        use std::path::Path;
+
+        // Read the file
+        let path = Path::new(&file_path);
+        if !path.exists() {
+            return Self::err("File not found", Some(&file_path));
+        }
+
+        let file_data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) => return Self::err("Failed to read file", Some(&e.to_string())),
+        };
+
+        let actual_file_name = file_name.unwrap_or_else(|| {
+            path.file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("attachment")
                 .to_string()
@@ -1699,7 +3885,141 @@ impl TaskServer {
     }
 
     #[tool(
-        description = "Update the PM (Project Manager) documentation for a project. Use this to save specifications, requirements, architecture notes, or any project documentation. The PM docs are stored as markdown and can be viewed in the PM Docs panel."
+        description = "List all labels of a project with their IDs, names, colors, and preferred executors. Use the IDs in 'label_ids' when creating tasks. `project_id` is required!"
+    )]
+    async fn list_labels(
+        &self,
+        Parameters(ListLabelsRequest { project_id }): Parameters<ListLabelsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/projects/{}/labels", project_id));
+        let labels: Vec<Label> = match self.send_json(self.client.get(&url)).await {
+            Ok(l) => l,
+            Err(e) => return Ok(e),
+        };
+
+        let labels: Vec<LabelInfo> = labels.into_iter().map(LabelInfo::from_label).collect();
+        TaskServer::success(&ListLabelsResponse {
+            project_id: project_id.to_string(),
+            count: labels.len(),
+            labels,
+        })
+    }
+
+    #[tool(description = "Get the labels attached to a task. `task_id` is required!")]
+    async fn get_task_labels(
+        &self,
+        Parameters(GetTaskLabelsRequest { task_id }): Parameters<GetTaskLabelsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/labels", task_id));
+        let labels: Vec<Label> = match self.send_json(self.client.get(&url)).await {
+            Ok(l) => l,
+            Err(e) => return Ok(e),
+        };
+
+        Self::task_labels_result(task_id, labels)
+    }
+
+    #[tool(
+        description = "Replace all labels of a task with `label_ids`. Labels must belong to the task's project. Returns the task's labels afterwards."
+    )]
+    async fn set_task_labels(
+        &self,
+        Parameters(SetTaskLabelsRequest { task_id, label_ids }): Parameters<SetTaskLabelsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/labels", task_id));
+        let body = serde_json::json!({ "label_ids": label_ids });
+        let labels: Vec<Label> = match self.send_json(self.client.put(&url).json(&body)).await {
+            Ok(l) => l,
+            Err(e) => return Ok(e),
+        };
+
+        Self::task_labels_result(task_id, labels)
+    }
+
+    #[tool(
+        description = "Add a label to a task, keeping its other labels. The label must belong to the task's project. Returns the task's labels afterwards."
+    )]
+    async fn add_task_label(
+        &self,
+        Parameters(TaskLabelRequest { task_id, label_id }): Parameters<TaskLabelRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/labels/{}", task_id, label_id));
+        let labels: Vec<Label> = match self.send_json(self.client.post(&url)).await {
+            Ok(l) => l,
+            Err(e) => return Ok(e),
+        };
+
+        Self::task_labels_result(task_id, labels)
+    }
+
+    #[tool(
+        description = "Remove a label from a task, keeping its other labels. Returns the task's labels afterwards."
+    )]
+    async fn remove_task_label(
+        &self,
+        Parameters(TaskLabelRequest { task_id, label_id }): Parameters<TaskLabelRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/labels/{}", task_id, label_id));
+        let labels: Vec<Label> = match self.send_json(self.client.delete(&url)).await {
+            Ok(l) => l,
+            Err(e) => return Ok(e),
+        };
+
+        Self::task_labels_result(task_id, labels)
+    }
+
+    #[tool(
+        description = "Suggest labels for a task from keywords in its title and description (e.g. bug/fix -> bug, UI/画面 -> frontend, API -> backend). Results are deterministic and ranked, strongest match first. Does not change the task; apply suggestions with 'set_task_labels' or 'add_task_label'. `task_id` is required!"
+    )]
+    async fn suggest_labels(
+        &self,
+        Parameters(SuggestLabelsRequest { task_id }): Parameters<SuggestLabelsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}", task_id));
+        let task: Task = match self.send_json(self.client.get(&url)).await {
+            Ok(t) => t,
+            Err(e) => return Ok(e),
+        };
+
+        let url = self.url(&format!(
+            "/api/projects/{}/tasks/{}/suggest-labels",
+            task.project_id, task_id
+        ));
+        let suggestions: Vec<LabelSuggestion> = match self.send_json(self.client.post(&url)).await {
+            Ok(s) => s,
+            Err(e) => return Ok(e),
+        };
+
+        let suggestions: Vec<LabelSuggestionInfo> = suggestions
+            .into_iter()
+            .map(LabelSuggestionInfo::from_suggestion)
+            .collect();
+        TaskServer::success(&SuggestLabelsResponse {
+            task_id: task_id.to_string(),
+            count: suggestions.len(),
+            suggestions,
+        })
+    }
+
+    #[tool(
+        description = "Recommend which coding agent should run a task, from the executors set on its labels. The executor set on the most labels wins; ties go to the label whose name sorts first. Returns executor=null when no label specifies one. Pass the result as 'executor' to 'start_workspace_session'. `task_id` is required!"
+    )]
+    async fn suggest_executor(
+        &self,
+        Parameters(SuggestExecutorRequest { task_id }): Parameters<SuggestExecutorRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let url = self.url(&format!("/api/tasks/{}/suggest-executor", task_id));
+        let suggestion: Option<ExecutorSuggestion> =
+            match self.send_optional_json(self.client.post(&url)).await {
+                Ok(s) => s,
+                Err(e) => return Ok(e),
+            };
+
+        TaskServer::success(&SuggestExecutorResponse::new(task_id, suggestion))
+    }
+
+    #[tool(
+        description = "Update the PM (Project Manager) documentation for a project. Use this to save specifications, requirements, architecture notes, or any project documentation. The PM docs are stored as markdown and can be viewed in the PM Docs panel. The previous content is kept in the PM docs history and can be restored."
     )]
     async fn update_pm_docs(
         &self,
@@ -1707,60 +4027,85 @@ impl TaskServer {
             project_id,
             content,
             mode,
+            section,
+            return_diff,
         }): Parameters<UpdatePmDocsRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        // First, get the current project to check existing docs
-        let url = self.url(&format!("/api/projects/{}", project_id));
-        let project: Project = match self.send_json(self.client.get(&url)).await {
-            Ok(p) => p,
-            Err(e) => return Ok(e),
-        };
-
-        // Determine new docs based on mode
-        let mode_str = mode.as_deref().unwrap_or("append");
-        let new_docs = if mode_str == "replace" {
-            content
-        } else {
-            // Append mode
-            match project.pm_docs {
-                Some(existing) if !existing.is_empty() => format!("{}\n\n{}", existing, content),
-                _ => content,
+        let mode = match mode.as_deref().map(PmDocsUpdateMode::from_str) {
+            None => PmDocsUpdateMode::Append,
+            Some(Ok(m)) => m,
+            Some(Err(e)) => {
+                return Self::err(
+                    "Invalid mode. Valid values: 'replace', 'append', 'prepend'".to_string(),
+                    Some(e),
+                );
             }
         };
-
-        // Update the project with new PM docs
+        let url = self.url(&format!("/api/projects/{}", project_id));
         let update_url = self.url(&format!("/api/projects/{}/pm-chat/docs", project_id));
-        let update_body = serde_json::json!({
-            "pm_docs": new_docs
-        });
+        let mut retry_on_conflict = mode.retries_on_conflict(section.as_deref());
 
-        let response = self
-            .client
-            .put(&update_url)
-            .json(&update_body)
-            .send()
-            .await;
+        loop {
+            // Get the current project to build on the existing docs
+            let project: Project = match self.send_json(self.client.get(&url)).await {
+                Ok(p) => p,
+                Err(e) => return Ok(e),
+            };
 
-        match response {
-            Ok(resp) if resp.status().is_success() => {
-                TaskServer::success(&UpdatePmDocsResponse {
-                    project_id: project_id.to_string(),
-                    success: true,
-                    pm_docs: Some(new_docs),
-                })
-            }
-            Ok(resp) => {
-                let status = resp.status();
-                let body = resp.text().await.unwrap_or_default();
-                Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Failed to update PM docs: {} - {}",
-                    status, body
-                ))]))
+            let new_docs = Self::updated_pm_docs(
+                project.pm_docs.as_deref(),
+                content.clone(),
+                mode,
+                section.as_deref(),
+            );
+            let diff = if return_diff.unwrap_or(false) {
+                pm_docs_diff(project.pm_docs.as_deref(), Some(&new_docs))
+            } else {
+                None
+            };
+
+            // Only save if nobody changed the docs since they were fetched
+            let update_body = serde_json::json!({
+                "pm_docs": new_docs,
+                "source": "agent",
+                "expected_updated_at": project.updated_at,
+            });
+
+            let response = self.client.put(&update_url).json(&update_body).send().await;
+
+            match response {
+                Ok(resp) if resp.status().is_success() => {
+                    return TaskServer::success(&UpdatePmDocsResponse {
+                        project_id: project_id.to_string(),
+                        success: true,
+                        pm_docs: Some(new_docs),
+                        diff,
+                    });
+                }
+                Ok(resp) if resp.status() == reqwest::StatusCode::CONFLICT => {
+                    if retry_on_conflict {
+                        retry_on_conflict = false;
+                        continue;
+                    }
+                    return Ok(CallToolResult::error(vec![Content::text(
+                        "Failed to update PM docs: they were changed by someone else in the meantime. Read them again with 'get_pm_docs' and retry.".to_string(),
+                    )]));
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Failed to update PM docs: {} - {}",
+                        status, body
+                    ))]));
+                }
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Failed to update PM docs: {}",
+                        e
+                    ))]));
+                }
             }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Failed to update PM docs: {}",
-                e
-            ))])),
         }
     }
 }
@@ -1768,7 +4113,7 @@ impl TaskServer {
 #[tool_handler]
 impl ServerHandler for TaskServer {
     fn get_info(&self) -> ServerInfo {
-        let mut instruction = "A task and project management server with PM (Project Manager) capabilities. TOOLS: 'list_projects', 'list_tasks', 'create_task', 'get_project_progress', 'start_workspace_session', 'get_task', 'update_task', 'delete_task', 'list_repos', 'get_repo', 'update_setup_script', 'update_cleanup_script', 'update_dev_server_script', 'get_pm_context', 'request_pm_review', 'update_pm_docs', 'list_task_attachments', 'attach_file_to_task', 'delete_task_attachment'. PM FEATURES: Use 'create_task' with check_duplicate=true to avoid creating duplicate tasks. Use 'create_task' with depends_on=[task_ids] to set task dependencies. Use 'get_project_progress' to get completion percentage and task status summary. Use 'get_pm_context' to fetch project specifications before implementing. Use 'request_pm_review' for review checklists. Use 'update_pm_docs' to save structured documentation. ATTACHMENTS: Use 'attach_file_to_task' to attach reference documents, design files, or screenshots to a task. Use 'list_task_attachments' to see all attached files. Always pass project_id where required.".to_string();
+        let mut instruction = "A task and project management server with PM (Project Manager) capabilities. TOOLS: 'list_projects', 'list_tasks', 'search_tasks', 'create_task', 'create_tasks', 'start_task', 'complete_task', 'get_project_progress', 'get_task_order', 'get_critical_path', 'get_actionable_tasks', 'start_workspace_session', 'get_task', 'update_task', 'delete_task', 'list_repos', 'get_repo', 'get_repos', 'update_setup_script', 'update_cleanup_script', 'update_dev_server_script', 'get_pm_context', 'request_pm_review', 'update_pm_docs', 'get_pm_docs', 'list_task_attachments', 'attach_file_to_task', 'delete_task_attachment', 'close_stale_reviews', 'list_labels', 'get_task_labels', 'set_task_labels', 'add_task_label', 'remove_task_label', 'suggest_labels', 'suggest_executor', 'get_task_dependencies', 'set_task_dependencies', 'create_subtask', 'list_subtasks', 'move_task', 'add_task_comment', 'list_task_comments'. PM FEATURES: Use 'create_task' with check_duplicate=true to avoid creating duplicate tasks. Use 'create_tasks' to create several related tasks at once, referencing each other by temp_id in depends_on. Prefer 'start_task' and 'complete_task' over 'update_task' for status changes: they refuse while dependencies are not done. Use 'create_task' with depends_on=[task_ids] to set task dependencies, and 'get_task_dependencies'/'set_task_dependencies' to read or change them later. Use 'create_subtask' to break a task down into subtasks (a hierarchy, unlike dependencies, which order tasks) and 'list_subtasks' to see them and how many are done. Use 'move_task' to order a backlog; listed tasks carry their position. Use 'search_tasks' to find tasks by text, status, or labels instead of listing all tasks. Use 'get_project_progress' to get completion percentage and task status summary. Use 'get_task_order' to plan work in dependency order and 'get_critical_path' to see which chain of tasks gates completion. Use 'get_actionable_tasks' to pick what to work on next: unfinished tasks with no open dependencies. Use 'get_pm_context' to fetch project specifications before implementing. Use 'request_pm_review' for review checklists; every review is stored on the task, and save_as_comment=true also posts the checklist as a comment. Use 'update_pm_docs' to save structured documentation. Use 'get_pm_docs' with max_chars/offset to read large PM docs in pages. Use 'close_stale_reviews' to find (mode='report') or reset (mode='apply') tasks stuck in review. ATTACHMENTS: Use 'attach_file_to_task' to attach reference documents, design files, or screenshots to a task. Use 'list_task_attachments' to see all attached files. COMMENTS: Use 'add_task_comment' to leave progress notes, decisions, or review findings on a task, and 'list_task_comments' to read them before picking up a task. LABELS: Use 'list_labels' to find label IDs for 'create_task' label_ids, and 'get_task_labels' to see the labels of a task. Use 'set_task_labels' to replace a task's labels, or 'add_task_label'/'remove_task_label' to change one. Use 'suggest_labels' for keyword-based label suggestions for a task, and 'suggest_executor' to recommend the coding agent for a task from its labels' executors. REPOS: Use 'get_repos' to read the scripts of several repositories in one call. Only pass include_path=true when you need repository paths, e.g. to reason about a monorepo layout. Always pass project_id where required.".to_string();
         if self.context.is_some() {
             let context_instruction = "Use 'get_context' to fetch project/task/workspace metadata (including PM context if available) for the active Vibe Kanban workspace session when available.";
             instruction = format!("{} {}", context_instruction, instruction);
@@ -1793,46 +4138,71 @@ mod tests {
     mod duplicate_detection {
         use super::*;
 
+        const T: f64 = DEFAULT_DUPLICATE_SIMILARITY_THRESHOLD;
+
         #[test]
         fn test_exact_match_is_duplicate() {
-            assert!(TaskServer::is_duplicate_title("Add login feature", "Add login feature"));
+            assert!(TaskServer::is_duplicate_title("Add login feature", "Add login feature", T));
         }
 
         #[test]
         fn test_case_insensitive_match_is_duplicate() {
-            assert!(TaskServer::is_duplicate_title("Add Login Feature", "add login feature"));
-            assert!(TaskServer::is_duplicate_title("ADD LOGIN FEATURE", "add login feature"));
+            assert!(TaskServer::is_duplicate_title("Add Login Feature", "add login feature", T));
+            assert!(TaskServer::is_duplicate_title("ADD LOGIN FEATURE", "add login feature", T));
+        }
+
+        #[test]
+        fn test_stop_words_and_punctuation_ignored() {
+            assert!(TaskServer::is_duplicate_title("Add the login feature", "add login feature", T));
+            assert!(TaskServer::is_duplicate_title("Login feature: add", "Add login feature.", T));
         }
 
         #[test]
-        fn test_new_title_contained_in_existing_is_duplicate() {
-            assert!(TaskServer::is_duplicate_title("login", "Add login feature"));
-            assert!(TaskServer::is_duplicate_title("Login", "add login feature"));
+        fn test_single_word_not_duplicate_of_longer_title() {
+            // Previously matched by substring containment
+            assert!(!TaskServer::is_duplicate_title("login", "Add blog login page", T));
+            assert!(!TaskServer::is_duplicate_title("Login", "add login feature", T));
         }
 
         #[test]
-        fn test_existing_title_contained_in_new_is_duplicate() {
-            assert!(TaskServer::is_duplicate_title("Add login feature with OAuth", "login feature"));
+        fn test_extended_title_not_duplicate() {
+            assert!(!TaskServer::is_duplicate_title("Add login feature with OAuth", "login feature", T));
         }
 
         #[test]
         fn test_completely_different_titles_not_duplicate() {
-            assert!(!TaskServer::is_duplicate_title("Add login feature", "Fix payment bug"));
-            assert!(!TaskServer::is_duplicate_title("User authentication", "Database migration"));
+            assert!(!TaskServer::is_duplicate_title("Add login feature", "Fix payment bug", T));
+            assert!(!TaskServer::is_duplicate_title("User authentication", "Database migration", T));
+        }
+
+        #[test]
+        fn test_partial_word_match_not_duplicate() {
+            // "auth" is contained in "authentication" but is a different word
+            assert!(!TaskServer::is_duplicate_title("auth", "User authentication", T));
         }
 
         #[test]
-        fn test_partial_word_match_is_duplicate() {
-            // "auth" is contained in "authentication"
-            assert!(TaskServer::is_duplicate_title("auth", "User authentication"));
+        fn test_threshold_is_configurable() {
+            // {add, login, feature} vs {add, login, feature, oauth} = 0.75
+            assert!(TaskServer::is_duplicate_title("Add login feature", "Add login feature OAuth", 0.7));
+            assert!(!TaskServer::is_duplicate_title("Add login feature", "Add login feature OAuth", T));
+        }
+
+        #[test]
+        fn test_title_similarity() {
+            assert_eq!(TaskServer::title_similarity("Fix payment bug", "fix PAYMENT bug"), 1.0);
+            assert_eq!(TaskServer::title_similarity("Fix payment bug", "Add login page"), 0.0);
+            assert_eq!(TaskServer::title_similarity("login", "Add login page"), 1.0 / 3.0);
         }
 
         #[test]
         fn test_empty_titles() {
-            assert!(TaskServer::is_duplicate_title("", ""));
-            // Empty string is contained in any string
-            assert!(TaskServer::is_duplicate_title("", "Some task"));
-            assert!(TaskServer::is_duplicate_title("Some task", ""));
+            assert!(TaskServer::is_duplicate_title("", "", T));
+            assert!(!TaskServer::is_duplicate_title("", "Some task", T));
+            assert!(!TaskServer::is_duplicate_title("Some task", "", T));
+            // Only stop words: never similar, but still an exact match
+            assert!(!TaskServer::is_duplicate_title("the", "a", T));
+            assert!(TaskServer::is_duplicate_title("The", "the", T));
         }
     }
 
@@ -1840,33 +4210,669 @@ mod tests {
         use super::*;
 
         #[test]
-        fn test_zero_tasks_returns_zero_percent() {
-            assert_eq!(TaskServer::calculate_progress(0, 0), 0.0);
+        fn test_status_summary_lists_every_status_and_blocked() {
+            let response = GetProjectProgressResponse::from_summary(ProgressSummary {
+                total_tasks: 4,
+                todo: 1,
+                in_progress: 1,
+                in_review: 0,
+                done: 2,
+                cancelled: 0,
+                blocked: 1,
+                progress_percent: 50.0,
+            });
+
+            assert_eq!(response.completed_tasks, 2);
+            assert_eq!(response.blocked_tasks, 1);
+            assert_eq!(response.status_summary["inprogress"], 1);
+            assert_eq!(response.status_summary["inreview"], 0);
+            assert_eq!(response.status_summary["blocked"], 1);
+            assert_eq!(response.status_summary.len(), 6);
+        }
+    }
+
+    mod project_references {
+        use super::*;
+
+        #[test]
+        fn test_cross_project_dependency_is_reported() {
+            let (own_task, other_project_task) = (Uuid::new_v4(), Uuid::new_v4());
+            let project_tasks = HashSet::from([own_task]);
+
+            assert_eq!(
+                TaskServer::ids_outside_project(
+                    &[own_task, other_project_task, other_project_task],
+                    &project_tasks
+                ),
+                vec![other_project_task]
+            );
+        }
+
+        #[test]
+        fn test_label_from_other_project_is_reported() {
+            let (bug, feature, other_project_label) =
+                (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+            let project_labels = HashSet::from([bug, feature]);
+
+            assert_eq!(
+                TaskServer::ids_outside_project(&[other_project_label, bug], &project_labels),
+                vec![other_project_label]
+            );
+            assert!(TaskServer::ids_outside_project(&[bug, feature], &project_labels).is_empty());
+        }
+    }
+
+    mod dependency_guard {
+        use db::test_support::task;
+
+        use super::*;
+
+        #[test]
+        fn test_lists_dependencies_that_are_not_done() {
+            let tasks = vec![
+                task("design", TaskStatus::Done),
+                task("api", TaskStatus::InProgress),
+                task("spec", TaskStatus::Cancelled),
+            ];
+            let depends_on: Vec<Uuid> = tasks.iter().map(|t| t.id).collect();
+
+            let incomplete = TaskServer::incomplete_dependencies(&depends_on, &tasks);
+
+            let titles: Vec<_> = incomplete.iter().map(|d| d.title.as_deref()).collect();
+            assert_eq!(titles, vec![Some("api"), Some("spec")]);
+            assert_eq!(incomplete[0].status.as_deref(), Some("inprogress"));
+        }
+
+        #[test]
+        fn test_missing_dependencies_are_ignored() {
+            let tasks = vec![task("design", TaskStatus::Done)];
+            let depends_on = vec![tasks[0].id, Uuid::new_v4()];
+
+            assert!(TaskServer::incomplete_dependencies(&depends_on, &tasks).is_empty());
+        }
+    }
+
+    mod pm_docs_paging {
+        use super::*;
+
+        #[test]
+        fn test_short_doc_fits_in_one_page() {
+            let page = TaskServer::paginate_chars("# Spec", 0, 100);
+            assert_eq!(page.content, "# Spec");
+            assert_eq!(page.total_chars, 6);
+            assert_eq!(page.next_offset, None);
+        }
+
+        #[test]
+        fn test_paging_through_long_doc() {
+            let doc = format!("{}{}{}", "a".repeat(10), "仕様".repeat(5), "🔥".repeat(5));
+            let mut collected = String::new();
+            let mut offset = 0;
+            let mut pages = 0;
+
+            loop {
+                let page = TaskServer::paginate_chars(&doc, offset, 7);
+                assert_eq!(page.total_chars, 25);
+                assert!(page.content.chars().count() <= 7);
+                collected.push_str(page.content);
+                pages += 1;
+                match page.next_offset {
+                    Some(next) => offset = next,
+                    None => break,
+                }
+            }
+
+            assert_eq!(pages, 4);
+            assert_eq!(collected, doc);
+        }
+
+        #[test]
+        fn test_offset_past_end_returns_empty_page() {
+            let page = TaskServer::paginate_chars("short", 50, 10);
+            assert_eq!(page.content, "");
+            assert_eq!(page.next_offset, None);
+        }
+    }
+
+    #[test]
+    fn test_repo_details_include_path_only_on_request() {
+        let repo = |path: &str| Repo {
+            id: Uuid::new_v4(),
+            path: path.into(),
+            name: "api".to_string(),
+            display_name: "API".to_string(),
+            setup_script: Some("npm install".to_string()),
+            cleanup_script: None,
+            copy_files: None,
+            parallel_setup_script: false,
+            dev_server_script: None,
+            default_target_branch: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        assert_eq!(RepoDetails::from_repo(repo("/src/api"), None).path, None);
+        assert_eq!(
+            RepoDetails::from_repo(repo("/src/api"), Some(false)).path,
+            None
+        );
+        assert_eq!(
+            RepoDetails::from_repo(repo("/src/api"), Some(true))
+                .path
+                .as_deref(),
+            Some("/src/api")
+        );
+    }
+
+    #[test]
+    fn test_subtask_progress_rollup() {
+        assert_eq!(subtask_progress(3, 5).as_deref(), Some("3/5 subtasks done"));
+        assert_eq!(subtask_progress(0, 0), None);
+    }
+
+    mod pm_docs_updates {
+        use super::*;
+
+        const DOCS: &str = "# Project\n\n## 仕様\nOld spec\n\n## Notes\nKeep me\n";
+
+        #[test]
+        fn test_mode_parsing_rejects_unknown() {
+            assert_eq!(
+                PmDocsUpdateMode::from_str(" Prepend "),
+                Ok(PmDocsUpdateMode::Prepend)
+            );
+            assert!(PmDocsUpdateMode::from_str("insert").is_err());
+        }
+
+        #[test]
+        fn test_append_and_prepend() {
+            let appended = TaskServer::updated_pm_docs(
+                Some("Existing"),
+                "New".to_string(),
+                PmDocsUpdateMode::Append,
+                None,
+            );
+            assert_eq!(appended, "Existing\n\nNew");
+
+            let prepended = TaskServer::updated_pm_docs(
+                Some("Existing"),
+                "New".to_string(),
+                PmDocsUpdateMode::Prepend,
+                None,
+            );
+            assert_eq!(prepended, "New\n\nExisting");
+
+            let empty = TaskServer::updated_pm_docs(
+                None,
+                "New".to_string(),
+                PmDocsUpdateMode::Prepend,
+                None,
+            );
+            assert_eq!(empty, "New");
+        }
+
+        #[test]
+        fn test_only_plain_appends_retry_on_conflict() {
+            assert!(PmDocsUpdateMode::Append.retries_on_conflict(None));
+            assert!(!PmDocsUpdateMode::Append.retries_on_conflict(Some("Notes")));
+            assert!(!PmDocsUpdateMode::Replace.retries_on_conflict(None));
+            assert!(!PmDocsUpdateMode::Prepend.retries_on_conflict(None));
+        }
+
+        #[test]
+        fn test_section_replaces_only_that_section() {
+            let updated = TaskServer::updated_pm_docs(
+                Some(DOCS),
+                "New spec".to_string(),
+                PmDocsUpdateMode::Replace,
+                Some("仕様"),
+            );
+            assert_eq!(
+                updated,
+                "# Project\n\n## 仕様\nNew spec\n\n## Notes\nKeep me\n"
+            );
+        }
+    }
+
+    mod tag_expansion {
+        use std::collections::HashMap;
+
+        use super::*;
+
+        #[test]
+        fn test_expands_nested_tags() {
+            let tags = HashMap::from([
+                ("setup", "Run @install then @test"),
+                ("install", "pnpm i"),
+                ("test", "cargo test"),
+            ]);
+
+            assert_eq!(
+                TaskServer::expand_tag_references("Before merging: @setup", &tags, 5),
+                "Before merging: Run pnpm i then cargo test"
+            );
+        }
+
+        #[test]
+        fn test_self_referential_cycle_terminates() {
+            let tags = HashMap::from([("a", "A needs @b"), ("b", "B needs @a"), ("c", "@c")]);
+
+            assert_eq!(
+                TaskServer::expand_tag_references("@a", &tags, 5),
+                "A needs B needs @a"
+            );
+            assert_eq!(TaskServer::expand_tag_references("@c", &tags, 5), "@c");
+        }
+
+        #[test]
+        fn test_depth_limit_and_unknown_tags() {
+            let tags = HashMap::from([("one", "1 @two"), ("two", "2 @three"), ("three", "3")]);
+
+            assert_eq!(
+                TaskServer::expand_tag_references("@one @missing", &tags, 2),
+                "1 2 @three @missing"
+            );
+        }
+    }
+
+    mod tag_cache {
+        use super::*;
+
+        fn tag(name: &str) -> Tag {
+            Tag {
+                id: Uuid::new_v4(),
+                tag_name: name.to_string(),
+                content: format!("{name} content"),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }
+        }
+
+        #[test]
+        fn test_reuses_tags_within_ttl() {
+            let fetched_at = Instant::now();
+            let cache = (fetched_at, vec![tag("setup")]);
+            let ttl = Duration::from_secs(5);
+
+            let tags =
+                TaskServer::fresh_tags(Some(&cache), ttl, fetched_at + Duration::from_secs(4));
+            assert_eq!(tags.map(|t| t[0].tag_name.as_str()), Some("setup"));
+        }
+
+        #[test]
+        fn test_refetches_after_ttl_or_without_cache() {
+            let fetched_at = Instant::now();
+            let cache = (fetched_at, vec![tag("setup")]);
+            let ttl = Duration::from_secs(5);
+
+            assert!(
+                TaskServer::fresh_tags(Some(&cache), ttl, fetched_at + Duration::from_secs(5))
+                    .is_none()
+            );
+            assert!(TaskServer::fresh_tags(Some(&cache), Duration::ZERO, fetched_at).is_none());
+            assert!(TaskServer::fresh_tags(None, ttl, fetched_at).is_none());
+        }
+    }
+
+    mod task_links {
+        use super::*;
+
+        #[test]
+        fn test_task_link_uses_backend_url_by_default() {
+            let server = TaskServer::new("http://127.0.0.1:3000/");
+            let (project_id, task_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+            assert_eq!(
+                server.task_link(project_id, task_id),
+                format!("http://127.0.0.1:3000/projects/{project_id}/tasks/{task_id}")
+            );
+        }
+
+        #[test]
+        fn test_task_link_uses_app_url_when_set() {
+            let server = TaskServer::new("http://127.0.0.1:3000")
+                .with_app_url("https://kanban.example.com/");
+            let (project_id, task_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+            assert_eq!(
+                server.task_link(project_id, task_id),
+                format!("https://kanban.example.com/projects/{project_id}/tasks/{task_id}")
+            );
+        }
+    }
+
+    mod batch_create {
+        use super::*;
+
+        fn input(temp_id: &str, depends_on: &[&str]) -> BatchTaskInput {
+            BatchTaskInput {
+                temp_id: temp_id.to_string(),
+                title: format!("Task {}", temp_id),
+                description: None,
+                priority: None,
+                label_ids: None,
+                depends_on: Some(depends_on.iter().map(|d| d.to_string()).collect()),
+            }
         }
 
         #[test]
-        fn test_no_completed_tasks_returns_zero_percent() {
-            assert_eq!(TaskServer::calculate_progress(10, 0), 0.0);
+        fn test_resolves_temp_ids_and_existing_ids() {
+            let existing = Uuid::new_v4();
+            let existing_str = existing.to_string();
+            let tasks = vec![
+                input("api", &[]),
+                input("ui", &["api", &existing_str]),
+                input("docs", &["ui", "api", "ui"]),
+            ];
+
+            let deps = TaskServer::resolve_batch_dependencies(&tasks).unwrap();
+
+            assert_eq!(deps[0], vec![]);
+            assert_eq!(
+                deps[1],
+                vec![
+                    BatchDependency::Batch(0),
+                    BatchDependency::Existing(existing)
+                ]
+            );
+            assert_eq!(
+                deps[2],
+                vec![BatchDependency::Batch(1), BatchDependency::Batch(0)]
+            );
         }
 
         #[test]
-        fn test_all_tasks_completed_returns_100_percent() {
-            assert_eq!(TaskServer::calculate_progress(10, 10), 100.0);
-            assert_eq!(TaskServer::calculate_progress(1, 1), 100.0);
+        fn test_rejects_unknown_and_duplicate_temp_ids() {
+            let unknown = TaskServer::resolve_batch_dependencies(&[input("a", &["b"])]);
+            assert!(unknown.unwrap_err().contains("unknown temp_id 'b'"));
+
+            let duplicate =
+                TaskServer::resolve_batch_dependencies(&[input("a", &[]), input("a", &[])]);
+            assert!(duplicate.unwrap_err().contains("Duplicate temp_id 'a'"));
         }
 
         #[test]
-        fn test_partial_completion() {
-            assert_eq!(TaskServer::calculate_progress(10, 5), 50.0);
-            assert_eq!(TaskServer::calculate_progress(4, 1), 25.0);
-            // Use approximate comparison for floating point
-            let progress = TaskServer::calculate_progress(3, 1);
-            assert!((progress - 33.333333).abs() < 0.001, "Expected ~33.33, got {}", progress);
+        fn test_rejects_cycles_within_batch() {
+            let tasks = vec![
+                input("a", &["c"]),
+                input("b", &["a"]),
+                input("c", &["b"]),
+                input("d", &[]),
+            ];
+            let err = TaskServer::resolve_batch_dependencies(&tasks).unwrap_err();
+            assert_eq!(err, "Dependencies form a cycle involving: a, b, c");
+
+            let self_dependency = TaskServer::resolve_batch_dependencies(&[input("a", &["a"])]);
+            assert!(self_dependency.is_err());
+        }
+    }
+
+    mod stale_reviews {
+        use chrono::TimeZone;
+        use db::test_support::task;
+
+        use super::*;
+
+        fn task_updated_at(title: &str, status: TaskStatus, updated_at: DateTime<Utc>) -> Task {
+            Task {
+                created_at: updated_at,
+                updated_at,
+                ..task(title, status)
+            }
+        }
+
+        fn now() -> DateTime<Utc> {
+            Utc.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap()
+        }
+
+        fn fixture() -> Vec<Task> {
+            let at = |day, hour| Utc.with_ymd_and_hms(2026, 1, day, hour, 0, 0).unwrap();
+            vec![
+                task_updated_at("old review", TaskStatus::InReview, at(5, 12)),
+                task_updated_at("fresh review", TaskStatus::InReview, at(10, 0)),
+                task_updated_at("old todo", TaskStatus::Todo, at(1, 0)),
+                task_updated_at("boundary review", TaskStatus::InReview, at(7, 12)),
+            ]
+        }
+
+        #[test]
+        fn test_mode_parsing_defaults_and_rejects_unknown() {
+            assert_eq!(
+                StaleReviewMode::from_str("report"),
+                Ok(StaleReviewMode::Report)
+            );
+            assert_eq!(
+                StaleReviewMode::from_str(" APPLY "),
+                Ok(StaleReviewMode::Apply)
+            );
+            assert!(StaleReviewMode::from_str("delete").is_err());
+        }
+
+        #[test]
+        fn test_report_mode_finds_only_stale_in_review_tasks() {
+            let tasks = fixture();
+            let stale = TaskServer::find_stale_reviews(&tasks, now(), chrono::Duration::hours(72));
+            let titles: Vec<_> = stale.iter().map(|(t, _)| t.title.as_str()).collect();
+            assert_eq!(titles, vec!["old review", "boundary review"]);
+            assert_eq!(stale[0].1.num_hours(), 120);
+            assert_eq!(stale[1].1.num_hours(), 72);
+        }
+
+        #[test]
+        fn test_apply_mode_note_is_appended_to_description() {
+            let tasks = fixture();
+            let stale = TaskServer::find_stale_reviews(&tasks, now(), chrono::Duration::hours(100));
+            assert_eq!(stale.len(), 1);
+
+            let (task, idle) = stale[0];
+            let note = TaskServer::stale_review_note(task.description.as_deref(), idle.num_hours());
+            assert_eq!(
+                note,
+                "> Moved back to todo: no review activity for 120 hours."
+            );
+
+            let with_existing = TaskServer::stale_review_note(Some("Existing details"), 120);
+            assert!(with_existing.starts_with("Existing details\n\n> Moved back to todo"));
+        }
+    }
+
+    mod task_comments {
+        use super::*;
+
+        #[test]
+        fn test_review_checklist_renders_as_task_list() {
+            let checklist = vec!["Test coverage".to_string(), "Edge cases".to_string()];
+            assert_eq!(
+                review_checklist_markdown(&checklist),
+                "- [ ] Test coverage\n- [ ] Edge cases"
+            );
+        }
+
+        #[test]
+        fn test_comment_info_reports_author_as_snake_case() {
+            let comment = TaskComment {
+                id: Uuid::new_v4(),
+                task_id: Uuid::new_v4(),
+                author: TaskCommentAuthor::Agent,
+                content: "Looks good".to_string(),
+                created_at: Utc::now(),
+            };
+            let info = TaskCommentInfo::from_comment(comment);
+            assert_eq!(info.author, "agent");
+            assert_eq!(info.content, "Looks good");
+        }
+    }
+
+    mod create_task_preview {
+        use db::test_support::label;
+
+        use super::*;
+
+        #[test]
+        fn test_preview_defaults_priority_to_medium() {
+            let preview = CreateTaskPreview::new("Add login", None, None, &[], &[], &[]);
+            assert_eq!(preview.priority, "medium");
+            assert!(preview.labels.is_empty());
+        }
+
+        #[test]
+        fn test_preview_resolves_label_names_in_request_order() {
+            let labels = vec![label("bug"), label("frontend")];
+            let label_ids = vec![labels[1].id.to_string(), labels[0].id.to_string()];
+            let depends_on = vec![Uuid::new_v4().to_string()];
+            let preview = CreateTaskPreview::new(
+                "Fix header",
+                Some("Details"),
+                Some(TaskPriority::High),
+                &depends_on,
+                &label_ids,
+                &labels,
+            );
+            assert_eq!(preview.priority, "high");
+            assert_eq!(preview.labels, vec!["frontend", "bug"]);
+            assert_eq!(preview.depends_on, depends_on);
+            assert_eq!(preview.description.as_deref(), Some("Details"));
+        }
+    }
+
+    mod workspace_repos {
+        use super::*;
+
+        #[test]
+        fn test_project_repos_are_accepted() {
+            let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+            assert_eq!(check_workspace_repos(&[b, a], &[a, b]), Ok(()));
+        }
+
+        #[test]
+        fn test_foreign_repo_is_rejected_by_id() {
+            let (own, foreign) = (Uuid::new_v4(), Uuid::new_v4());
+            let err = check_workspace_repos(&[own, foreign], &[own]).unwrap_err();
+            assert!(err.contains(&foreign.to_string()));
+            assert!(!err.contains(&own.to_string()));
+        }
+
+        #[test]
+        fn test_duplicate_repo_is_rejected() {
+            let repo = Uuid::new_v4();
+            let err = check_workspace_repos(&[repo, repo], &[repo]).unwrap_err();
+            assert_eq!(
+                err,
+                format!("Repository {} is listed more than once.", repo)
+            );
+        }
+    }
+
+    mod executor_resolution {
+        use super::*;
+
+        #[test]
+        fn test_strict_names_resolve_in_any_case() {
+            assert_eq!(
+                resolve_executor("CLAUDE_CODE"),
+                Ok(BaseCodingAgent::ClaudeCode)
+            );
+            assert_eq!(resolve_executor("qwen-code"), Ok(BaseCodingAgent::QwenCode));
+            assert_eq!(resolve_executor("cursor"), Ok(BaseCodingAgent::CursorAgent));
+        }
+
+        #[test]
+        fn test_aliases_resolve() {
+            assert_eq!(resolve_executor("claude"), Ok(BaseCodingAgent::ClaudeCode));
+            assert_eq!(
+                resolve_executor(" Claude "),
+                Ok(BaseCodingAgent::ClaudeCode)
+            );
+            assert_eq!(resolve_executor("gpt"), Ok(BaseCodingAgent::Codex));
+            assert_eq!(resolve_executor("OpenAI"), Ok(BaseCodingAgent::Codex));
+            assert_eq!(
+                resolve_executor("github copilot"),
+                Ok(BaseCodingAgent::Copilot)
+            );
+        }
+
+        #[test]
+        fn test_every_alias_is_lowercase_snake_case() {
+            for (alias, _) in EXECUTOR_ALIASES {
+                assert_eq!(*alias, alias.to_ascii_lowercase().replace(['-', ' '], "_"));
+            }
+        }
+
+        #[test]
+        fn test_unknown_executor_lists_valid_values() {
+            let err = resolve_executor("skynet").unwrap_err();
+            assert!(err.starts_with("Unknown executor 'skynet'. Valid executors: "));
+            assert!(err.contains("CLAUDE_CODE"));
+            assert!(err.contains("CODEX"));
+            assert!(err.contains("DROID"));
+        }
+    }
+
+    mod list_task_filters {
+        use super::*;
+
+        #[test]
+        fn test_parse_time_filter_accepts_rfc3339_offsets() {
+            assert_eq!(parse_time_filter("created_after", None), Ok(None));
+            assert_eq!(
+                parse_time_filter("created_after", Some("2025-03-14T17:00:00+02:00")),
+                Ok(Some(
+                    DateTime::parse_from_rfc3339("2025-03-14T15:00:00Z")
+                        .unwrap()
+                        .with_timezone(&Utc)
+                ))
+            );
+        }
+
+        #[test]
+        fn test_parse_time_filter_rejects_other_formats() {
+            let err = parse_time_filter("updated_after", Some("yesterday")).unwrap_err();
+            assert!(err.starts_with("Invalid updated_after 'yesterday'"));
+            assert!(parse_time_filter("created_before", Some("2025-03-14")).is_err());
+        }
+    }
+
+    mod task_moves {
+        use super::*;
+
+        fn order(positions: &[i32]) -> Vec<(Uuid, i32)> {
+            positions.iter().map(|p| (Uuid::new_v4(), *p)).collect()
+        }
+
+        #[test]
+        fn test_move_between_two_tasks_takes_midpoint() {
+            let order = order(&[1024, 2048, 3072]);
+            let updates = TaskServer::plan_task_move(&order, order[2].0, Some(order[1].0)).unwrap();
+            assert_eq!(updates, vec![(order[2].0, 1536)]);
+        }
+
+        #[test]
+        fn test_move_to_start_and_end() {
+            let order = order(&[1024, 2048, 3072]);
+            let to_end = TaskServer::plan_task_move(&order, order[0].0, None).unwrap();
+            assert_eq!(to_end, vec![(order[0].0, 3072 + TASK_POSITION_GAP)]);
+
+            let to_start =
+                TaskServer::plan_task_move(&order, order[2].0, Some(order[0].0)).unwrap();
+            assert_eq!(to_start, vec![(order[2].0, 0)]);
+        }
+
+        #[test]
+        fn test_move_renumbers_when_there_is_no_gap() {
+            let order = order(&[1, 2, 3]);
+            let updates = TaskServer::plan_task_move(&order, order[2].0, Some(order[1].0)).unwrap();
+            assert_eq!(
+                updates,
+                vec![(order[0].0, 1024), (order[2].0, 2048), (order[1].0, 3072)]
+            );
         }
 
         #[test]
-        fn test_single_task_completed() {
-            assert_eq!(TaskServer::calculate_progress(5, 1), 20.0);
+        fn test_move_rejects_unknown_and_self_targets() {
+            let order = order(&[1024, 2048]);
+            assert!(TaskServer::plan_task_move(&order, order[0].0, Some(Uuid::new_v4())).is_err());
+            assert!(TaskServer::plan_task_move(&order, order[0].0, Some(order[0].0)).is_err());
+            assert!(TaskServer::plan_task_move(&order, Uuid::new_v4(), None).is_err());
         }
     }
 }
@@ -0,0 +1,59 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::Json as ResponseJson,
+    routing::{delete, get, post},
+};
+use deployment::Deployment;
+use services::services::attachment_gc::{
+    AttachmentGcError, AttachmentGcReport, cleanup_orphaned_attachments,
+};
+use utils::response::ApiResponse;
+use uuid::Uuid;
+
+use crate::{
+    DeploymentImpl,
+    error::ApiError,
+    routes::pm_chat::{AiChatSession, kill_ai_chat_session, list_ai_chat_sessions},
+};
+
+/// Remove attachment files on disk that no attachment row references
+pub async fn gc_attachments(
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<AttachmentGcReport>>, ApiError> {
+    let report = cleanup_orphaned_attachments(&deployment.db().pool)
+        .await
+        .map_err(|e| match e {
+            AttachmentGcError::Database(e) => ApiError::Database(e),
+            AttachmentGcError::Io(e) => ApiError::Io(e),
+        })?;
+    Ok(ResponseJson(ApiResponse::success(report)))
+}
+
+/// List the `ai_chat` streams running across all projects
+pub async fn get_pm_chat_sessions() -> ResponseJson<ApiResponse<Vec<AiChatSession>>> {
+    ResponseJson(ApiResponse::success(list_ai_chat_sessions()))
+}
+
+/// Kill a running `ai_chat` stream, e.g. when its CLI hangs
+pub async fn delete_pm_chat_session(
+    Path(stream_id): Path<Uuid>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    if !kill_ai_chat_session(stream_id) {
+        return Err(ApiError::NotFound(format!(
+            "No running AI chat session {}",
+            stream_id
+        )));
+    }
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+pub fn router() -> Router<DeploymentImpl> {
+    Router::new()
+        .route("/admin/attachments/gc", post(gc_attachments))
+        .route("/admin/pm-chat/sessions", get(get_pm_chat_sessions))
+        .route(
+            "/admin/pm-chat/sessions/{stream_id}",
+            delete(delete_pm_chat_session),
+        )
+}
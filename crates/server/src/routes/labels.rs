@@ -1,16 +1,41 @@
 use axum::{
-    Extension, Json, Router, extract::State, middleware::from_fn_with_state,
-    response::Json as ResponseJson, routing::get,
+    Extension, Json, Router,
+    extract::{Query, State},
+    middleware::from_fn_with_state,
+    response::Json as ResponseJson,
+    routing::{get, post},
 };
 use db::models::{
-    label::{CreateLabel, Label, UpdateLabel},
+    label::{CreateLabel, Label, LabelError, LabelMergeResult, LabelWithCount, UpdateLabel},
     project::Project,
 };
 use deployment::Deployment;
+use serde::Deserialize;
+use services::services::events::BoardEvent;
+use ts_rs::TS;
 use utils::response::ApiResponse;
+use uuid::Uuid;
 
 use crate::{DeploymentImpl, error::ApiError, middleware::load_label_middleware};
 
+/// Tell the board subscribers of a project that a label changed
+fn publish_label_changed(deployment: &DeploymentImpl, project_id: Uuid, label_id: Uuid) {
+    deployment
+        .events()
+        .board()
+        .publish(project_id, BoardEvent::LabelChanged { label_id });
+}
+
+fn label_error(e: LabelError) -> ApiError {
+    match e {
+        LabelError::Database(e) => ApiError::Database(e),
+        LabelError::OutsideProject(_) => ApiError::Forbidden(e.to_string()),
+        LabelError::InvalidColor(_)
+        | LabelError::UnknownExecutor(_)
+        | LabelError::MergeIntoSource => ApiError::BadRequest(e.to_string()),
+    }
+}
+
 pub async fn get_labels(
     Extension(project): Extension<Project>,
     State(deployment): State<DeploymentImpl>,
@@ -19,6 +44,15 @@ pub async fn get_labels(
     Ok(ResponseJson(ApiResponse::success(labels)))
 }
 
+/// Labels of a project with the number of tasks carrying each
+pub async fn get_labels_with_counts(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<LabelWithCount>>>, ApiError> {
+    let labels = Label::find_by_project_id_with_counts(&deployment.db().pool, project.id).await?;
+    Ok(ResponseJson(ApiResponse::success(labels)))
+}
+
 pub async fn create_label(
     Extension(project): Extension<Project>,
     State(deployment): State<DeploymentImpl>,
@@ -27,7 +61,10 @@ pub async fn create_label(
     // Override project_id from path
     payload.project_id = project.id;
 
-    let label = Label::create(&deployment.db().pool, &payload).await?;
+    let label = Label::create(&deployment.db().pool, &payload)
+        .await
+        .map_err(label_error)?;
+    publish_label_changed(&deployment, project.id, label.id);
 
     deployment
         .track_if_analytics_allowed(
@@ -55,7 +92,10 @@ pub async fn update_label(
     State(deployment): State<DeploymentImpl>,
     Json(payload): Json<UpdateLabel>,
 ) -> Result<ResponseJson<ApiResponse<Label>>, ApiError> {
-    let updated_label = Label::update(&deployment.db().pool, label.id, &payload).await?;
+    let updated_label = Label::update(&deployment.db().pool, label.id, &payload)
+        .await
+        .map_err(label_error)?;
+    publish_label_changed(&deployment, label.project_id, label.id);
 
     deployment
         .track_if_analytics_allowed(
@@ -70,18 +110,88 @@ pub async fn update_label(
     Ok(ResponseJson(ApiResponse::success(updated_label)))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DeleteLabelQuery {
+    /// Label that takes over the tasks of the deleted label
+    pub reassign_to: Option<Uuid>,
+}
+
 pub async fn delete_label(
     Extension(label): Extension<Label>,
     State(deployment): State<DeploymentImpl>,
+    Query(query): Query<DeleteLabelQuery>,
 ) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
-    let rows_affected = Label::delete(&deployment.db().pool, label.id).await?;
+    let rows_affected = match query.reassign_to {
+        Some(target_id) => {
+            Label::merge(
+                &deployment.db().pool,
+                label.project_id,
+                &[label.id],
+                target_id,
+            )
+            .await
+            .map_err(label_error)?
+            .deleted
+        }
+        None => Label::delete(&deployment.db().pool, label.id).await?,
+    };
     if rows_affected == 0 {
         Err(ApiError::Database(sqlx::Error::RowNotFound))
     } else {
+        publish_label_changed(&deployment, label.project_id, label.id);
+        if let Some(target_id) = query.reassign_to {
+            publish_label_changed(&deployment, label.project_id, target_id);
+        }
         Ok(ResponseJson(ApiResponse::success(())))
     }
 }
 
+#[derive(Debug, Deserialize, TS)]
+pub struct MergeLabelsRequest {
+    pub source_ids: Vec<Uuid>,
+    pub target_id: Uuid,
+}
+
+/// Merge labels into one: tasks of the source labels get the target label and
+/// the source labels are deleted
+pub async fn merge_labels(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<MergeLabelsRequest>,
+) -> Result<ResponseJson<ApiResponse<LabelMergeResult>>, ApiError> {
+    if payload.source_ids.is_empty() {
+        return Err(ApiError::BadRequest(
+            "source_ids must not be empty".to_string(),
+        ));
+    }
+
+    let result = Label::merge(
+        &deployment.db().pool,
+        project.id,
+        &payload.source_ids,
+        payload.target_id,
+    )
+    .await
+    .map_err(label_error)?;
+    for label_id in payload.source_ids.iter().chain([&payload.target_id]) {
+        publish_label_changed(&deployment, project.id, *label_id);
+    }
+
+    deployment
+        .track_if_analytics_allowed(
+            "labels_merged",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "target_id": payload.target_id.to_string(),
+                "labels_deleted": result.deleted,
+                "tasks_reassigned": result.reassigned,
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(result)))
+}
+
 pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
     let label_router = Router::new()
         .route("/", get(get_label).put(update_label).delete(delete_label))
@@ -92,5 +202,7 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
 
     Router::new()
         .route("/", get(get_labels).post(create_label))
+        .route("/with-counts", get(get_labels_with_counts))
+        .route("/merge", post(merge_labels))
         .nest("/{label_id}", label_router)
 }
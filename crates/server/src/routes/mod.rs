@@ -1,11 +1,12 @@
 use axum::{
     Router,
-    routing::{IntoMakeService, get},
+    routing::{IntoMakeService, get, post},
 };
 use tower_http::validate_request::ValidateRequestHeaderLayer;
 
 use crate::{DeploymentImpl, middleware};
 
+pub mod admin;
 pub mod approvals;
 pub mod config;
 pub mod containers;
@@ -17,6 +18,7 @@ pub mod frontend;
 pub mod health;
 pub mod images;
 pub mod labels;
+mod multipart_upload;
 pub mod oauth;
 pub mod organizations;
 pub mod pm_chat;
@@ -29,12 +31,17 @@ pub mod task_attachments;
 pub mod task_attempts;
 pub mod tasks;
 pub mod terminal;
+pub mod webhooks;
 
 pub fn router(deployment: DeploymentImpl) -> IntoMakeService<Router> {
     // Create routers with different middleware layers
     let base_routes = Router::new()
         .route("/health", get(health::health_check))
         .route("/pm-chat/ai-agents", get(pm_chat::get_available_agents))
+        .route(
+            "/pm-chat/agents/{agent}/selftest",
+            post(pm_chat::selftest_agent),
+        )
         .merge(config::router())
         .merge(containers::router(&deployment))
         .merge(projects::router(&deployment))
@@ -45,6 +52,7 @@ pub fn router(deployment: DeploymentImpl) -> IntoMakeService<Router> {
         .merge(oauth::router())
         .merge(organizations::router())
         .merge(filesystem::router())
+        .merge(admin::router())
         .merge(repo::router())
         .merge(events::router(&deployment))
         .merge(approvals::router())
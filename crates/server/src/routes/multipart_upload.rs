@@ -0,0 +1,223 @@
+//! Streaming of multipart file fields to disk
+//!
+//! Uploaded files are written chunk by chunk while being hashed, so a file is
+//! never held in memory as a whole, and oversized files are rejected as soon
+//! as they cross their size limit instead of after being read completely.
+
+use std::path::{Path, PathBuf};
+
+use axum::extract::multipart::Field;
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+/// Leading bytes buffered before the size limit of an upload is decided;
+/// enough to detect the file type from its content
+pub const HEAD_BYTES: usize = 512;
+
+/// A file field written to a temporary file. The file is removed when the
+/// upload is dropped, unless it has been moved away (see [`Self::persist`]).
+#[derive(Debug)]
+pub struct StreamedUpload {
+    path: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+    /// Up to [`HEAD_BYTES`] leading bytes of the file
+    pub head: Vec<u8>,
+}
+
+impl StreamedUpload {
+    /// Temporary file holding the upload
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the upload to `dest`, keeping it on disk
+    pub async fn persist(self, dest: &Path) -> std::io::Result<()> {
+        tokio::fs::rename(&self.path, dest).await
+    }
+}
+
+impl Drop for StreamedUpload {
+    fn drop(&mut self) {
+        // Nothing to do once the file has been persisted
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Stream a file field into a temporary file in `dir`, hashing it on the way.
+/// `limit` is called once with the leading bytes of the file (all of it, for
+/// files shorter than [`HEAD_BYTES`]) and returns the maximum size in bytes,
+/// or an error to reject the file. Nothing past the limit is read, and the
+/// partial file is removed on any error.
+pub async fn stream_to_file(
+    field: &mut Field<'_>,
+    dir: &Path,
+    limit: impl FnOnce(&[u8]) -> Result<u64, ApiError>,
+) -> Result<StreamedUpload, ApiError> {
+    tokio::fs::create_dir_all(dir).await?;
+    let mut upload = StreamedUpload {
+        path: dir.join(format!(".upload-{}", Uuid::new_v4())),
+        size: 0,
+        sha256: String::new(),
+        head: Vec::new(),
+    };
+    let mut file = File::create(&upload.path).await?;
+    let mut hasher = Sha256::new();
+    let mut limit = Some(limit);
+    let mut max = u64::MAX;
+
+    while let Some(chunk) = field.chunk().await? {
+        if upload.head.len() < HEAD_BYTES {
+            let take = (HEAD_BYTES - upload.head.len()).min(chunk.len());
+            upload.head.extend_from_slice(&chunk[..take]);
+        }
+        if upload.head.len() == HEAD_BYTES
+            && let Some(limit) = limit.take()
+        {
+            max = limit(&upload.head)?;
+        }
+
+        upload.size += chunk.len() as u64;
+        if upload.size > max {
+            return Err(too_large(max));
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+
+    if let Some(limit) = limit.take() {
+        max = limit(&upload.head)?;
+        if upload.size > max {
+            return Err(too_large(max));
+        }
+    }
+    file.flush().await?;
+
+    upload.sha256 = format!("{:x}", hasher.finalize());
+    Ok(upload)
+}
+
+fn too_large(max: u64) -> ApiError {
+    ApiError::PayloadTooLarge(format!("File too large (max {} bytes)", max))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        extract::{FromRequest, Multipart},
+        http::{Request, header},
+    };
+
+    use super::*;
+
+    const BOUNDARY: &str = "upload-boundary";
+
+    fn multipart(file_name: &str, data: &[u8]) -> Request<Body> {
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+        Request::builder()
+            .method("POST")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn stream(
+        request: Request<Body>,
+        dir: &Path,
+        max: u64,
+    ) -> Result<StreamedUpload, ApiError> {
+        let mut multipart = Multipart::from_request(request, &()).await.unwrap();
+        let mut field = multipart.next_field().await.unwrap().unwrap();
+        stream_to_file(&mut field, dir, |_| Ok(max)).await
+    }
+
+    fn files_in(dir: &Path) -> usize {
+        std::fs::read_dir(dir).unwrap().count()
+    }
+
+    #[tokio::test]
+    async fn test_streams_large_file_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..8 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let upload = stream(multipart("big.bin", &data), dir.path(), u64::MAX)
+            .await
+            .unwrap();
+
+        assert_eq!(upload.size, data.len() as u64);
+        assert_eq!(upload.sha256, format!("{:x}", Sha256::digest(&data)));
+        assert_eq!(upload.head, data[..HEAD_BYTES]);
+        assert_eq!(std::fs::read(upload.path()).unwrap(), data);
+
+        let dest = dir.path().join("big.bin");
+        upload.persist(&dest).await.unwrap();
+        assert_eq!(std::fs::metadata(&dest).unwrap().len(), data.len() as u64);
+        assert_eq!(files_in(dir.path()), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_file_and_removes_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = vec![7u8; 4 * 1024 * 1024];
+
+        let result = stream(multipart("big.bin", &data), dir.path(), 1024 * 1024).await;
+
+        assert!(matches!(result, Err(ApiError::PayloadTooLarge(_))));
+        assert_eq!(files_in(dir.path()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_limit_sees_whole_short_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut multipart = Multipart::from_request(multipart("a.txt", b"hello"), &())
+            .await
+            .unwrap();
+        let mut field = multipart.next_field().await.unwrap().unwrap();
+
+        let result = stream_to_file(&mut field, dir.path(), |head| {
+            assert_eq!(head, b"hello");
+            Err(ApiError::BadRequest("rejected".to_string()))
+        })
+        .await;
+
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+        assert_eq!(files_in(dir.path()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_uploads_are_kept_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = futures::future::join_all((0..8u8).map(|i| {
+            let data = vec![i; 512 * 1024 + i as usize];
+            let dir = dir.path().to_path_buf();
+            async move {
+                let upload = stream(multipart("part.bin", &data), &dir, u64::MAX)
+                    .await
+                    .unwrap();
+                (data, upload)
+            }
+        }))
+        .await;
+
+        for (data, upload) in &uploads {
+            assert_eq!(upload.size, data.len() as u64);
+            assert_eq!(std::fs::read(upload.path()).unwrap(), *data);
+        }
+        assert_eq!(files_in(dir.path()), 8);
+
+        drop(uploads);
+        assert_eq!(files_in(dir.path()), 0);
+    }
+}
@@ -1,49 +1,82 @@
-use std::{env, fs, path::PathBuf, process::Stdio, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    ops::Range,
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
 use utils::port_file::read_port_file;
 
+use async_zip::{Compression, ZipEntryBuilder, base::write::ZipFileWriter};
 use axum::{
     Extension, Json, Router,
     body::Body,
-    extract::{DefaultBodyLimit, Multipart, Path, State},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
     http::{StatusCode, header},
     response::{
-        Json as ResponseJson, Response,
+        IntoResponse, Json as ResponseJson, Response,
         sse::{Event, KeepAlive, KeepAliveStream, Sse},
     },
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use db::models::{
     label::TaskDependency,
     pm_conversation::{
         CreatePmAttachment, CreatePmConversation, PmAttachment, PmConversation, PmMessageRole,
+        PmModelUsage, PmRoleCount,
     },
-    project::Project,
+    pm_docs_history::{PmDocsSource, PmDocsVersion},
+    pm_message_task::PmMessageTask,
+    project::{Project, UpdateProject},
     project_repo::ProjectRepo,
-    task::Task,
+    task::{CreateTask, Task, TaskPriority},
 };
 use deployment::Deployment;
+use executors::{
+    executors::{BaseCodingAgent, StandardCodingAgentExecutor},
+    mcp_config::read_agent_config,
+    profile::{ExecutorConfigs, ExecutorProfileId},
+};
 use futures::stream::BoxStream;
 use futures_util::StreamExt;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sha2::{Digest, Sha256};
+use services::services::{
+    attachment_layout,
+    config::{PmAttachmentPolicy, PmChatSettings},
+    docs_scanner::{ScanOptions, invalidate_docs_cache, scan_docs_folder},
+    pm_attachments, pm_chat_retention, task_inference,
+    webhooks::{self, WebhookEvent},
+};
 use strum_macros::{Display, EnumString};
 use tokio::{
     fs::File,
-    io::{AsyncBufReadExt, BufReader},
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
     process::Command,
     sync::Mutex,
 };
-use tokio_util::io::ReaderStream;
+use tokio_util::{compat::TokioAsyncReadCompatExt, io::ReaderStream, sync::CancellationToken};
+use tracing::Instrument;
 use ts_rs::TS;
-use utils::{response::ApiResponse, shell::resolve_executable_path};
+use utils::{diff::create_unified_diff, response::ApiResponse, shell::resolve_executable_path};
 use uuid::Uuid;
 
-use crate::{DeploymentImpl, error::ApiError};
+use crate::{
+    DeploymentImpl,
+    error::ApiError,
+    routes::{
+        config::get_mcp_servers_from_config_path,
+        multipart_upload::{StreamedUpload, stream_to_file},
+        tasks::publish_task_created,
+    },
+};
 
 /// Available AI CLI providers for PM Chat
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS, Display, EnumString, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, TS, Display, EnumString, Default)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 #[ts(export)]
@@ -57,6 +90,8 @@ pub enum PmChatAgent {
     GeminiCli,
     /// OpenCode CLI (opencode) - Open source coding assistant
     OpencodeCli,
+    /// Ollama CLI (ollama) - Local models, without MCP tool support
+    OllamaCli,
 }
 
 impl PmChatAgent {
@@ -67,6 +102,7 @@ impl PmChatAgent {
             PmChatAgent::CodexCli => "codex",
             PmChatAgent::GeminiCli => "gemini",
             PmChatAgent::OpencodeCli => "opencode",
+            PmChatAgent::OllamaCli => "ollama",
         }
     }
 
@@ -77,6 +113,7 @@ impl PmChatAgent {
             PmChatAgent::CodexCli => "Codex CLI",
             PmChatAgent::GeminiCli => "Gemini CLI",
             PmChatAgent::OpencodeCli => "OpenCode CLI",
+            PmChatAgent::OllamaCli => "Ollama CLI",
         }
     }
 
@@ -87,6 +124,78 @@ impl PmChatAgent {
             PmChatAgent::CodexCli => true,
             PmChatAgent::GeminiCli => true, // Gemini CLI supports --output-format stream-json
             PmChatAgent::OpencodeCli => true,
+            PmChatAgent::OllamaCli => true, // Plain text, streamed line by line
+        }
+    }
+
+    /// How this CLI gets the vibe_kanban MCP server
+    pub fn mcp_config_mode(&self) -> McpConfigMode {
+        match self {
+            PmChatAgent::ClaudeCli => McpConfigMode::PerRun,
+            // These CLIs don't accept --mcp-config; see build_agent_command
+            PmChatAgent::CodexCli | PmChatAgent::GeminiCli | PmChatAgent::OpencodeCli => {
+                McpConfigMode::Preconfigured
+            }
+            PmChatAgent::OllamaCli => McpConfigMode::Unsupported,
+        }
+    }
+
+    /// Check if this CLI can call the vibe_kanban MCP tools (create tasks, update docs, ...)
+    pub fn supports_tools(&self) -> bool {
+        self.mcp_config_mode() != McpConfigMode::Unsupported
+    }
+
+    /// Coding agent executor whose MCP config file this CLI reads
+    fn mcp_executor(&self) -> Option<BaseCodingAgent> {
+        match self {
+            PmChatAgent::CodexCli => Some(BaseCodingAgent::Codex),
+            PmChatAgent::GeminiCli => Some(BaseCodingAgent::Gemini),
+            PmChatAgent::OpencodeCli => Some(BaseCodingAgent::Opencode),
+            PmChatAgent::ClaudeCli | PmChatAgent::OllamaCli => None,
+        }
+    }
+
+    /// Check if the tools are actually reachable: pre-configured CLIs only get
+    /// them when the vibe_kanban server is registered in their own MCP config
+    pub async fn tools_configured(&self) -> bool {
+        match self.mcp_config_mode() {
+            McpConfigMode::PerRun => true,
+            McpConfigMode::Unsupported => false,
+            McpConfigMode::Preconfigured => match self.mcp_executor() {
+                Some(executor) => vibe_kanban_mcp_registered(executor).await,
+                None => false,
+            },
+        }
+    }
+
+    /// Parser for the events this CLI writes to stdout
+    fn stream_parser(&self) -> Box<dyn AgentStreamParser> {
+        match self {
+            PmChatAgent::ClaudeCli => Box::new(ClaudeParser),
+            PmChatAgent::CodexCli => Box::new(CodexParser),
+            PmChatAgent::GeminiCli => Box::new(GeminiParser),
+            PmChatAgent::OpencodeCli => Box::new(OpencodeParser),
+            PmChatAgent::OllamaCli => Box::new(PlainTextParser),
+        }
+    }
+
+    /// Check if this CLI streams its answer as incremental fragments and then
+    /// sends the whole message, which replaces the fragments
+    fn streams_deltas(&self) -> bool {
+        matches!(self, PmChatAgent::GeminiCli)
+    }
+
+    /// Check if this CLI reads the prompt from stdin instead of its arguments
+    fn reads_prompt_from_stdin(&self) -> bool {
+        matches!(self, PmChatAgent::OllamaCli)
+    }
+
+    /// Model used when the request doesn't name one
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            PmChatAgent::ClaudeCli => "sonnet",
+            PmChatAgent::OllamaCli => DEFAULT_OLLAMA_MODEL,
+            PmChatAgent::CodexCli | PmChatAgent::GeminiCli | PmChatAgent::OpencodeCli => "default",
         }
     }
 
@@ -95,6 +204,26 @@ impl PmChatAgent {
         resolve_executable_path(self.command_name()).await.is_some()
     }
 
+    /// Version reported by `<cli> --version`; `None` if the CLI is missing, fails
+    /// or doesn't answer in time. Results are cached for a short while.
+    pub async fn version(&self) -> Option<String> {
+        let cached = AGENT_VERSION_CACHE.lock().ok().and_then(|cache| {
+            cache
+                .get(self)
+                .filter(|(checked_at, _)| checked_at.elapsed() < AGENT_VERSION_CACHE_TTL)
+                .map(|(_, version)| version.clone())
+        });
+        if let Some(version) = cached {
+            return version;
+        }
+
+        let version = detect_cli_version(self.command_name()).await;
+        if let Ok(mut cache) = AGENT_VERSION_CACHE.lock() {
+            cache.insert(*self, (Instant::now(), version.clone()));
+        }
+        version
+    }
+
     /// Get all available CLI agents on this system
     pub async fn available_agents() -> Vec<PmChatAgent> {
         let all_agents = vec![
@@ -102,6 +231,7 @@ impl PmChatAgent {
             PmChatAgent::CodexCli,
             PmChatAgent::GeminiCli,
             PmChatAgent::OpencodeCli,
+            PmChatAgent::OllamaCli,
         ];
 
         let mut available = Vec::new();
@@ -114,6 +244,111 @@ impl PmChatAgent {
     }
 }
 
+/// How a PM chat agent gets the vibe_kanban MCP server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum McpConfigMode {
+    /// Passed on every run with `--mcp-config`
+    PerRun,
+    /// Must be registered in the CLI's own MCP config beforehand
+    Preconfigured,
+    /// The CLI cannot call MCP tools
+    Unsupported,
+}
+
+/// Name the vibe_kanban MCP server is registered under in agent MCP configs
+const VIBE_KANBAN_MCP_SERVER: &str = "vibe_kanban";
+
+/// Check whether the vibe_kanban MCP server is registered in the MCP config
+/// file of `executor` (the same file the MCP settings page edits)
+async fn vibe_kanban_mcp_registered(executor: BaseCodingAgent) -> bool {
+    let Some(coding_agent) =
+        ExecutorConfigs::get_cached().get_coding_agent(&ExecutorProfileId::new(executor))
+    else {
+        return false;
+    };
+    let Some(config_path) = coding_agent.default_mcp_config_path() else {
+        return false;
+    };
+
+    let mcp_config = coding_agent.get_mcp_config();
+    match read_agent_config(&config_path, &mcp_config).await {
+        Ok(raw_config) => get_mcp_servers_from_config_path(&raw_config, &mcp_config.servers_path)
+            .contains_key(VIBE_KANBAN_MCP_SERVER),
+        Err(e) => {
+            tracing::debug!("Failed to read MCP config {:?}: {}", config_path, e);
+            false
+        }
+    }
+}
+
+/// How long a detected CLI version is reused before `--version` is run again
+const AGENT_VERSION_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How long to wait for `<cli> --version`
+const AGENT_VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Last detected version per agent with the time it was checked
+static AGENT_VERSION_CACHE: LazyLock<
+    std::sync::Mutex<HashMap<PmChatAgent, (Instant, Option<String>)>>,
+> = LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Run `<command> --version` and parse the version it prints
+async fn detect_cli_version(command_name: &str) -> Option<String> {
+    let path = resolve_executable_path(command_name).await?;
+    let mut command = Command::new(path);
+    command
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+
+    let output = match tokio::time::timeout(AGENT_VERSION_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) if output.status.success() => output,
+        Ok(Ok(output)) => {
+            tracing::debug!("{} --version exited with {}", command_name, output.status);
+            return None;
+        }
+        Ok(Err(e)) => {
+            tracing::debug!("Failed to run {} --version: {}", command_name, e);
+            return None;
+        }
+        Err(_) => {
+            tracing::debug!("{} --version timed out", command_name);
+            return None;
+        }
+    };
+    parse_cli_version(&String::from_utf8_lossy(&output.stdout))
+        .or_else(|| parse_cli_version(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Extract the version number from `--version` output such as
+/// `1.0.51 (Claude Code)` or `ollama version is 0.3.12`. Falls back to the first
+/// non-empty line when it contains no version number.
+fn parse_cli_version(output: &str) -> Option<String> {
+    static VERSION: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)+(?:-[0-9A-Za-z.]+)?").unwrap());
+
+    let line = output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
+    let version = VERSION.find(line).map_or(line, |m| m.as_str());
+    Some(version.to_string())
+}
+
+/// Ollama model used when none is selected
+const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+
+/// Appended to the system prompt for agents that cannot call MCP tools, so the
+/// model doesn't pretend to have created tasks
+const NO_TOOLS_PROMPT_NOTE: &str = r#"
+
+## Tools Unavailable
+You are running without the vibe_kanban MCP tools in this session. You cannot create,
+update or list tasks, and you cannot change the PM docs. Answer from the context above,
+and when the user asks for a change, describe what should be done and suggest switching
+to an agent with tool support to apply it."#;
+
 /// Type alias for boxed SSE stream to unify different stream implementations
 type SseStream = KeepAliveStream<BoxStream<'static, Result<Event, std::convert::Infallible>>>;
 
@@ -122,6 +357,24 @@ type SseStream = KeepAliveStream<BoxStream<'static, Result<Event, std::convert::
 pub struct SendMessageRequest {
     pub content: String,
     pub role: Option<String>, // "user", "assistant", or "system" - defaults to "user"
+    /// Client-generated id of the message; sending again with the same id
+    /// returns the message stored the first time instead of a duplicate
+    pub client_message_id: Option<String>,
+}
+
+/// Maximum length of a `client_message_id`
+const MAX_CLIENT_MESSAGE_ID_LEN: usize = 128;
+
+/// Check a client-generated message id for `send_message`
+fn validate_client_message_id(id: &str) -> Result<&str, ApiError> {
+    let id = id.trim();
+    if id.is_empty() || id.len() > MAX_CLIENT_MESSAGE_ID_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "client_message_id must be 1 to {} characters",
+            MAX_CLIENT_MESSAGE_ID_LEN
+        )));
+    }
+    Ok(id)
 }
 
 /// Request payload for AI-assisted chat
@@ -129,8 +382,11 @@ pub struct SendMessageRequest {
 #[ts(export)]
 pub struct AiChatRequest {
     pub content: String,
-    pub model: Option<String>, // e.g., "sonnet", "opus", "haiku"
-    pub agent: Option<PmChatAgent>, // CLI agent to use (defaults to ClaudeCli)
+    pub model: Option<String>,       // e.g., "sonnet", "opus", "haiku"
+    pub agent: Option<PmChatAgent>,  // CLI agent to use (defaults to ClaudeCli)
+    pub backend_url: Option<String>, // Override the backend URL the MCP server connects to
+    /// Fail instead of chatting without tools when the agent cannot call MCP tools
+    pub require_tools: Option<bool>,
 }
 
 /// Response for available PM Chat agents
@@ -148,67 +404,263 @@ pub struct PmChatAgentInfo {
     pub display_name: String,
     pub available: bool,
     pub supports_streaming: bool,
+    /// Version reported by `<cli> --version`, if the CLI runs
+    pub version: Option<String>,
+    /// False for agents that chat without the vibe_kanban MCP tools
+    pub supports_tools: bool,
+    pub mcp_config_mode: McpConfigMode,
+    /// Whether the tools are actually wired up; false for a pre-configured
+    /// agent whose MCP config lacks the vibe_kanban server
+    pub tools_configured: bool,
 }
 
 /// SSE event data for streaming AI response
 #[derive(Debug, Clone, Serialize)]
 pub struct AiChatStreamEvent {
     #[serde(rename = "type")]
-    pub event_type: String, // "content", "done", "error", "tool_use", "task_created", "docs_updated"
+    pub event_type: String, // "content", "done", "error", "tool_use", "task_created", "docs_updated", "cancelled", "debug"
     pub content: Option<String>,
     pub error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub task_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub task_title: Option<String>,
+    /// Number of tasks created during the turn (only set on the "done" event)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tasks_created: Option<usize>,
+    /// Id for cancelling this stream via `POST /ai-chat/cancel` or resuming it via
+    /// `GET /ai-chat/{stream_id}/events` (only set on the first event)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_id: Option<String>,
+    /// Length in characters of the PM docs after an update (only set on "docs_updated" events)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs_length: Option<usize>,
+}
+
+/// A chat message together with the attachments linked to it
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct PmChatMessage {
+    #[serde(flatten)]
+    pub message: PmConversation,
+    pub attachments: Vec<PmAttachment>,
 }
 
 /// Response for PM chat with messages and attachments
 #[derive(Debug, Clone, Serialize, TS)]
 pub struct PmChatResponse {
-    pub messages: Vec<PmConversation>,
+    pub messages: Vec<PmChatMessage>,
+    /// Attachments of the returned messages, also nested under each message
+    pub attachments: Vec<PmAttachment>,
     pub pm_docs: Option<String>,
+    /// Cursor for the next (older) page when paginating with `limit`
+    pub next_cursor: Option<Uuid>,
+}
+
+/// Default page size when `before` is given without `limit`
+const DEFAULT_PM_CHAT_PAGE_SIZE: i64 = 50;
+const MAX_PM_CHAT_PAGE_SIZE: i64 = 500;
+
+/// Query params for paginating PM chat messages
+#[derive(Debug, Deserialize)]
+pub struct PmChatQuery {
+    pub limit: Option<i64>,
+    /// Message id returned as `next_cursor`; only older messages are returned
+    pub before: Option<Uuid>,
 }
 
 /// Request for updating PM docs
 #[derive(Debug, Clone, Deserialize, TS)]
 pub struct UpdatePmDocsRequest {
     pub pm_docs: Option<String>,
+    /// Who is making the change, recorded in the docs history (defaults to `user`)
+    pub source: Option<PmDocsSource>,
+    /// Include a unified diff of the change in the response
+    pub return_diff: Option<bool>,
+    /// `updated_at` of the project the edit is based on. If the project has
+    /// been updated since, nothing is saved and `409 Conflict` is returned.
+    #[ts(type = "Date | null")]
+    pub expected_updated_at: Option<DateTime<Utc>>,
+}
+
+/// Error data of a PM docs update rejected because the docs changed meanwhile
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct PmDocsConflict {
+    /// Current PM docs, for the client to merge its edit into
+    pub pm_docs: Option<String>,
+    #[ts(type = "Date")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Updated project, with the diff of the PM docs when it was requested
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct UpdatePmDocsResponse {
+    #[serde(flatten)]
+    pub project: Project,
+    /// Unified diff from the previous docs; `None` if not requested or unchanged
+    pub diff: Option<String>,
+}
+
+/// File name shown in PM docs diffs
+const PM_DOCS_DIFF_PATH: &str = "PM_DOCS.md";
+
+/// Unified diff between two versions of the PM docs; `None` if nothing changed
+pub(crate) fn pm_docs_diff(old: Option<&str>, new: Option<&str>) -> Option<String> {
+    let (old, new) = (old.unwrap_or_default(), new.unwrap_or_default());
+    if old == new {
+        return None;
+    }
+    Some(create_unified_diff(PM_DOCS_DIFF_PATH, old, new))
+}
+
+/// Replace the body of the `## <title>` section of `docs` (up to the next `## `
+/// heading) with `body`. The section is appended if the docs don't have it yet.
+pub(crate) fn replace_docs_section(docs: Option<&str>, title: &str, body: &str) -> String {
+    let docs = docs.unwrap_or_default();
+    let section = format!("## {}\n{}\n", title.trim(), body.trim_matches('\n'));
+    splice_docs_block(docs, docs_section_range(docs, title), &section)
+}
+
+/// Byte range of the `## <title>` section of `docs`, from its heading up to the
+/// next `## ` heading or the end of the docs
+fn docs_section_range(docs: &str, title: &str) -> Option<Range<usize>> {
+    let heading = format!("## {}", title.trim());
+    let mut start = None;
+    let mut offset = 0;
+    for line in docs.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        match start {
+            None if trimmed == heading => start = Some(offset),
+            Some(start) if trimmed.starts_with("## ") => return Some(start..offset),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    start.map(|start| start..docs.len())
 }
 
-/// Get all PM chat messages for a project
+/// Put `block` in place of `range` of `docs`, or append it when there is no range
+fn splice_docs_block(docs: &str, range: Option<Range<usize>>, block: &str) -> String {
+    match range {
+        Some(range) => match docs[range.end..].trim_start_matches('\n') {
+            "" => format!("{}{}", &docs[..range.start], block),
+            rest => format!("{}{}\n{}", &docs[..range.start], block, rest),
+        },
+        None if docs.trim().is_empty() => block.to_string(),
+        None => format!("{}\n\n{}", docs.trim_end(), block),
+    }
+}
+
+/// Get PM chat messages for a project.
+/// Without `limit`/`before` all messages are returned oldest first. With `limit`
+/// only the newest page is returned (oldest first); pages fetched with `before`
+/// are returned newest first so they can be prepended while scrolling up.
 pub async fn get_pm_chat(
     Extension(project): Extension<Project>,
     State(deployment): State<DeploymentImpl>,
+    Query(query): Query<PmChatQuery>,
 ) -> Result<ResponseJson<ApiResponse<PmChatResponse>>, ApiError> {
-    let messages = PmConversation::find_by_project_id(&deployment.db().pool, project.id).await?;
+    let pool = &deployment.db().pool;
+
+    let (messages, next_cursor) = if query.limit.is_none() && query.before.is_none() {
+        (
+            PmConversation::find_by_project_id(pool, project.id).await?,
+            None,
+        )
+    } else {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PM_CHAT_PAGE_SIZE)
+            .clamp(1, MAX_PM_CHAT_PAGE_SIZE);
+        let mut page =
+            PmConversation::find_by_project_id_paginated(pool, project.id, limit, query.before)
+                .await?;
+        if query.before.is_none() {
+            page.messages.reverse();
+        }
+        (page.messages, page.next_cursor)
+    };
+
+    // One query for the whole project instead of one per message
+    let message_ids: HashSet<Uuid> = messages.iter().map(|m| m.id).collect();
+    let attachments: Vec<PmAttachment> = PmAttachment::find_by_project_id(pool, project.id)
+        .await?
+        .into_iter()
+        .filter(|a| message_ids.contains(&a.conversation_id))
+        .collect();
 
     Ok(ResponseJson(ApiResponse::success(PmChatResponse {
-        messages,
+        messages: nest_attachments(messages, &attachments),
+        attachments,
         pm_docs: project.pm_docs,
+        next_cursor,
     })))
 }
 
+/// Group attachments under the message they belong to, keeping their order
+fn nest_attachments(
+    messages: Vec<PmConversation>,
+    attachments: &[PmAttachment],
+) -> Vec<PmChatMessage> {
+    let mut by_message: HashMap<Uuid, Vec<PmAttachment>> = HashMap::new();
+    for attachment in attachments {
+        by_message
+            .entry(attachment.conversation_id)
+            .or_default()
+            .push(attachment.clone());
+    }
+
+    messages
+        .into_iter()
+        .map(|message| PmChatMessage {
+            attachments: by_message.remove(&message.id).unwrap_or_default(),
+            message,
+        })
+        .collect()
+}
+
+/// Role of a message sent to the PM chat; user if none is given
+fn parse_message_role(role: Option<&str>) -> Result<PmMessageRole, ApiError> {
+    match role {
+        None => Ok(PmMessageRole::User),
+        Some(role) => role.parse().map_err(ApiError::BadRequest),
+    }
+}
+
 /// Send a new message to the PM chat
 pub async fn send_message(
     Extension(project): Extension<Project>,
     State(deployment): State<DeploymentImpl>,
     Json(payload): Json<SendMessageRequest>,
 ) -> Result<ResponseJson<ApiResponse<PmConversation>>, ApiError> {
-    let role = match payload.role.as_deref().unwrap_or("user") {
-        "assistant" => PmMessageRole::Assistant,
-        "system" => PmMessageRole::System,
-        _ => PmMessageRole::User,
-    };
+    let role = parse_message_role(payload.role.as_deref())?;
+    let client_message_id = payload
+        .client_message_id
+        .as_deref()
+        .map(validate_client_message_id)
+        .transpose()?;
 
     let create_data = CreatePmConversation {
         project_id: project.id,
         role,
         content: payload.content,
         model: None,
+        input_tokens: None,
+        output_tokens: None,
     };
 
-    let message = PmConversation::create(&deployment.db().pool, &create_data).await?;
+    let pool = &deployment.db().pool;
+    let message = match client_message_id {
+        Some(client_message_id) => {
+            let (message, created) =
+                PmConversation::create_idempotent(pool, &create_data, client_message_id).await?;
+            if !created {
+                // A retried send: the message is already stored and tracked
+                return Ok(ResponseJson(ApiResponse::success(message)));
+            }
+            message
+        }
+        None => PmConversation::create(pool, &create_data).await?,
+    };
 
     deployment
         .track_if_analytics_allowed(
@@ -230,11 +682,93 @@ pub async fn ai_chat(
     State(deployment): State<DeploymentImpl>,
     Json(payload): Json<AiChatRequest>,
 ) -> Result<Sse<SseStream>, ApiError> {
-    // Get conversation history for context
-    let messages = PmConversation::find_by_project_id(&deployment.db().pool, project.id).await?;
+    let settings = deployment.config().read().await.pm_chat.clone();
+    let permit = acquire_ai_chat_permit(project.id, &settings)?;
+
+    let system_prompt = current_system_prompt(&deployment.db().pool, &project).await?;
+
+    let agent = payload.agent.unwrap_or_default();
+    if payload.require_tools.unwrap_or(false) && !agent.supports_tools() {
+        return Err(ApiError::BadRequest(format!(
+            "{} cannot call MCP tools, so it can't create tasks or update docs. Choose an agent with tool support.",
+            agent.display_name()
+        )));
+    }
+
+    let model_name = payload
+        .model
+        .clone()
+        .unwrap_or_else(|| agent.default_model().to_string());
+    let user_content = payload.content.clone();
+    let pool = deployment.db().pool.clone();
+    let project_id = project.id;
 
+    // Use CLI mode with MCP for reliable tool execution
+    create_mcp_cli_stream(
+        agent,
+        model_name,
+        system_prompt,
+        user_content,
+        pool,
+        project_id,
+        payload.backend_url,
+        cli_chat_timeout(&settings),
+        permit,
+    )
+    .await
+}
+
+/// Environment variable overriding how many tokens of conversation history
+/// the system prompt may include
+const HISTORY_TOKEN_BUDGET_ENV: &str = "VIBE_PM_CHAT_HISTORY_TOKENS";
+
+const DEFAULT_HISTORY_TOKEN_BUDGET: usize = 2000;
+
+/// Token budget of the conversation history in the system prompt, from
+/// `VIBE_PM_CHAT_HISTORY_TOKENS` or the default. 0 leaves the history out.
+fn history_token_budget() -> usize {
+    env::var(HISTORY_TOKEN_BUDGET_ENV)
+        .ok()
+        .and_then(|tokens| tokens.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_HISTORY_TOKEN_BUDGET)
+}
+
+/// Conversation context for [`build_system_prompt`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SystemPromptOptions<'a> {
+    /// Conversation messages, oldest first
+    pub history: &'a [PmConversation],
+    /// Estimated tokens the most recent `history` messages may take up
+    pub history_token_budget: usize,
+}
+
+impl<'a> SystemPromptOptions<'a> {
+    pub(crate) fn new(history: &'a [PmConversation]) -> Self {
+        Self {
+            history,
+            history_token_budget: history_token_budget(),
+        }
+    }
+}
+
+/// System prompt for the next AI chat message of `project`, built from its full conversation
+async fn current_system_prompt(
+    pool: &sqlx::SqlitePool,
+    project: &Project,
+) -> Result<String, sqlx::Error> {
+    let messages = PmConversation::find_by_project_id(pool, project.id).await?;
+    Ok(build_system_prompt(pool, project, SystemPromptOptions::new(&messages)).await)
+}
+
+/// Build the PM chat system prompt: MCP tool guide, labels, PM docs, current
+/// tasks and the most recent messages of the conversation
+pub(crate) async fn build_system_prompt(
+    pool: &sqlx::SqlitePool,
+    project: &Project,
+    opts: SystemPromptOptions<'_>,
+) -> String {
     // Get project labels for AI context
-    let labels = db::models::label::Label::find_by_project_id(&deployment.db().pool, project.id)
+    let labels = db::models::label::Label::find_by_project_id(pool, project.id)
         .await
         .unwrap_or_default();
 
@@ -260,20 +794,39 @@ You have access to vibe_kanban MCP tools for comprehensive project management:
   - `depends_on`: List of task IDs this depends on
   - `label_ids`: List of matching label IDs
   - `check_duplicate: true` to avoid duplicates
+- **create_tasks**: Create several tasks in one call (e.g. when breaking down an epic)
+  - Give each task a `temp_id` and the same fields as `create_task`
+  - `depends_on` may reference other tasks of the batch by `temp_id`
+  - Returns the real task ID for each `temp_id`
 - **get_project_progress**: Get completion percentage and status summary for project_id
+- **get_task_order**: Get a dependency-safe execution order for the tasks of project_id
+- **get_critical_path**: Get the longest chain of unfinished dependent tasks for project_id
+- **get_actionable_tasks**: Get the todo/in-progress tasks of project_id whose dependencies are all done, most urgent first
 - **list_tasks**: List all tasks in the project
-- **update_task**: Update task status, title, description
+- **search_tasks**: Find tasks by text in title/description, with optional status and label filters
+- **update_task**: Update task status, title, description, priority
+- **start_task** / **complete_task**: Move a task to in progress / done. Refused while its dependencies are not done; only pass `force: true` if the user explicitly asks
 - **get_task**: Get detailed task information
+- **get_task_dependencies** / **set_task_dependencies**: Read or replace the dependencies of an existing task
+- **create_subtask** / **list_subtasks**: Break a task down into subtasks (hierarchy, not ordering) and check how many are done
+- **move_task**: Move a task before another task (or to the end) to order the backlog
+- **list_labels**: List the labels of project_id with their IDs (for `label_ids`)
+- **get_task_labels**: Get the labels attached to a task
+- **set_task_labels** / **add_task_label** / **remove_task_label**: Change the labels of an existing task
+- **suggest_labels**: Get keyword-based label suggestions for a task, strongest match first
+- **add_task_comment** / **list_task_comments**: Leave a note on a task or read its notes, oldest first
 
 ### Documentation
 - **update_pm_docs**: Update project documentation
   - Use `mode: "append"` to add to existing docs
+  - Use `mode: "prepend"` to add to the beginning of the docs
   - Use `mode: "replace"` to replace all docs
+  - Use `section: "仕様"` to replace only the body of the `## 仕様` section
   - Structure docs with markdown sections: ## 仕様, ## 設計, ## メモ, etc.
 
 ### PM Context
 - **get_pm_context**: Get PM specifications and guidelines
-- **request_pm_review**: Generate review checklist based on PM specs
+- **request_pm_review**: Generate and store a review checklist based on PM specs; pass `save_as_comment: true` to also post it as a task comment
 
 ## When to Use Tools
 - Before creating a task → use list_tasks to understand existing tasks and their dependencies
@@ -349,28 +902,30 @@ When creating ANY task, you MUST:
     }
 
     // Get task summary with IDs, labels, and dependencies
-    let tasks_with_status =
-        Task::find_by_project_id_with_attempt_status(&deployment.db().pool, project.id)
+    let tasks_with_status = Task::find_by_project_id_with_attempt_status(pool, project.id)
+        .await
+        .unwrap_or_default();
+
+    if !tasks_with_status.is_empty() {
+        // Load labels and dependencies of all tasks up front
+        let task_ids: Vec<Uuid> = tasks_with_status.iter().map(|t| t.task.id).collect();
+        let task_labels = db::models::label::Label::find_for_tasks(pool, &task_ids)
+            .await
+            .unwrap_or_default();
+        let dependency_pairs = TaskDependency::find_all_for_project(pool, project.id)
             .await
             .unwrap_or_default();
+        let (dependencies, _) = group_dependencies(&dependency_pairs);
 
-    if !tasks_with_status.is_empty() {
         system_prompt.push_str("## Current Tasks (use these IDs for depends_on)\n");
         for task_with_status in &tasks_with_status {
             let task = &task_with_status.task;
 
-            // Get task labels
-            let task_labels = db::models::label::Label::find_by_task_id(&deployment.db().pool, task.id)
-                .await
-                .unwrap_or_default();
-            let label_names: Vec<String> = task_labels.iter()
-                .map(|l| l.name.clone())
-                .collect();
-
-            // Get task dependencies
-            let deps = TaskDependency::find_dependencies(&deployment.db().pool, task.id)
-                .await
+            let label_names: Vec<String> = task_labels
+                .get(&task.id)
+                .map(|labels| labels.iter().map(|l| l.name.clone()).collect())
                 .unwrap_or_default();
+            let deps = dependencies.get(&task.id).map_or(&[][..], Vec::as_slice);
 
             // Format: - [status] title (id: xxx, priority: P, labels: [L1, L2], depends_on: [id1, id2])
             let mut task_info = format!(
@@ -393,37 +948,217 @@ When creating ANY task, you MUST:
         system_prompt.push('\n');
     }
 
-    // Add recent conversation history for context (last 10 messages)
-    if !messages.is_empty() {
+    // Add as much recent conversation history as fits in the token budget
+    let history = recent_history(opts.history, opts.history_token_budget);
+    if !history.is_empty() {
         system_prompt.push_str("## Recent Conversation History\n");
-        let recent_messages: Vec<_> = messages.iter().rev().take(10).collect();
-        for msg in recent_messages.iter().rev() {
-            let role_str = match msg.role.as_str() {
-                "user" => "User",
-                "assistant" => "Assistant",
-                "system" => "System",
-                _ => "User",
-            };
-            // Truncate long messages to avoid context overflow (UTF-8 safe)
-            let content = if msg.content.len() > 500 {
-                let truncated = utils::text::truncate_to_char_boundary(&msg.content, 500);
-                format!("{}...", truncated)
-            } else {
-                msg.content.clone()
-            };
-            system_prompt.push_str(&format!("**{}**: {}\n\n", role_str, content));
+        for entry in history {
+            system_prompt.push_str(&entry);
         }
     }
 
-    let model_name = payload.model.clone().unwrap_or_else(|| "sonnet".to_string());
-    let user_content = payload.content.clone();
+    system_prompt
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct RegenerateAiChatRequest {
+    /// Model for the new reply; defaults to the model of the replaced reply
+    pub model: Option<String>,
+    pub agent: Option<PmChatAgent>,
+    pub backend_url: Option<String>,
+}
+
+/// Replace the last assistant reply by re-running the AI on the preceding user message
+pub async fn regenerate_ai_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<RegenerateAiChatRequest>,
+) -> Result<Sse<SseStream>, ApiError> {
+    // Take the slot before deleting the reply, so a rejected request leaves it in place
+    let settings = deployment.config().read().await.pm_chat.clone();
+    let permit = acquire_ai_chat_permit(project.id, &settings)?;
     let pool = deployment.db().pool.clone();
-    let project_id = project.id;
+    let mut messages = PmConversation::find_by_project_id(&pool, project.id).await?;
+
+    let last_reply = match messages.pop() {
+        Some(message) if message.role == PmMessageRole::Assistant.to_string() => message,
+        _ => {
+            return Err(ApiError::BadRequest(
+                "The last message is not an assistant reply".to_string(),
+            ));
+        }
+    };
+    let user_content = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == PmMessageRole::User.to_string())
+        .map(|m| m.content.clone())
+        .ok_or_else(|| {
+            ApiError::BadRequest("No user message to regenerate a reply for".to_string())
+        })?;
+
+    PmConversation::delete(&pool, last_reply.id).await?;
+
+    let system_prompt =
+        build_system_prompt(&pool, &project, SystemPromptOptions::new(&messages)).await;
     let agent = payload.agent.unwrap_or_default();
+    let model_name = payload
+        .model
+        .or(last_reply.model)
+        .unwrap_or_else(|| agent.default_model().to_string());
+
+    tracing::info!("Regenerating PM chat reply with {:?}", agent);
+    create_mcp_cli_stream(
+        agent,
+        model_name,
+        system_prompt,
+        user_content,
+        pool,
+        project.id,
+        payload.backend_url,
+        cli_chat_timeout(&settings),
+        permit,
+    )
+    .await
+}
 
-    // Use CLI mode with MCP for reliable tool execution
-    tracing::info!("Using {:?} with MCP tools for PM Chat", agent);
-    create_mcp_cli_stream(agent, model_name, system_prompt, user_content, pool, project_id).await
+/// The system prompt the next AI chat message would be sent with
+#[derive(Debug, Serialize, TS)]
+pub struct PmChatContextPreview {
+    pub system_prompt: String,
+    /// Length of `system_prompt` in characters
+    pub characters: usize,
+    /// Rough token count of `system_prompt`, see [`estimate_tokens`]
+    pub estimated_tokens: usize,
+}
+
+impl PmChatContextPreview {
+    fn new(system_prompt: String) -> Self {
+        Self {
+            characters: system_prompt.chars().count(),
+            estimated_tokens: estimate_tokens(&system_prompt),
+            system_prompt,
+        }
+    }
+}
+
+/// Rough token count of `text`: about four ASCII characters per token, and one
+/// token per non-ASCII character (CJK text tokenizes far less densely)
+fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(char::is_ascii).count();
+    let other = text.chars().count() - ascii;
+    ascii.div_ceil(4) + other
+}
+
+/// Show the system prompt `ai_chat` would build for the project right now
+pub async fn get_context_preview(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<PmChatContextPreview>>, ApiError> {
+    let system_prompt = current_system_prompt(&deployment.db().pool, &project).await?;
+    let preview = PmChatContextPreview::new(system_prompt);
+    Ok(ResponseJson(ApiResponse::success(preview)))
+}
+
+/// Longest prefix of `text` whose estimated tokens do not exceed `max_tokens`
+fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    // Count in quarter tokens: an ASCII character costs one, any other four
+    let mut quarters = 0;
+    for (i, c) in text.char_indices() {
+        quarters += if c.is_ascii() { 1 } else { 4 };
+        if quarters > max_tokens * 4 {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// Conversation history line of a message in the system prompt
+fn history_entry(role: &str, content: &str) -> String {
+    let role = match role {
+        "assistant" => "Assistant",
+        "system" => "System",
+        _ => "User",
+    };
+    format!("**{}**: {}\n\n", role, content)
+}
+
+/// History entries of the most recent `messages` that fit in `budget` estimated
+/// tokens, oldest first. The oldest included message is cut short to use up
+/// the rest of the budget rather than dropped.
+fn recent_history(messages: &[PmConversation], budget: usize) -> Vec<String> {
+    let mut remaining = budget;
+    let mut entries = Vec::new();
+
+    for message in messages.iter().rev() {
+        let entry = history_entry(&message.role, &message.content);
+        let tokens = estimate_tokens(&entry);
+        if tokens <= remaining {
+            remaining -= tokens;
+            entries.push(entry);
+            continue;
+        }
+
+        let overhead = estimate_tokens(&history_entry(&message.role, "..."));
+        let trimmed = truncate_to_tokens(&message.content, remaining.saturating_sub(overhead));
+        if !trimmed.is_empty() {
+            entries.push(history_entry(&message.role, &format!("{}...", trimmed)));
+        }
+        break;
+    }
+
+    entries.reverse();
+    entries
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct CancelAiChatRequest {
+    pub stream_id: Uuid,
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct CancelAiChatResponse {
+    /// False if the stream had already finished or does not exist
+    pub cancelled: bool,
+}
+
+/// Cancel an in-flight AI chat stream: kills the CLI process and keeps the partial response
+pub async fn cancel_ai_chat(
+    Extension(project): Extension<Project>,
+    Json(payload): Json<CancelAiChatRequest>,
+) -> Result<ResponseJson<ApiResponse<CancelAiChatResponse>>, ApiError> {
+    let cancelled = cancel_ai_chat_stream(project.id, payload.stream_id);
+    Ok(ResponseJson(ApiResponse::success(CancelAiChatResponse {
+        cancelled,
+    })))
+}
+
+/// Query parameters for resuming an `ai_chat` stream
+#[derive(Debug, Deserialize)]
+pub struct ResumeAiChatQuery {
+    /// Fallback for clients that can't set the `Last-Event-ID` header
+    pub last_event_id: Option<usize>,
+}
+
+/// Resume an `ai_chat` stream after a dropped connection. Sends the events
+/// after the id in the `Last-Event-ID` header (or `last_event_id` query
+/// parameter), then follows the stream until it finishes.
+pub async fn resume_ai_chat(
+    Extension(project): Extension<Project>,
+    Path(stream_id): Path<Uuid>,
+    Query(query): Query<ResumeAiChatQuery>,
+    headers: header::HeaderMap,
+) -> Result<Sse<SseStream>, ApiError> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .or(query.last_event_id)
+        .unwrap_or(0);
+    let receiver = find_ai_chat_stream_buffer(project.id, stream_id)
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+
+    Ok(Sse::new(replay_ai_chat_stream(receiver, last_event_id)).keep_alive(KeepAlive::default()))
 }
 
 /// Get available PM Chat agents
@@ -433,57 +1168,251 @@ pub async fn get_available_agents() -> Result<ResponseJson<ApiResponse<Available
         PmChatAgent::CodexCli,
         PmChatAgent::GeminiCli,
         PmChatAgent::OpencodeCli,
+        PmChatAgent::OllamaCli,
     ];
 
-    let mut agents = Vec::new();
-    for agent in all_agents {
-        agents.push(PmChatAgentInfo {
+    // Checked concurrently: each uncached version check spawns the CLI
+    let agents = futures::future::join_all(all_agents.into_iter().map(|agent| async move {
+        PmChatAgentInfo {
             agent,
             display_name: agent.display_name().to_string(),
             available: agent.is_available().await,
             supports_streaming: agent.supports_streaming(),
-        });
-    }
+            version: agent.version().await,
+            supports_tools: agent.supports_tools(),
+            mcp_config_mode: agent.mcp_config_mode(),
+            tools_configured: agent.tools_configured().await,
+        }
+    }))
+    .await;
 
     Ok(ResponseJson(ApiResponse::success(AvailablePmChatAgentsResponse { agents })))
 }
 
-/// Create MCP config JSON for the specified agent
-/// Different CLIs have different MCP configuration formats
-fn create_mcp_config_for_agent(
-    agent: PmChatAgent,
-    mcp_binary_path: &Option<PathBuf>,
-    backend_url: &str,
-) -> serde_json::Value {
-    let (command, args) = if let Some(binary_path) = mcp_binary_path {
-        // Use compiled binary directly
-        tracing::info!("Using compiled MCP binary: {:?}", binary_path);
-        (binary_path.to_string_lossy().to_string(), vec![])
-    } else {
-        // Fallback: use npx to run vibe-kanban-pm with --mcp flag
-        tracing::info!("MCP binary not found, using npx vibe-kanban-pm --mcp");
-        ("npx".to_string(), vec!["-y".to_string(), "vibe-kanban-pm@latest".to_string(), "--mcp".to_string()])
-    };
+/// Parse an agent from a URL path segment, e.g. `claude-cli` or `CLAUDE_CLI`
+fn parse_agent_path(agent: &str) -> Result<PmChatAgent, ApiError> {
+    agent
+        .to_uppercase()
+        .replace('-', "_")
+        .parse()
+        .map_err(|_| ApiError::BadRequest(format!("Unknown agent: {}", agent)))
+}
 
-    match agent {
-        PmChatAgent::ClaudeCli => {
-            // Claude CLI uses mcpServers format
-            json!({
-                "mcpServers": {
-                    "vibe_kanban": {
-                        "command": command,
-                        "args": args,
-                        "env": {
-                            "VIBE_BACKEND_URL": backend_url
-                        }
-                    }
-                }
-            })
+/// A model a PM chat agent can be asked to use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+pub struct ModelInfo {
+    /// Value to send as `AiChatRequest.model`
+    pub id: String,
+    pub display_name: String,
+}
+
+impl ModelInfo {
+    fn new(id: &str, display_name: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            display_name: display_name.to_string(),
         }
-        PmChatAgent::CodexCli => {
-            // Codex CLI uses mcp_servers format
-            json!({
-                "mcp_servers": {
+    }
+}
+
+/// Models available for a PM chat agent
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct AvailableModelsResponse {
+    pub agent: PmChatAgent,
+    pub models: Vec<ModelInfo>,
+}
+
+/// Comma-separated model ids offered for OpenCode, e.g. `anthropic/claude-sonnet-4,openai/gpt-4.1`
+const OPENCODE_MODELS_ENV: &str = "VIBE_PM_OPENCODE_MODELS";
+
+/// How long to wait for a CLI to list its models
+const MODEL_LIST_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl PmChatAgent {
+    /// Models known to work with this CLI, without asking the CLI
+    fn known_models(&self) -> Vec<ModelInfo> {
+        match self {
+            PmChatAgent::ClaudeCli => vec![
+                ModelInfo::new("sonnet", "Sonnet"),
+                ModelInfo::new("opus", "Opus"),
+                ModelInfo::new("haiku", "Haiku"),
+            ],
+            PmChatAgent::CodexCli => vec![
+                ModelInfo::new("codex-1", "Codex-1 (o3 optimized)"),
+                ModelInfo::new("codex-mini-latest", "Codex Mini"),
+                ModelInfo::new("gpt-5.2-codex", "GPT-5.2 Codex"),
+                ModelInfo::new("o3", "o3"),
+                ModelInfo::new("o4-mini", "o4-mini"),
+                ModelInfo::new("gpt-4.1", "GPT-4.1"),
+            ],
+            PmChatAgent::GeminiCli => vec![
+                ModelInfo::new("gemini-3-flash", "Gemini 3 Flash"),
+                ModelInfo::new("gemini-2.5-pro", "Gemini 2.5 Pro"),
+                ModelInfo::new("gemini-2.5-flash", "Gemini 2.5 Flash"),
+            ],
+            PmChatAgent::OpencodeCli => {
+                let configured: Vec<ModelInfo> = env::var(OPENCODE_MODELS_ENV)
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(|id| ModelInfo::new(id, id))
+                    .collect();
+                if configured.is_empty() {
+                    vec![ModelInfo::new("default", "Default")]
+                } else {
+                    configured
+                }
+            }
+            PmChatAgent::OllamaCli => vec![
+                ModelInfo::new(DEFAULT_OLLAMA_MODEL, "Llama 3.2"),
+                ModelInfo::new("qwen2.5", "Qwen 2.5"),
+                ModelInfo::new("mistral", "Mistral"),
+            ],
+        }
+    }
+
+    /// Models this CLI can use. Ollama is asked for its installed models; the
+    /// known list is the fallback when that fails.
+    pub async fn models(&self) -> Vec<ModelInfo> {
+        if *self == PmChatAgent::OllamaCli
+            && let Some(models) = list_ollama_models().await
+            && !models.is_empty()
+        {
+            return models;
+        }
+        self.known_models()
+    }
+}
+
+/// Run `ollama list` and parse the installed models
+async fn list_ollama_models() -> Option<Vec<ModelInfo>> {
+    let ollama = resolve_executable_path(PmChatAgent::OllamaCli.command_name()).await?;
+    let mut command = Command::new(ollama);
+    command.arg("list").stdin(Stdio::null()).kill_on_drop(true);
+
+    let output = match tokio::time::timeout(MODEL_LIST_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) if output.status.success() => output,
+        Ok(Ok(output)) => {
+            tracing::debug!("ollama list exited with {}", output.status);
+            return None;
+        }
+        Ok(Err(e)) => {
+            tracing::debug!("Failed to run ollama list: {}", e);
+            return None;
+        }
+        Err(_) => {
+            tracing::debug!("ollama list timed out");
+            return None;
+        }
+    };
+    Some(parse_ollama_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `ollama list` output: a `NAME ID SIZE MODIFIED` header, then one model per line
+fn parse_ollama_list(output: &str) -> Vec<ModelInfo> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "NAME")
+        .map(|name| {
+            let display_name = name.strip_suffix(":latest").unwrap_or(name);
+            ModelInfo::new(name, display_name)
+        })
+        .collect()
+}
+
+/// Get the models available for a PM chat agent
+pub async fn get_agent_models(
+    Path((_project_id, agent)): Path<(Uuid, String)>,
+) -> Result<ResponseJson<ApiResponse<AvailableModelsResponse>>, ApiError> {
+    let agent = parse_agent_path(&agent)?;
+    let models = agent.models().await;
+    Ok(ResponseJson(ApiResponse::success(
+        AvailableModelsResponse { agent, models },
+    )))
+}
+
+/// Environment variable that pins the backend URL handed to the MCP server
+const MCP_BACKEND_URL_ENV: &str = "VIBE_MCP_BACKEND_URL";
+
+/// Fallback port when neither the port file nor `BACKEND_PORT` is available
+const DEFAULT_BACKEND_PORT: &str = "45557";
+
+/// Check that a backend URL is a well-formed http(s) URL and normalize it
+fn validate_backend_url(raw: &str) -> Result<String, String> {
+    let parsed =
+        url::Url::parse(raw.trim()).map_err(|e| format!("Invalid backend URL '{}': {}", raw, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!(
+            "Invalid backend URL '{}': scheme must be http or https",
+            raw
+        ));
+    }
+    if parsed.host_str().is_none() {
+        return Err(format!("Invalid backend URL '{}': missing host", raw));
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+/// Resolve the backend URL the MCP server should connect to
+/// Priority: VIBE_MCP_BACKEND_URL env > request override > port file > BACKEND_PORT env > 45557
+fn resolve_mcp_backend_url(
+    env_override: Option<&str>,
+    request_override: Option<&str>,
+    port_file_port: Option<u16>,
+    backend_port: Option<&str>,
+) -> Result<String, String> {
+    if let Some(url) = env_override.filter(|u| !u.trim().is_empty()) {
+        return validate_backend_url(url);
+    }
+    if let Some(url) = request_override.filter(|u| !u.trim().is_empty()) {
+        return validate_backend_url(url);
+    }
+
+    let port = port_file_port
+        .map(|p| p.to_string())
+        .or_else(|| backend_port.map(|p| p.to_string()))
+        .unwrap_or_else(|| DEFAULT_BACKEND_PORT.to_string());
+    validate_backend_url(&format!("http://localhost:{}", port))
+}
+
+/// Create MCP config JSON for the specified agent
+/// Different CLIs have different MCP configuration formats
+fn create_mcp_config_for_agent(
+    agent: PmChatAgent,
+    mcp_binary_path: &Option<PathBuf>,
+    backend_url: &str,
+) -> serde_json::Value {
+    let (command, args) = if let Some(binary_path) = mcp_binary_path {
+        // Use compiled binary directly
+        tracing::info!("Using compiled MCP binary: {:?}", binary_path);
+        (binary_path.to_string_lossy().to_string(), vec![])
+    } else {
+        // Fallback: use npx to run vibe-kanban-pm with --mcp flag
+        tracing::info!("MCP binary not found, using npx vibe-kanban-pm --mcp");
+        ("npx".to_string(), vec!["-y".to_string(), "vibe-kanban-pm@latest".to_string(), "--mcp".to_string()])
+    };
+
+    match agent {
+        PmChatAgent::ClaudeCli => {
+            // Claude CLI uses mcpServers format
+            json!({
+                "mcpServers": {
+                    "vibe_kanban": {
+                        "command": command,
+                        "args": args,
+                        "env": {
+                            "VIBE_BACKEND_URL": backend_url
+                        }
+                    }
+                }
+            })
+        }
+        PmChatAgent::CodexCli => {
+            // Codex CLI uses mcp_servers format
+            json!({
+                "mcp_servers": {
                     "vibe_kanban": {
                         "command": command,
                         "args": args,
@@ -523,34 +1452,33 @@ fn create_mcp_config_for_agent(
                 "$schema": "https://opencode.ai/config.json"
             })
         }
+        PmChatAgent::OllamaCli => {
+            // Ollama has no MCP support; the (empty) file only keeps cleanup uniform
+            json!({})
+        }
     }
 }
 
-/// Create a streaming response using the specified CLI with MCP tools for task creation and docs management
-/// This version streams CLI output line-by-line for real-time feedback
-async fn create_mcp_cli_stream(
-    agent: PmChatAgent,
-    model: String,
-    system_prompt: String,
-    user_content: String,
-    pool: sqlx::SqlitePool,
-    project_id: Uuid,
-) -> Result<Sse<SseStream>, ApiError> {
-    // Resolve the CLI path based on the agent
-    let cli_path_result = resolve_executable_path(agent.command_name()).await;
-    let npx_path_result = resolve_executable_path("npx").await;
-
-    // Get the backend URL for MCP server to connect to
-    // Priority: BACKEND_PORT env var > port file > fallback to 45557
-    let backend_port = if let Ok(port) = env::var("BACKEND_PORT") {
-        port
-    } else if let Ok(port) = read_port_file("vibe-kanban").await {
-        port.to_string()
-    } else {
-        "45557".to_string()
-    };
-    let backend_url = format!("http://localhost:{}", backend_port);
+/// Resolve the backend URL handed to the MCP server for this request
+async fn mcp_backend_url(request_override: Option<&str>) -> Result<String, ApiError> {
+    let env_override = env::var(MCP_BACKEND_URL_ENV).ok();
+    let port_file_port = read_port_file("vibe-kanban").await.ok();
+    let backend_port = env::var("BACKEND_PORT").ok();
+    resolve_mcp_backend_url(
+        env_override.as_deref(),
+        request_override,
+        port_file_port,
+        backend_port.as_deref(),
+    )
+    .map_err(ApiError::BadRequest)
+}
 
+/// Write the MCP config for `agent` to a temp file named `vibe-pm-mcp-{name}.json`
+fn write_mcp_config_file(
+    agent: PmChatAgent,
+    backend_url: &str,
+    name: &str,
+) -> Result<PathBuf, ApiError> {
     // Get path to the compiled mcp_task_server binary
     // First try to find the binary in the target directory relative to current exe
     let current_exe = env::current_exe().ok();
@@ -560,64 +1488,56 @@ async fn create_mcp_cli_stream(
         .map(|dir| dir.join("mcp_task_server"))
         .filter(|p| p.exists());
 
-    // Create temporary MCP config file based on agent type
-    let mcp_config = create_mcp_config_for_agent(agent, &mcp_binary_path, &backend_url);
+    let mcp_config = create_mcp_config_for_agent(agent, &mcp_binary_path, backend_url);
 
-    // Write MCP config to temp file
-    let temp_dir = env::temp_dir();
-    let config_path = temp_dir.join(format!("vibe-pm-mcp-{}.json", project_id));
+    let config_path = env::temp_dir().join(format!("vibe-pm-mcp-{}.json", name));
 
-    if let Err(e) = fs::write(&config_path, serde_json::to_string_pretty(&mcp_config).unwrap_or_default()) {
+    if let Err(e) = fs::write(
+        &config_path,
+        serde_json::to_string_pretty(&mcp_config).unwrap_or_default(),
+    ) {
         tracing::error!("Failed to write MCP config: {}", e);
-        return Err(ApiError::BadRequest(format!("Failed to create MCP config: {}", e)));
+        return Err(ApiError::BadRequest(format!(
+            "Failed to create MCP config: {}",
+            e
+        )));
     }
 
-    tracing::info!("Created MCP config at {:?} with backend URL: {} for {:?}", config_path, backend_url, agent);
+    tracing::info!(
+        "Created MCP config at {:?} with backend URL: {} for {:?}",
+        config_path,
+        backend_url,
+        agent
+    );
 
-    // Prepare command based on agent and available CLI
-    let (command_path, use_npx_fallback): (Option<PathBuf>, bool) = if let Some(path) = cli_path_result {
-        tracing::info!("Running {:?} from: {:?} (streaming mode)", agent, path);
-        (Some(path), false)
-    } else if agent == PmChatAgent::ClaudeCli && npx_path_result.is_some() {
-        // Only Claude CLI has npx fallback
-        tracing::info!("Running Claude CLI with MCP via npx (streaming mode)");
-        (npx_path_result, true)
-    } else {
-        // CLI not available - return error stream
-        let agent_name = agent.display_name();
-        let stream = async_stream::stream! {
-            let event = AiChatStreamEvent {
-                event_type: "error".to_string(),
-                content: None,
-                error: Some(format!("{} not found. Please install it first.", agent_name)),
-                task_id: None,
-                task_title: None,
-            };
-            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-            let done = AiChatStreamEvent { event_type: "done".to_string(), content: None, error: None, task_id: None, task_title: None };
-            yield Ok(Event::default().data(serde_json::to_string(&done).unwrap_or_default()));
-        };
-        return Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()));
-    };
+    Ok(config_path)
+}
 
-    let Some(cmd_path) = command_path else {
-        let stream = async_stream::stream! {
-            let event = AiChatStreamEvent {
-                event_type: "error".to_string(),
-                content: None,
-                error: Some("CLI executable not found.".to_string()),
-                task_id: None,
-                task_title: None,
-            };
-            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-            let done = AiChatStreamEvent { event_type: "done".to_string(), content: None, error: None, task_id: None, task_title: None };
-            yield Ok(Event::default().data(serde_json::to_string(&done).unwrap_or_default()));
-        };
-        return Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()));
-    };
+/// Resolve the executable used to run `agent`
+/// Returns the path and whether it is the `npx` fallback (only Claude CLI has one)
+async fn resolve_agent_command(agent: PmChatAgent) -> Option<(PathBuf, bool)> {
+    if let Some(path) = resolve_executable_path(agent.command_name()).await {
+        return Some((path, false));
+    }
+    if agent == PmChatAgent::ClaudeCli {
+        return resolve_executable_path("npx")
+            .await
+            .map(|path| (path, true));
+    }
+    None
+}
 
-    // Build command based on agent type
-    let mut command = Command::new(&cmd_path);
+/// Build the CLI command for `agent` with its MCP config, model and prompt
+fn build_agent_command(
+    agent: PmChatAgent,
+    cmd_path: &std::path::Path,
+    use_npx_fallback: bool,
+    config_path: &std::path::Path,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+) -> Command {
+    let mut command = Command::new(cmd_path);
 
     // Add npx-specific args for Claude CLI fallback
     if use_npx_fallback {
@@ -635,12 +1555,12 @@ async fn create_mcp_cli_stream(
                 .arg("--no-session-persistence")
                 .arg("--dangerously-skip-permissions")
                 .arg("--mcp-config")
-                .arg(&config_path)
+                .arg(config_path)
                 .arg("--model")
-                .arg(&model)
+                .arg(model)
                 .arg("--system-prompt")
-                .arg(&system_prompt)
-                .arg(&user_content);
+                .arg(system_prompt)
+                .arg(user_content);
         }
         PmChatAgent::CodexCli => {
             // Codex CLI uses exec subcommand with --json for streaming
@@ -652,7 +1572,7 @@ async fn create_mcp_cli_stream(
 
             // Add model if specified (o3, o4-mini, gpt-4.1, codex-1, etc.)
             if !model.is_empty() && model != "default" {
-                command.arg("--model").arg(&model);
+                command.arg("--model").arg(model);
             }
 
             command.arg(format!("{}\n\n{}", system_prompt, user_content));
@@ -667,7 +1587,7 @@ async fn create_mcp_cli_stream(
 
             // Add model if specified (gemini-3-flash, gemini-2.5-pro, etc.)
             if !model.is_empty() && model != "default" {
-                command.arg("--model").arg(&model);
+                command.arg("--model").arg(model);
             }
 
             // Gemini doesn't have --system-prompt, include in the message
@@ -680,944 +1600,5805 @@ async fn create_mcp_cli_stream(
                 .arg("--format")
                 .arg("json")
                 .arg("--model")
-                .arg(&model)
+                .arg(model)
                 .arg(format!("{}\n\n{}", system_prompt, user_content));
         }
+        PmChatAgent::OllamaCli => {
+            // Ollama reads the prompt (system prompt included) from stdin, see agent_stdin_prompt
+            let model = if model.is_empty() || model == "default" {
+                DEFAULT_OLLAMA_MODEL
+            } else {
+                model
+            };
+            command.arg("run").arg(model).arg("--nowordwrap");
+        }
     }
 
+    let stdin = if agent.reads_prompt_from_stdin() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
     command
-        .stdin(Stdio::null())
+        .stdin(stdin)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
-    // Spawn process
-    let mut child = match command.spawn() {
-        Ok(child) => child,
-        Err(e) => {
-            // Clean up config file
-            let _ = fs::remove_file(&config_path);
-            let stream = async_stream::stream! {
-                let event = AiChatStreamEvent {
-                    event_type: "error".to_string(),
-                    content: None,
-                    error: Some(format!("Failed to spawn CLI: {}", e)),
-                    task_id: None,
-                    task_title: None,
-                };
-                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-                let done = AiChatStreamEvent { event_type: "done".to_string(), content: None, error: None, task_id: None, task_title: None };
-                yield Ok(Event::default().data(serde_json::to_string(&done).unwrap_or_default()));
-            };
-            return Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()));
-        }
-    };
-
-    // Take ownership of stdout and stderr
-    let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
+    command
+}
 
-    // Shared state for collecting full response
-    let full_response = Arc::new(Mutex::new(String::new()));
-    let full_response_clone = full_response.clone();
-    let model_clone = model.clone();
-    let config_path_clone = config_path.clone();
+/// Prompt written to stdin for agents that read it from there
+fn agent_stdin_prompt(system_prompt: &str, user_content: &str) -> String {
+    format!("{}\n\n{}", system_prompt, user_content)
+}
 
-    // Create the streaming response
-    let stream = async_stream::stream! {
-        // Send initial "thinking" indicator
-        let thinking_event = AiChatStreamEvent {
-            event_type: "thinking".to_string(),
-            content: Some("AI is processing...".to_string()),
-            error: None,
-            task_id: None,
-            task_title: None,
-        };
-        yield Ok(Event::default().data(serde_json::to_string(&thinking_event).unwrap_or_default()));
+/// Write `prompt` to the child's stdin in the background and close it, so a
+/// large prompt can't block while the CLI is still starting up
+fn feed_agent_stdin(child: &mut tokio::process::Child, prompt: String) {
+    let Some(mut stdin) = child.stdin.take() else {
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = stdin.write_all(prompt.as_bytes()).await {
+            tracing::warn!("Failed to write prompt to CLI stdin: {}", e);
+        }
+        // Dropping stdin closes it so the CLI knows the prompt is complete
+    });
+}
 
-        if let Some(stdout) = stdout {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-
-            // Stream each line as it comes
-            // Each CLI has a different JSON format:
-            // - Claude: {"type":"assistant","message":{"content":[{"type":"text","text":"..."}]}}
-            // - Codex: {"type":"item.completed","item":{"type":"agent_message","text":"..."}}
-            // - Gemini: {"type":"message","role":"assistant","content":"...","delta":true}
-            while let Ok(Some(line)) = lines.next_line().await {
-                if line.is_empty() {
-                    continue;
-                }
+/// Assistant text extracted from one line of agent CLI output
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AgentText {
+    text: String,
+    /// Whether the text is an incremental fragment of the current message
+    /// rather than a whole message
+    delta: bool,
+}
 
-                // Skip non-JSON lines (like "Loading extension: ...")
-                if !line.starts_with('{') {
-                    tracing::debug!("CLI non-JSON output: {}", line);
-                    continue;
-                }
+impl AgentText {
+    fn message(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            delta: false,
+        }
+    }
+}
 
-                if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&line) {
-                    let event_type = json_value.get("type").and_then(|t| t.as_str());
-                    let mut extracted_text: Option<String> = None;
-
-                    match event_type {
-                        // === Claude CLI format ===
-                        // {"type":"assistant","message":{"content":[{"type":"text","text":"..."}]}}
-                        Some("assistant") => {
-                            if let Some(message) = json_value.get("message")
-                                && let Some(content_array) = message.get("content").and_then(|c| c.as_array())
-                            {
-                                for block in content_array {
-                                    if let Some(text) = block.get("text").and_then(|t| t.as_str())
-                                        && !text.is_empty()
-                                    {
-                                        extracted_text = Some(text.to_string());
-                                    }
-                                }
-                            }
-                        }
+/// Assistant response assembled from the text an agent CLI streams.
+/// Delta fragments are concatenated verbatim, whole messages are separated
+/// by a blank line and plain text output is kept line by line. For CLIs that
+/// stream deltas, a whole message following fragments replaces them.
+#[derive(Debug, Default)]
+struct ResponseAccumulator {
+    text: String,
+    /// Whether a whole message replaces the fragments streamed before it
+    replaces_fragments: bool,
+    /// Whether the last text added was a delta fragment
+    in_delta: bool,
+    /// Offset in `text` where the current message starts
+    message_start: usize,
+}
 
-                        // === Codex CLI format ===
-                        // {"type":"item.completed","item":{"type":"agent_message","text":"..."}}
-                        // {"type":"item.completed","item":{"type":"reasoning","text":"..."}}
-                        Some("item.completed") => {
-                            if let Some(item) = json_value.get("item") {
-                                let item_type = item.get("type").and_then(|t| t.as_str());
-                                // Only extract agent_message, skip reasoning
-                                if item_type == Some("agent_message") {
-                                    if let Some(text) = item.get("text").and_then(|t| t.as_str())
-                                        && !text.is_empty()
-                                    {
-                                        extracted_text = Some(text.to_string());
-                                    }
-                                }
-                            }
-                        }
+impl ResponseAccumulator {
+    fn new(agent: PmChatAgent) -> Self {
+        Self {
+            replaces_fragments: agent.streams_deltas(),
+            ..Default::default()
+        }
+    }
 
-                        // === Gemini CLI format ===
-                        // {"type":"message","role":"assistant","content":"...","delta":true}
-                        Some("message") => {
-                            let role = json_value.get("role").and_then(|r| r.as_str());
-                            if role == Some("assistant") {
-                                if let Some(content) = json_value.get("content").and_then(|c| c.as_str())
-                                    && !content.is_empty()
-                                {
-                                    extracted_text = Some(content.to_string());
-                                }
-                            }
-                        }
+    fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
 
-                        // === Result events (Claude & Gemini) ===
-                        Some("result") => {
-                            // Claude: {"type":"result","result":"..."}
-                            if let Some(result_text) = json_value.get("result").and_then(|r| r.as_str()) {
-                                let current_response = full_response_clone.lock().await.clone();
-                                if current_response.is_empty() && !result_text.is_empty() {
-                                    extracted_text = Some(result_text.to_string());
-                                }
-                            }
-                            // Gemini result is just stats, no text content
-                        }
+    /// Add extracted text, returning the part the client hasn't been sent yet
+    fn push(&mut self, text: &AgentText) -> Option<String> {
+        let was_delta = std::mem::replace(&mut self.in_delta, text.delta);
 
-                        // System/init events - log for debugging
-                        Some("system") | Some("init") | Some("thread.started") | Some("turn.started") | Some("turn.completed") => {
-                            tracing::debug!("CLI event: {:?}", event_type);
-                        }
+        // A fragment continues the message being streamed
+        if text.delta && was_delta {
+            self.text.push_str(&text.text);
+            return Some(text.text.clone());
+        }
 
-                        // Unknown types - log and skip
-                        _ => {
-                            tracing::debug!("CLI unknown event type: {:?}", event_type);
-                        }
-                    }
+        // The whole message replaces its fragments; only stream what they missed
+        if !text.delta && was_delta && self.replaces_fragments {
+            let streamed = &self.text[self.message_start..];
+            let unseen = text
+                .text
+                .strip_prefix(streamed)
+                .unwrap_or(&text.text)
+                .to_string();
+            self.text.truncate(self.message_start);
+            self.text.push_str(&text.text);
+            return (!unseen.is_empty()).then_some(unseen);
+        }
 
-                    // If we extracted text, send it as SSE event
-                    if let Some(text) = extracted_text {
-                        // Append to full response
-                        {
-                            let mut response = full_response_clone.lock().await;
-                            if !response.is_empty() && !text.starts_with(' ') {
-                                response.push(' ');
-                            }
-                            response.push_str(&text);
-                        }
+        // Anything else starts a new message
+        if !self.text.is_empty() {
+            self.text.push_str("\n\n");
+        }
+        self.message_start = self.text.len();
+        self.text.push_str(&text.text);
+        Some(text.text.clone())
+    }
 
-                        // Send as SSE event
-                        let event = AiChatStreamEvent {
-                            event_type: "content".to_string(),
-                            content: Some(text),
-                            error: None,
-                            task_id: None,
-                            task_title: None,
-                        };
-                        yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-                    }
-                } else {
-                    // If not valid JSON, treat as plain text (fallback)
-                    tracing::debug!("CLI non-JSON line: {}", line);
-                    {
-                        let mut response = full_response_clone.lock().await;
-                        if !response.is_empty() {
-                            response.push('\n');
-                        }
-                        response.push_str(&line);
-                    }
+    /// Add the final result, which repeats the answer; it is only used when
+    /// nothing was streamed before it
+    fn push_result(&mut self, text: &str) -> Option<String> {
+        if !self.is_empty() {
+            return None;
+        }
+        self.push(&AgentText::message(text))
+    }
 
-                    let event = AiChatStreamEvent {
-                        event_type: "content".to_string(),
-                        content: Some(line),
-                        error: None,
-                        task_id: None,
-                        task_title: None,
-                    };
-                    yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-                }
-            }
+    fn push_line(&mut self, line: &str) {
+        if !self.text.is_empty() {
+            self.text.push('\n');
         }
+        self.text.push_str(line);
+        self.in_delta = false;
+    }
+}
 
-        // Check for stderr messages
-        if let Some(stderr) = stderr {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
+/// A tool invocation or tool result observed in agent CLI JSON output
+#[derive(Debug, Clone, PartialEq)]
+enum AgentToolEvent {
+    /// The agent invoked a tool
+    Call {
+        id: Option<String>,
+        name: String,
+        input: Option<serde_json::Value>,
+    },
+    /// A tool invocation finished
+    Result {
+        id: Option<String>,
+        name: Option<String>,
+        success: bool,
+        output: Option<String>,
+    },
+}
 
-            while let Ok(Some(line)) = lines.next_line().await {
-                if !line.is_empty() {
-                    tracing::debug!("Claude CLI stderr: {}", line);
-                }
-            }
+/// Strip MCP server prefixes from a tool name
+/// e.g. `mcp__vibe_kanban__list_projects` / `vibe_kanban_list_projects` -> `list_projects`
+fn tool_base_name(name: &str) -> &str {
+    let name = name.rsplit("__").next().unwrap_or(name);
+    name.strip_prefix("vibe_kanban_").unwrap_or(name)
+}
+
+/// Flatten a tool result payload into text
+/// Handles plain strings, `[{"type":"text","text":"..."}]` arrays and `{"content":[...]}` objects
+fn tool_output_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(items) => {
+            let texts: Vec<String> = items.iter().filter_map(tool_output_text).collect();
+            (!texts.is_empty()).then(|| texts.join("\n"))
         }
+        serde_json::Value::Object(map) => map
+            .get("text")
+            .or_else(|| map.get("content"))
+            .and_then(tool_output_text),
+        _ => None,
+    }
+}
 
-        // Wait for the child process to complete
-        let exit_status = child.wait().await;
+/// One thing parsed from a line of agent CLI output
+#[derive(Debug, Clone, PartialEq)]
+enum ParsedEvent {
+    /// Assistant text
+    Content(AgentText),
+    /// A line of plain text output, kept as is
+    Line(String),
+    /// A tool call or tool result
+    ToolUse(AgentToolEvent),
+    /// The final answer, repeating the text streamed before it
+    Result(String),
+    /// Tokens used by the turn
+    Usage(TokenUsage),
+    /// Nothing relevant to the chat
+    Ignore,
+}
 
-        // Clean up temp config file
-        if let Err(e) = fs::remove_file(&config_path_clone) {
-            tracing::warn!("Failed to remove temp MCP config: {}", e);
+/// Tokens an agent CLI reports for a turn. Input tokens include cached prompt tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct TokenUsage {
+    input_tokens: i64,
+    output_tokens: i64,
+}
+
+impl TokenUsage {
+    /// Read a usage object, summing all `input_keys` (some CLIs count cached
+    /// prompt tokens separately). `None` if none of the counts is present.
+    fn from_counts(
+        usage: &serde_json::Value,
+        input_keys: &[&str],
+        output_key: &str,
+    ) -> Option<Self> {
+        let count = |key: &str| usage.get(key).and_then(|v| v.as_i64());
+        let inputs: Vec<i64> = input_keys.iter().filter_map(|key| count(key)).collect();
+        let output = count(output_key);
+        if inputs.is_empty() && output.is_none() {
+            return None;
         }
+        Some(Self {
+            input_tokens: inputs.iter().sum(),
+            output_tokens: output.unwrap_or(0),
+        })
+    }
 
-        // Get the full response and save to conversation history
-        let final_response = full_response_clone.lock().await.clone();
-        if !final_response.is_empty() {
-            let _ = PmConversation::create(
-                &pool,
-                &CreatePmConversation {
-                    project_id,
-                    role: PmMessageRole::Assistant,
-                    content: final_response,
-                    model: Some(model_clone),
-                },
-            )
-            .await;
-        }
-
-        // Check exit status for errors
-        match exit_status {
-            Ok(status) if !status.success() => {
-                let event = AiChatStreamEvent {
-                    event_type: "error".to_string(),
-                    content: None,
-                    error: Some(format!("CLI exited with status: {}", status)),
-                    task_id: None,
-                    task_title: None,
-                };
-                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-            }
-            Err(e) => {
-                let event = AiChatStreamEvent {
-                    event_type: "error".to_string(),
-                    content: None,
-                    error: Some(format!("CLI error: {}", e)),
-                    task_id: None,
-                    task_title: None,
-                };
-                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
-            }
-            _ => {}
-        }
+    fn accumulate(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
 
-        // Send done event
-        let done = AiChatStreamEvent {
-            event_type: "done".to_string(),
-            content: None,
-            error: None,
-            task_id: None,
-            task_title: None,
-        };
-        yield Ok(Event::default().data(serde_json::to_string(&done).unwrap_or_default()));
-    };
+/// Parses the stdout of one agent CLI, line by line
+trait AgentStreamParser: Send {
+    fn parse_line(&mut self, line: &str) -> Vec<ParsedEvent>;
+}
 
-    Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()))
+/// Parse a line of JSON output, or return the events for a line that isn't JSON.
+/// Lines like "Loading extension: ..." are ignored and broken JSON is kept as text.
+fn parse_json_line(line: &str) -> Result<serde_json::Value, Vec<ParsedEvent>> {
+    if !line.starts_with('{') {
+        if !line.is_empty() {
+            tracing::debug!("CLI non-JSON output: {}", line);
+        }
+        return Err(vec![ParsedEvent::Ignore]);
+    }
+    serde_json::from_str(line).map_err(|_| {
+        tracing::debug!("CLI non-JSON line: {}", line);
+        vec![ParsedEvent::Line(line.to_string())]
+    })
 }
 
-/// Clear all PM chat messages for a project
-pub async fn clear_chat(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
-    let rows_affected =
-        PmConversation::delete_by_project_id(&deployment.db().pool, project.id).await?;
+fn event_type(event: &serde_json::Value) -> Option<&str> {
+    event.get("type").and_then(|t| t.as_str())
+}
 
-    deployment
-        .track_if_analytics_allowed(
-            "pm_chat_cleared",
-            serde_json::json!({
-                "project_id": project.id.to_string(),
-                "messages_deleted": rows_affected,
-            }),
-        )
-        .await;
+fn str_field(value: &serde_json::Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
 
-    Ok(ResponseJson(ApiResponse::success(())))
+/// Non-empty string field, as assistant text
+fn text_field(value: &serde_json::Value, key: &str) -> Option<String> {
+    str_field(value, key).filter(|text| !text.is_empty())
 }
 
-/// Delete a specific message
-/// Uses tuple to extract both project_id (from parent route) and message_id
-pub async fn delete_message(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-    Path((_project_id, message_id)): Path<(Uuid, Uuid)>,
-) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
-    // First verify the message belongs to this project
-    let message = PmConversation::find_by_id(&deployment.db().pool, message_id).await?;
+fn or_ignore(events: Vec<ParsedEvent>) -> Vec<ParsedEvent> {
+    if events.is_empty() {
+        vec![ParsedEvent::Ignore]
+    } else {
+        events
+    }
+}
 
-    match message {
-        Some(msg) if msg.project_id == project.id => {
-            PmConversation::delete(&deployment.db().pool, message_id).await?;
-            Ok(ResponseJson(ApiResponse::success(())))
+/// Claude CLI `--output-format stream-json`
+/// - {"type":"assistant","message":{"content":[{"type":"text","text":"..."},{"type":"tool_use",...}]}}
+/// - {"type":"user","message":{"content":[{"type":"tool_result",...}]}}
+/// - {"type":"result","result":"..."}
+struct ClaudeParser;
+
+impl AgentStreamParser for ClaudeParser {
+    fn parse_line(&mut self, line: &str) -> Vec<ParsedEvent> {
+        let event = match parse_json_line(line) {
+            Ok(event) => event,
+            Err(events) => return events,
+        };
+        let mut events = Vec::new();
+
+        match event_type(&event) {
+            Some("assistant") | Some("user") => {
+                let blocks = event
+                    .get("message")
+                    .and_then(|m| m.get("content"))
+                    .and_then(|c| c.as_array());
+                for block in blocks.into_iter().flatten() {
+                    match event_type(block) {
+                        Some("text") => {
+                            if let Some(text) = text_field(block, "text") {
+                                events.push(ParsedEvent::Content(AgentText::message(&text)));
+                            }
+                        }
+                        Some("tool_use") => {
+                            if let Some(name) = str_field(block, "name") {
+                                events.push(ParsedEvent::ToolUse(AgentToolEvent::Call {
+                                    id: str_field(block, "id"),
+                                    name,
+                                    input: block.get("input").cloned(),
+                                }));
+                            }
+                        }
+                        Some("tool_result") => {
+                            let is_error = block
+                                .get("is_error")
+                                .and_then(|e| e.as_bool())
+                                .unwrap_or(false);
+                            events.push(ParsedEvent::ToolUse(AgentToolEvent::Result {
+                                id: str_field(block, "tool_use_id"),
+                                name: None,
+                                success: !is_error,
+                                output: block.get("content").and_then(tool_output_text),
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some("result") => {
+                if let Some(text) = text_field(&event, "result") {
+                    events.push(ParsedEvent::Result(text));
+                }
+                if let Some(usage) = event.get("usage").and_then(|usage| {
+                    TokenUsage::from_counts(
+                        usage,
+                        &[
+                            "input_tokens",
+                            "cache_creation_input_tokens",
+                            "cache_read_input_tokens",
+                        ],
+                        "output_tokens",
+                    )
+                }) {
+                    events.push(ParsedEvent::Usage(usage));
+                }
+            }
+            other => tracing::debug!("Claude CLI event: {:?}", other),
         }
-        Some(_) => Err(ApiError::BadRequest(
-            "Message does not belong to this project".to_string(),
-        )),
-        None => Err(ApiError::Database(sqlx::Error::RowNotFound)),
+
+        or_ignore(events)
     }
 }
 
-/// Get all attachments for a project
-pub async fn get_attachments(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-) -> Result<ResponseJson<ApiResponse<Vec<PmAttachment>>>, ApiError> {
-    let attachments = PmAttachment::find_by_project_id(&deployment.db().pool, project.id).await?;
-    Ok(ResponseJson(ApiResponse::success(attachments)))
+/// Codex CLI `exec --json`
+/// - {"type":"item.completed","item":{"type":"agent_message","text":"..."}}
+/// - {"type":"item.started"|"item.completed","item":{"type":"mcp_tool_call",...}}
+/// Reasoning items are skipped.
+struct CodexParser;
+
+impl AgentStreamParser for CodexParser {
+    fn parse_line(&mut self, line: &str) -> Vec<ParsedEvent> {
+        let event = match parse_json_line(line) {
+            Ok(event) => event,
+            Err(events) => return events,
+        };
+        let mut events = Vec::new();
+
+        match (event_type(&event), event.get("item")) {
+            (Some(kind @ ("item.started" | "item.completed")), Some(item)) => {
+                match event_type(item) {
+                    Some("agent_message") if kind == "item.completed" => {
+                        if let Some(text) = text_field(item, "text") {
+                            events.push(ParsedEvent::Content(AgentText::message(&text)));
+                        }
+                    }
+                    Some("mcp_tool_call") => {
+                        if let Some(name) = str_field(item, "tool") {
+                            let id = str_field(item, "id");
+                            let tool_event = if kind == "item.started" {
+                                AgentToolEvent::Call {
+                                    id,
+                                    name,
+                                    input: item.get("arguments").cloned(),
+                                }
+                            } else {
+                                AgentToolEvent::Result {
+                                    id,
+                                    name: Some(name),
+                                    success: item.get("status").and_then(|s| s.as_str())
+                                        == Some("completed"),
+                                    output: item.get("result").and_then(tool_output_text),
+                                }
+                            };
+                            events.push(ParsedEvent::ToolUse(tool_event));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (Some("turn.completed"), _) => {
+                if let Some(usage) = event.get("usage").and_then(|usage| {
+                    TokenUsage::from_counts(usage, &["input_tokens"], "output_tokens")
+                }) {
+                    events.push(ParsedEvent::Usage(usage));
+                }
+            }
+            (other, _) => tracing::debug!("Codex CLI event: {:?}", other),
+        }
+
+        or_ignore(events)
+    }
 }
 
-/// Get the PM attachments directory
-fn get_pm_attachments_dir() -> PathBuf {
-    let cache_dir = utils::cache_dir().join("pm-attachments");
-    fs::create_dir_all(&cache_dir).ok();
-    cache_dir
+/// Gemini CLI `--output-format stream-json`
+/// - {"type":"message","role":"assistant","content":"...","delta":true}
+/// - {"type":"tool_use","tool_name":"...","tool_id":"...","parameters":{...}}
+/// - {"type":"tool_result","tool_id":"...","status":"success","output":"..."}
+/// - {"type":"result","status":"success","stats":{"input_tokens":...,"output_tokens":...}}
+struct GeminiParser;
+
+impl AgentStreamParser for GeminiParser {
+    fn parse_line(&mut self, line: &str) -> Vec<ParsedEvent> {
+        let event = match parse_json_line(line) {
+            Ok(event) => event,
+            Err(events) => return events,
+        };
+        let mut events = Vec::new();
+
+        match event_type(&event) {
+            Some("message") if event.get("role").and_then(|r| r.as_str()) == Some("assistant") => {
+                if let Some(text) = text_field(&event, "content") {
+                    events.push(ParsedEvent::Content(AgentText {
+                        text,
+                        // Without the flag the event holds the whole message
+                        delta: event
+                            .get("delta")
+                            .and_then(|d| d.as_bool())
+                            .unwrap_or(false),
+                    }));
+                }
+            }
+            Some("tool_use") => {
+                if let Some(name) = str_field(&event, "tool_name") {
+                    events.push(ParsedEvent::ToolUse(AgentToolEvent::Call {
+                        id: str_field(&event, "tool_id"),
+                        name,
+                        input: event.get("parameters").cloned(),
+                    }));
+                }
+            }
+            Some("tool_result") => {
+                events.push(ParsedEvent::ToolUse(AgentToolEvent::Result {
+                    id: str_field(&event, "tool_id"),
+                    name: str_field(&event, "tool_name"),
+                    success: event.get("status").and_then(|s| s.as_str()) == Some("success"),
+                    output: event.get("output").and_then(tool_output_text),
+                }));
+            }
+            Some("result") => {
+                if let Some(usage) = event.get("stats").and_then(|stats| {
+                    TokenUsage::from_counts(stats, &["input_tokens"], "output_tokens")
+                }) {
+                    events.push(ParsedEvent::Usage(usage));
+                }
+            }
+            other => tracing::debug!("Gemini CLI event: {:?}", other),
+        }
+
+        or_ignore(events)
+    }
 }
 
-/// Sanitize filename for filesystem safety
-fn sanitize_filename(name: &str) -> String {
-    let stem = std::path::Path::new(name)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("file");
+/// OpenCode CLI `run --format json`
+/// - {"type":"text","part":{"type":"text","text":"..."}}
+/// - {"type":"tool_use","part":{"tool":"...","callID":"...","state":{"status":"...",...}}}
+/// - {"type":"step_finish","part":{"tokens":{"input":...,"output":...}}}
+struct OpencodeParser;
+
+impl AgentStreamParser for OpencodeParser {
+    fn parse_line(&mut self, line: &str) -> Vec<ParsedEvent> {
+        let event = match parse_json_line(line) {
+            Ok(event) => event,
+            Err(events) => return events,
+        };
+        let mut events = Vec::new();
 
-    let clean: String = stem
-        .to_lowercase()
-        .chars()
-        .map(|c| if c.is_whitespace() { '_' } else { c })
-        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-        .collect();
+        match (event_type(&event), event.get("part")) {
+            (Some("text"), Some(part)) => {
+                if let Some(text) = text_field(part, "text") {
+                    events.push(ParsedEvent::Content(AgentText::message(&text)));
+                }
+            }
+            (Some("tool_use"), Some(part)) => {
+                if let Some(name) = str_field(part, "tool") {
+                    let id = str_field(part, "callID");
+                    let state = part.get("state");
+                    events.push(ParsedEvent::ToolUse(AgentToolEvent::Call {
+                        id: id.clone(),
+                        name: name.clone(),
+                        input: state.and_then(|s| s.get("input")).cloned(),
+                    }));
+                    let status = state.and_then(|s| s.get("status")).and_then(|s| s.as_str());
+                    if matches!(status, Some("completed") | Some("error")) {
+                        events.push(ParsedEvent::ToolUse(AgentToolEvent::Result {
+                            id,
+                            name: Some(name),
+                            success: status == Some("completed"),
+                            output: state
+                                .and_then(|s| s.get("output"))
+                                .and_then(tool_output_text),
+                        }));
+                    }
+                }
+            }
+            (Some("step_finish"), Some(part)) => {
+                if let Some(usage) = part
+                    .get("tokens")
+                    .and_then(|tokens| TokenUsage::from_counts(tokens, &["input"], "output"))
+                {
+                    events.push(ParsedEvent::Usage(usage));
+                }
+            }
+            (other, _) => tracing::debug!("OpenCode CLI event: {:?}", other),
+        }
 
-    let max_len = 50;
-    if clean.len() > max_len {
-        clean[..max_len].to_string()
-    } else if clean.is_empty() {
-        "file".to_string()
-    } else {
-        clean
+        or_ignore(events)
     }
 }
 
-/// Get MIME type from file extension
-fn get_mime_type(filename: &str) -> String {
-    let extension = std::path::Path::new(filename)
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+/// CLIs that print the answer as plain text: every line (blank ones included)
+/// is part of the answer
+struct PlainTextParser;
 
-    match extension.as_str() {
-        "png" => "image/png",
-        "jpg" | "jpeg" => "image/jpeg",
-        "gif" => "image/gif",
-        "webp" => "image/webp",
-        "bmp" => "image/bmp",
-        "svg" => "image/svg+xml",
-        "pdf" => "application/pdf",
-        "txt" => "text/plain",
-        "md" => "text/markdown",
-        "json" => "application/json",
-        "xml" => "application/xml",
-        "html" | "htm" => "text/html",
-        "css" => "text/css",
-        "js" => "application/javascript",
-        "ts" => "application/typescript",
-        "zip" => "application/zip",
-        "doc" => "application/msword",
-        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-        "xls" => "application/vnd.ms-excel",
-        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-        "ppt" => "application/vnd.ms-powerpoint",
-        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-        "csv" => "text/csv",
-        _ => "application/octet-stream",
+impl AgentStreamParser for PlainTextParser {
+    fn parse_line(&mut self, line: &str) -> Vec<ParsedEvent> {
+        vec![ParsedEvent::Line(line.to_string())]
     }
-    .to_string()
 }
 
-/// Upload an attachment to PM chat
-pub async fn upload_attachment(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-    mut multipart: Multipart,
-) -> Result<ResponseJson<ApiResponse<PmAttachment>>, ApiError> {
-    let attachments_dir = get_pm_attachments_dir();
+/// Tracks MCP tool calls during one streamed agent turn and turns them into
+/// `tool_use` / `task_created` SSE events
+#[derive(Debug, Default)]
+struct ToolCallTracker {
+    /// Tool name and requested task title, keyed by call id
+    calls: HashMap<String, (String, Option<String>)>,
+    /// Number of new tasks created via `create_task` this turn
+    tasks_created: usize,
+}
 
-    while let Some(field) = multipart.next_field().await? {
-        if field.name() == Some("file") {
-            let original_filename = field
-                .file_name()
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| "file".to_string());
-
-            let data = field.bytes().await?;
-            let file_size = data.len() as i64;
-
-            // Check file size limit (20MB)
-            const MAX_SIZE: i64 = 20 * 1024 * 1024;
-            if file_size > MAX_SIZE {
-                return Err(ApiError::BadRequest(format!(
-                    "File too large: {} bytes (max: {} bytes)",
-                    file_size, MAX_SIZE
-                )));
+impl ToolCallTracker {
+    /// Record a tool event and return the SSE event to emit, if any
+    fn handle(&mut self, event: AgentToolEvent) -> Option<AiChatStreamEvent> {
+        match event {
+            AgentToolEvent::Call { id, name, input } => {
+                // Some CLIs repeat the call on each state change; only announce it once
+                if let Some(id) = &id
+                    && self.calls.contains_key(id)
+                {
+                    return None;
+                }
+                let tool = tool_base_name(&name).to_string();
+                let title = input
+                    .as_ref()
+                    .and_then(|i| i.get("title"))
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.to_string());
+                if let Some(id) = id {
+                    self.calls.insert(id, (tool.clone(), title));
+                }
+                Some(AiChatStreamEvent {
+                    event_type: "tool_use".to_string(),
+                    content: Some(tool),
+                    error: None,
+                    task_id: None,
+                    task_title: None,
+                    tasks_created: None,
+                    stream_id: None,
+                    docs_length: None,
+                })
             }
+            AgentToolEvent::Result {
+                id,
+                name,
+                success,
+                output,
+            } => {
+                let call = id.as_ref().and_then(|id| self.calls.get(id));
+                let tool = name
+                    .as_deref()
+                    .map(tool_base_name)
+                    .or_else(|| call.map(|(tool, _)| tool.as_str()));
+                if !success {
+                    return None;
+                }
+                match tool {
+                    Some("create_task") => {}
+                    // The stream fills in the diff, it needs the docs from the database
+                    Some("update_pm_docs") => {
+                        return Some(AiChatStreamEvent {
+                            event_type: "docs_updated".to_string(),
+                            content: None,
+                            error: None,
+                            task_id: None,
+                            task_title: None,
+                            tasks_created: None,
+                            stream_id: None,
+                            docs_length: None,
+                        });
+                    }
+                    _ => return None,
+                }
 
-            // Calculate hash for deduplication
-            let hash = format!("{:x}", Sha256::digest(&data));
-
-            // Get extension and mime type
-            let extension = std::path::Path::new(&original_filename)
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("bin");
-            let mime_type = get_mime_type(&original_filename);
+                // create_task returns {"task_id": "...", "is_new": bool, "title": "...", ...};
+                // a dry run has a `preview` and no task id, as nothing was created
+                let response: serde_json::Value = serde_json::from_str(output.as_deref()?).ok()?;
+                let task_id = response.get("task_id")?.as_str()?.to_string();
+                let is_preview = response.get("preview").is_some_and(|p| !p.is_null());
+                if task_id.is_empty()
+                    || is_preview
+                    || response.get("is_new").and_then(|n| n.as_bool()) == Some(false)
+                {
+                    return None;
+                }
+                // Prefer the stored title over the one the agent asked for
+                let task_title = response
+                    .get("title")
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.to_string())
+                    .or_else(|| call.and_then(|(_, title)| title.clone()));
+
+                self.tasks_created += 1;
+                Some(AiChatStreamEvent {
+                    event_type: "task_created".to_string(),
+                    content: task_title.clone(),
+                    error: None,
+                    task_id: Some(task_id),
+                    task_title,
+                    tasks_created: None,
+                    stream_id: None,
+                    docs_length: None,
+                })
+            }
+        }
+    }
+}
 
-            // Create unique filename
-            let clean_name = sanitize_filename(&original_filename);
-            let new_filename = format!("{}_{}.{}", Uuid::new_v4(), clean_name, extension);
-            let file_path = attachments_dir.join(&new_filename);
+/// An in-flight `ai_chat` stream that can be cancelled
+struct ActiveAiChatStream {
+    project_id: Uuid,
+    agent: PmChatAgent,
+    model: String,
+    started_at: DateTime<Utc>,
+    cancel: CancellationToken,
+}
 
-            // Write file to disk
-            fs::write(&file_path, &data)?;
+/// A running `ai_chat` stream, as listed by `GET /api/admin/pm-chat/sessions`
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct AiChatSession {
+    pub stream_id: Uuid,
+    pub project_id: Uuid,
+    pub agent: PmChatAgent,
+    pub model: String,
+    pub started_at: DateTime<Utc>,
+}
 
-            // Create a placeholder conversation for direct attachments
-            // In a real implementation, you might want to link this to a specific message
-            let conversation = PmConversation::create(
-                &deployment.db().pool,
-                &CreatePmConversation {
-                    project_id: project.id,
-                    role: PmMessageRole::User,
-                    content: format!("[Attachment: {}]", original_filename),
-                    model: None,
-                },
-            )
-            .await?;
+/// In-flight `ai_chat` streams keyed by the stream id sent in their first SSE event
+static ACTIVE_AI_CHAT_STREAMS: LazyLock<std::sync::Mutex<HashMap<Uuid, ActiveAiChatStream>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
 
-            // Create attachment record
-            let attachment = PmAttachment::create(
-                &deployment.db().pool,
-                &CreatePmAttachment {
-                    conversation_id: conversation.id,
-                    project_id: project.id,
-                    file_name: original_filename,
-                    file_path: new_filename,
-                    mime_type,
-                    file_size,
-                    sha256: Some(hash),
-                },
-            )
-            .await?;
-
-            deployment
-                .track_if_analytics_allowed(
-                    "pm_attachment_uploaded",
-                    serde_json::json!({
-                        "project_id": project.id.to_string(),
-                        "attachment_id": attachment.id.to_string(),
-                        "file_size": file_size,
-                        "mime_type": &attachment.mime_type,
-                    }),
-                )
-                .await;
+/// Removes a stream from the registry when the stream finishes or is dropped
+struct ActiveAiChatStreamGuard(Uuid);
 
-            return Ok(ResponseJson(ApiResponse::success(attachment)));
+impl Drop for ActiveAiChatStreamGuard {
+    fn drop(&mut self) {
+        if let Ok(mut streams) = ACTIVE_AI_CHAT_STREAMS.lock() {
+            streams.remove(&self.0);
         }
     }
-
-    Err(ApiError::BadRequest("No file provided".to_string()))
 }
 
-/// Serve an attachment file
-pub async fn serve_attachment(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-    Path((_project_id, attachment_id)): Path<(Uuid, Uuid)>,
-) -> Result<Response, ApiError> {
-    let attachment = PmAttachment::find_by_id(&deployment.db().pool, attachment_id)
-        .await?
-        .ok_or_else(|| ApiError::BadRequest("Attachment not found".to_string()))?;
+/// Register a new cancellable stream for a project
+fn register_ai_chat_stream(
+    stream_id: Uuid,
+    project_id: Uuid,
+    agent: PmChatAgent,
+    model: &str,
+) -> (CancellationToken, ActiveAiChatStreamGuard) {
+    let cancel = CancellationToken::new();
+    if let Ok(mut streams) = ACTIVE_AI_CHAT_STREAMS.lock() {
+        streams.insert(
+            stream_id,
+            ActiveAiChatStream {
+                project_id,
+                agent,
+                model: model.to_string(),
+                started_at: Utc::now(),
+                cancel: cancel.clone(),
+            },
+        );
+    }
+    (cancel, ActiveAiChatStreamGuard(stream_id))
+}
 
-    // Verify the attachment belongs to this project
-    if attachment.project_id != project.id {
-        return Err(ApiError::BadRequest(
-            "Attachment does not belong to this project".to_string(),
-        ));
+/// Signal cancellation of a project's stream. Returns false if no such stream is running.
+fn cancel_ai_chat_stream(project_id: Uuid, stream_id: Uuid) -> bool {
+    let Ok(streams) = ACTIVE_AI_CHAT_STREAMS.lock() else {
+        return false;
+    };
+    match streams.get(&stream_id) {
+        Some(stream) if stream.project_id == project_id => {
+            stream.cancel.cancel();
+            true
+        }
+        _ => false,
     }
+}
 
-    let attachments_dir = get_pm_attachments_dir();
-    let file_path = attachments_dir.join(&attachment.file_path);
+/// Running `ai_chat` streams of all projects, oldest first
+pub(crate) fn list_ai_chat_sessions() -> Vec<AiChatSession> {
+    let Ok(streams) = ACTIVE_AI_CHAT_STREAMS.lock() else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<AiChatSession> = streams
+        .iter()
+        .map(|(stream_id, stream)| AiChatSession {
+            stream_id: *stream_id,
+            project_id: stream.project_id,
+            agent: stream.agent,
+            model: stream.model.clone(),
+            started_at: stream.started_at,
+        })
+        .collect();
+    sessions.sort_by_key(|session| session.started_at);
+    sessions
+}
 
-    let file = File::open(&file_path)
-        .await
-        .map_err(|_| ApiError::BadRequest("Attachment file not found".to_string()))?;
-    let metadata = file.metadata().await?;
+/// Signal cancellation of a stream of any project, killing its CLI. Returns
+/// false if no such stream is running.
+pub(crate) fn kill_ai_chat_session(stream_id: Uuid) -> bool {
+    let project_id = match ACTIVE_AI_CHAT_STREAMS.lock() {
+        Ok(streams) => streams.get(&stream_id).map(|stream| stream.project_id),
+        Err(_) => None,
+    };
+    project_id.is_some_and(|project_id| cancel_ai_chat_stream(project_id, stream_id))
+}
 
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+/// Running `ai_chat` streams of each project
+type ActiveAiChatCounts = Arc<std::sync::Mutex<HashMap<Uuid, usize>>>;
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, &attachment.mime_type)
-        .header(header::CONTENT_LENGTH, metadata.len())
-        .header(
-            header::CONTENT_DISPOSITION,
-            format!("inline; filename=\"{}\"", attachment.file_name),
-        )
-        .header(header::CACHE_CONTROL, "public, max-age=31536000")
-        .body(body)
-        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+/// Caps how many `ai_chat` CLI processes run at once for each project. The
+/// limit is passed on every acquire, so a config change applies to the next
+/// request without waiting for running streams.
+#[derive(Default)]
+struct AiChatLimiter {
+    active: ActiveAiChatCounts,
+}
+
+impl AiChatLimiter {
+    /// A permit for one more stream of the project, or `None` if it already
+    /// runs `max_streams`
+    fn try_acquire(&self, project_id: Uuid, max_streams: usize) -> Option<AiChatPermit> {
+        let mut active = self.active.lock().ok()?;
+        let count = active.entry(project_id).or_insert(0);
+        if *count >= max_streams.max(1) {
+            return None;
+        }
+        *count += 1;
+        Some(AiChatPermit {
+            project_id,
+            active: self.active.clone(),
+        })
+    }
+}
+
+/// A stream slot of a project, given back when dropped
+struct AiChatPermit {
+    project_id: Uuid,
+    active: ActiveAiChatCounts,
+}
+
+impl Drop for AiChatPermit {
+    fn drop(&mut self) {
+        let Ok(mut active) = self.active.lock() else {
+            return;
+        };
+        if let Some(count) = active.get_mut(&self.project_id) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&self.project_id);
+            }
+        }
+    }
+}
+
+static AI_CHAT_LIMITER: LazyLock<AiChatLimiter> = LazyLock::new(AiChatLimiter::default);
+
+/// A stream slot of the project, or `TooManyRequests` if all
+/// `settings.max_streams_per_project` are taken
+fn acquire_ai_chat_permit(
+    project_id: Uuid,
+    settings: &PmChatSettings,
+) -> Result<AiChatPermit, ApiError> {
+    let max_streams = settings.max_streams_per_project as usize;
+    AI_CHAT_LIMITER
+        .try_acquire(project_id, max_streams)
+        .ok_or_else(|| {
+            ApiError::TooManyRequests(format!(
+                "This project already has {max_streams} AI chat responses running. Wait for one to finish and try again."
+            ))
+        })
+}
+
+/// How long one PM chat CLI turn may run
+fn cli_chat_timeout(settings: &PmChatSettings) -> Duration {
+    Duration::from_secs(u64::from(settings.cli_timeout_secs.max(1)))
+}
+
+/// Hold `permit` until `events` finishes or is dropped
+fn hold_ai_chat_permit(
+    mut events: BoxStream<'static, AiChatStreamEvent>,
+    permit: AiChatPermit,
+) -> BoxStream<'static, AiChatStreamEvent> {
+    let stream = async_stream::stream! {
+        let _permit = permit;
+        while let Some(event) = events.next().await {
+            yield event;
+        }
+    };
+    stream.boxed()
+}
+
+/// Poll `events` inside `span`, so everything logged while an event is
+/// produced belongs to the stream it came from
+fn instrument_ai_chat_stream<T: Send + 'static>(
+    mut events: BoxStream<'static, T>,
+    span: tracing::Span,
+) -> BoxStream<'static, T> {
+    let stream = async_stream::stream! {
+        while let Some(event) = events.next().instrument(span.clone()).await {
+            yield event;
+        }
+    };
+    stream.boxed()
+}
+
+/// Create a streaming response using the specified CLI with MCP tools for task creation and docs management
+/// This version streams CLI output line-by-line for real-time feedback.
+/// Everything from spawning the CLI to its exit is logged in a `pm_chat_stream` span.
+#[allow(clippy::too_many_arguments)]
+async fn create_mcp_cli_stream(
+    agent: PmChatAgent,
+    model: String,
+    system_prompt: String,
+    user_content: String,
+    pool: sqlx::SqlitePool,
+    project_id: Uuid,
+    backend_url_override: Option<String>,
+    timeout: Duration,
+    permit: AiChatPermit,
+) -> Result<Sse<SseStream>, ApiError> {
+    // stream_id is recorded once the stream is registered
+    let span = tracing::info_span!(
+        "pm_chat_stream",
+        %project_id,
+        %agent,
+        %model,
+        stream_id = tracing::field::Empty,
+    );
+
+    // Get the backend URL for MCP server to connect to
+    let backend_url = mcp_backend_url(backend_url_override.as_deref()).await?;
+
+    // Prepare command based on agent and available CLI
+    let Some((cmd_path, use_npx_fallback)) = resolve_agent_command(agent).await else {
+        // CLI not available - return error stream
+        tracing::warn!(parent: &span, "PM chat CLI not found");
+        let agent_name = agent.display_name();
+        let stream = async_stream::stream! {
+            let event = AiChatStreamEvent {
+                event_type: "error".to_string(),
+                content: None,
+                error: Some(format!("{} not found. Please install it first.", agent_name)),
+                task_id: None,
+                task_title: None,
+                tasks_created: None,
+                stream_id: None,
+                docs_length: None,
+            };
+            yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+            let done = AiChatStreamEvent { event_type: "done".to_string(), content: None, error: None, task_id: None, task_title: None, tasks_created: None, stream_id: None, docs_length: None };
+            yield Ok(Event::default().data(serde_json::to_string(&done).unwrap_or_default()));
+        };
+        return Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()));
+    };
+
+    let system_prompt = if agent.supports_tools() {
+        system_prompt
+    } else {
+        format!("{}{}", system_prompt, NO_TOOLS_PROMPT_NOTE)
+    };
+
+    // Create temporary MCP config file based on agent type, one per stream so
+    // concurrent streams of a project don't share it
+    let stream_id = Uuid::new_v4();
+    let config_path = write_mcp_config_file(agent, &backend_url, &stream_id.to_string())?;
+
+    let mut command = build_agent_command(
+        agent,
+        &cmd_path,
+        use_npx_fallback,
+        &config_path,
+        &model,
+        &system_prompt,
+        &user_content,
+    );
+
+    // Spawn process
+    let mut child = match command.spawn() {
+        Ok(child) => {
+            tracing::info!(
+                parent: &span,
+                pid = child.id(),
+                cmd = ?cmd_path,
+                npx = use_npx_fallback,
+                "PM chat CLI spawned"
+            );
+            child
+        }
+        Err(e) => {
+            tracing::error!(
+                parent: &span,
+                error = %e,
+                cmd = ?cmd_path,
+                "Failed to spawn PM chat CLI"
+            );
+            // Clean up config file
+            let _ = fs::remove_file(&config_path);
+            let stream = async_stream::stream! {
+                let event = AiChatStreamEvent {
+                    event_type: "error".to_string(),
+                    content: None,
+                    error: Some(format!("Failed to spawn CLI: {}", e)),
+                    task_id: None,
+                    task_title: None,
+                    tasks_created: None,
+                    stream_id: None,
+                    docs_length: None,
+                };
+                yield Ok(Event::default().data(serde_json::to_string(&event).unwrap_or_default()));
+                let done = AiChatStreamEvent { event_type: "done".to_string(), content: None, error: None, task_id: None, task_title: None, tasks_created: None, stream_id: None, docs_length: None };
+                yield Ok(Event::default().data(serde_json::to_string(&done).unwrap_or_default()));
+            };
+            return Ok(Sse::new(stream.boxed()).keep_alive(KeepAlive::default()));
+        }
+    };
+
+    if agent.reads_prompt_from_stdin() {
+        feed_agent_stdin(
+            &mut child,
+            agent_stdin_prompt(&system_prompt, &user_content),
+        );
+    }
+
+    let events = cli_chat_events(
+        child,
+        config_path,
+        agent,
+        timeout,
+        model,
+        pool,
+        project_id,
+        stream_id,
+        span,
+    );
+    let events = hold_ai_chat_permit(events, permit);
+    let stream = spawn_buffered_ai_chat_stream(project_id, events).await;
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// How long the events of a finished `ai_chat` stream stay available for reconnects
+const AI_CHAT_STREAM_RETENTION: Duration = Duration::from_secs(60);
+
+/// The events an `ai_chat` stream has produced so far. The SSE id of an event
+/// is its 1-based position.
+#[derive(Debug, Default)]
+struct AiChatStreamBuffer {
+    events: Vec<AiChatStreamEvent>,
+    finished: bool,
+}
+
+/// Buffered `ai_chat` streams by stream id, with their project
+static AI_CHAT_STREAM_BUFFERS: LazyLock<
+    std::sync::Mutex<HashMap<Uuid, (Uuid, tokio::sync::watch::Receiver<AiChatStreamBuffer>)>>,
+> = LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Run `events` to completion in the background, independent of the client
+/// connection, and buffer them so a client that lost the connection can resume
+/// via `GET /ai-chat/{stream_id}/events`. Returns the SSE stream for the first
+/// client. The buffer is dropped [`AI_CHAT_STREAM_RETENTION`] after the stream
+/// finishes.
+async fn spawn_buffered_ai_chat_stream(
+    project_id: Uuid,
+    mut events: BoxStream<'static, AiChatStreamEvent>,
+) -> BoxStream<'static, Result<Event, std::convert::Infallible>> {
+    // The first event announces the stream id
+    let first = events.next().await;
+    let stream_id = first
+        .as_ref()
+        .and_then(|event| event.stream_id.as_deref())
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    let (sender, receiver) = tokio::sync::watch::channel(AiChatStreamBuffer {
+        events: first.into_iter().collect(),
+        finished: false,
+    });
+    if let Ok(mut buffers) = AI_CHAT_STREAM_BUFFERS.lock() {
+        buffers.insert(stream_id, (project_id, receiver.clone()));
+    }
+
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            sender.send_modify(|buffer| buffer.events.push(event));
+        }
+        sender.send_modify(|buffer| buffer.finished = true);
+
+        tokio::time::sleep(AI_CHAT_STREAM_RETENTION).await;
+        if let Ok(mut buffers) = AI_CHAT_STREAM_BUFFERS.lock() {
+            buffers.remove(&stream_id);
+        }
+    });
+
+    replay_ai_chat_stream(receiver, 0)
+}
+
+/// SSE events of a buffered stream after the event with id `last_event_id`,
+/// followed by new events as they arrive, until the stream finishes
+fn replay_ai_chat_stream(
+    mut receiver: tokio::sync::watch::Receiver<AiChatStreamBuffer>,
+    last_event_id: usize,
+) -> BoxStream<'static, Result<Event, std::convert::Infallible>> {
+    let stream = async_stream::stream! {
+        let mut sent = last_event_id;
+        loop {
+            let (new_events, finished) = {
+                let buffer = receiver.borrow_and_update();
+                let new_events = buffer.events.get(sent..).unwrap_or_default().to_vec();
+                (new_events, buffer.finished)
+            };
+            for event in new_events {
+                sent += 1;
+                yield Ok(Event::default()
+                    .id(sent.to_string())
+                    .data(serde_json::to_string(&event).unwrap_or_default()));
+            }
+            if finished || receiver.changed().await.is_err() {
+                break;
+            }
+        }
+    };
+    stream.boxed()
+}
+
+/// The buffer of a project's `ai_chat` stream, if it is still kept
+fn find_ai_chat_stream_buffer(
+    project_id: Uuid,
+    stream_id: Uuid,
+) -> Option<tokio::sync::watch::Receiver<AiChatStreamBuffer>> {
+    let buffers = AI_CHAT_STREAM_BUFFERS.lock().ok()?;
+    match buffers.get(&stream_id) {
+        Some((project, receiver)) if *project == project_id => Some(receiver.clone()),
+        _ => None,
+    }
+}
+
+/// Number of trailing stderr lines quoted in the error of a failed CLI run
+const CLI_ERROR_STDERR_LINES: usize = 3;
+
+/// Longest stderr excerpt quoted in the error of a failed CLI run, in bytes
+const CLI_ERROR_STDERR_MAX_LEN: usize = 500;
+
+/// Error shown for a CLI that exited with `status`, ending with the last lines
+/// it wrote to stderr
+fn cli_exit_error(
+    status: std::process::ExitStatus,
+    stderr_tail: &std::collections::VecDeque<String>,
+) -> String {
+    let message = format!("CLI exited with status: {}", status);
+    if stderr_tail.is_empty() {
+        return message;
+    }
+    let stderr = stderr_tail
+        .iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    // Keep the end of the output, where the cause usually is
+    let mut start = stderr.len().saturating_sub(CLI_ERROR_STDERR_MAX_LEN);
+    while !stderr.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("{}\n{}", message, &stderr[start..])
+}
+
+/// One line read from the CLI's stdout or stderr (`None` once the pipe is closed)
+enum CliOutput {
+    Stdout(Option<String>),
+    Stderr(Option<String>),
+}
+
+/// Read the next line from an optional pipe; `None` if it is closed or was never opened
+async fn next_pipe_line<R: tokio::io::AsyncBufRead + Unpin>(
+    lines: &mut Option<tokio::io::Lines<R>>,
+) -> Option<String> {
+    match lines {
+        Some(lines) => lines.next_line().await.ok().flatten(),
+        None => None,
+    }
+}
+
+/// How often a reply is written to its message row while it is streamed
+const REPLY_PERSIST_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Assistant reply saved to the conversation while it is streamed, so what the
+/// user has already seen survives a server crash
+struct StreamedReply {
+    pool: sqlx::SqlitePool,
+    project_id: Uuid,
+    model: String,
+    /// Message row of the reply, created with its first text so a stream that
+    /// fails or is cancelled before any token leaves no empty message behind
+    message_id: Option<Uuid>,
+    /// Content as last written to the row
+    persisted: String,
+    last_persisted: Instant,
+}
+
+impl StreamedReply {
+    fn new(pool: sqlx::SqlitePool, project_id: Uuid, model: String) -> Self {
+        Self {
+            pool,
+            project_id,
+            model,
+            message_id: None,
+            persisted: String::new(),
+            last_persisted: Instant::now(),
+        }
+    }
+
+    /// Whether the reply is due to be written again
+    fn is_due(&self) -> bool {
+        self.last_persisted.elapsed() >= REPLY_PERSIST_INTERVAL
+    }
+
+    /// Write `text` to the message row, appending when it only grew. The row
+    /// is created once `text` is not empty.
+    async fn persist(&mut self, text: &str) {
+        self.last_persisted = Instant::now();
+        let Some(id) = self.message_id else {
+            if !text.is_empty() {
+                self.create(text, None).await;
+            }
+            return;
+        };
+        let result = match text.strip_prefix(self.persisted.as_str()) {
+            Some("") => return,
+            Some(delta) => PmConversation::append_content(&self.pool, id, delta).await,
+            None => PmConversation::set_content(&self.pool, id, text).await,
+        };
+        match result {
+            Ok(_) => self.persisted = text.to_string(),
+            Err(e) => tracing::warn!("Failed to save the streamed PM chat reply: {}", e),
+        }
+    }
+
+    /// Create the message row of the reply with `text`
+    async fn create(&mut self, text: &str, usage: Option<TokenUsage>) {
+        let message = PmConversation::create(
+            &self.pool,
+            &CreatePmConversation {
+                project_id: self.project_id,
+                role: PmMessageRole::Assistant,
+                content: text.to_string(),
+                model: Some(self.model.clone()),
+                input_tokens: usage.map(|usage| usage.input_tokens),
+                output_tokens: usage.map(|usage| usage.output_tokens),
+            },
+        )
+        .await;
+        match message {
+            Ok(message) => {
+                self.message_id = Some(message.id);
+                self.persisted = text.to_string();
+            }
+            Err(e) => tracing::warn!("Failed to save the PM chat reply: {}", e),
+        }
+    }
+
+    /// Save the complete reply and its token usage. An empty reply is not kept.
+    async fn finish(mut self, text: String, usage: Option<TokenUsage>) {
+        let Some(id) = self.message_id else {
+            if !text.is_empty() {
+                self.create(&text, usage).await;
+            }
+            return;
+        };
+
+        if text.is_empty() {
+            let _ = PmConversation::delete(&self.pool, id).await;
+            return;
+        }
+        self.persist(&text).await;
+        let input_tokens = usage.map(|usage| usage.input_tokens);
+        let output_tokens = usage.map(|usage| usage.output_tokens);
+        let _ = PmConversation::set_usage(&self.pool, id, input_tokens, output_tokens).await;
+    }
+}
+
+/// Turn a spawned agent CLI's output into PM chat stream events.
+/// `agent` decides how the output is parsed and joined into the response.
+/// The CLI is killed if it hasn't exited within `timeout`.
+/// The assistant response is saved to the conversation as it streams and
+/// completed once the CLI exits; the temp MCP config file is then removed.
+/// The stream is polled inside `span`, which gets the stream id recorded.
+#[allow(clippy::too_many_arguments)]
+fn cli_chat_events(
+    mut child: tokio::process::Child,
+    config_path: PathBuf,
+    agent: PmChatAgent,
+    timeout: Duration,
+    model: String,
+    pool: sqlx::SqlitePool,
+    project_id: Uuid,
+    stream_id: Uuid,
+    span: tracing::Span,
+) -> BoxStream<'static, AiChatStreamEvent> {
+    // Take ownership of stdout and stderr
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let mut parser = agent.stream_parser();
+
+    // Shared state for collecting full response
+    let full_response = Arc::new(Mutex::new(ResponseAccumulator::new(agent)));
+    let full_response_clone = full_response.clone();
+
+    // Register the stream so it can be cancelled via POST /ai-chat/cancel
+    let (cancel_token, stream_guard) =
+        register_ai_chat_stream(stream_id, project_id, agent, &model);
+    span.record("stream_id", tracing::field::display(stream_id));
+
+    // Create the streaming response
+    let stream = async_stream::stream! {
+        // Keep the stream registered until it finishes or the client goes away
+        let _stream_guard = stream_guard;
+        let started = Instant::now();
+        let mut first_token_logged = false;
+
+        // Send initial "thinking" indicator
+        let thinking_event = AiChatStreamEvent {
+            event_type: "thinking".to_string(),
+            content: Some("AI is processing...".to_string()),
+            error: None,
+            task_id: None,
+            task_title: None,
+            tasks_created: None,
+            stream_id: Some(stream_id.to_string()),
+            docs_length: None,
+        };
+        yield thinking_event;
+
+        let mut reply = StreamedReply::new(pool.clone(), project_id, model);
+        let mut tool_tracker = ToolCallTracker::default();
+        let mut usage: Option<TokenUsage> = None;
+
+        // Docs as last seen, to show what each update_pm_docs call changed
+        let mut docs_before = Project::find_by_id(&pool, project_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|project| project.pm_docs);
+
+        let mut stdout_lines = stdout.map(|stdout| BufReader::new(stdout).lines());
+        let mut stderr_lines = stderr.map(|stderr| BufReader::new(stderr).lines());
+        let mut stdout_open = stdout_lines.is_some();
+        let mut stderr_open = stderr_lines.is_some();
+        // Stderr is passed on as "debug" events; its last lines explain a failed exit
+        let mut stderr_tail = std::collections::VecDeque::new();
+
+        // Covers reading the output and waiting for the CLI to exit
+        let mut deadline = Box::pin(tokio::time::sleep(timeout));
+        let mut timed_out = false;
+
+        // Read stdout and stderr together so a chatty stderr can't fill its pipe
+        // and block the CLI (see the AgentStreamParser impls for the per-CLI stdout formats)
+        while stdout_open || stderr_open {
+            let output = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = &mut deadline => {
+                    timed_out = true;
+                    break;
+                }
+                line = next_pipe_line(&mut stdout_lines), if stdout_open => CliOutput::Stdout(line),
+                line = next_pipe_line(&mut stderr_lines), if stderr_open => CliOutput::Stderr(line),
+            };
+
+            let line = match output {
+                CliOutput::Stdout(Some(line)) => line,
+                CliOutput::Stdout(None) => {
+                    stdout_open = false;
+                    continue;
+                }
+                CliOutput::Stderr(Some(line)) => {
+                    if !line.trim().is_empty() {
+                        tracing::debug!(%line, "PM chat CLI stderr");
+                        if stderr_tail.len() == CLI_ERROR_STDERR_LINES {
+                            stderr_tail.pop_front();
+                        }
+                        stderr_tail.push_back(line.clone());
+                        let event = AiChatStreamEvent {
+                            event_type: "debug".to_string(),
+                            content: Some(line),
+                            error: None,
+                            task_id: None,
+                            task_title: None,
+                            tasks_created: None,
+                            stream_id: None,
+                            docs_length: None,
+                        };
+                        yield event;
+                    }
+                    continue;
+                }
+                CliOutput::Stderr(None) => {
+                    stderr_open = false;
+                    continue;
+                }
+            };
+
+            for parsed in parser.parse_line(&line) {
+                // Append to full response, keeping the text to send
+                let content = match parsed {
+                    ParsedEvent::Content(text) => full_response_clone.lock().await.push(&text),
+                    ParsedEvent::Result(text) => {
+                        full_response_clone.lock().await.push_result(&text)
+                    }
+                    ParsedEvent::Line(line) => {
+                        full_response_clone.lock().await.push_line(&line);
+                        Some(format!("{}\n", line))
+                    }
+                    ParsedEvent::Usage(turn_usage) => {
+                        usage.get_or_insert_default().accumulate(turn_usage);
+                        None
+                    }
+                    // Surface MCP tool calls and newly created tasks
+                    ParsedEvent::ToolUse(tool_event) => {
+                        if let Some(mut event) = tool_tracker.handle(tool_event) {
+                            tracing::info!(
+                                event_type = %event.event_type,
+                                task_id = event.task_id.as_deref(),
+                                "PM chat tool use"
+                            );
+                            if event.event_type == "docs_updated" {
+                                let docs_after = Project::find_by_id(&pool, project_id)
+                                    .await
+                                    .ok()
+                                    .flatten()
+                                    .and_then(|project| project.pm_docs);
+                                event.content =
+                                    pm_docs_diff(docs_before.as_deref(), docs_after.as_deref());
+                                event.docs_length = Some(
+                                    docs_after.as_deref().map_or(0, |docs| docs.chars().count()),
+                                );
+                                docs_before = docs_after;
+                            }
+                            yield event;
+                        }
+                        None
+                    }
+                    ParsedEvent::Ignore => None,
+                };
+
+                // If there is new text, send it as SSE event
+                if let Some(content) = content {
+                    if !first_token_logged {
+                        first_token_logged = true;
+                        tracing::info!(
+                            elapsed_ms = started.elapsed().as_millis() as u64,
+                            "PM chat first token"
+                        );
+                    }
+                    let event = AiChatStreamEvent {
+                        event_type: "content".to_string(),
+                        content: Some(content),
+                        error: None,
+                        task_id: None,
+                        task_title: None,
+                        tasks_created: None,
+                        stream_id: None,
+                        docs_length: None,
+                    };
+                    yield event;
+                }
+            }
+
+            if reply.is_due() {
+                let text = full_response_clone.lock().await.text.clone();
+                reply.persist(&text).await;
+            }
+        }
+
+        let cancelled = cancel_token.is_cancelled();
+
+        // Let the CLI exit on its own, but not past the deadline
+        let mut exit_status = None;
+        if !cancelled && !timed_out {
+            match tokio::time::timeout_at(deadline.deadline(), child.wait()).await {
+                Ok(status) => exit_status = Some(status),
+                Err(_) => timed_out = true,
+            }
+        }
+
+        if cancelled || timed_out {
+            if cancelled {
+                tracing::info!("PM chat stream cancelled, killing the CLI");
+            } else {
+                tracing::warn!(
+                    timeout_secs = timeout.as_secs(),
+                    "PM chat CLI timed out, killing it"
+                );
+            }
+            // Kills and reaps the process
+            if let Err(e) = child.kill().await {
+                tracing::warn!(error = %e, "Failed to kill PM chat CLI");
+            }
+        }
+
+        let exit_status = match exit_status {
+            Some(status) => status,
+            None => child.wait().await,
+        };
+        match &exit_status {
+            Ok(status) => tracing::info!(
+                exit_code = status.code(),
+                success = status.success(),
+                cancelled,
+                timed_out,
+                "PM chat CLI exited"
+            ),
+            Err(e) => tracing::warn!(error = %e, "Failed to wait for PM chat CLI"),
+        }
+
+        // Clean up temp config file
+        if let Err(e) = fs::remove_file(&config_path) {
+            tracing::warn!(error = %e, "Failed to remove temp MCP config");
+        }
+
+        // Save the full response to conversation history
+        // (a partial response is kept when the stream was cancelled)
+        let final_response = full_response_clone.lock().await.text.clone();
+        reply.finish(final_response, usage).await;
+
+        if cancelled {
+            let event = AiChatStreamEvent {
+                event_type: "cancelled".to_string(),
+                content: None,
+                error: None,
+                task_id: None,
+                task_title: None,
+                tasks_created: Some(tool_tracker.tasks_created),
+                stream_id: Some(stream_id.to_string()),
+                docs_length: None,
+            };
+            yield event;
+            return;
+        }
+
+        // Check exit status for errors
+        match exit_status {
+            _ if timed_out => {
+                let event = AiChatStreamEvent {
+                    event_type: "error".to_string(),
+                    content: None,
+                    error: Some(format!("CLI timed out after {}s", timeout.as_secs())),
+                    task_id: None,
+                    task_title: None,
+                    tasks_created: None,
+                    stream_id: None,
+                    docs_length: None,
+                };
+                yield event;
+            }
+            Ok(status) if !status.success() => {
+                let event = AiChatStreamEvent {
+                    event_type: "error".to_string(),
+                    content: None,
+                    error: Some(cli_exit_error(status, &stderr_tail)),
+                    task_id: None,
+                    task_title: None,
+                    tasks_created: None,
+                    stream_id: None,
+                    docs_length: None,
+                };
+                yield event;
+            }
+            Err(e) => {
+                let event = AiChatStreamEvent {
+                    event_type: "error".to_string(),
+                    content: None,
+                    error: Some(format!("CLI error: {}", e)),
+                    task_id: None,
+                    task_title: None,
+                    tasks_created: None,
+                    stream_id: None,
+                    docs_length: None,
+                };
+                yield event;
+            }
+            _ => {}
+        }
+
+        tracing::info!(
+            duration_ms = started.elapsed().as_millis() as u64,
+            tasks_created = tool_tracker.tasks_created,
+            "PM chat stream completed"
+        );
+
+        // Send done event
+        let done = AiChatStreamEvent {
+            event_type: "done".to_string(),
+            content: None,
+            error: None,
+            task_id: None,
+            task_title: None,
+            tasks_created: Some(tool_tracker.tasks_created),
+            stream_id: None,
+            docs_length: None,
+        };
+        yield done;
+    };
+
+    instrument_ai_chat_stream(stream.boxed(), span)
+}
+
+/// MCP tool the agent self-test asks the agent to call
+const SELFTEST_TOOL: &str = "list_projects";
+
+/// System prompt for the agent self-test
+const SELFTEST_SYSTEM_PROMPT: &str = "You are running a connectivity self-test of the vibe_kanban MCP integration. Do not create, update or delete anything.";
+
+/// User prompt for the agent self-test
+const SELFTEST_PROMPT: &str = "Call the vibe_kanban MCP tool `list_projects` exactly once, then reply with the single word OK.";
+
+/// Maximum time the agent self-test may run before the CLI is killed
+const SELFTEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Number of trailing stderr lines kept in the self-test report
+const SELFTEST_STDERR_TAIL_LINES: usize = 10;
+
+/// Query parameters for the agent self-test
+#[derive(Debug, Deserialize)]
+pub struct AgentSelftestQuery {
+    pub model: Option<String>,
+    pub backend_url: Option<String>,
+}
+
+/// Diagnostic report of an agent self-test run
+#[derive(Debug, Clone, Default, Serialize, TS)]
+pub struct AgentSelftestReport {
+    pub agent: PmChatAgent,
+    /// Whether the CLI process was started
+    pub spawned: bool,
+    /// Whether the CLI produced any assistant output
+    pub streamed_output: bool,
+    /// Whether the agent invoked the `list_projects` MCP tool
+    pub tool_call_observed: bool,
+    /// Whether the `list_projects` tool call returned successfully
+    pub tool_call_succeeded: bool,
+    pub exit_code: Option<i32>,
+    pub exit_success: bool,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// Overall result: spawned, exited cleanly and the tool call succeeded
+    pub passed: bool,
+    pub error: Option<String>,
+    /// Last lines written to stderr, useful when the CLI fails to start MCP
+    pub stderr_tail: Vec<String>,
+}
+
+/// Run an already-built agent command and collect a self-test report
+/// The process is killed if it does not finish within `timeout`.
+async fn run_agent_selftest(
+    mut command: Command,
+    agent: PmChatAgent,
+    timeout: Duration,
+) -> AgentSelftestReport {
+    let started = std::time::Instant::now();
+    let mut report = AgentSelftestReport {
+        agent,
+        ..Default::default()
+    };
+
+    command.kill_on_drop(true);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            report.error = Some(format!("Failed to spawn CLI: {}", e));
+            report.duration_ms = started.elapsed().as_millis() as u64;
+            return report;
+        }
+    };
+    report.spawned = true;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // Drain stderr concurrently so a chatty CLI can't block on a full pipe
+    let stderr_tail = Arc::new(Mutex::new(std::collections::VecDeque::new()));
+    let stderr_tail_clone = stderr_tail.clone();
+    let stderr_task = tokio::spawn(async move {
+        if let Some(stderr) = stderr {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let mut tail = stderr_tail_clone.lock().await;
+                if tail.len() == SELFTEST_STDERR_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        }
+    });
+
+    let run = async {
+        let mut parser = agent.stream_parser();
+        let mut selftest_call_ids: Vec<Option<String>> = Vec::new();
+
+        if let Some(stdout) = stdout {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                for parsed in parser.parse_line(&line) {
+                    match parsed {
+                        ParsedEvent::Content(_) | ParsedEvent::Result(_) => {
+                            report.streamed_output = true;
+                        }
+                        ParsedEvent::ToolUse(AgentToolEvent::Call { id, name, .. })
+                            if tool_base_name(&name) == SELFTEST_TOOL =>
+                        {
+                            report.tool_call_observed = true;
+                            selftest_call_ids.push(id);
+                        }
+                        ParsedEvent::ToolUse(AgentToolEvent::Result {
+                            id, name, success, ..
+                        }) => {
+                            let is_selftest_tool = match name {
+                                Some(name) => tool_base_name(&name) == SELFTEST_TOOL,
+                                None => id.is_some() && selftest_call_ids.contains(&id),
+                            };
+                            if is_selftest_tool {
+                                report.tool_call_observed = true;
+                                report.tool_call_succeeded |= success;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        child.wait().await
+    };
+
+    let outcome = tokio::time::timeout(timeout, run).await;
+    match outcome {
+        Ok(Ok(status)) => {
+            report.exit_code = status.code();
+            report.exit_success = status.success();
+            if !status.success() {
+                report.error = Some(format!("CLI exited with status: {}", status));
+            }
+        }
+        Ok(Err(e)) => {
+            report.error = Some(format!("CLI error: {}", e));
+        }
+        Err(_) => {
+            report.timed_out = true;
+            report.error = Some(format!("Self-test timed out after {}s", timeout.as_secs()));
+            let _ = child.kill().await;
+        }
+    }
+
+    // Subprocesses of the CLI may keep stderr open after it exits; don't wait on them
+    if tokio::time::timeout(Duration::from_secs(1), stderr_task)
+        .await
+        .is_err()
+    {
+        tracing::debug!("stderr still open after agent self-test finished");
+    }
+    report.stderr_tail = stderr_tail.lock().await.iter().cloned().collect();
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report.passed = report.spawned && report.exit_success && report.tool_call_succeeded;
+    report
+}
+
+/// Smoke test an agent end-to-end: spawn the CLI with the vibe_kanban MCP
+/// config, ask it to call `list_projects`, and report what happened.
+/// Nothing is persisted.
+pub async fn selftest_agent(
+    Path(agent): Path<String>,
+    Query(query): Query<AgentSelftestQuery>,
+) -> Result<ResponseJson<ApiResponse<AgentSelftestReport>>, ApiError> {
+    let agent = parse_agent_path(&agent)?;
+
+    // The self-test checks for an MCP tool call, which tool-less agents never make
+    if !agent.supports_tools() {
+        return Ok(ResponseJson(ApiResponse::success(AgentSelftestReport {
+            agent,
+            error: Some(format!(
+                "{} does not support MCP tools",
+                agent.display_name()
+            )),
+            ..Default::default()
+        })));
+    }
+
+    let backend_url = mcp_backend_url(query.backend_url.as_deref()).await?;
+
+    let Some((cmd_path, use_npx_fallback)) = resolve_agent_command(agent).await else {
+        return Ok(ResponseJson(ApiResponse::success(AgentSelftestReport {
+            agent,
+            error: Some(format!(
+                "{} not found. Please install it first.",
+                agent.display_name()
+            )),
+            ..Default::default()
+        })));
+    };
+
+    let config_path =
+        write_mcp_config_file(agent, &backend_url, &format!("selftest-{}", Uuid::new_v4()))?;
+    let model = query
+        .model
+        .unwrap_or_else(|| agent.default_model().to_string());
+    let command = build_agent_command(
+        agent,
+        &cmd_path,
+        use_npx_fallback,
+        &config_path,
+        &model,
+        SELFTEST_SYSTEM_PROMPT,
+        SELFTEST_PROMPT,
+    );
+
+    let report = run_agent_selftest(command, agent, SELFTEST_TIMEOUT).await;
+
+    if let Err(e) = fs::remove_file(&config_path) {
+        tracing::warn!("Failed to remove temp MCP config: {}", e);
+    }
+
+    tracing::info!(
+        "Agent self-test for {:?}: passed={}, tool_call_observed={}",
+        agent,
+        report.passed,
+        report.tool_call_observed
+    );
+
+    Ok(ResponseJson(ApiResponse::success(report)))
+}
+
+/// Clear the PM chat of a project. Messages are archived, not deleted, so the
+/// chat can be restored; `DELETE /archived` removes them for good.
+pub async fn clear_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    let rows_affected =
+        PmConversation::archive_by_project_id(&deployment.db().pool, project.id).await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_chat_cleared",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "messages_archived": rows_affected,
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+/// Archived PM chat messages of a project, oldest first
+pub async fn get_archived_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<PmChatMessage>>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let messages = PmConversation::find_archived_by_project_id(pool, project.id).await?;
+
+    let message_ids: HashSet<Uuid> = messages.iter().map(|m| m.id).collect();
+    let attachments: Vec<PmAttachment> = PmAttachment::find_by_project_id(pool, project.id)
+        .await?
+        .into_iter()
+        .filter(|a| message_ids.contains(&a.conversation_id))
+        .collect();
+
+    Ok(ResponseJson(ApiResponse::success(nest_attachments(
+        messages,
+        &attachments,
+    ))))
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct RestoreChatResponse {
+    pub restored: u64,
+}
+
+/// Move all archived messages of a project back into the conversation
+pub async fn restore_archived_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<RestoreChatResponse>>, ApiError> {
+    let restored = PmConversation::restore_by_project_id(&deployment.db().pool, project.id).await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_chat_restored",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "messages_restored": restored,
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(RestoreChatResponse {
+        restored,
+    })))
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct PurgeChatResponse {
+    pub removed: u64,
+}
+
+/// Permanently delete the archived messages of a project and their attachments
+pub async fn purge_archived_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<PurgeChatResponse>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let message_ids: Vec<Uuid> = PmConversation::find_archived_by_project_id(pool, project.id)
+        .await?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    let removed = pm_chat_retention::delete_messages(pool, &message_ids).await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_chat_purged",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "messages_deleted": removed,
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(PurgeChatResponse {
+        removed,
+    })))
+}
+
+/// Archive a specific message; it is restored with the rest of the archive
+/// Uses tuple to extract both project_id (from parent route) and message_id
+pub async fn delete_message(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    // First verify the message belongs to this project
+    let message = PmConversation::find_by_id(&deployment.db().pool, message_id).await?;
+
+    match message {
+        Some(msg) if msg.project_id == project.id => {
+            PmConversation::archive(&deployment.db().pool, message_id).await?;
+            Ok(ResponseJson(ApiResponse::success(())))
+        }
+        Some(_) => Err(ApiError::Forbidden(
+            "Message does not belong to this project".to_string(),
+        )),
+        None => Err(ApiError::Database(sqlx::Error::RowNotFound)),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct UpdateMessageRequest {
+    pub content: String,
+}
+
+/// Edit the content of a message
+pub async fn update_message(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMessageRequest>,
+) -> Result<ResponseJson<ApiResponse<PmConversation>>, ApiError> {
+    let updated = edit_user_message(
+        &deployment.db().pool,
+        project.id,
+        message_id,
+        &payload.content,
+    )
+    .await?;
+
+    Ok(ResponseJson(ApiResponse::success(updated)))
+}
+
+/// Replace the content of one of a project's user messages. Assistant and
+/// system messages are kept as they were written.
+async fn edit_user_message(
+    pool: &sqlx::SqlitePool,
+    project_id: Uuid,
+    message_id: Uuid,
+    content: &str,
+) -> Result<PmConversation, ApiError> {
+    if content.trim().is_empty() {
+        return Err(ApiError::BadRequest(
+            "Message content cannot be empty".to_string(),
+        ));
+    }
+
+    let message = PmConversation::find_by_id(pool, message_id)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+
+    if message.project_id != project_id {
+        return Err(ApiError::Forbidden(
+            "Message does not belong to this project".to_string(),
+        ));
+    }
+    if message.role != PmMessageRole::User.to_string() {
+        return Err(ApiError::BadRequest(
+            "Only user messages can be edited".to_string(),
+        ));
+    }
+
+    PmConversation::update_content(pool, message_id, content)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct SetMessagePinnedRequest {
+    pub pinned: bool,
+}
+
+/// Pin or unpin a message so it survives retention pruning
+pub async fn set_message_pinned(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<SetMessagePinnedRequest>,
+) -> Result<ResponseJson<ApiResponse<PmConversation>>, ApiError> {
+    let message = PmConversation::find_by_id(&deployment.db().pool, message_id)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+
+    if message.project_id != project.id {
+        return Err(ApiError::Forbidden(
+            "Message does not belong to this project".to_string(),
+        ));
+    }
+
+    let updated = PmConversation::set_pinned(&deployment.db().pool, message_id, payload.pinned)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+
+    Ok(ResponseJson(ApiResponse::success(updated)))
+}
+
+/// Longest title derived from a message, in characters
+const MESSAGE_TASK_TITLE_MAX_CHARS: usize = 80;
+
+#[derive(Debug, Deserialize, TS)]
+pub struct MessageToTaskRequest {
+    /// Title of the task; derived from the first line of the message if omitted
+    pub title: Option<String>,
+    /// Priority of the task; inferred from the title if omitted
+    pub priority: Option<TaskPriority>,
+}
+
+/// Task title from the first non-empty line of a message, without markdown
+/// heading, list or quote markers, shortened to [`MESSAGE_TASK_TITLE_MAX_CHARS`]
+fn task_title_from_message(content: &str) -> Option<String> {
+    let line = content
+        .lines()
+        .map(|line| {
+            let line = line.trim().trim_start_matches(['#', '>', '-', '*', '+']);
+            let line = line.trim_start();
+            // Numbered list items
+            match line.split_once(". ") {
+                Some((number, rest)) if number.chars().all(|c| c.is_ascii_digit()) => rest,
+                _ => line,
+            }
+            .trim()
+        })
+        .find(|line| !line.is_empty())?;
+
+    if line.chars().count() <= MESSAGE_TASK_TITLE_MAX_CHARS {
+        return Some(line.to_string());
+    }
+    let truncated: String = line
+        .chars()
+        .take(MESSAGE_TASK_TITLE_MAX_CHARS - 1)
+        .collect();
+    Some(format!("{}…", truncated.trim_end()))
+}
+
+/// Create a task from a chat message: the message becomes the description and
+/// the task is linked back to the message
+pub async fn create_task_from_message(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<MessageToTaskRequest>,
+) -> Result<ResponseJson<ApiResponse<Task>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let message = PmConversation::find_by_id(pool, message_id)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+
+    if message.project_id != project.id {
+        return Err(ApiError::Forbidden(
+            "Message does not belong to this project".to_string(),
+        ));
+    }
+
+    let title = match payload.title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => task_title_from_message(&message.content).ok_or_else(|| {
+            ApiError::BadRequest("Message is empty; pass a title for the task".to_string())
+        })?,
+    };
+    let priority = payload
+        .priority
+        .or_else(|| task_inference::infer_priority(&title));
+
+    let mut create =
+        CreateTask::from_title_description(project.id, title, Some(message.content.clone()));
+    create.priority = priority;
+
+    let task = Task::create(pool, &create, Uuid::new_v4()).await?;
+    PmMessageTask::create(pool, task.id, message.id).await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "task_created",
+            serde_json::json!({
+                "task_id": task.id.to_string(),
+                "project_id": project.id,
+                "has_description": true,
+                "has_images": false,
+                "from_pm_message": true,
+            }),
+        )
+        .await;
+    publish_task_created(&deployment, &task);
+    webhooks::dispatch(pool.clone(), WebhookEvent::task_created(&task));
+
+    Ok(ResponseJson(ApiResponse::success(task)))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct UpdateChatRetentionRequest {
+    /// Number of days to keep messages; `null` keeps them forever
+    pub chat_retention_days: Option<i32>,
+}
+
+/// Configure how long PM chat messages are kept for a project
+pub async fn update_chat_retention(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<UpdateChatRetentionRequest>,
+) -> Result<ResponseJson<ApiResponse<Project>>, ApiError> {
+    if let Some(days) = payload.chat_retention_days
+        && days < 1
+    {
+        return Err(ApiError::BadRequest(
+            "chat_retention_days must be at least 1".to_string(),
+        ));
+    }
+
+    let pool = &deployment.db().pool;
+    Project::set_chat_retention_days(pool, project.id, payload.chat_retention_days).await?;
+    let updated = Project::find_by_id(pool, project.id)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+
+    Ok(ResponseJson(ApiResponse::success(updated)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PruneChatQuery {
+    /// Override the project's retention window for this run
+    pub retention_days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct PruneChatResponse {
+    pub removed: u64,
+    pub retention_days: i32,
+}
+
+/// Manually prune messages older than the retention window (pinned messages are kept)
+pub async fn prune_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<PruneChatQuery>,
+) -> Result<ResponseJson<ApiResponse<PruneChatResponse>>, ApiError> {
+    let retention_days = query
+        .retention_days
+        .or(project.chat_retention_days)
+        .ok_or_else(|| {
+            ApiError::BadRequest("No chat retention window configured for this project".to_string())
+        })?;
+
+    if retention_days < 1 {
+        return Err(ApiError::BadRequest(
+            "retention_days must be at least 1".to_string(),
+        ));
+    }
+
+    let removed = pm_chat_retention::prune_project_messages(
+        &deployment.db().pool,
+        project.id,
+        retention_days,
+    )
+    .await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_chat_pruned",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "messages_deleted": removed,
+                "retention_days": retention_days,
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(PruneChatResponse {
+        removed,
+        retention_days,
+    })))
+}
+
+/// Characters of context kept on each side of the first match in a search snippet
+const PM_CHAT_SEARCH_CONTEXT_CHARS: usize = 80;
+const DEFAULT_PM_CHAT_SEARCH_LIMIT: i64 = 20;
+const MAX_PM_CHAT_SEARCH_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct PmChatSearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+/// Half-open `[start, end)` range in UTF-16 code units (as used by JS string indexing)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A PM chat message matching a search query
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct PmChatSearchResult {
+    pub message_id: Uuid,
+    pub role: String,
+    #[ts(type = "Date")]
+    pub created_at: chrono::DateTime<Utc>,
+    /// Excerpt of the message around the first match
+    pub snippet: String,
+    /// Matches within `snippet`
+    pub highlights: Vec<TextRange>,
+    /// Number of matches in the whole message
+    pub match_count: usize,
+}
+
+/// Build an excerpt of `content` around the first case-insensitive (ASCII) match
+/// of `query`, with the ranges of all matches inside the excerpt.
+/// Returns `None` if `query` does not occur in `content`.
+fn search_snippet(
+    content: &str,
+    query: &str,
+    context_chars: usize,
+) -> Option<(String, Vec<TextRange>, usize)> {
+    // ASCII lowercasing keeps byte offsets identical to `content`
+    let haystack = content.to_ascii_lowercase();
+    let needle = query.to_ascii_lowercase();
+    if needle.is_empty() {
+        return None;
+    }
+    let matches: Vec<usize> = haystack.match_indices(&needle).map(|(i, _)| i).collect();
+    let first = *matches.first()?;
+
+    let start = match context_chars {
+        0 => first,
+        n => content[..first]
+            .char_indices()
+            .rev()
+            .nth(n - 1)
+            .map_or(0, |(i, _)| i),
+    };
+    let match_end = first + needle.len();
+    let end = content[match_end..]
+        .char_indices()
+        .nth(context_chars)
+        .map_or(content.len(), |(i, _)| match_end + i);
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < content.len() { "…" } else { "" };
+    let snippet = format!("{}{}{}", prefix, &content[start..end], suffix);
+
+    let utf16_len = |text: &str| text.encode_utf16().count();
+    let highlights = matches
+        .iter()
+        .filter(|&&m| m >= start && m + needle.len() <= end)
+        .map(|&m| {
+            let offset = utf16_len(prefix) + utf16_len(&content[start..m]);
+            TextRange {
+                start: offset,
+                end: offset + utf16_len(&content[m..m + needle.len()]),
+            }
+        })
+        .collect();
+
+    Some((snippet, highlights, matches.len()))
+}
+
+/// Token usage of a project's PM chat
+#[derive(Debug, Serialize, TS)]
+pub struct PmChatUsageResponse {
+    /// Usage per model, counting assistant messages that reported it
+    pub models: Vec<PmModelUsage>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+impl PmChatUsageResponse {
+    async fn load(pool: &sqlx::SqlitePool, project_id: Uuid) -> Result<Self, sqlx::Error> {
+        let models = PmConversation::usage_by_model(pool, project_id).await?;
+        Ok(Self {
+            input_tokens: models.iter().map(|m| m.input_tokens).sum(),
+            output_tokens: models.iter().map(|m| m.output_tokens).sum(),
+            models,
+        })
+    }
+}
+
+/// Total tokens used by the PM chat of a project, by model
+pub async fn get_chat_usage(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<PmChatUsageResponse>>, ApiError> {
+    let response = PmChatUsageResponse::load(&deployment.db().pool, project.id).await?;
+
+    Ok(ResponseJson(ApiResponse::success(response)))
+}
+
+/// Activity summary of a project's PM chat
+#[derive(Debug, Serialize, TS)]
+pub struct PmChatStats {
+    pub message_count: i64,
+    pub messages_by_role: Vec<PmRoleCount>,
+    pub attachment_count: i64,
+    /// Total size of all attachments
+    pub attachment_bytes: i64,
+    /// `None` while the chat has no messages
+    #[ts(type = "Date | null")]
+    pub first_message_at: Option<DateTime<Utc>>,
+    #[ts(type = "Date | null")]
+    pub last_message_at: Option<DateTime<Utc>>,
+    /// Models that replied in the chat, sorted by name
+    pub models: Vec<String>,
+}
+
+impl PmChatStats {
+    async fn load(pool: &sqlx::SqlitePool, project_id: Uuid) -> Result<Self, sqlx::Error> {
+        let messages_by_role = PmConversation::count_by_role(pool, project_id).await?;
+        let (attachment_count, attachment_bytes) =
+            PmAttachment::totals_by_project_id(pool, project_id).await?;
+        let time_range = PmConversation::time_range(pool, project_id).await?;
+        let models = PmConversation::models_used(pool, project_id).await?;
+
+        Ok(Self {
+            message_count: messages_by_role.iter().map(|r| r.count).sum(),
+            messages_by_role,
+            attachment_count,
+            attachment_bytes,
+            first_message_at: time_range.map(|(first, _)| first),
+            last_message_at: time_range.map(|(_, last)| last),
+            models,
+        })
+    }
+}
+
+/// Message, attachment and model summary of a project's PM chat
+pub async fn get_chat_stats(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<PmChatStats>>, ApiError> {
+    let stats = PmChatStats::load(&deployment.db().pool, project.id).await?;
+
+    Ok(ResponseJson(ApiResponse::success(stats)))
+}
+
+/// Search PM chat messages of a project, newest first
+pub async fn search_pm_chat(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<PmChatSearchQuery>,
+) -> Result<ResponseJson<ApiResponse<Vec<PmChatSearchResult>>>, ApiError> {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(ApiError::BadRequest("Search query is required".to_string()));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PM_CHAT_SEARCH_LIMIT)
+        .clamp(1, MAX_PM_CHAT_SEARCH_LIMIT);
+
+    let messages = PmConversation::search(&deployment.db().pool, project.id, q, limit).await?;
+
+    let results = messages
+        .into_iter()
+        .filter_map(|message| {
+            let (snippet, highlights, match_count) =
+                search_snippet(&message.content, q, PM_CHAT_SEARCH_CONTEXT_CHARS)?;
+            Some(PmChatSearchResult {
+                message_id: message.id,
+                role: message.role,
+                created_at: message.created_at,
+                snippet,
+                highlights,
+                match_count,
+            })
+        })
+        .collect();
+
+    Ok(ResponseJson(ApiResponse::success(results)))
+}
+
+/// Get all attachments for a project
+pub async fn get_attachments(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<PmAttachment>>>, ApiError> {
+    let attachments = PmAttachment::find_by_project_id(&deployment.db().pool, project.id).await?;
+    Ok(ResponseJson(ApiResponse::success(attachments)))
+}
+
+/// Get the PM attachments directory
+fn get_pm_attachments_dir() -> PathBuf {
+    pm_chat_retention::pm_attachments_dir()
+}
+
+/// Detect the MIME type of common binary formats from their leading magic bytes
+fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+    ];
+
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if is_bmp(data) {
+        return Some("image/bmp");
+    }
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+        .map(|(_, mime)| *mime)
+}
+
+/// Whether `data` starts with a BMP file header. "BM" alone is too common a
+/// start of text, so the reserved bytes must be zero and the DIB header that
+/// follows must have one of the known sizes.
+fn is_bmp(data: &[u8]) -> bool {
+    const DIB_HEADER_SIZES: &[u32] = &[12, 40, 52, 56, 64, 108, 124];
+
+    if data.len() < 18 || !data.starts_with(b"BM") || data[6..10] != [0; 4] {
+        return false;
+    }
+    let dib_header_size = u32::from_le_bytes([data[14], data[15], data[16], data[17]]);
+    DIB_HEADER_SIZES.contains(&dib_header_size)
+}
+
+/// Get MIME type from file extension
+fn get_mime_type(filename: &str) -> String {
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "ts" => "application/typescript",
+        "zip" => "application/zip",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "csv" => "text/csv",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// Whether uploads without a `conversation_id` get a placeholder
+/// `[Attachment: name]` message. When disabled, `conversation_id` is required.
+const CREATE_ATTACHMENT_PLACEHOLDER_MESSAGES: bool = true;
+
+/// Longest accepted `conversation_id` field of an upload; a UUID fits easily
+const MAX_CONVERSATION_ID_FIELD_BYTES: usize = 64;
+
+/// A file of an attachment upload, streamed to a temporary file and checked
+/// against the attachment policy
+#[derive(Debug)]
+struct UploadedFile {
+    original_filename: String,
+    mime_type: String,
+    upload: StreamedUpload,
+}
+
+/// Files and target message of an attachment upload
+#[derive(Debug, Default)]
+struct AttachmentUpload {
+    /// Files in upload order; their temporary files are removed if the upload is dropped
+    files: Vec<UploadedFile>,
+    conversation_id: Option<Uuid>,
+}
+
+/// Read every `file`/`file[]` field and the optional `conversation_id` field
+/// of an attachment upload. Fields may arrive in any order. Files are streamed
+/// into `dir` and checked against `policy` while they are read, so an upload
+/// is rejected as soon as one of its files violates the policy or the files
+/// together exceed `policy.max_upload_bytes`. Nothing else of the request is
+/// buffered, so the route needs no body limit of its own.
+async fn read_attachment_upload(
+    mut multipart: Multipart,
+    dir: &std::path::Path,
+    policy: &PmAttachmentPolicy,
+) -> Result<AttachmentUpload, ApiError> {
+    let mut upload = AttachmentUpload::default();
+    // Bytes the files still to come may take up
+    let mut remaining = policy.max_upload_bytes;
+    while let Some(mut field) = multipart.next_field().await? {
+        match field.name() {
+            Some("file" | "file[]") => {
+                let original_filename = field
+                    .file_name()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "file".to_string());
+                let mut mime_type = String::new();
+                let mut limited_by_total = false;
+                let streamed = stream_to_file(&mut field, dir, |head| {
+                    // Prefer the type detected from the content: clipboard pastes
+                    // often arrive without a (meaningful) file name
+                    mime_type = sniff_mime_type(head)
+                        .map(|mime| mime.to_string())
+                        .unwrap_or_else(|| get_mime_type(&original_filename));
+                    let max = pm_attachments::upload_size_limit(
+                        policy,
+                        &original_filename,
+                        &mime_type,
+                        head,
+                    )
+                    .map_err(|e| ApiError::BadRequest(format!("{}: {}", original_filename, e)))?;
+                    limited_by_total = remaining < max;
+                    Ok(max.min(remaining))
+                })
+                .await
+                .map_err(|e| match e {
+                    // Size limit crossed while streaming
+                    ApiError::PayloadTooLarge(_) if limited_by_total => {
+                        ApiError::PayloadTooLarge(format!(
+                            "Upload too large (max {} bytes in total)",
+                            policy.max_upload_bytes
+                        ))
+                    }
+                    ApiError::PayloadTooLarge(msg) => {
+                        ApiError::PayloadTooLarge(format!("{}: {}", original_filename, msg))
+                    }
+                    e => e,
+                })?;
+                remaining -= streamed.size;
+                upload.files.push(UploadedFile {
+                    original_filename,
+                    mime_type,
+                    upload: streamed,
+                });
+            }
+            Some("conversation_id") => {
+                let mut value = Vec::new();
+                while let Some(chunk) = field.chunk().await? {
+                    value.extend_from_slice(&chunk);
+                    if value.len() > MAX_CONVERSATION_ID_FIELD_BYTES {
+                        return Err(ApiError::BadRequest("Invalid conversation_id".to_string()));
+                    }
+                }
+                let value = String::from_utf8_lossy(&value);
+                let id = Uuid::parse_str(value.trim()).map_err(|_| {
+                    ApiError::BadRequest(format!("Invalid conversation_id: {}", value))
+                })?;
+                upload.conversation_id = Some(id);
+            }
+            _ => {}
+        }
+    }
+    Ok(upload)
+}
+
+/// Upload one or more attachments to PM chat.
+/// Every `file` (or `file[]`) field is stored; if any file violates the
+/// attachment policy, none are. The optional `conversation_id` multipart field
+/// links the attachments to an existing message of the project.
+pub async fn upload_attachment(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    multipart: Multipart,
+) -> Result<ResponseJson<ApiResponse<Vec<PmAttachment>>>, ApiError> {
+    let attachments_dir = get_pm_attachments_dir();
+    let policy = deployment
+        .config()
+        .read()
+        .await
+        .pm_attachment_policy
+        .clone();
+
+    // Stream and check every file before storing any of them; the temporary
+    // files are removed if anything fails before they are stored
+    let upload = read_attachment_upload(multipart, &attachments_dir, &policy).await?;
+    if upload.files.is_empty() {
+        return Err(ApiError::BadRequest("No file provided".to_string()));
+    }
+    let files = upload.files;
+
+    let pool = &deployment.db().pool;
+
+    // Validate the target message before writing anything to disk
+    let message = match upload.conversation_id {
+        Some(id) => {
+            let message = PmConversation::find_by_id(pool, id)
+                .await?
+                .ok_or_else(|| ApiError::NotFound("Message not found".to_string()))?;
+            if message.project_id != project.id {
+                return Err(ApiError::Forbidden(
+                    "Message does not belong to this project".to_string(),
+                ));
+            }
+            Some(message)
+        }
+        None if CREATE_ATTACHMENT_PLACEHOLDER_MESSAGES => None,
+        None => {
+            return Err(ApiError::BadRequest(
+                "conversation_id is required".to_string(),
+            ));
+        }
+    };
+
+    let linked_to_message = message.is_some();
+    let conversation_id = match message {
+        Some(message) => message.id,
+        // Attachments uploaded on their own get a placeholder message to hang off
+        None => {
+            let names: Vec<&str> = files
+                .iter()
+                .map(|file| file.original_filename.as_str())
+                .collect();
+            let label = if names.len() == 1 {
+                "Attachment"
+            } else {
+                "Attachments"
+            };
+            PmConversation::create(
+                pool,
+                &CreatePmConversation {
+                    project_id: project.id,
+                    role: PmMessageRole::User,
+                    content: format!("[{}: {}]", label, names.join(", ")),
+                    model: None,
+                    input_tokens: None,
+                    output_tokens: None,
+                },
+            )
+            .await?
+            .id
+        }
+    };
+
+    let mut attachments = Vec::with_capacity(files.len());
+    for UploadedFile {
+        original_filename,
+        mime_type,
+        upload,
+    } in files
+    {
+        let file_size = upload.size as i64;
+        let extension = match attachment_layout::clean_extension(&original_filename) {
+            extension if extension.is_empty() => "bin".to_string(),
+            extension => extension,
+        };
+
+        // Hash computed while streaming, for deduplication
+        let hash = upload.sha256.clone();
+
+        // Content-addressed location of the file
+        let new_filename = attachment_layout::content_path(&hash, &extension);
+
+        // Reuse the file of an earlier upload with the same content
+        let existing = PmAttachment::find_by_sha256(pool, project.id, &hash).await?;
+        let file_path = pm_attachments::store_file(
+            &attachments_dir,
+            existing.as_ref().map(|a| a.file_path.as_str()),
+            new_filename,
+            upload.path(),
+        )?;
+        let dimensions = mime_type
+            .starts_with("image/")
+            .then(|| pm_attachments::image_dimensions(&attachments_dir.join(&file_path)))
+            .flatten();
+
+        // Create attachment record
+        let attachment = PmAttachment::create(
+            pool,
+            &CreatePmAttachment {
+                conversation_id,
+                project_id: project.id,
+                file_name: original_filename,
+                file_path,
+                mime_type,
+                file_size,
+                sha256: Some(hash),
+                width: dimensions.map(|(width, _)| width as i64),
+                height: dimensions.map(|(_, height)| height as i64),
+            },
+        )
+        .await?;
+
+        deployment
+            .track_if_analytics_allowed(
+                "pm_attachment_uploaded",
+                serde_json::json!({
+                    "project_id": project.id.to_string(),
+                    "attachment_id": attachment.id.to_string(),
+                    "file_size": file_size,
+                    "mime_type": &attachment.mime_type,
+                    "linked_to_message": linked_to_message,
+                }),
+            )
+            .await;
+        attachments.push(attachment);
+    }
+
+    Ok(ResponseJson(ApiResponse::success(attachments)))
+}
+
+/// Serve an attachment file
+pub async fn serve_attachment(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, ApiError> {
+    let attachment = PmAttachment::find_by_id(&deployment.db().pool, attachment_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Attachment not found".to_string()))?;
+
+    // Verify the attachment belongs to this project
+    if attachment.project_id != project.id {
+        return Err(ApiError::Forbidden(
+            "Attachment does not belong to this project".to_string(),
+        ));
+    }
+
+    let file_path = attachment_layout::resolve(
+        &get_pm_attachments_dir(),
+        &attachment.file_path,
+        attachment.sha256.as_deref(),
+    );
+
+    let file = File::open(&file_path)
+        .await
+        .map_err(|_| ApiError::NotFound("Attachment file not found".to_string()))?;
+    let metadata = file.metadata().await?;
+
+    let stream = ReaderStream::new(file);
+    let body = Body::from_stream(stream);
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, &attachment.mime_type)
+        .header(header::CONTENT_LENGTH, metadata.len())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename=\"{}\"", attachment.file_name),
+        )
+        .header(header::CACHE_CONTROL, "public, max-age=31536000")
+        .body(body)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    Ok(response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailQuery {
+    /// Maximum thumbnail width in pixels
+    pub w: Option<u32>,
+    /// Maximum thumbnail height in pixels
+    pub h: Option<u32>,
+}
+
+/// Serve a thumbnail of an image attachment, generating it on first request.
+/// Responds with 415 for attachments that are not PNG or JPEG images.
+pub async fn serve_attachment_thumbnail(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, attachment_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<ThumbnailQuery>,
+) -> Result<Response, ApiError> {
+    let attachment = PmAttachment::find_by_id(&deployment.db().pool, attachment_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Attachment not found".to_string()))?;
+
+    // Verify the attachment belongs to this project
+    if attachment.project_id != project.id {
+        return Err(ApiError::Forbidden(
+            "Attachment does not belong to this project".to_string(),
+        ));
+    }
+
+    let width = query.w.unwrap_or(pm_attachments::DEFAULT_THUMBNAIL_SIZE);
+    let height = query.h.unwrap_or(width);
+    let thumbnail = tokio::task::spawn_blocking(move || {
+        let dir = get_pm_attachments_dir();
+        let file_path = attachment_layout::resolve_relative(
+            &dir,
+            &attachment.file_path,
+            attachment.sha256.as_deref(),
+        );
+        pm_attachments::thumbnail(&dir, &file_path, &attachment.mime_type, width, height)
+    })
+    .await
+    .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let (thumbnail_path, thumbnail_mime_type) = match thumbnail {
+        Ok(thumbnail) => thumbnail,
+        Err(e @ pm_attachments::ThumbnailError::UnsupportedType(_)) => {
+            return Ok((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ResponseJson(ApiResponse::<()>::error(&e.to_string())),
+            )
+                .into_response());
+        }
+        Err(pm_attachments::ThumbnailError::Io(e)) => return Err(ApiError::Io(e)),
+        Err(e) => return Err(ApiError::BadRequest(e.to_string())),
+    };
+
+    let file = File::open(&thumbnail_path).await?;
+    let metadata = file.metadata().await?;
+    let body = Body::from_stream(ReaderStream::new(file));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, thumbnail_mime_type)
+        .header(header::CONTENT_LENGTH, metadata.len())
+        .header(header::CACHE_CONTROL, "public, max-age=31536000")
+        .body(body)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))
+}
+
+/// Delete an attachment
+pub async fn delete_attachment(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    let attachment = PmAttachment::find_by_id(&deployment.db().pool, attachment_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Attachment not found".to_string()))?;
+
+    // Verify the attachment belongs to this project
+    if attachment.project_id != project.id {
+        return Err(ApiError::Forbidden(
+            "Attachment does not belong to this project".to_string(),
+        ));
+    }
+
+    // Delete from database, then the file once no other attachment shares it.
+    // The file may already have been moved out of a legacy path the row names.
+    let pool = &deployment.db().pool;
+    let attachments_dir = get_pm_attachments_dir();
+    let file_path = attachment_layout::resolve_relative(
+        &attachments_dir,
+        &attachment.file_path,
+        attachment.sha256.as_deref(),
+    );
+    PmAttachment::delete(pool, attachment_id).await?;
+    let remaining =
+        PmAttachment::count_sharing_file(pool, &file_path, attachment.sha256.as_deref()).await?;
+    pm_attachments::release_file(&attachments_dir, &file_path, remaining);
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_attachment_deleted",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "attachment_id": attachment_id.to_string(),
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+/// Name of the manifest entry written at the end of attachment archives
+const ARCHIVE_MANIFEST_NAME: &str = "MANIFEST.txt";
+
+/// Bytes of archive buffered between the task writing it and the response
+const ARCHIVE_PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Pick a unique zip entry name for each attachment. Names shared by more than
+/// one attachment (compared case-insensitively) get the attachment id appended.
+fn archive_entry_names(attachments: &[PmAttachment]) -> Vec<String> {
+    let base_names: Vec<String> = attachments
+        .iter()
+        .map(|attachment| {
+            std::path::Path::new(&attachment.file_name)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("file")
+                .to_string()
+        })
+        .collect();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    counts.insert(ARCHIVE_MANIFEST_NAME.to_lowercase(), 1);
+    for name in &base_names {
+        *counts.entry(name.to_lowercase()).or_default() += 1;
+    }
+
+    base_names
+        .into_iter()
+        .zip(attachments)
+        .map(|(name, attachment)| {
+            if counts[&name.to_lowercase()] == 1 {
+                return name;
+            }
+            let path = std::path::Path::new(&name);
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+            match path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => format!("{}-{}.{}", stem, attachment.id, ext),
+                None => format!("{}-{}", stem, attachment.id),
+            }
+        })
+        .collect()
+}
+
+/// Build the MANIFEST.txt listing what an attachment archive contains
+fn archive_manifest(project_name: &str, included: &[String], skipped: &[String]) -> String {
+    let mut manifest = format!("Attachments for {}\n", project_name);
+    manifest.push_str(&format!("\nIncluded ({}):\n", included.len()));
+    for line in included {
+        manifest.push_str(&format!("  {}\n", line));
+    }
+    manifest.push_str(&format!("\nSkipped ({}):\n", skipped.len()));
+    for line in skipped {
+        manifest.push_str(&format!("  {}\n", line));
+    }
+    manifest
+}
+
+/// ASCII-only archive file name for the Content-Disposition header
+fn archive_file_name(project_name: &str) -> String {
+    let slug: String = project_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let slug = slug.trim_matches('_');
+    if slug.is_empty() {
+        "project-attachments.zip".to_string()
+    } else {
+        format!("{}-attachments.zip", slug)
+    }
+}
+
+/// Write a zip archive of `files` (entry name and path of each), followed by
+/// MANIFEST.txt, to `writer`. Entries are streamed from disk, so no file is
+/// held in memory.
+async fn write_attachments_archive<W>(
+    writer: W,
+    files: Vec<(String, PathBuf)>,
+    manifest: Vec<u8>,
+) -> std::io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let mut zip = ZipFileWriter::with_tokio(writer);
+    for (entry_name, path) in files {
+        let file = File::open(&path).await?;
+        let entry = ZipEntryBuilder::new(entry_name.into(), Compression::Stored);
+        let mut entry_writer = zip
+            .write_entry_stream(entry)
+            .await
+            .map_err(std::io::Error::other)?;
+        futures::io::copy(file.compat(), &mut entry_writer).await?;
+        entry_writer.close().await.map_err(std::io::Error::other)?;
+    }
+
+    let entry = ZipEntryBuilder::new(
+        ARCHIVE_MANIFEST_NAME.to_string().into(),
+        Compression::Stored,
+    );
+    zip.write_entry_whole(entry, &manifest)
+        .await
+        .map_err(std::io::Error::other)?;
+    zip.close().await.map_err(std::io::Error::other)?;
+    Ok(())
+}
+
+/// Download all of a project's attachments as a zip archive.
+/// The archive is written by a background task and streamed into the response
+/// as it is built. Files missing from disk are skipped and listed in MANIFEST.txt.
+pub async fn download_attachments_archive(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<Response, ApiError> {
+    let attachments = PmAttachment::find_by_project_id(&deployment.db().pool, project.id).await?;
+    let entry_names = archive_entry_names(&attachments);
+    let attachments_dir = get_pm_attachments_dir();
+    let file_name = archive_file_name(&project.name);
+
+    // (entry name, path) of every file that is on disk
+    let mut files = Vec::new();
+    let mut included = Vec::new();
+    let mut skipped = Vec::new();
+    for (attachment, entry_name) in attachments.iter().zip(entry_names) {
+        let path = attachment_layout::resolve(
+            &attachments_dir,
+            &attachment.file_path,
+            attachment.sha256.as_deref(),
+        );
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) => {
+                included.push(format!("{} ({} bytes)", entry_name, metadata.len()));
+                files.push((entry_name, path));
+            }
+            Err(e) => skipped.push(format!("{} ({})", entry_name, e)),
+        }
+    }
+    let manifest = archive_manifest(&project.name, &included, &skipped).into_bytes();
+
+    let (writer, reader) = tokio::io::duplex(ARCHIVE_PIPE_CAPACITY);
+    let archive = tokio::spawn(write_attachments_archive(writer, files, manifest));
+    let stream = async_stream::stream! {
+        let mut chunks = ReaderStream::new(reader);
+        while let Some(chunk) = chunks.next().await {
+            yield chunk;
+        }
+        // End the body with an error rather than cleanly if the archive is incomplete
+        match archive.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => yield Err(e),
+            Err(e) => yield Err(std::io::Error::other(e)),
+        }
+    };
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file_name),
+        )
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from_stream(stream))
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
 
     Ok(response)
 }
 
-/// Delete an attachment
-pub async fn delete_attachment(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-    Path((_project_id, attachment_id)): Path<(Uuid, Uuid)>,
-) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
-    let attachment = PmAttachment::find_by_id(&deployment.db().pool, attachment_id)
-        .await?
-        .ok_or_else(|| ApiError::BadRequest("Attachment not found".to_string()))?;
+/// Get PM docs for a project
+pub async fn get_pm_docs(
+    Extension(project): Extension<Project>,
+) -> Result<ResponseJson<ApiResponse<Option<String>>>, ApiError> {
+    Ok(ResponseJson(ApiResponse::success(project.pm_docs)))
+}
+
+/// Query parameters for rendering the PM docs
+#[derive(Debug, Deserialize)]
+pub struct RenderPmDocsQuery {
+    /// Title of the `##` section to render instead of the whole docs
+    pub section: Option<String>,
+}
+
+/// PM docs rendered to sanitized HTML
+#[derive(Debug, Serialize, TS)]
+pub struct RenderedPmDocs {
+    pub html: String,
+    /// The rendered section, if only one was requested
+    pub section: Option<String>,
+}
+
+/// Render markdown to HTML, removing scripts, event handlers, unsafe URLs and
+/// any other markup that isn't safe to embed
+fn render_docs_html(markdown: &str) -> String {
+    let options = pulldown_cmark::Options::ENABLE_TABLES
+        | pulldown_cmark::Options::ENABLE_STRIKETHROUGH
+        | pulldown_cmark::Options::ENABLE_TASKLISTS;
+    let parser = pulldown_cmark::Parser::new_ext(markdown, options);
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    ammonia::clean(&html)
+}
+
+/// Render the PM docs, or one `##` section of them, to sanitized HTML
+pub async fn render_pm_docs(
+    Extension(project): Extension<Project>,
+    Query(query): Query<RenderPmDocsQuery>,
+) -> Result<ResponseJson<ApiResponse<RenderedPmDocs>>, ApiError> {
+    let docs = project.pm_docs.unwrap_or_default();
+    let section = query
+        .section
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+    let markdown = match &section {
+        Some(title) => {
+            let range = docs_section_range(&docs, title).ok_or_else(|| {
+                ApiError::NotFound(format!("PM docs have no '## {}' section", title))
+            })?;
+            &docs[range]
+        }
+        None => docs.as_str(),
+    };
+
+    Ok(ResponseJson(ApiResponse::success(RenderedPmDocs {
+        html: render_docs_html(markdown),
+        section,
+    })))
+}
+
+/// Number of previous PM docs versions kept per project
+const PM_DOCS_HISTORY_LIMIT: i64 = 50;
+
+/// Overwrite a project's PM docs, saving the current content to the docs
+/// history. `None` keeps the docs as they are.
+async fn save_pm_docs(
+    pool: &sqlx::SqlitePool,
+    project: &Project,
+    pm_docs: Option<String>,
+    source: PmDocsSource,
+) -> Result<Project, ApiError> {
+    save_pm_docs_if_unchanged(pool, project, pm_docs, source, None)
+        .await?
+        .ok_or(ApiError::Database(sqlx::Error::RowNotFound))
+}
+
+/// Like [`save_pm_docs`], but with `expected_updated_at` only saves if the
+/// project has not been updated since. Returns `None` if it has.
+async fn save_pm_docs_if_unchanged(
+    pool: &sqlx::SqlitePool,
+    project: &Project,
+    pm_docs: Option<String>,
+    source: PmDocsSource,
+    expected_updated_at: Option<DateTime<Utc>>,
+) -> Result<Option<Project>, ApiError> {
+    let Some(updated) =
+        Project::update_pm_docs(pool, project.id, pm_docs.as_deref(), expected_updated_at).await?
+    else {
+        return Ok(None);
+    };
+
+    if let (Some(previous), Some(new_docs)) = (&project.pm_docs, &pm_docs)
+        && !previous.is_empty()
+        && previous != new_docs
+    {
+        PmDocsVersion::create(pool, project.id, previous, source).await?;
+        PmDocsVersion::prune(pool, project.id, PM_DOCS_HISTORY_LIMIT).await?;
+    }
+
+    Ok(Some(updated))
+}
+
+/// Update PM docs for a project
+pub async fn update_pm_docs(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<UpdatePmDocsRequest>,
+) -> Result<Response, ApiError> {
+    let pool = &deployment.db().pool;
+    let source = payload.source.unwrap_or_default();
+    let Some(updated_project) = save_pm_docs_if_unchanged(
+        pool,
+        &project,
+        payload.pm_docs,
+        source,
+        payload.expected_updated_at,
+    )
+    .await?
+    else {
+        let current = Project::find_by_id(pool, project.id)
+            .await?
+            .ok_or(ApiError::Database(sqlx::Error::RowNotFound))?;
+        let conflict = PmDocsConflict {
+            pm_docs: current.pm_docs,
+            updated_at: current.updated_at,
+        };
+        return Ok((
+            StatusCode::CONFLICT,
+            ResponseJson(ApiResponse::<UpdatePmDocsResponse, _>::error_with_data(
+                conflict,
+            )),
+        )
+            .into_response());
+    };
+    let diff = if payload.return_diff.unwrap_or(false) {
+        pm_docs_diff(
+            project.pm_docs.as_deref(),
+            updated_project.pm_docs.as_deref(),
+        )
+    } else {
+        None
+    };
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_docs_updated",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::<_, PmDocsConflict>::success(
+        UpdatePmDocsResponse {
+            project: updated_project,
+            diff,
+        },
+    ))
+    .into_response())
+}
+
+/// Get previous versions of the PM docs, newest first
+pub async fn get_pm_docs_history(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<PmDocsVersion>>>, ApiError> {
+    let versions = PmDocsVersion::find_by_project_id(&deployment.db().pool, project.id).await?;
+    Ok(ResponseJson(ApiResponse::success(versions)))
+}
+
+/// Restore a previous version of the PM docs; the current docs are saved to the history
+pub async fn restore_pm_docs_version(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, version_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<Project>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let version = match PmDocsVersion::find_by_id(pool, version_id).await? {
+        Some(version) if version.project_id == project.id => version,
+        Some(_) => {
+            return Err(ApiError::Forbidden(
+                "PM docs version does not belong to this project".to_string(),
+            ));
+        }
+        None => return Err(ApiError::Database(sqlx::Error::RowNotFound)),
+    };
+
+    let updated_project =
+        save_pm_docs(pool, &project, Some(version.content), PmDocsSource::Restore).await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_docs_restored",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "version_id": version_id.to_string(),
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(updated_project)))
+}
+
+/// Response for task summary with dependencies
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct TaskWithDependencies {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub priority: String,
+    pub depends_on: Vec<String>,  // Task IDs this task depends on
+    pub depended_by: Vec<String>, // Task IDs that depend on this task
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct TaskSummaryResponse {
+    pub tasks: Vec<TaskWithDependencies>,
+    pub summary_text: String, // Formatted text for PM docs
+}
+
+/// Language of the generated task summary text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum TaskSummaryLocale {
+    En,
+    #[default]
+    Ja,
+}
+
+/// Options for building the task summary, read from the query string
+#[derive(Debug, Default, Deserialize)]
+pub struct TaskSummaryOptions {
+    /// Language of the summary text, Japanese if not set
+    pub locale: Option<TaskSummaryLocale>,
+}
+
+/// Labels used in the task summary text for one locale
+struct TaskSummaryLabels {
+    heading: &'static str,
+    updated_at: &'static str,
+    /// Section label per lowercased task status, in display order
+    statuses: [(&'static str, &'static str); 4],
+    depends_on: &'static str,
+    blocking: &'static str,
+    blocked_heading: &'static str,
+    waiting_on: &'static str,
+}
+
+const EN_SUMMARY_LABELS: TaskSummaryLabels = TaskSummaryLabels {
+    heading: "Tasks and dependencies",
+    updated_at: "Last updated",
+    statuses: [
+        ("todo", "📋 Todo"),
+        ("inprogress", "🔄 In Progress"),
+        ("inreview", "👀 In Review"),
+        ("done", "✅ Done"),
+    ],
+    depends_on: "⬅️ Depends on",
+    blocking: "➡️ Blocking",
+    blocked_heading: "⚠️ Blocked tasks",
+    waiting_on: "is waiting on",
+};
+
+const JA_SUMMARY_LABELS: TaskSummaryLabels = TaskSummaryLabels {
+    heading: TASK_SUMMARY_SECTION,
+    updated_at: "最終更新",
+    statuses: [
+        ("todo", "📋 未着手 (Todo)"),
+        ("inprogress", "🔄 進行中 (In Progress)"),
+        ("inreview", "👀 レビュー中 (In Review)"),
+        ("done", "✅ 完了 (Done)"),
+    ],
+    depends_on: "⬅️ 依存",
+    blocking: "➡️ ブロック中",
+    blocked_heading: "⚠️ ブロックされているタスク",
+    waiting_on: "は以下の完了待ち",
+};
+
+impl TaskSummaryLocale {
+    fn labels(self) -> &'static TaskSummaryLabels {
+        match self {
+            TaskSummaryLocale::En => &EN_SUMMARY_LABELS,
+            TaskSummaryLocale::Ja => &JA_SUMMARY_LABELS,
+        }
+    }
+}
+
+/// Icon shown before a task title for its lowercased priority
+fn priority_icon(priority: &str) -> &'static str {
+    match priority {
+        "urgent" => "🔴",
+        "high" => "🟠",
+        "medium" => "🟡",
+        "low" => "🟢",
+        _ => "⚪",
+    }
+}
+
+impl TaskWithDependencies {
+    fn new(task: &Task, depends_on: &[Uuid], depended_by: &[Uuid]) -> Self {
+        Self {
+            id: task.id.to_string(),
+            title: task.title.clone(),
+            description: task.description.clone(),
+            status: format!("{:?}", task.status).to_lowercase(),
+            priority: format!("{:?}", task.priority).to_lowercase(),
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            depended_by: depended_by.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+}
+
+/// Group `(task_id, depends_on_task_id)` pairs into the dependencies and the
+/// dependents of each task
+fn group_dependencies(
+    pairs: &[(Uuid, Uuid)],
+) -> (HashMap<Uuid, Vec<Uuid>>, HashMap<Uuid, Vec<Uuid>>) {
+    let mut dependencies: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    let mut dependents: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for &(task_id, depends_on) in pairs {
+        dependencies.entry(task_id).or_default().push(depends_on);
+        dependents.entry(depends_on).or_default().push(task_id);
+    }
+    (dependencies, dependents)
+}
+
+/// Load the project's tasks with their dependencies and format the summary text.
+/// The task summary endpoint and the PM docs sync both use this, so they agree.
+async fn build_task_summary(
+    pool: &sqlx::SqlitePool,
+    project_id: Uuid,
+    opts: &TaskSummaryOptions,
+) -> Result<(Vec<TaskWithDependencies>, String), ApiError> {
+    let tasks_with_status = Task::find_by_project_id_with_attempt_status(pool, project_id).await?;
+    let dependency_pairs = TaskDependency::find_all_for_project(pool, project_id).await?;
+    let (dependencies, dependents) = group_dependencies(&dependency_pairs);
+
+    let tasks = tasks_with_status
+        .iter()
+        .map(|task| {
+            TaskWithDependencies::new(
+                &task.task,
+                dependencies.get(&task.id).map_or(&[][..], Vec::as_slice),
+                dependents.get(&task.id).map_or(&[][..], Vec::as_slice),
+            )
+        })
+        .collect();
+
+    let summary_text = format_task_summary(&tasks, opts.locale.unwrap_or_default());
+    Ok((tasks, summary_text))
+}
+
+/// Format the markdown task summary, starting with its `## ` heading
+fn format_task_summary(tasks: &[TaskWithDependencies], locale: TaskSummaryLocale) -> String {
+    let labels = locale.labels();
+    let task_map: HashMap<&str, &TaskWithDependencies> =
+        tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+    let titles = |ids: &[String], only_unfinished: bool| -> Vec<String> {
+        ids.iter()
+            .filter_map(|id| task_map.get(id.as_str()))
+            .filter(|t| !only_unfinished || t.status != "done")
+            .map(|t| t.title.clone())
+            .collect()
+    };
+
+    let mut summary_lines = vec![format!("## {}", labels.heading), "".to_string()];
+
+    // Group by status
+    for (status, label) in labels.statuses.iter() {
+        let status_tasks: Vec<_> = tasks.iter().filter(|t| t.status == *status).collect();
+
+        if !status_tasks.is_empty() {
+            summary_lines.push(format!("### {}", label));
+            summary_lines.push("".to_string());
+
+            for task in status_tasks {
+                summary_lines.push(format!(
+                    "- {} **{}**",
+                    priority_icon(&task.priority),
+                    task.title
+                ));
+
+                let dep_names = titles(&task.depends_on, false);
+                if !dep_names.is_empty() {
+                    summary_lines.push(format!(
+                        "  - {}: {}",
+                        labels.depends_on,
+                        dep_names.join(", ")
+                    ));
+                }
+
+                let blocking_names = titles(&task.depended_by, false);
+                if !blocking_names.is_empty() {
+                    summary_lines.push(format!(
+                        "  - {}: {}",
+                        labels.blocking,
+                        blocking_names.join(", ")
+                    ));
+                }
+            }
+            summary_lines.push("".to_string());
+        }
+    }
+
+    // Add dependency chain analysis
+    let blocked_tasks: Vec<_> = tasks
+        .iter()
+        .filter(|t| t.status != "done")
+        .map(|t| (t, titles(&t.depends_on, true)))
+        .filter(|(_, waiting_on)| !waiting_on.is_empty())
+        .collect();
+
+    if !blocked_tasks.is_empty() {
+        summary_lines.push(format!("### {}", labels.blocked_heading));
+        summary_lines.push("".to_string());
+        for (task, waiting_on) in blocked_tasks {
+            summary_lines.push(format!(
+                "- **{}** {}: {}",
+                task.title,
+                labels.waiting_on,
+                waiting_on.join(", ")
+            ));
+        }
+        summary_lines.push("".to_string());
+    }
+
+    summary_lines.join("\n")
+}
+
+/// Get task summary with dependencies for PM context
+pub async fn get_task_summary(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(opts): Query<TaskSummaryOptions>,
+) -> Result<ResponseJson<ApiResponse<TaskSummaryResponse>>, ApiError> {
+    let (tasks, summary_text) =
+        build_task_summary(&deployment.db().pool, project.id, &opts).await?;
+
+    Ok(ResponseJson(ApiResponse::success(TaskSummaryResponse {
+        tasks,
+        summary_text,
+    })))
+}
+
+/// Heading of the PM docs section maintained by `sync_task_summary_to_docs`
+const TASK_SUMMARY_SECTION: &str = "タスク一覧と依存関係";
+/// Markers around the block written by `sync_task_summary_to_docs`, so it can be
+/// found again whatever the heading says
+const TASK_SUMMARY_START_MARKER: &str = "<!-- vk:task-summary:start -->";
+const TASK_SUMMARY_END_MARKER: &str = "<!-- vk:task-summary:end -->";
+
+/// The marked PM docs block for a task summary, with a last-updated line below it
+fn task_summary_docs_block(
+    summary: &str,
+    locale: TaskSummaryLocale,
+    updated_at: DateTime<Utc>,
+) -> String {
+    format!(
+        "{}\n{}\n\n*{}: {}*\n{}\n",
+        TASK_SUMMARY_START_MARKER,
+        summary.trim_matches('\n'),
+        locale.labels().updated_at,
+        updated_at.format("%Y-%m-%d %H:%M UTC"),
+        TASK_SUMMARY_END_MARKER
+    )
+}
+
+/// Replace the task summary block of `docs` with `block`, or append it. Docs
+/// written before the block had markers are matched by the Japanese heading.
+fn replace_task_summary_block(docs: Option<&str>, block: &str) -> String {
+    let docs = docs.unwrap_or_default();
+    let range = docs
+        .find(TASK_SUMMARY_START_MARKER)
+        .and_then(|start| {
+            docs[start..]
+                .find(TASK_SUMMARY_END_MARKER)
+                .map(|end| start..start + end + TASK_SUMMARY_END_MARKER.len())
+        })
+        .or_else(|| docs_section_range(docs, TASK_SUMMARY_SECTION));
+    splice_docs_block(docs, range, block)
+}
+
+/// Sync task summary to PM docs
+pub async fn sync_task_summary_to_docs(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(opts): Query<TaskSummaryOptions>,
+) -> Result<ResponseJson<ApiResponse<Project>>, ApiError> {
+    let (tasks, summary_text) =
+        build_task_summary(&deployment.db().pool, project.id, &opts).await?;
+    let block = task_summary_docs_block(&summary_text, opts.locale.unwrap_or_default(), Utc::now());
+
+    // Update PM docs - replace the task summary block, or append it
+    let new_docs = replace_task_summary_block(project.pm_docs.as_deref(), &block);
+
+    let updated_project = save_pm_docs(
+        &deployment.db().pool,
+        &project,
+        Some(new_docs),
+        PmDocsSource::TaskSummary,
+    )
+    .await?;
+
+    deployment
+        .track_if_analytics_allowed(
+            "pm_task_summary_synced",
+            serde_json::json!({
+                "project_id": project.id.to_string(),
+                "task_count": tasks.len(),
+            }),
+        )
+        .await;
+
+    Ok(ResponseJson(ApiResponse::success(updated_project)))
+}
+
+/// A workspace document from the docs/ folder
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct WorkspaceDoc {
+    pub path: String,      // Relative path from docs/ folder
+    pub repo_name: String, // Which repo this doc is from
+    pub content: String,   // Content of the document, without front matter
+    /// `title` from the document's YAML front matter
+    pub title: Option<String>,
+    /// Fields of the document's YAML front matter
+    #[ts(type = "Record<string, unknown>")]
+    pub metadata: serde_json::Value,
+    /// Size of the whole file in bytes
+    pub size_bytes: usize,
+    /// True if `content` is only the first part of the file
+    pub truncated: bool,
+}
+
+/// Response for workspace docs
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct WorkspaceDocsResponse {
+    pub docs: Vec<WorkspaceDoc>,
+    /// Docs left out because of the total size limit, as `repo_name: path`
+    pub skipped: Vec<String>,
+}
+
+/// Overrides for the docs scan, read from the query string
+#[derive(Debug, Default, Deserialize)]
+pub struct WorkspaceDocsQuery {
+    /// Comma-separated file extensions to include, e.g. `md,adoc,mdx`
+    pub extensions: Option<String>,
+    /// Maximum total size of the returned docs in bytes
+    pub max_total: Option<usize>,
+    /// Maximum size of a single doc in bytes
+    pub max_single: Option<usize>,
+    /// Read every doc from disk instead of reusing cached contents
+    #[serde(default)]
+    pub force_refresh: bool,
+}
+
+impl WorkspaceDocsQuery {
+    fn scan_options(&self, ignore_globs: Vec<String>) -> ScanOptions {
+        let defaults = ScanOptions::default();
+        let extensions: Vec<String> = self
+            .extensions
+            .iter()
+            .flat_map(|list| list.split(','))
+            .map(|ext| ext.trim().trim_start_matches('.').to_string())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        ScanOptions {
+            extensions: if extensions.is_empty() {
+                defaults.extensions
+            } else {
+                extensions
+            },
+            max_total: self.max_total.unwrap_or(defaults.max_total),
+            max_single: self.max_single.unwrap_or(defaults.max_single),
+            ignore_globs,
+        }
+    }
+}
+
+/// Get workspace documentation files from project repos
+pub async fn get_workspace_docs(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<WorkspaceDocsQuery>,
+) -> Result<ResponseJson<ApiResponse<WorkspaceDocsResponse>>, ApiError> {
+    // Get all repos for this project
+    let repos = ProjectRepo::find_repos_for_project(&deployment.db().pool, project.id).await?;
+
+    let ignore_globs = deployment.config().read().await.docs_scan_ignore.clone();
+    let scan_options = query.scan_options(ignore_globs);
+    let mut all_docs = Vec::new();
+    let mut skipped = Vec::new();
+
+    for repo in repos {
+        if query.force_refresh {
+            invalidate_docs_cache(&repo.path);
+        }
+        // Scan docs folder for this repo
+        let scan = scan_docs_folder(&repo.path, &scan_options).await;
+
+        skipped.extend(
+            scan.skipped
+                .into_iter()
+                .map(|path| format!("{}: {}", repo.display_name, path)),
+        );
+        for doc in scan.docs {
+            all_docs.push(WorkspaceDoc {
+                path: doc.relative_path,
+                repo_name: repo.display_name.clone(),
+                content: doc.content,
+                title: doc.title,
+                metadata: doc.metadata,
+                size_bytes: doc.size_bytes,
+                truncated: doc.truncated,
+            });
+        }
+    }
+
+    Ok(ResponseJson(ApiResponse::success(WorkspaceDocsResponse {
+        docs: all_docs,
+        skipped,
+    })))
+}
+
+pub fn router(_deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
+    Router::new()
+        .route("/", get(get_pm_chat).post(send_message).delete(clear_chat))
+        .route(
+            "/archived",
+            get(get_archived_chat).delete(purge_archived_chat),
+        )
+        .route("/archived/restore", post(restore_archived_chat))
+        .route("/ai-chat", post(ai_chat))
+        .route("/ai-chat/cancel", post(cancel_ai_chat))
+        .route("/ai-chat/{stream_id}/events", get(resume_ai_chat))
+        .route("/regenerate", post(regenerate_ai_chat))
+        .route("/context-preview", get(get_context_preview))
+        .route("/ai-agents", get(get_available_agents))
+        .route("/ai-agents/{agent}/models", get(get_agent_models))
+        .route(
+            "/messages/{message_id}",
+            delete(delete_message).patch(update_message),
+        )
+        .route("/messages/{message_id}/pin", put(set_message_pinned))
+        .route(
+            "/messages/{message_id}/to-task",
+            post(create_task_from_message),
+        )
+        .route("/retention", put(update_chat_retention))
+        .route("/prune", post(prune_chat))
+        .route("/search", get(search_pm_chat))
+        .route("/usage", get(get_chat_usage))
+        .route("/stats", get(get_chat_stats))
+        .route(
+            "/attachments",
+            // `read_attachment_upload` enforces the attachment policy's limits
+            get(get_attachments)
+                .post(upload_attachment)
+                .layer(DefaultBodyLimit::disable()),
+        )
+        .route("/attachments/archive", get(download_attachments_archive))
+        .route("/attachments/{attachment_id}", delete(delete_attachment))
+        .route("/attachments/{attachment_id}/file", get(serve_attachment))
+        .route(
+            "/attachments/{attachment_id}/thumbnail",
+            get(serve_attachment_thumbnail),
+        )
+        .route("/docs", get(get_pm_docs).put(update_pm_docs))
+        .route("/docs/render", get(render_pm_docs))
+        .route("/docs/history", get(get_pm_docs_history))
+        .route(
+            "/docs/history/{version_id}/restore",
+            post(restore_pm_docs_version),
+        )
+        .route("/workspace-docs", get(get_workspace_docs))
+        .route(
+            "/task-summary",
+            get(get_task_summary).post(sync_task_summary_to_docs),
+        )
+        .layer(DefaultBodyLimit::max(20 * 1024 * 1024)) // 20MB limit, e.g. for large docs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_client_message_id() {
+        assert_eq!(validate_client_message_id(" abc-123 ").unwrap(), "abc-123");
+        assert!(validate_client_message_id("  ").is_err());
+        assert!(validate_client_message_id(&"x".repeat(MAX_CLIENT_MESSAGE_ID_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_role_defaults_to_user() {
+        assert_eq!(parse_message_role(None).unwrap(), PmMessageRole::User);
+        assert_eq!(
+            parse_message_role(Some("assistant")).unwrap(),
+            PmMessageRole::Assistant
+        );
+    }
+
+    #[test]
+    fn test_parse_message_role_rejects_unknown_role() {
+        let error = parse_message_role(Some("assisstant")).unwrap_err();
+        assert!(matches!(error, ApiError::BadRequest(ref msg) if msg.contains("assisstant")));
+        assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_truncate_to_tokens_keeps_short_content() {
+        assert_eq!(truncate_to_tokens("hello", 500), "hello");
+    }
+
+    #[test]
+    fn test_truncate_to_tokens_does_not_split_cjk() {
+        // Each CJK character is an estimated token of 3 bytes
+        let content = "漢".repeat(200);
+        assert_eq!(truncate_to_tokens(&content, 166), "漢".repeat(166));
+    }
+
+    #[test]
+    fn test_truncate_to_tokens_does_not_split_emoji() {
+        // "ab" takes half a token, so only one 4-byte emoji fits in two tokens
+        let content = format!("ab{}", "🔥".repeat(10));
+        assert_eq!(truncate_to_tokens(&content, 2), "ab🔥");
+    }
+
+    fn history_message(role: &str, content: &str) -> PmConversation {
+        PmConversation {
+            id: Uuid::new_v4(),
+            project_id: Uuid::new_v4(),
+            role: role.to_string(),
+            content: content.to_string(),
+            model: None,
+            pinned: false,
+            edited: false,
+            input_tokens: None,
+            output_tokens: None,
+            archived_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_recent_history_keeps_everything_under_budget() {
+        let messages = vec![
+            history_message("user", "Plan the release"),
+            history_message("assistant", "Created 3 tasks"),
+        ];
+
+        let history = recent_history(&messages, 1000);
+
+        assert_eq!(
+            history,
+            vec![
+                "**User**: Plan the release\n\n".to_string(),
+                "**Assistant**: Created 3 tasks\n\n".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recent_history_trims_oldest_message_over_budget() {
+        let messages = vec![
+            history_message("user", &"old ".repeat(100)),
+            history_message("user", &"middle ".repeat(100)),
+            history_message("assistant", "latest"),
+        ];
+        let latest_tokens = estimate_tokens("**Assistant**: latest\n\n");
+        let budget = latest_tokens + 50;
+
+        let history = recent_history(&messages, budget);
+
+        assert_eq!(history.len(), 2);
+        assert!(history[0].starts_with("**User**: middle middle"));
+        assert!(history[0].ends_with("...\n\n"));
+        assert_eq!(history[1], "**Assistant**: latest\n\n");
+        let used: usize = history.iter().map(|entry| estimate_tokens(entry)).sum();
+        assert!(used <= budget);
+    }
+
+    #[test]
+    fn test_recent_history_drops_message_without_room_to_trim() {
+        let messages = vec![
+            history_message("user", "older"),
+            history_message("assistant", "latest"),
+        ];
+        let budget = estimate_tokens("**Assistant**: latest\n\n");
+
+        let history = recent_history(&messages, budget);
+
+        assert_eq!(history, vec!["**Assistant**: latest\n\n".to_string()]);
+        assert!(recent_history(&messages, 0).is_empty());
+    }
+
+    #[test]
+    fn test_search_snippet_highlights_all_matches() {
+        let content = "We decided to use Postgres. postgres is final.";
+        let (snippet, highlights, count) = search_snippet(content, "POSTGRES", 80).unwrap();
+
+        assert_eq!(snippet, content);
+        assert_eq!(count, 2);
+        assert_eq!(
+            highlights,
+            vec![
+                TextRange { start: 18, end: 26 },
+                TextRange { start: 28, end: 36 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_snippet_trims_context_around_first_match() {
+        let content = "aaaaaaaaaa decision bbbbbbbbbb";
+        let (snippet, highlights, _) = search_snippet(content, "decision", 5).unwrap();
+
+        assert_eq!(snippet, "…aaaa decision bbbb…");
+        assert_eq!(highlights, vec![TextRange { start: 6, end: 14 }]);
+    }
+
+    #[test]
+    fn test_search_snippet_offsets_are_utf16() {
+        let (_, highlights, _) = search_snippet("🔥 fix bug", "bug", 80).unwrap();
+        assert_eq!(highlights, vec![TextRange { start: 7, end: 10 }]);
+    }
+
+    #[test]
+    fn test_search_snippet_no_match() {
+        assert!(search_snippet("nothing here", "decision", 80).is_none());
+        assert!(search_snippet("nothing here", "", 80).is_none());
+    }
+
+    #[test]
+    fn test_sniff_mime_type_from_magic_bytes() {
+        assert_eq!(
+            sniff_mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_mime_type(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_mime_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(
+            sniff_mime_type(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(
+            sniff_mime_type(b"BM\x46\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0\x02\0\0\0"),
+            Some("image/bmp")
+        );
+    }
+
+    #[test]
+    fn test_sniff_mime_type_ignores_text_starting_with_bm() {
+        assert_eq!(
+            sniff_mime_type(b"BM25 ranking notes for the search task"),
+            None
+        );
+        assert_eq!(sniff_mime_type(b"BM"), None);
+        assert_eq!(sniff_mime_type(b"BMW\0\0\0\0\0\0\0 meeting notes"), None);
+    }
+
+    #[test]
+    fn test_sniff_mime_type_unknown_falls_back_to_extension() {
+        let data = b"plain text notes";
+        assert_eq!(sniff_mime_type(data), None);
+        assert_eq!(
+            sniff_mime_type(data)
+                .map(|mime| mime.to_string())
+                .unwrap_or_else(|| get_mime_type("notes.md")),
+            "text/markdown"
+        );
+    }
+
+    #[test]
+    fn test_backend_url_env_override_takes_precedence() {
+        let url = resolve_mcp_backend_url(
+            Some("https://vk.example.com/"),
+            Some("http://localhost:9000"),
+            Some(1234),
+            Some("5678"),
+        )
+        .unwrap();
+        assert_eq!(url, "https://vk.example.com");
+    }
 
-    // Verify the attachment belongs to this project
-    if attachment.project_id != project.id {
-        return Err(ApiError::BadRequest(
-            "Attachment does not belong to this project".to_string(),
+    #[test]
+    fn test_backend_url_request_override_beats_port_sources() {
+        let url = resolve_mcp_backend_url(
+            None,
+            Some("http://localhost:9000"),
+            Some(1234),
+            Some("5678"),
+        )
+        .unwrap();
+        assert_eq!(url, "http://localhost:9000");
+    }
+
+    #[test]
+    fn test_backend_url_port_file_then_backend_port_then_default() {
+        assert_eq!(
+            resolve_mcp_backend_url(None, None, Some(1234), Some("5678")).unwrap(),
+            "http://localhost:1234"
+        );
+        assert_eq!(
+            resolve_mcp_backend_url(None, None, None, Some("5678")).unwrap(),
+            "http://localhost:5678"
+        );
+        assert_eq!(
+            resolve_mcp_backend_url(None, None, None, None).unwrap(),
+            "http://localhost:45557"
+        );
+    }
+
+    #[test]
+    fn test_backend_url_rejects_malformed_urls() {
+        assert!(resolve_mcp_backend_url(Some("ftp://example.com"), None, None, None).is_err());
+        assert!(resolve_mcp_backend_url(None, Some("not a url"), None, None).is_err());
+        assert!(resolve_mcp_backend_url(None, None, None, Some("abc")).is_err());
+    }
+
+    /// Run CLI JSON lines through the same parsing and joining as a chat stream
+    fn accumulate_response(agent: PmChatAgent, lines: &[serde_json::Value]) -> String {
+        let mut parser = agent.stream_parser();
+        let mut response = ResponseAccumulator::new(agent);
+        for line in lines {
+            for parsed in parser.parse_line(&line.to_string()) {
+                match parsed {
+                    ParsedEvent::Content(text) => {
+                        response.push(&text);
+                    }
+                    ParsedEvent::Result(text) => {
+                        response.push_result(&text);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        response.text
+    }
+
+    #[test]
+    fn test_gemini_delta_fragments_are_concatenated_verbatim() {
+        let original = "Here are the tasks:\n- Fix the login bug\n- Write docs for the API";
+        let fragments = [
+            "Here a",
+            "re the ta",
+            "sks:\n- Fix",
+            " the log",
+            "in bug\n",
+            "- Write docs",
+            " for the API",
+        ];
+        assert_eq!(fragments.concat(), original);
+
+        let lines: Vec<_> = fragments
+            .iter()
+            .map(|fragment| {
+                serde_json::json!({"type":"message","role":"assistant","content":fragment,"delta":true})
+            })
+            .collect();
+
+        assert_eq!(
+            accumulate_response(PmChatAgent::GeminiCli, &lines),
+            original
+        );
+    }
+
+    #[test]
+    fn test_gemini_full_message_replaces_streamed_deltas() {
+        // Captured from `gemini --output-format stream-json`
+        let lines = [
+            serde_json::json!({"type":"init","session_id":"3f0c","model":"gemini-2.5-pro"}),
+            serde_json::json!({"type":"message","role":"user","content":"What is open?"}),
+            serde_json::json!({"type":"message","role":"assistant","content":"There are ","delta":true}),
+            serde_json::json!({"type":"message","role":"assistant","content":"2 open tasks","delta":true}),
+            serde_json::json!({"type":"message","role":"assistant","content":"There are 2 open tasks."}),
+            serde_json::json!({"type":"result","status":"success","stats":{"total_tokens":812}}),
+        ];
+
+        assert_eq!(
+            accumulate_response(PmChatAgent::GeminiCli, &lines),
+            "There are 2 open tasks."
+        );
+    }
+
+    #[test]
+    fn test_replacing_message_only_streams_unseen_text() {
+        let mut response = ResponseAccumulator::new(PmChatAgent::GeminiCli);
+        let fragment = AgentText {
+            text: "Created ".to_string(),
+            delta: true,
+        };
+
+        assert_eq!(response.push(&fragment), Some("Created ".to_string()));
+        assert_eq!(
+            response.push(&AgentText::message("Created 2 tasks.")),
+            Some("2 tasks.".to_string())
+        );
+        assert_eq!(
+            response.push(&AgentText::message("Done.")),
+            Some("Done.".to_string())
+        );
+        assert_eq!(response.text, "Created 2 tasks.\n\nDone.");
+    }
+
+    #[test]
+    fn test_full_messages_are_kept_apart() {
+        let lines = [
+            serde_json::json!({"type":"assistant","message":{"content":[{"type":"text","text":"Let me check the board."}]}}),
+            serde_json::json!({"type":"assistant","message":{"content":[{"type":"text","text":"There are 3 open tasks."}]}}),
+            serde_json::json!({"type":"result","result":"Let me check the board.There are 3 open tasks."}),
+        ];
+
+        assert_eq!(
+            accumulate_response(PmChatAgent::ClaudeCli, &lines),
+            "Let me check the board.\n\nThere are 3 open tasks."
+        );
+    }
+
+    /// Check each `(line, expected events)` pair against a fresh parser
+    fn assert_parses(agent: PmChatAgent, cases: Vec<(&str, Vec<ParsedEvent>)>) {
+        for (line, expected) in cases {
+            assert_eq!(
+                agent.stream_parser().parse_line(line),
+                expected,
+                "{agent} line: {line}"
+            );
+        }
+    }
+
+    fn content(text: &str) -> ParsedEvent {
+        ParsedEvent::Content(AgentText::message(text))
+    }
+
+    fn usage(input_tokens: i64, output_tokens: i64) -> ParsedEvent {
+        ParsedEvent::Usage(TokenUsage {
+            input_tokens,
+            output_tokens,
+        })
+    }
+
+    #[test]
+    fn test_claude_parser() {
+        assert_parses(
+            PmChatAgent::ClaudeCli,
+            vec![
+                (
+                    r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Checking."},{"type":"tool_use","id":"toolu_1","name":"mcp__vibe_kanban__list_projects","input":{}}]}}"#,
+                    vec![
+                        content("Checking."),
+                        ParsedEvent::ToolUse(AgentToolEvent::Call {
+                            id: Some("toolu_1".to_string()),
+                            name: "mcp__vibe_kanban__list_projects".to_string(),
+                            input: Some(serde_json::json!({})),
+                        }),
+                    ],
+                ),
+                (
+                    r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"[]","is_error":false}]}}"#,
+                    vec![ParsedEvent::ToolUse(AgentToolEvent::Result {
+                        id: Some("toolu_1".to_string()),
+                        name: None,
+                        success: true,
+                        output: Some("[]".to_string()),
+                    })],
+                ),
+                (
+                    r#"{"type":"result","result":"Checking.","usage":{"input_tokens":12,"cache_creation_input_tokens":300,"cache_read_input_tokens":1000,"output_tokens":40}}"#,
+                    vec![
+                        ParsedEvent::Result("Checking.".to_string()),
+                        usage(1312, 40),
+                    ],
+                ),
+                (
+                    r#"{"type":"assistant","message":{"content":[{"type":"text","text":""}]}}"#,
+                    vec![ParsedEvent::Ignore],
+                ),
+                (
+                    r#"{"type":"system","subtype":"init"}"#,
+                    vec![ParsedEvent::Ignore],
+                ),
+                ("", vec![ParsedEvent::Ignore]),
+                ("Loading MCP servers...", vec![ParsedEvent::Ignore]),
+                (
+                    "{not json}",
+                    vec![ParsedEvent::Line("{not json}".to_string())],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_codex_parser() {
+        assert_parses(
+            PmChatAgent::CodexCli,
+            vec![
+                (
+                    r#"{"type":"item.completed","item":{"id":"item_0","type":"agent_message","text":"Done."}}"#,
+                    vec![content("Done.")],
+                ),
+                (
+                    r#"{"type":"item.completed","item":{"id":"item_1","type":"reasoning","text":"Thinking"}}"#,
+                    vec![ParsedEvent::Ignore],
+                ),
+                (
+                    r#"{"type":"item.started","item":{"id":"item_2","type":"mcp_tool_call","server":"vibe_kanban","tool":"create_task","arguments":{"title":"Dup"}}}"#,
+                    vec![ParsedEvent::ToolUse(AgentToolEvent::Call {
+                        id: Some("item_2".to_string()),
+                        name: "create_task".to_string(),
+                        input: Some(serde_json::json!({"title":"Dup"})),
+                    })],
+                ),
+                (
+                    r#"{"type":"item.completed","item":{"id":"item_2","type":"mcp_tool_call","server":"vibe_kanban","tool":"create_task","status":"failed"}}"#,
+                    vec![ParsedEvent::ToolUse(AgentToolEvent::Result {
+                        id: Some("item_2".to_string()),
+                        name: Some("create_task".to_string()),
+                        success: false,
+                        output: None,
+                    })],
+                ),
+                (
+                    r#"{"type":"turn.completed","usage":{"input_tokens":900,"cached_input_tokens":800,"output_tokens":25}}"#,
+                    vec![usage(900, 25)],
+                ),
+                (r#"{"type":"turn.completed"}"#, vec![ParsedEvent::Ignore]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_gemini_parser() {
+        assert_parses(
+            PmChatAgent::GeminiCli,
+            vec![
+                (
+                    r#"{"type":"message","role":"assistant","content":"Hel","delta":true}"#,
+                    vec![ParsedEvent::Content(AgentText {
+                        text: "Hel".to_string(),
+                        delta: true,
+                    })],
+                ),
+                (
+                    r#"{"type":"message","role":"assistant","content":"Hello"}"#,
+                    vec![content("Hello")],
+                ),
+                (
+                    r#"{"type":"message","role":"user","content":"Hi"}"#,
+                    vec![ParsedEvent::Ignore],
+                ),
+                (
+                    r#"{"type":"tool_use","tool_name":"list_projects","tool_id":"t1"}"#,
+                    vec![ParsedEvent::ToolUse(AgentToolEvent::Call {
+                        id: Some("t1".to_string()),
+                        name: "list_projects".to_string(),
+                        input: None,
+                    })],
+                ),
+                (
+                    r#"{"type":"tool_result","tool_id":"t1","status":"success","output":"[]"}"#,
+                    vec![ParsedEvent::ToolUse(AgentToolEvent::Result {
+                        id: Some("t1".to_string()),
+                        name: None,
+                        success: true,
+                        output: Some("[]".to_string()),
+                    })],
+                ),
+                (
+                    r#"{"type":"result","status":"success","stats":{"total_tokens":70,"input_tokens":50,"output_tokens":20}}"#,
+                    vec![usage(50, 20)],
+                ),
+                (
+                    r#"{"type":"result","status":"success","stats":{"total_tokens":10}}"#,
+                    vec![ParsedEvent::Ignore],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_opencode_parser() {
+        assert_parses(
+            PmChatAgent::OpencodeCli,
+            vec![
+                (
+                    r#"{"type":"text","part":{"type":"text","text":"All set."}}"#,
+                    vec![content("All set.")],
+                ),
+                (
+                    r#"{"type":"tool_use","part":{"tool":"vibe_kanban_list_tasks","callID":"c1","state":{"status":"running","input":{}}}}"#,
+                    vec![ParsedEvent::ToolUse(AgentToolEvent::Call {
+                        id: Some("c1".to_string()),
+                        name: "vibe_kanban_list_tasks".to_string(),
+                        input: Some(serde_json::json!({})),
+                    })],
+                ),
+                (
+                    r#"{"type":"tool_use","part":{"tool":"vibe_kanban_list_tasks","callID":"c1","state":{"status":"error","output":"boom"}}}"#,
+                    vec![
+                        ParsedEvent::ToolUse(AgentToolEvent::Call {
+                            id: Some("c1".to_string()),
+                            name: "vibe_kanban_list_tasks".to_string(),
+                            input: None,
+                        }),
+                        ParsedEvent::ToolUse(AgentToolEvent::Result {
+                            id: Some("c1".to_string()),
+                            name: Some("vibe_kanban_list_tasks".to_string()),
+                            success: false,
+                            output: Some("boom".to_string()),
+                        }),
+                    ],
+                ),
+                (
+                    r#"{"type":"step_finish","part":{"type":"step-finish","tokens":{"input":120,"output":8,"reasoning":0}}}"#,
+                    vec![usage(120, 8)],
+                ),
+                (r#"{"type":"step_start"}"#, vec![ParsedEvent::Ignore]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_plain_text_parser_keeps_every_line() {
+        assert_parses(
+            PmChatAgent::OllamaCli,
+            vec![
+                ("", vec![ParsedEvent::Line(String::new())]),
+                (
+                    r#"{"type":"result"}"#,
+                    vec![ParsedEvent::Line(r#"{"type":"result"}"#.to_string())],
+                ),
+            ],
+        );
+    }
+
+    fn tracked_events(
+        agent: PmChatAgent,
+        tracker: &mut ToolCallTracker,
+        line: serde_json::Value,
+    ) -> Vec<String> {
+        agent
+            .stream_parser()
+            .parse_line(&line.to_string())
+            .into_iter()
+            .filter_map(|parsed| match parsed {
+                ParsedEvent::ToolUse(event) => tracker.handle(event),
+                _ => None,
+            })
+            .map(|event| serde_json::to_string(&event).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_tool_tracker_emits_tool_use_and_task_created_for_claude() {
+        let mut tracker = ToolCallTracker::default();
+
+        let call = serde_json::json!({"type":"assistant","message":{"content":[
+            {"type":"tool_use","id":"toolu_1","name":"mcp__vibe_kanban__create_task",
+             "input":{"project_id":"p","title":"Add login page"}}
+        ]}});
+        assert_eq!(
+            tracked_events(PmChatAgent::ClaudeCli, &mut tracker, call),
+            vec![r#"{"type":"tool_use","content":"create_task","error":null}"#]
+        );
+
+        let result = serde_json::json!({"type":"user","message":{"content":[
+            {"type":"tool_result","tool_use_id":"toolu_1","content":[
+                {"type":"text","text":"{\"task_id\": \"abc\", \"is_new\": true}"}
+            ]}
+        ]}});
+        assert_eq!(
+            tracked_events(PmChatAgent::ClaudeCli, &mut tracker, result),
+            vec![
+                r#"{"type":"task_created","content":"Add login page","error":null,"task_id":"abc","task_title":"Add login page"}"#
+            ]
+        );
+        assert_eq!(tracker.tasks_created, 1);
+    }
+
+    #[test]
+    fn test_tool_tracker_ignores_duplicates_and_failed_calls() {
+        let mut tracker = ToolCallTracker::default();
+
+        // Codex: start + completed with an existing duplicate task
+        let started = serde_json::json!({"type":"item.started","item":{
+            "id":"item_1","type":"mcp_tool_call","tool":"create_task","arguments":{"title":"Dup"}
+        }});
+        let completed = serde_json::json!({"type":"item.completed","item":{
+            "id":"item_1","type":"mcp_tool_call","tool":"create_task","status":"completed",
+            "result":{"content":[{"type":"text","text":"{\"task_id\":\"t1\",\"is_new\":false}"}]}
+        }});
+        assert_eq!(
+            tracked_events(PmChatAgent::CodexCli, &mut tracker, started).len(),
+            1
+        );
+        assert!(tracked_events(PmChatAgent::CodexCli, &mut tracker, completed).is_empty());
+
+        // Gemini: failed create_task
+        let failed = serde_json::json!({"type":"tool_result","tool_id":"g1","tool_name":"create_task",
+            "status":"error","output":"boom"});
+        assert!(tracked_events(PmChatAgent::GeminiCli, &mut tracker, failed).is_empty());
+
+        // OpenCode repeats the call as its state changes
+        let running = serde_json::json!({"type":"tool_use","part":{
+            "tool":"vibe_kanban_list_tasks","callID":"c1","state":{"status":"running"}
+        }});
+        assert_eq!(
+            tracked_events(PmChatAgent::OpencodeCli, &mut tracker, running.clone()).len(),
+            1
+        );
+        assert!(tracked_events(PmChatAgent::OpencodeCli, &mut tracker, running).is_empty());
+
+        assert_eq!(tracker.tasks_created, 0);
+    }
+
+    #[test]
+    fn test_tool_tracker_ignores_dry_run_of_create_task() {
+        let mut tracker = ToolCallTracker::default();
+
+        let call = serde_json::json!({"type":"assistant","message":{"content":[
+            {"type":"tool_use","id":"toolu_1","name":"mcp__vibe_kanban__create_task",
+             "input":{"project_id":"p","title":"Add login page","dry_run":true}}
+        ]}});
+        tracked_events(PmChatAgent::ClaudeCli, &mut tracker, call);
+
+        let output = serde_json::json!({
+            "task_id": "",
+            "is_new": true,
+            "title": "Add login page",
+            "preview": {"title": "Add login page"},
+        });
+        let result = serde_json::json!({"type":"user","message":{"content":[
+            {"type":"tool_result","tool_use_id":"toolu_1","content":[
+                {"type":"text","text":output.to_string()}
+            ]}
+        ]}});
+        assert!(tracked_events(PmChatAgent::ClaudeCli, &mut tracker, result).is_empty());
+        assert_eq!(tracker.tasks_created, 0);
+    }
+
+    #[test]
+    fn test_tool_tracker_emits_docs_updated_for_successful_update() {
+        let mut tracker = ToolCallTracker::default();
+
+        let call = serde_json::json!({"type":"tool_use","tool_name":"update_pm_docs","tool_id":"g1",
+            "parameters":{"project_id":"p","content":"# Spec","mode":"append"}});
+        tracked_events(PmChatAgent::GeminiCli, &mut tracker, call);
+
+        let result = serde_json::json!({"type":"tool_result","tool_id":"g1","status":"success",
+            "output":"{}"});
+        assert_eq!(
+            tracked_events(PmChatAgent::GeminiCli, &mut tracker, result),
+            vec![r#"{"type":"docs_updated","content":null,"error":null}"#]
+        );
+
+        let failed = serde_json::json!({"type":"tool_result","tool_id":"g2",
+            "tool_name":"update_pm_docs","status":"error","output":"boom"});
+        assert!(tracked_events(PmChatAgent::GeminiCli, &mut tracker, failed).is_empty());
+    }
+
+    async fn multipart(parts: &[(&str, Option<&str>, &str)]) -> Multipart {
+        use axum::extract::FromRequest;
+
+        let boundary = "attachment-boundary";
+        let mut body = String::new();
+        for (name, file_name, content) in parts {
+            body.push_str(&format!("--{}\r\n", boundary));
+            match file_name {
+                Some(file_name) => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\r\n",
+                    name, file_name
+                )),
+                None => body.push_str(&format!(
+                    "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                    name
+                )),
+            }
+            body.push_str(&format!("{}\r\n", content));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        Multipart::from_request(request, &()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_read_attachment_upload_keeps_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let conversation_id = Uuid::new_v4();
+        let upload = read_attachment_upload(
+            multipart(&[
+                ("file", Some("a.txt"), "first"),
+                ("conversation_id", None, &conversation_id.to_string()),
+                ("file[]", Some("b.md"), "second"),
+                ("file", Some("c.txt"), "%PDF-1.7"),
+                ("other", None, "ignored"),
+            ])
+            .await,
+            dir.path(),
+            &PmAttachmentPolicy::default(),
+        )
+        .await
+        .unwrap();
+
+        let files: Vec<_> = upload
+            .files
+            .iter()
+            .map(|file| {
+                (
+                    file.original_filename.as_str(),
+                    file.mime_type.as_str(),
+                    std::fs::read_to_string(file.upload.path()).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("a.txt", "text/plain", "first".to_string()),
+                ("b.md", "text/markdown", "second".to_string()),
+                ("c.txt", "application/pdf", "%PDF-1.7".to_string()),
+            ]
+        );
+        assert_eq!(upload.conversation_id, Some(conversation_id));
+    }
+
+    #[tokio::test]
+    async fn test_read_attachment_upload_rejects_invalid_conversation_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = read_attachment_upload(
+            multipart(&[
+                ("file", Some("a.txt"), "first"),
+                ("conversation_id", None, "not-a-uuid"),
+            ])
+            .await,
+            dir.path(),
+            &PmAttachmentPolicy::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+        // The file streamed before the bad field is not left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_attachment_upload_rejects_policy_violation_while_streaming() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = read_attachment_upload(
+            multipart(&[
+                ("file", Some("a.txt"), "first"),
+                ("file", Some("notes.txt"), "#!/bin/sh\nrm -rf ~"),
+            ])
+            .await,
+            dir.path(),
+            &PmAttachmentPolicy {
+                reject_shebang_scripts: true,
+                ..PmAttachmentPolicy::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::BadRequest(msg)) if msg.starts_with("notes.txt:")));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_attachment_upload_rejects_oversized_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = PmAttachmentPolicy {
+            max_size_bytes: 1024,
+            max_size_bytes_by_mime_prefix: Default::default(),
+            ..PmAttachmentPolicy::default()
+        };
+        let content = "x".repeat(64 * 1024);
+        let result = read_attachment_upload(
+            multipart(&[("file", Some("big.txt"), &content)]).await,
+            dir.path(),
+            &policy,
+        )
+        .await;
+        assert!(
+            matches!(result, Err(ApiError::PayloadTooLarge(msg)) if msg.starts_with("big.txt:"))
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_attachment_upload_rejects_files_over_total_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = PmAttachmentPolicy {
+            max_upload_bytes: 8,
+            ..PmAttachmentPolicy::default()
+        };
+        let result = read_attachment_upload(
+            multipart(&[
+                ("file", Some("a.txt"), "first"),
+                ("file", Some("b.txt"), "second"),
+            ])
+            .await,
+            dir.path(),
+            &policy,
+        )
+        .await;
+        assert!(
+            matches!(result, Err(ApiError::PayloadTooLarge(msg)) if msg.starts_with("Upload too large"))
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_task_title_from_message_uses_first_line() {
+        assert_eq!(
+            task_title_from_message("\n## Add login page\n\nDetails follow").as_deref(),
+            Some("Add login page")
+        );
+        assert_eq!(
+            task_title_from_message("- **Fix** the header").as_deref(),
+            Some("**Fix** the header")
+        );
+        assert_eq!(
+            task_title_from_message("2. Write the migration").as_deref(),
+            Some("Write the migration")
+        );
+        assert_eq!(task_title_from_message(" \n- \n"), None);
+    }
+
+    #[test]
+    fn test_task_title_from_message_truncates_long_lines() {
+        let title = task_title_from_message(&"タスク".repeat(40)).unwrap();
+        assert_eq!(title.chars().count(), MESSAGE_TASK_TITLE_MAX_CHARS);
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn test_render_docs_html_renders_markdown() {
+        let html = render_docs_html("## Spec\n\n- **bold** item\n\n| a |\n|---|\n| 1 |\n");
+        assert!(html.contains("<h2>Spec</h2>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains("<table>"));
+    }
+
+    #[test]
+    fn test_render_docs_html_strips_script_injection() {
+        let html = render_docs_html(concat!(
+            "Hello <script>alert('xss')</script>\n\n",
+            "<img src=\"x.png\" onerror=\"alert(1)\">\n\n",
+            "[click](javascript:alert(1))\n\n",
+            "<iframe src=\"https://example.com\"></iframe>\n",
         ));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert('xss')"));
+        assert!(!html.contains("onerror"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("<iframe"));
+        assert!(html.contains("Hello"));
+        assert!(html.contains("<img src=\"x.png\">"));
+    }
+
+    #[test]
+    fn test_replace_docs_section_keeps_following_sections() {
+        let docs = "# Project\n\n## Spec\nOld\n### Detail\nOld detail\n\n## Notes\nKeep me\n";
+
+        assert_eq!(
+            replace_docs_section(Some(docs), "Spec", "New\n"),
+            "# Project\n\n## Spec\nNew\n\n## Notes\nKeep me\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_docs_section_replaces_last_section() {
+        let docs = "## Notes\nKeep me\n\n## Spec\nOld\n";
+
+        assert_eq!(
+            replace_docs_section(Some(docs), "Spec", "New"),
+            "## Notes\nKeep me\n\n## Spec\nNew\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_docs_section_appends_missing_section() {
+        assert_eq!(
+            replace_docs_section(Some("# Project\n\n"), "Spec", "New"),
+            "# Project\n\n## Spec\nNew\n"
+        );
+        assert_eq!(replace_docs_section(None, "Spec", "New"), "## Spec\nNew\n");
+
+        // A deeper heading with the same title is not the section
+        let docs = "### Spec\nDetail\n";
+        assert_eq!(
+            replace_docs_section(Some(docs), "Spec", "New"),
+            "### Spec\nDetail\n\n## Spec\nNew\n"
+        );
+    }
+
+    fn summary_task(
+        id: &str,
+        title: &str,
+        status: &str,
+        priority: &str,
+        depends_on: &[&str],
+        depended_by: &[&str],
+    ) -> TaskWithDependencies {
+        TaskWithDependencies {
+            id: id.to_string(),
+            title: title.to_string(),
+            description: None,
+            status: status.to_string(),
+            priority: priority.to_string(),
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            depended_by: depended_by.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn summary_fixture() -> Vec<TaskWithDependencies> {
+        vec![
+            summary_task("a", "Design", "inprogress", "high", &[], &["b"]),
+            summary_task("b", "Build", "todo", "medium", &["a"], &[]),
+        ]
+    }
+
+    fn summary_updated_at() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-01-31T09:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_task_summary_sync_twice_keeps_one_block() {
+        let docs = "# Project\n\n## Tasks and dependencies\nHand-written\n\n## Notes\nKeep me\n";
+        let block =
+            |summary| task_summary_docs_block(summary, TaskSummaryLocale::En, summary_updated_at());
+
+        let first = replace_task_summary_block(Some(docs), &block("- 🟡 First\n"));
+        let second = replace_task_summary_block(Some(&first), &block("- 🟡 Second\n"));
+
+        assert_eq!(second.matches(TASK_SUMMARY_START_MARKER).count(), 1);
+        assert_eq!(second.matches(TASK_SUMMARY_END_MARKER).count(), 1);
+        assert!(!second.contains("First"));
+        assert!(second.starts_with(docs.trim_end()));
+        assert_eq!(
+            replace_task_summary_block(Some(&second), &block("- 🟡 Second\n")),
+            second
+        );
+    }
+
+    #[test]
+    fn test_task_summary_replaces_legacy_heading_section() {
+        let docs = format!(
+            "# Project\n\n## {}\n- old\n\n## Notes\nKeep me\n",
+            TASK_SUMMARY_SECTION
+        );
+
+        let summary = format!("## {}\n- new", TASK_SUMMARY_SECTION);
+        let block = task_summary_docs_block(&summary, TaskSummaryLocale::Ja, summary_updated_at());
+        let synced = replace_task_summary_block(Some(&docs), &block);
+
+        assert_eq!(
+            synced,
+            format!(
+                "# Project\n\n{}\n## {}\n- new\n\n*最終更新: 2026-01-31 09:30 UTC*\n{}\n\n\
+                 ## Notes\nKeep me\n",
+                TASK_SUMMARY_START_MARKER, TASK_SUMMARY_SECTION, TASK_SUMMARY_END_MARKER
+            )
+        );
+    }
+
+    #[test]
+    fn test_task_summary_in_english() {
+        let summary = format_task_summary(&summary_fixture(), TaskSummaryLocale::En);
+
+        assert_eq!(
+            summary,
+            "## Tasks and dependencies\n\n\
+             ### 📋 Todo\n\n\
+             - 🟡 **Build**\n  - ⬅️ Depends on: Design\n\n\
+             ### 🔄 In Progress\n\n\
+             - 🟠 **Design**\n  - ➡️ Blocking: Build\n\n\
+             ### ⚠️ Blocked tasks\n\n\
+             - **Build** is waiting on: Design\n"
+        );
+    }
+
+    #[test]
+    fn test_task_summary_defaults_to_japanese() {
+        let summary = format_task_summary(&summary_fixture(), TaskSummaryLocale::default());
+
+        assert_eq!(
+            summary,
+            "## タスク一覧と依存関係\n\n\
+             ### 📋 未着手 (Todo)\n\n\
+             - 🟡 **Build**\n  - ⬅️ 依存: Design\n\n\
+             ### 🔄 進行中 (In Progress)\n\n\
+             - 🟠 **Design**\n  - ➡️ ブロック中: Build\n\n\
+             ### ⚠️ ブロックされているタスク\n\n\
+             - **Build** は以下の完了待ち: Design\n"
+        );
+    }
+
+    #[test]
+    fn test_task_summary_endpoint_text_matches_synced_docs() {
+        for locale in [TaskSummaryLocale::En, TaskSummaryLocale::Ja] {
+            let summary_text = format_task_summary(&summary_fixture(), locale);
+            let block = task_summary_docs_block(&summary_text, locale, summary_updated_at());
+            let docs = replace_task_summary_block(Some("# Project\n"), &block);
+
+            assert!(docs.contains(&summary_text), "{docs}");
+        }
+    }
+
+    #[test]
+    fn test_group_dependencies_for_large_project() {
+        // A chain of 200 tasks, each depending on the one before it
+        let ids: Vec<Uuid> = (0..200).map(|_| Uuid::new_v4()).collect();
+        let pairs: Vec<(Uuid, Uuid)> = ids.windows(2).map(|w| (w[1], w[0])).collect();
+
+        let (dependencies, dependents) = group_dependencies(&pairs);
+
+        assert_eq!(dependencies.len(), 199);
+        assert_eq!(dependents.len(), 199);
+        assert!(!dependencies.contains_key(&ids[0]));
+        assert!(!dependents.contains_key(&ids[199]));
+        assert_eq!(dependencies[&ids[100]], vec![ids[99]]);
+        assert_eq!(dependents[&ids[100]], vec![ids[101]]);
+    }
+
+    #[test]
+    fn test_pm_docs_diff() {
+        assert_eq!(pm_docs_diff(Some("# Spec\n"), Some("# Spec\n")), None);
+        assert_eq!(pm_docs_diff(None, Some("")), None);
+
+        let diff = pm_docs_diff(Some("# Spec\nOld goal\n"), Some("# Spec\nNew goal\n")).unwrap();
+        assert!(diff.contains("-Old goal"));
+        assert!(diff.contains("+New goal"));
+        assert!(diff.contains(PM_DOCS_DIFF_PATH));
+    }
+
+    #[test]
+    fn test_tool_base_name_strips_mcp_prefixes() {
+        assert_eq!(
+            tool_base_name("mcp__vibe_kanban__list_projects"),
+            "list_projects"
+        );
+        assert_eq!(tool_base_name("vibe_kanban_list_projects"), "list_projects");
+        assert_eq!(tool_base_name("list_projects"), "list_projects");
+    }
+
+    /// Write an executable shell script that stands in for an agent CLI
+    #[cfg(unix)]
+    fn write_stub_cli(body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("vibe-pm-selftest-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("claude");
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    fn stub_command(cli: &std::path::Path) -> Command {
+        let config_path = cli.with_file_name("mcp.json");
+        build_agent_command(
+            PmChatAgent::ClaudeCli,
+            cli,
+            false,
+            &config_path,
+            "sonnet",
+            SELFTEST_SYSTEM_PROMPT,
+            SELFTEST_PROMPT,
+        )
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_selftest_with_stub_cli_observes_tool_call() {
+        let cli = write_stub_cli(
+            r#"cat <<'JSON'
+{"type":"system","subtype":"init"}
+{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_1","name":"mcp__vibe_kanban__list_projects","input":{}}]}}
+{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"[]"}]}}
+{"type":"assistant","message":{"content":[{"type":"text","text":"OK"}]}}
+{"type":"result","result":"OK"}
+JSON
+echo "mcp server started" >&2"#,
+        );
+
+        let report = run_agent_selftest(
+            stub_command(&cli),
+            PmChatAgent::ClaudeCli,
+            Duration::from_secs(10),
+        )
+        .await;
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        assert!(report.spawned);
+        assert!(report.streamed_output);
+        assert!(report.tool_call_observed);
+        assert!(report.tool_call_succeeded);
+        assert!(report.exit_success);
+        assert!(!report.timed_out);
+        assert!(report.passed);
+        assert_eq!(report.stderr_tail, vec!["mcp server started".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_selftest_with_stub_cli_reports_missing_tool_call() {
+        let cli = write_stub_cli(
+            r#"echo '{"type":"assistant","message":{"content":[{"type":"text","text":"no tools"}]}}'
+exit 3"#,
+        );
+
+        let report = run_agent_selftest(
+            stub_command(&cli),
+            PmChatAgent::ClaudeCli,
+            Duration::from_secs(10),
+        )
+        .await;
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        assert!(report.spawned);
+        assert!(report.streamed_output);
+        assert!(!report.tool_call_observed);
+        assert_eq!(report.exit_code, Some(3));
+        assert!(!report.passed);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_selftest_kills_cli_after_timeout() {
+        let cli = write_stub_cli("exec sleep 30");
+
+        let report = run_agent_selftest(
+            stub_command(&cli),
+            PmChatAgent::ClaudeCli,
+            Duration::from_millis(200),
+        )
+        .await;
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        assert!(report.spawned);
+        assert!(report.timed_out);
+        assert!(!report.passed);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cli_chat_events_reads_stderr_while_stdout_is_open() {
+        // Enough stderr to fill the pipe buffer before stdout is closed
+        let cli = write_stub_cli(
+            r#"i=0
+while [ $i -lt 2000 ]; do
+  echo "loading mcp server, attempt $i of a fairly long stderr log line" >&2
+  i=$((i+1))
+done
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"hello"}]}}'
+echo "ERROR: tool call failed" >&2
+echo '{"type":"result","result":"hello"}'"#,
+        );
+        let config_path = cli.with_file_name("mcp.json");
+        fs::write(&config_path, "{}").unwrap();
+        let child = Command::new(&cli)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pool = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+
+        let events = cli_chat_events(
+            child,
+            config_path,
+            PmChatAgent::ClaudeCli,
+            cli_chat_timeout(&PmChatSettings::default()),
+            "sonnet".to_string(),
+            pool,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            test_stream_span(),
+        );
+        let events: Vec<AiChatStreamEvent> =
+            tokio::time::timeout(Duration::from_secs(10), events.collect())
+                .await
+                .expect("CLI output stream deadlocked");
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        let position = |event_type: &str, content: &str| {
+            events
+                .iter()
+                .position(|e| e.event_type == event_type && e.content.as_deref() == Some(content))
+        };
+        let debug_line = position("debug", "ERROR: tool call failed").unwrap();
+        let content = position("content", "hello").unwrap();
+        let done = events.iter().position(|e| e.event_type == "done").unwrap();
+
+        assert_eq!(done, events.len() - 1);
+        assert!(debug_line < done);
+        assert!(content < done);
+        assert_eq!(
+            events.iter().filter(|e| e.event_type == "debug").count(),
+            2001
+        );
+        // Stderr of a successful run is not reported as an error
+        assert!(events.iter().all(|e| e.event_type != "error"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cli_chat_events_summarizes_stderr_of_failed_cli() {
+        let cli = write_stub_cli(
+            r#"i=0
+while [ $i -lt 50 ]; do
+  echo "debug: starting mcp server $i" >&2
+  i=$((i+1))
+done
+echo "Error: invalid API key" >&2
+exit 3"#,
+        );
+        let config_path = cli.with_file_name("mcp.json");
+        fs::write(&config_path, "{}").unwrap();
+        let child = Command::new(&cli)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pool = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+
+        let events = cli_chat_events(
+            child,
+            config_path,
+            PmChatAgent::ClaudeCli,
+            cli_chat_timeout(&PmChatSettings::default()),
+            "sonnet".to_string(),
+            pool,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            test_stream_span(),
+        );
+        let events: Vec<AiChatStreamEvent> =
+            tokio::time::timeout(Duration::from_secs(10), events.collect())
+                .await
+                .expect("CLI output stream deadlocked");
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        let errors: Vec<_> = events.iter().filter_map(|e| e.error.as_deref()).collect();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("CLI exited with status: exit status: 3\n"));
+        assert!(errors[0].ends_with("starting mcp server 49\nError: invalid API key"));
+        assert!(!errors[0].contains("server 47"));
+        assert_eq!(events.last().unwrap().event_type, "done");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cli_chat_events_streams_plain_text_from_stdin_prompt() {
+        // Echo the prompt back like a model would answer it
+        let cli = write_stub_cli(
+            r#"prompt=$(cat)
+echo "{not json}"
+echo
+echo "you said: $prompt""#,
+        );
+        let config_path = cli.with_file_name("mcp.json");
+        fs::write(&config_path, "{}").unwrap();
+        let mut child = Command::new(&cli)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        feed_agent_stdin(&mut child, agent_stdin_prompt("system", "hi"));
+        let pool = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+
+        let events = cli_chat_events(
+            child,
+            config_path,
+            PmChatAgent::OllamaCli,
+            cli_chat_timeout(&PmChatSettings::default()),
+            DEFAULT_OLLAMA_MODEL.to_string(),
+            pool,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            test_stream_span(),
+        );
+        let events: Vec<AiChatStreamEvent> =
+            tokio::time::timeout(Duration::from_secs(10), events.collect())
+                .await
+                .expect("CLI output stream deadlocked");
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        let content: String = events
+            .iter()
+            .filter(|e| e.event_type == "content")
+            .filter_map(|e| e.content.as_deref())
+            .collect();
+        assert_eq!(content, "{not json}\n\nyou said: system\n\nhi\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cli_chat_events_kills_cli_after_timeout() {
+        let cli = write_stub_cli(
+            r#"echo '{"type":"assistant","message":{"content":[{"type":"text","text":"partial"}]}}'
+exec sleep 30"#,
+        );
+        let config_path = cli.with_file_name("mcp.json");
+        fs::write(&config_path, "{}").unwrap();
+        let child = Command::new(&cli)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        let pool = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+
+        let events = cli_chat_events(
+            child,
+            config_path.clone(),
+            PmChatAgent::ClaudeCli,
+            Duration::from_secs(1),
+            "sonnet".to_string(),
+            pool,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            test_stream_span(),
+        );
+        let events: Vec<AiChatStreamEvent> =
+            tokio::time::timeout(Duration::from_secs(10), events.collect())
+                .await
+                .expect("timeout did not fire");
+        let config_removed = !config_path.exists();
+        let _ = fs::remove_dir_all(cli.parent().unwrap());
+
+        assert!(
+            events
+                .iter()
+                .any(|e| e.event_type == "content" && e.content.as_deref() == Some("partial"))
+        );
+        let last_two: Vec<&str> = events[events.len() - 2..]
+            .iter()
+            .map(|e| e.event_type.as_str())
+            .collect();
+        assert_eq!(last_two, vec!["error", "done"]);
+        assert_eq!(
+            events[events.len() - 2].error.as_deref(),
+            Some("CLI timed out after 1s")
+        );
+        assert!(config_removed);
+
+        // The process was killed and reaped, so signalling it fails
+        let alive = std::process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .status()
+            .unwrap()
+            .success();
+        assert!(!alive);
+    }
+
+    #[test]
+    fn test_ollama_agent_has_no_tools() {
+        assert!(!PmChatAgent::OllamaCli.supports_tools());
+        assert!(PmChatAgent::ClaudeCli.supports_tools());
+        assert_eq!(PmChatAgent::OllamaCli.command_name(), "ollama");
+        assert_eq!(PmChatAgent::OllamaCli.default_model(), DEFAULT_OLLAMA_MODEL);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_config_mode_per_agent() {
+        assert_eq!(
+            PmChatAgent::ClaudeCli.mcp_config_mode(),
+            McpConfigMode::PerRun
+        );
+        for agent in [
+            PmChatAgent::CodexCli,
+            PmChatAgent::GeminiCli,
+            PmChatAgent::OpencodeCli,
+        ] {
+            assert_eq!(agent.mcp_config_mode(), McpConfigMode::Preconfigured);
+            assert!(agent.supports_tools());
+        }
+
+        assert!(PmChatAgent::ClaudeCli.tools_configured().await);
+        assert!(!PmChatAgent::OllamaCli.tools_configured().await);
+    }
+
+    #[test]
+    fn test_parse_ollama_list() {
+        let output = "NAME               ID              SIZE      MODIFIED\n\
+                      llama3.2:latest    a80c4f17acd5    2.0 GB    2 weeks ago\n\
+                      qwen2.5:7b         845dbda0ea48    4.7 GB    3 days ago\n";
+
+        assert_eq!(
+            parse_ollama_list(output),
+            vec![
+                ModelInfo::new("llama3.2:latest", "llama3.2"),
+                ModelInfo::new("qwen2.5:7b", "qwen2.5:7b"),
+            ]
+        );
+        assert!(parse_ollama_list("NAME ID SIZE MODIFIED\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_agent_path_accepts_kebab_case() {
+        assert_eq!(
+            parse_agent_path("ollama-cli").unwrap(),
+            PmChatAgent::OllamaCli
+        );
+        assert!(parse_agent_path("unknown").is_err());
+    }
+
+    #[test]
+    fn test_parse_cli_version() {
+        assert_eq!(
+            parse_cli_version("1.0.51 (Claude Code)\n").as_deref(),
+            Some("1.0.51")
+        );
+        assert_eq!(
+            parse_cli_version("codex-cli 0.46.0").as_deref(),
+            Some("0.46.0")
+        );
+        assert_eq!(
+            parse_cli_version("\nollama version is 0.3.12\n").as_deref(),
+            Some("0.3.12")
+        );
+        assert_eq!(
+            parse_cli_version("0.2.0-nightly.20250101").as_deref(),
+            Some("0.2.0-nightly.20250101")
+        );
+        assert_eq!(parse_cli_version("dev build").as_deref(), Some("dev build"));
+        assert_eq!(parse_cli_version("  \n"), None);
+    }
+
+    fn attachment_named(file_name: &str) -> PmAttachment {
+        PmAttachment {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            project_id: Uuid::new_v4(),
+            file_name: file_name.to_string(),
+            file_path: format!("{}_{}", Uuid::new_v4(), file_name),
+            mime_type: "image/png".to_string(),
+            file_size: 3,
+            sha256: None,
+            width: None,
+            height: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_archive_entry_names_suffix_collisions_with_id() {
+        let attachments = vec![
+            attachment_named("shot.png"),
+            attachment_named("Shot.PNG"),
+            attachment_named("notes.txt"),
+            attachment_named("MANIFEST.txt"),
+            attachment_named("../secret"),
+        ];
+        let names = archive_entry_names(&attachments);
+
+        assert_eq!(names[0], format!("shot-{}.png", attachments[0].id));
+        assert_eq!(names[1], format!("Shot-{}.PNG", attachments[1].id));
+        assert_eq!(names[2], "notes.txt");
+        assert_eq!(names[3], format!("MANIFEST-{}.txt", attachments[3].id));
+        assert_eq!(names[4], "secret");
+    }
+
+    #[test]
+    fn test_nest_attachments_groups_by_message() {
+        let message = |content: &str| PmConversation {
+            id: Uuid::new_v4(),
+            project_id: Uuid::new_v4(),
+            role: "user".to_string(),
+            content: content.to_string(),
+            model: None,
+            pinned: false,
+            edited: false,
+            input_tokens: None,
+            output_tokens: None,
+            archived_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let (first, second) = (message("first"), message("second"));
+        let mut a = attachment_named("a.png");
+        a.conversation_id = second.id;
+        let mut b = attachment_named("b.png");
+        b.conversation_id = second.id;
+
+        let nested = nest_attachments(vec![first, second], &[a.clone(), b.clone()]);
+
+        assert!(nested[0].attachments.is_empty());
+        let ids: Vec<Uuid> = nested[1].attachments.iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![a.id, b.id]);
+    }
+
+    #[test]
+    fn test_archive_file_name_is_ascii() {
+        assert_eq!(
+            archive_file_name("My Project"),
+            "My_Project-attachments.zip"
+        );
+        assert_eq!(archive_file_name("\"計画\""), "project-attachments.zip");
+    }
+
+    #[tokio::test]
+    async fn test_write_attachments_archive_streams_files_and_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.txt");
+        let image = dir.path().join("image.png");
+        fs::write(&notes, "meeting notes").unwrap();
+        fs::write(&image, vec![7u8; 200 * 1024]).unwrap();
+
+        let mut archive = Vec::new();
+        write_attachments_archive(
+            &mut archive,
+            vec![
+                ("notes.txt".to_string(), notes),
+                ("image.png".to_string(), image),
+            ],
+            b"Attachments for Demo\n".to_vec(),
+        )
+        .await
+        .unwrap();
+
+        let reader = async_zip::base::read::mem::ZipFileReader::new(archive)
+            .await
+            .unwrap();
+        let names: Vec<String> = reader
+            .file()
+            .entries()
+            .iter()
+            .map(|entry| entry.filename().as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["notes.txt", "image.png", ARCHIVE_MANIFEST_NAME]);
+
+        let mut contents = Vec::new();
+        for index in 0..names.len() {
+            let mut data = Vec::new();
+            reader
+                .reader_with_entry(index)
+                .await
+                .unwrap()
+                .read_to_end_checked(&mut data)
+                .await
+                .unwrap();
+            contents.push(data);
+        }
+        assert_eq!(contents[0], b"meeting notes");
+        assert_eq!(contents[1], vec![7u8; 200 * 1024]);
+        assert_eq!(contents[2], b"Attachments for Demo\n");
     }
 
-    // Delete the file from disk
-    let attachments_dir = get_pm_attachments_dir();
-    let file_path = attachments_dir.join(&attachment.file_path);
-    if file_path.exists() {
-        fs::remove_file(file_path).ok();
+    #[test]
+    fn test_mcp_config_file_is_per_stream() {
+        let first = Uuid::new_v4().to_string();
+        let second = Uuid::new_v4().to_string();
+        let first_path =
+            write_mcp_config_file(PmChatAgent::ClaudeCli, "http://127.0.0.1:1", &first).unwrap();
+        let second_path =
+            write_mcp_config_file(PmChatAgent::ClaudeCli, "http://127.0.0.1:1", &second).unwrap();
+        let first_exists = first_path.exists();
+        let second_exists = second_path.exists();
+        let _ = fs::remove_file(&first_path);
+        let _ = fs::remove_file(&second_path);
+
+        assert_ne!(first_path, second_path);
+        assert!(first_exists && second_exists);
+        assert!(first_path.to_string_lossy().contains(&first));
     }
 
-    // Delete from database
-    PmAttachment::delete(&deployment.db().pool, attachment_id).await?;
-
-    deployment
-        .track_if_analytics_allowed(
-            "pm_attachment_deleted",
-            serde_json::json!({
-                "project_id": project.id.to_string(),
-                "attachment_id": attachment_id.to_string(),
-            }),
-        )
-        .await;
+    #[test]
+    fn test_cancel_ai_chat_stream_requires_matching_project() {
+        let project_id = Uuid::new_v4();
+        let stream_id = Uuid::new_v4();
+        let (cancel, _guard) =
+            register_ai_chat_stream(stream_id, project_id, PmChatAgent::ClaudeCli, "sonnet");
 
-    Ok(ResponseJson(ApiResponse::success(())))
-}
+        assert!(!cancel_ai_chat_stream(Uuid::new_v4(), stream_id));
+        assert!(!cancel.is_cancelled());
 
-/// Get PM docs for a project
-pub async fn get_pm_docs(
-    Extension(project): Extension<Project>,
-) -> Result<ResponseJson<ApiResponse<Option<String>>>, ApiError> {
-    Ok(ResponseJson(ApiResponse::success(project.pm_docs)))
-}
+        assert!(cancel_ai_chat_stream(project_id, stream_id));
+        assert!(cancel.is_cancelled());
+    }
 
-/// Update PM docs for a project
-pub async fn update_pm_docs(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-    Json(payload): Json<UpdatePmDocsRequest>,
-) -> Result<ResponseJson<ApiResponse<Project>>, ApiError> {
-    use db::models::project::UpdateProject;
+    #[test]
+    fn test_finished_ai_chat_stream_is_unregistered() {
+        let project_id = Uuid::new_v4();
+        let stream_id = Uuid::new_v4();
+        let (_cancel, guard) =
+            register_ai_chat_stream(stream_id, project_id, PmChatAgent::ClaudeCli, "sonnet");
+        drop(guard);
+
+        assert!(!cancel_ai_chat_stream(project_id, stream_id));
+        assert!(!cancel_ai_chat_stream(project_id, Uuid::new_v4()));
+        assert!(
+            !list_ai_chat_sessions()
+                .iter()
+                .any(|session| session.stream_id == stream_id)
+        );
+    }
 
-    let update_data = UpdateProject {
-        name: None,
-        pm_task_id: None,
-        pm_docs: payload.pm_docs,
-    };
+    #[test]
+    fn test_ai_chat_sessions_are_listed_and_killed() {
+        let project_id = Uuid::new_v4();
+        let stream_id = Uuid::new_v4();
+        let (cancel, _guard) =
+            register_ai_chat_stream(stream_id, project_id, PmChatAgent::ClaudeCli, "sonnet");
 
-    let updated_project =
-        db::models::project::Project::update(&deployment.db().pool, project.id, &update_data)
-            .await?;
+        let sessions = list_ai_chat_sessions();
+        let session = sessions
+            .iter()
+            .find(|session| session.stream_id == stream_id)
+            .unwrap();
+        assert_eq!(session.project_id, project_id);
+        assert_eq!(session.agent, PmChatAgent::ClaudeCli);
+        assert_eq!(session.model, "sonnet");
+
+        assert!(!kill_ai_chat_session(Uuid::new_v4()));
+        assert!(kill_ai_chat_session(stream_id));
+        assert!(cancel.is_cancelled());
+    }
 
-    deployment
-        .track_if_analytics_allowed(
-            "pm_docs_updated",
-            serde_json::json!({
-                "project_id": project.id.to_string(),
-            }),
-        )
-        .await;
+    #[tokio::test]
+    async fn test_ai_chat_limiter_rejects_stream_over_limit() {
+        let limiter = AiChatLimiter::default();
+        let project_id = Uuid::new_v4();
+        let mut streams: Vec<_> = (0..2)
+            .map(|_| {
+                let permit = limiter.try_acquire(project_id, 2).unwrap();
+                hold_ai_chat_permit(futures::stream::pending().boxed(), permit)
+            })
+            .collect();
 
-    Ok(ResponseJson(ApiResponse::success(updated_project)))
-}
+        assert!(limiter.try_acquire(project_id, 2).is_none());
+        assert!(limiter.try_acquire(Uuid::new_v4(), 2).is_some());
 
-/// Response for task summary with dependencies
-#[derive(Debug, Clone, Serialize, TS)]
-pub struct TaskWithDependencies {
-    pub id: String,
-    pub title: String,
-    pub description: Option<String>,
-    pub status: String,
-    pub priority: String,
-    pub depends_on: Vec<String>,  // Task IDs this task depends on
-    pub depended_by: Vec<String>, // Task IDs that depend on this task
-}
+        // A cancelled stream gives its slot back
+        streams.pop();
+        assert!(limiter.try_acquire(project_id, 2).is_some());
+    }
 
-#[derive(Debug, Clone, Serialize, TS)]
-pub struct TaskSummaryResponse {
-    pub tasks: Vec<TaskWithDependencies>,
-    pub summary_text: String, // Formatted text for PM docs
-}
+    #[test]
+    fn test_ai_chat_limiter_applies_changed_limit() {
+        let limiter = AiChatLimiter::default();
+        let project_id = Uuid::new_v4();
+        let _first = limiter.try_acquire(project_id, 1).unwrap();
+        assert!(limiter.try_acquire(project_id, 1).is_none());
+
+        // Raising the limit frees a slot without waiting for the running stream
+        let second = limiter.try_acquire(project_id, 3).unwrap();
+        assert!(limiter.try_acquire(project_id, 2).is_none());
+
+        // Lowering it keeps running streams but admits no new ones
+        drop(second);
+        assert!(limiter.try_acquire(project_id, 1).is_none());
+        assert!(limiter.try_acquire(project_id, 0).is_none());
+    }
 
-/// Get task summary with dependencies for PM context
-pub async fn get_task_summary(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-) -> Result<ResponseJson<ApiResponse<TaskSummaryResponse>>, ApiError> {
-    // Get all tasks for this project
-    let tasks_with_status =
-        Task::find_by_project_id_with_attempt_status(&deployment.db().pool, project.id).await?;
-    let tasks: Vec<Task> = tasks_with_status.iter().map(|t| t.task.clone()).collect();
+    /// A `pm_chat_stream` span like the one `create_mcp_cli_stream` opens
+    fn test_stream_span() -> tracing::Span {
+        tracing::info_span!("pm_chat_stream", stream_id = tracing::field::Empty)
+    }
 
-    // Build task map for quick lookup
-    let task_map: std::collections::HashMap<_, _> = tasks.iter().map(|t| (t.id, t)).collect();
+    #[tokio::test]
+    async fn test_instrumented_stream_is_polled_in_span() {
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+        let span = test_stream_span();
+        let inner = futures::stream::iter(0..2)
+            .map(|_| tracing::Span::current().metadata().map(|m| m.name()))
+            .boxed();
 
-    // Get dependencies for each task
-    let mut tasks_with_deps = Vec::new();
-    for task in &tasks {
-        let depends_on = TaskDependency::find_dependencies(&deployment.db().pool, task.id).await?;
-        let depended_by = TaskDependency::find_dependents(&deployment.db().pool, task.id).await?;
+        let spans: Vec<_> = instrument_ai_chat_stream(inner, span).collect().await;
 
-        tasks_with_deps.push(TaskWithDependencies {
-            id: task.id.to_string(),
-            title: task.title.clone(),
-            description: task.description.clone(),
-            status: format!("{:?}", task.status).to_lowercase(),
-            priority: format!("{:?}", task.priority).to_lowercase(),
-            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
-            depended_by: depended_by.iter().map(|id| id.to_string()).collect(),
-        });
+        assert_eq!(spans, vec![Some("pm_chat_stream"); 2]);
+        assert!(tracing::Span::current().is_none());
     }
 
-    // Generate formatted summary text
-    let mut summary_lines = vec!["## タスク一覧と依存関係".to_string(), "".to_string()];
-
-    // Group by status
-    let status_labels = [
-        ("todo", "📋 未着手 (Todo)"),
-        ("inprogress", "🔄 進行中 (In Progress)"),
-        ("inreview", "👀 レビュー中 (In Review)"),
-        ("done", "✅ 完了 (Done)"),
-    ];
+    #[tokio::test]
+    async fn test_finished_ai_chat_stream_releases_permit() {
+        let limiter = AiChatLimiter::default();
+        let project_id = Uuid::new_v4();
+        let permit = limiter.try_acquire(project_id, 1).unwrap();
+        let events = hold_ai_chat_permit(futures::stream::empty().boxed(), permit);
 
-    for (status, label) in status_labels.iter() {
-        let status_tasks: Vec<_> = tasks_with_deps
-            .iter()
-            .filter(|t| t.status == *status)
-            .collect();
+        assert!(limiter.try_acquire(project_id, 1).is_none());
+        let _: Vec<_> = events.collect().await;
+        assert!(limiter.try_acquire(project_id, 1).is_some());
+    }
 
-        if !status_tasks.is_empty() {
-            summary_lines.push(format!("### {}", label));
-            summary_lines.push("".to_string());
+    #[test]
+    fn test_workspace_docs_query_overrides_scan_defaults() {
+        let query = WorkspaceDocsQuery {
+            extensions: Some(" .adoc, mdx,,".to_string()),
+            max_total: Some(1_000),
+            max_single: None,
+            force_refresh: false,
+        };
+        let options = query.scan_options(vec!["vendor/**".to_string()]);
 
-            for task in status_tasks {
-                // Task title with priority indicator
-                let priority_icon = match task.priority.as_str() {
-                    "urgent" => "🔴",
-                    "high" => "🟠",
-                    "medium" => "🟡",
-                    "low" => "🟢",
-                    _ => "⚪",
-                };
+        assert_eq!(options.extensions, vec!["adoc", "mdx"]);
+        assert_eq!(options.max_total, 1_000);
+        assert_eq!(options.max_single, ScanOptions::default().max_single);
+        assert_eq!(options.ignore_globs, vec!["vendor/**"]);
 
-                summary_lines.push(format!("- {} **{}**", priority_icon, task.title));
-
-                // Dependencies
-                if !task.depends_on.is_empty() {
-                    let dep_names: Vec<_> = task
-                        .depends_on
-                        .iter()
-                        .filter_map(|id| {
-                            uuid::Uuid::parse_str(id)
-                                .ok()
-                                .and_then(|uuid| task_map.get(&uuid))
-                                .map(|t| t.title.clone())
-                        })
-                        .collect();
-                    if !dep_names.is_empty() {
-                        summary_lines.push(format!("  - ⬅️ 依存: {}", dep_names.join(", ")));
-                    }
-                }
+        let defaults = WorkspaceDocsQuery::default().scan_options(Vec::new());
+        assert_eq!(defaults.extensions, ScanOptions::default().extensions);
+    }
 
-                // Dependents (blocking)
-                if !task.depended_by.is_empty() {
-                    let blocking_names: Vec<_> = task
-                        .depended_by
-                        .iter()
-                        .filter_map(|id| {
-                            uuid::Uuid::parse_str(id)
-                                .ok()
-                                .and_then(|uuid| task_map.get(&uuid))
-                                .map(|t| t.title.clone())
-                        })
-                        .collect();
-                    if !blocking_names.is_empty() {
-                        summary_lines
-                            .push(format!("  - ➡️ ブロック中: {}", blocking_names.join(", ")));
-                    }
-                }
-            }
-            summary_lines.push("".to_string());
+    fn stream_event(event_type: &str, stream_id: Option<Uuid>) -> AiChatStreamEvent {
+        AiChatStreamEvent {
+            event_type: event_type.to_string(),
+            content: None,
+            error: None,
+            task_id: None,
+            task_title: None,
+            tasks_created: None,
+            stream_id: stream_id.map(|id| id.to_string()),
+            docs_length: None,
         }
     }
 
-    // Add dependency chain analysis
-    let blocked_tasks: Vec<_> = tasks_with_deps
-        .iter()
-        .filter(|t| {
-            t.status != "done"
-                && !t.depends_on.is_empty()
-                && t.depends_on.iter().any(|dep_id| {
-                    uuid::Uuid::parse_str(dep_id)
-                        .ok()
-                        .and_then(|uuid| task_map.get(&uuid))
-                        .map(|dep_task| format!("{:?}", dep_task.status).to_lowercase() != "done")
-                        .unwrap_or(false)
-                })
-        })
-        .collect();
+    #[tokio::test]
+    async fn test_ai_chat_stream_resumes_after_last_event_id() {
+        let project_id = Uuid::new_v4();
+        let stream_id = Uuid::new_v4();
+        let events = futures::stream::iter(vec![
+            stream_event("thinking", Some(stream_id)),
+            stream_event("content", None),
+            stream_event("content", None),
+            stream_event("done", None),
+        ])
+        .boxed();
+
+        let first: Vec<_> = spawn_buffered_ai_chat_stream(project_id, events)
+            .await
+            .collect()
+            .await;
+        assert_eq!(first.len(), 4);
 
-    if !blocked_tasks.is_empty() {
-        summary_lines.push("### ⚠️ ブロックされているタスク".to_string());
-        summary_lines.push("".to_string());
-        for task in blocked_tasks {
-            let blocking_names: Vec<_> = task
-                .depends_on
-                .iter()
-                .filter_map(|id| {
-                    uuid::Uuid::parse_str(id)
-                        .ok()
-                        .and_then(|uuid| task_map.get(&uuid))
-                        .filter(|t| format!("{:?}", t.status).to_lowercase() != "done")
-                        .map(|t| t.title.clone())
-                })
-                .collect();
-            summary_lines.push(format!(
-                "- **{}** は以下の完了待ち: {}",
-                task.title,
-                blocking_names.join(", ")
-            ));
-        }
-        summary_lines.push("".to_string());
+        let receiver = find_ai_chat_stream_buffer(project_id, stream_id).unwrap();
+        let resumed: Vec<_> = replay_ai_chat_stream(receiver, 2).collect().await;
+        assert_eq!(resumed.len(), 2);
+
+        assert!(find_ai_chat_stream_buffer(Uuid::new_v4(), stream_id).is_none());
     }
 
-    let summary_text = summary_lines.join("\n");
+    #[test]
+    fn test_estimate_tokens_counts_non_ascii_per_char() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("仕様書"), 3);
+    }
 
-    Ok(ResponseJson(ApiResponse::success(TaskSummaryResponse {
-        tasks: tasks_with_deps,
-        summary_text,
-    })))
-}
+    #[tokio::test]
+    async fn test_streamed_reply_is_saved_while_streaming() {
+        use db::models::project::CreateProject;
 
-/// Sync task summary to PM docs
-pub async fn sync_task_summary_to_docs(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-) -> Result<ResponseJson<ApiResponse<Project>>, ApiError> {
-    use db::models::project::UpdateProject;
-
-    // Get task summary
-    let tasks_with_status =
-        Task::find_by_project_id_with_attempt_status(&deployment.db().pool, project.id).await?;
-    let tasks: Vec<Task> = tasks_with_status.iter().map(|t| t.task.clone()).collect();
-    let task_map: std::collections::HashMap<_, _> = tasks.iter().map(|t| (t.id, t)).collect();
-
-    // Generate summary (same logic as above, simplified for docs)
-    let mut summary_lines = vec![
-        "## タスク一覧と依存関係".to_string(),
-        format!("*最終更新: {}*", Utc::now().format("%Y-%m-%d %H:%M UTC")),
-        "".to_string(),
-    ];
+        let pool = db::test_support::test_pool().await;
+        let project_id = Uuid::new_v4();
+        let create = CreateProject {
+            name: "Streaming".to_string(),
+            repositories: Vec::new(),
+        };
+        Project::create(&pool, &create, project_id).await.unwrap();
+        let saved_content = || async {
+            PmConversation::find_by_project_id(&pool, project_id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|m| m.content)
+                .collect::<Vec<_>>()
+        };
 
-    let status_labels = [
-        ("Todo", "📋 未着手"),
-        ("InProgress", "🔄 進行中"),
-        ("InReview", "👀 レビュー中"),
-        ("Done", "✅ 完了"),
-    ];
+        // Nothing is saved before the first token
+        let mut reply = StreamedReply::new(pool.clone(), project_id, "sonnet".to_string());
+        reply.persist("").await;
+        assert!(saved_content().await.is_empty());
 
-    for (status_variant, label) in status_labels.iter() {
-        let status_tasks: Vec<_> = tasks
-            .iter()
-            .filter(|t| format!("{:?}", t.status) == *status_variant)
-            .collect();
+        reply.persist("Creating").await;
+        reply.persist("Creating tasks").await;
+        assert_eq!(saved_content().await, vec!["Creating tasks"]);
 
-        if !status_tasks.is_empty() {
-            summary_lines.push(format!("### {}", label));
+        let usage = TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+        };
+        reply
+            .finish("Created 2 tasks".to_string(), Some(usage))
+            .await;
+        let messages = PmConversation::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "Created 2 tasks");
+        assert_eq!(messages[0].output_tokens, Some(5));
+        assert!(!messages[0].edited);
+
+        // A reply that fails or is cancelled before any token leaves no row
+        let reply = StreamedReply::new(pool.clone(), project_id, "sonnet".to_string());
+        reply.finish(String::new(), None).await;
+        assert_eq!(saved_content().await, vec!["Created 2 tasks"]);
+    }
 
-            for task in status_tasks {
-                let deps =
-                    TaskDependency::find_dependencies(&deployment.db().pool, task.id).await?;
-                let priority_icon = match format!("{:?}", task.priority).as_str() {
-                    "Urgent" => "🔴",
-                    "High" => "🟠",
-                    "Medium" => "🟡",
-                    "Low" => "🟢",
-                    _ => "⚪",
-                };
+    /// Create a project with a user message and an assistant reply
+    async fn project_with_messages(
+        pool: &sqlx::SqlitePool,
+    ) -> (Uuid, PmConversation, PmConversation) {
+        let project_id = db::test_support::insert_project(pool).await;
+        let message = |role, content: &str| CreatePmConversation {
+            project_id,
+            role,
+            content: content.to_string(),
+            model: None,
+            input_tokens: None,
+            output_tokens: None,
+        };
+        let question = PmConversation::create(pool, &message(PmMessageRole::User, "Plan it"))
+            .await
+            .unwrap();
+        let reply = PmConversation::create(pool, &message(PmMessageRole::Assistant, "Planned"))
+            .await
+            .unwrap();
+        (project_id, question, reply)
+    }
 
-                summary_lines.push(format!("- {} {}", priority_icon, task.title));
+    #[tokio::test]
+    async fn test_edit_user_message_replaces_content() {
+        let pool = db::test_support::test_pool().await;
+        let (project_id, question, _) = project_with_messages(&pool).await;
 
-                if !deps.is_empty() {
-                    let dep_names: Vec<_> = deps
-                        .iter()
-                        .filter_map(|id| task_map.get(id).map(|t| t.title.clone()))
-                        .collect();
-                    if !dep_names.is_empty() {
-                        summary_lines.push(format!("  - 依存: {}", dep_names.join(", ")));
-                    }
-                }
-            }
-            summary_lines.push("".to_string());
-        }
+        let edited = edit_user_message(&pool, project_id, question.id, "Plan it all")
+            .await
+            .unwrap();
+
+        assert_eq!(edited.content, "Plan it all");
+        assert!(edited.edited);
+        let stored = PmConversation::find_by_id(&pool, question.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.content, "Plan it all");
     }
 
-    let task_summary = summary_lines.join("\n");
+    #[tokio::test]
+    async fn test_edit_user_message_rejects_other_projects_and_replies() {
+        let pool = db::test_support::test_pool().await;
+        let (project_id, question, reply) = project_with_messages(&pool).await;
+        let other_project_id = db::test_support::insert_project(&pool).await;
 
-    // Update PM docs - append or replace task summary section
-    let new_docs = if let Some(existing_docs) = &project.pm_docs {
-        // Find and replace existing task summary section, or append
-        if existing_docs.contains("## タスク一覧と依存関係") {
-            // Replace existing section
-            let parts: Vec<&str> = existing_docs.split("## タスク一覧と依存関係").collect();
-            if parts.len() >= 2 {
-                // Find the end of the task section (next ## or end of doc)
-                let after_task_section = parts[1];
-                let end_of_section = after_task_section
-                    .find("\n## ")
-                    .map(|pos| &after_task_section[pos..])
-                    .unwrap_or("");
-                format!("{}{}{}", parts[0], task_summary, end_of_section)
-            } else {
-                format!("{}\n\n{}", existing_docs, task_summary)
-            }
-        } else {
-            format!("{}\n\n{}", existing_docs, task_summary)
+        let missing = edit_user_message(&pool, project_id, Uuid::new_v4(), "Edit").await;
+        assert!(matches!(
+            missing,
+            Err(ApiError::Database(sqlx::Error::RowNotFound))
+        ));
+        let other_project = edit_user_message(&pool, other_project_id, question.id, "Edit").await;
+        assert!(matches!(other_project, Err(ApiError::Forbidden(_))));
+        let assistant = edit_user_message(&pool, project_id, reply.id, "Edit").await;
+        assert!(matches!(assistant, Err(ApiError::BadRequest(_))));
+        let empty = edit_user_message(&pool, project_id, question.id, "  ").await;
+        assert!(matches!(empty, Err(ApiError::BadRequest(_))));
+
+        // None of the rejected edits changed anything
+        for message in [&question, &reply] {
+            let stored = PmConversation::find_by_id(&pool, message.id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(stored.content, message.content);
+            assert!(!stored.edited);
         }
-    } else {
-        task_summary
-    };
-
-    let update_data = UpdateProject {
-        name: None,
-        pm_task_id: None,
-        pm_docs: Some(new_docs),
-    };
+    }
 
-    let updated_project =
-        db::models::project::Project::update(&deployment.db().pool, project.id, &update_data)
-            .await?;
+    #[tokio::test]
+    async fn test_chat_usage_totals_persisted_token_usage_by_model() {
+        let pool = db::test_support::test_pool().await;
+        let (project_id, _, unreported) = project_with_messages(&pool).await;
+        let reply = |project_id, model: &str, tokens: Option<i64>| CreatePmConversation {
+            project_id,
+            role: PmMessageRole::Assistant,
+            content: "Done".to_string(),
+            model: Some(model.to_string()),
+            input_tokens: tokens,
+            output_tokens: tokens.map(|t| t / 2),
+        };
+        PmConversation::create(&pool, &reply(project_id, "sonnet", Some(10)))
+            .await
+            .unwrap();
+        // Usage recorded once the turn is over, as for streamed replies
+        let streamed = PmConversation::create(&pool, &reply(project_id, "opus", None))
+            .await
+            .unwrap();
+        PmConversation::set_usage(&pool, streamed.id, Some(100), Some(40))
+            .await
+            .unwrap();
+        PmConversation::create(&pool, &reply(project_id, "opus", Some(1000)))
+            .await
+            .unwrap();
+        let other_project_id = db::test_support::insert_project(&pool).await;
+        PmConversation::create(&pool, &reply(other_project_id, "opus", Some(7)))
+            .await
+            .unwrap();
 
-    deployment
-        .track_if_analytics_allowed(
-            "pm_task_summary_synced",
-            serde_json::json!({
-                "project_id": project.id.to_string(),
-                "task_count": tasks.len(),
-            }),
-        )
-        .await;
+        let stored = PmConversation::find_by_id(&pool, streamed.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (stored.input_tokens, stored.output_tokens),
+            (Some(100), Some(40))
+        );
+        assert_eq!(unreported.input_tokens, None);
+
+        let usage = PmChatUsageResponse::load(&pool, project_id).await.unwrap();
+        let models: Vec<_> = usage
+            .models
+            .iter()
+            .map(|m| {
+                (
+                    m.model.as_deref(),
+                    m.messages,
+                    m.input_tokens,
+                    m.output_tokens,
+                )
+            })
+            .collect();
+        assert_eq!(
+            models,
+            vec![(Some("opus"), 2, 1100, 540), (Some("sonnet"), 1, 10, 5)]
+        );
+        assert_eq!((usage.input_tokens, usage.output_tokens), (1110, 545));
+    }
 
-    Ok(ResponseJson(ApiResponse::success(updated_project)))
-}
+    #[tokio::test]
+    async fn test_chat_stats_summarize_messages_attachments_and_models() {
+        let pool = db::test_support::test_pool().await;
+        let (project_id, question, _) = project_with_messages(&pool).await;
+        let reply = |model: &str| CreatePmConversation {
+            project_id,
+            role: PmMessageRole::Assistant,
+            content: "Done".to_string(),
+            model: Some(model.to_string()),
+            input_tokens: None,
+            output_tokens: None,
+        };
+        PmConversation::create(&pool, &reply("sonnet"))
+            .await
+            .unwrap();
+        PmConversation::create(&pool, &reply("haiku"))
+            .await
+            .unwrap();
+        let archived = PmConversation::create(&pool, &reply("opus")).await.unwrap();
+        PmConversation::archive(&pool, archived.id).await.unwrap();
+        let attach = |project_id, conversation_id, file_size| CreatePmAttachment {
+            conversation_id,
+            project_id,
+            file_name: "spec.pdf".to_string(),
+            file_path: format!("{}.pdf", Uuid::new_v4()),
+            mime_type: "application/pdf".to_string(),
+            file_size,
+            sha256: None,
+            width: None,
+            height: None,
+        };
+        PmAttachment::create(&pool, &attach(project_id, question.id, 100))
+            .await
+            .unwrap();
+        PmAttachment::create(&pool, &attach(project_id, question.id, 250))
+            .await
+            .unwrap();
+        let (other_project_id, other_question, _) = project_with_messages(&pool).await;
+        PmAttachment::create(&pool, &attach(other_project_id, other_question.id, 999))
+            .await
+            .unwrap();
 
-/// A workspace document from the docs/ folder
-#[derive(Debug, Clone, Serialize, TS)]
-#[ts(export)]
-pub struct WorkspaceDoc {
-    pub path: String,      // Relative path from docs/ folder
-    pub repo_name: String, // Which repo this doc is from
-    pub content: String,   // Full content of the document
-}
+        let stats = PmChatStats::load(&pool, project_id).await.unwrap();
 
-/// Response for workspace docs
-#[derive(Debug, Clone, Serialize, TS)]
-#[ts(export)]
-pub struct WorkspaceDocsResponse {
-    pub docs: Vec<WorkspaceDoc>,
-}
+        assert_eq!(stats.message_count, 4);
+        let roles: Vec<(&str, i64)> = stats
+            .messages_by_role
+            .iter()
+            .map(|r| (r.role.as_str(), r.count))
+            .collect();
+        assert_eq!(roles, vec![("assistant", 3), ("user", 1)]);
+        assert_eq!((stats.attachment_count, stats.attachment_bytes), (2, 350));
+        assert_eq!(stats.first_message_at, Some(question.created_at));
+        assert!(stats.last_message_at >= stats.first_message_at);
+        assert_eq!(
+            stats.models,
+            vec!["haiku".to_string(), "sonnet".to_string()]
+        );
+    }
 
-/// Get workspace documentation files from project repos
-pub async fn get_workspace_docs(
-    Extension(project): Extension<Project>,
-    State(deployment): State<DeploymentImpl>,
-) -> Result<ResponseJson<ApiResponse<WorkspaceDocsResponse>>, ApiError> {
-    use services::services::docs_scanner::scan_docs_folder;
+    #[tokio::test]
+    async fn test_chat_stats_of_empty_chat() {
+        let pool = db::test_support::test_pool().await;
+        let project_id = db::test_support::insert_project(&pool).await;
 
-    // Get all repos for this project
-    let repos = ProjectRepo::find_repos_for_project(&deployment.db().pool, project.id).await?;
+        let stats = PmChatStats::load(&pool, project_id).await.unwrap();
 
-    let mut all_docs = Vec::new();
+        assert_eq!(stats.message_count, 0);
+        assert!(stats.messages_by_role.is_empty());
+        assert_eq!((stats.attachment_count, stats.attachment_bytes), (0, 0));
+        assert_eq!(stats.first_message_at, None);
+        assert!(stats.models.is_empty());
+    }
 
-    for repo in repos {
-        // Scan docs folder for this repo
-        let scanned_docs = scan_docs_folder(&repo.path).await;
+    #[tokio::test]
+    async fn test_context_preview_matches_ai_chat_prompt() {
+        use db::models::{
+            label::{CreateLabel, Label},
+            project::CreateProject,
+        };
 
-        for doc in scanned_docs {
-            all_docs.push(WorkspaceDoc {
-                path: doc.relative_path,
-                repo_name: repo.display_name.clone(),
-                content: doc.content,
-            });
+        let pool = db::test_support::test_pool().await;
+        let project_id = Uuid::new_v4();
+        let create = CreateProject {
+            name: "Preview".to_string(),
+            repositories: Vec::new(),
+        };
+        Project::create(&pool, &create, project_id).await.unwrap();
+        sqlx::query("UPDATE projects SET pm_docs = '## 仕様\nShip it' WHERE id = $1")
+            .bind(project_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let project = Project::find_by_id(&pool, project_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let label = CreateLabel {
+            project_id,
+            name: "backend".to_string(),
+            color: None,
+            executor: None,
+        };
+        Label::create(&pool, &label).await.unwrap();
+        for i in 0..12 {
+            let message = CreatePmConversation {
+                project_id,
+                role: PmMessageRole::User,
+                content: format!("message #{}", i),
+                model: None,
+                input_tokens: None,
+                output_tokens: None,
+            };
+            PmConversation::create(&pool, &message).await.unwrap();
         }
-    }
 
-    Ok(ResponseJson(ApiResponse::success(WorkspaceDocsResponse {
-        docs: all_docs,
-    })))
-}
+        // `get_context_preview` and `ai_chat` both build the prompt this way
+        let preview =
+            PmChatContextPreview::new(current_system_prompt(&pool, &project).await.unwrap());
 
-pub fn router(_deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
-    Router::new()
-        .route("/", get(get_pm_chat).post(send_message).delete(clear_chat))
-        .route("/ai-chat", post(ai_chat))
-        .route("/ai-agents", get(get_available_agents))
-        .route("/messages/{message_id}", delete(delete_message))
-        .route("/attachments", get(get_attachments).post(upload_attachment))
-        .route("/attachments/{attachment_id}", delete(delete_attachment))
-        .route("/attachments/{attachment_id}/file", get(serve_attachment))
-        .route("/docs", get(get_pm_docs).put(update_pm_docs))
-        .route("/workspace-docs", get(get_workspace_docs))
-        .route(
-            "/task-summary",
-            get(get_task_summary).post(sync_task_summary_to_docs),
-        )
-        .layer(DefaultBodyLimit::max(20 * 1024 * 1024)) // 20MB limit for file uploads
+        let messages = PmConversation::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        let sent = build_system_prompt(&pool, &project, SystemPromptOptions::new(&messages)).await;
+        assert_eq!(preview.system_prompt, sent);
+        assert_eq!(preview.characters, sent.chars().count());
+        assert_eq!(preview.estimated_tokens, estimate_tokens(&sent));
+        assert!(sent.contains(&project_id.to_string()));
+        assert!(sent.contains("**backend**"));
+        assert!(sent.contains("Ship it"));
+        assert!(sent.contains("**User**: message #11"));
+    }
 }
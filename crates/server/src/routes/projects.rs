@@ -2,14 +2,17 @@ use std::path::PathBuf;
 
 use anyhow;
 use axum::{
-    Extension, Json, Router,
+    BoxError, Extension, Json, Router,
     extract::{
         Path, Query, State,
         ws::{WebSocket, WebSocketUpgrade},
     },
     http::StatusCode,
     middleware::from_fn_with_state,
-    response::{IntoResponse, Json as ResponseJson},
+    response::{
+        IntoResponse, Json as ResponseJson, Sse,
+        sse::{Event, KeepAlive},
+    },
     routing::{get, post},
 };
 use db::models::{
@@ -35,7 +38,7 @@ use crate::{
     DeploymentImpl,
     error::ApiError,
     middleware::load_project_middleware,
-    routes::{labels, pm_chat},
+    routes::{labels, pm_chat, tasks, webhooks},
 };
 
 #[derive(Deserialize, TS)]
@@ -98,6 +101,24 @@ async fn handle_projects_ws(socket: WebSocket, deployment: DeploymentImpl) -> an
     Ok(())
 }
 
+/// Live task board changes of a project as Server-Sent Events
+pub async fn stream_project_events(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, BoxError>>> {
+    let stream = deployment
+        .events()
+        .board()
+        .subscribe(project.id)
+        .map(|event| {
+            Event::default()
+                .event(event.name())
+                .json_data(&event)
+                .map_err(|e| -> BoxError { e.into() })
+        });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 pub async fn get_project(
     Extension(project): Extension<Project>,
 ) -> Result<ResponseJson<ApiResponse<Project>>, ApiError> {
@@ -591,8 +612,20 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
             "/repositories",
             get(get_project_repositories).post(add_project_repository),
         )
+        .route("/tasks/quick", post(tasks::create_quick_task))
+        .route("/tasks/search", get(tasks::search_tasks))
+        .route(
+            "/tasks/{task_id}/suggest-labels",
+            post(tasks::suggest_task_labels),
+        )
+        .route("/task-order", get(tasks::get_task_order))
+        .route("/actionable-tasks", get(tasks::get_actionable_tasks))
+        .route("/critical-path", get(tasks::get_critical_path))
+        .route("/progress", get(tasks::get_project_progress))
+        .route("/events", get(stream_project_events))
         .nest("/labels", labels::router(deployment))
         .nest("/pm-chat", pm_chat::router(deployment))
+        .nest("/webhook", webhooks::router())
         .layer(from_fn_with_state(
             deployment.clone(),
             load_project_middleware,
@@ -7,27 +7,28 @@ use axum::{
     handler::Handler,
     http::{StatusCode, header},
     response::{Json as ResponseJson, Response},
-    routing::{delete, get},
+    routing::{delete, get, post},
 };
 use chrono::{DateTime, Utc};
 use db::models::{
+    pm_conversation::PmAttachment,
     task::Task,
     task_attachment::{CreateTaskAttachment, TaskAttachment},
 };
 use deployment::Deployment;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use services::services::{
+    attachment_gc, attachment_layout, pm_attachments, pm_chat_retention::pm_attachments_dir,
+};
 use sqlx::Error as SqlxError;
 use tokio::fs::{self, File};
-use tokio::io::AsyncWriteExt;
 use tokio_util::io::ReaderStream;
 use ts_rs::TS;
 use utils::response::ApiResponse;
 use uuid::Uuid;
 
-use crate::{DeploymentImpl, error::ApiError};
+use crate::{DeploymentImpl, error::ApiError, routes::multipart_upload::stream_to_file};
 
-const ATTACHMENTS_DIR: &str = "attachments";
 const MAX_FILE_SIZE: usize = 50 * 1024 * 1024; // 50MB limit
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -63,7 +64,7 @@ impl TaskAttachmentResponse {
 
 /// Get the attachments storage directory
 fn get_attachments_dir() -> PathBuf {
-    utils::cache_dir().join(ATTACHMENTS_DIR)
+    attachment_gc::task_attachments_dir()
 }
 
 /// Upload a file attachment to a task
@@ -80,7 +81,7 @@ pub async fn upload_task_attachment(
     let attachments_dir = get_attachments_dir();
     fs::create_dir_all(&attachments_dir).await?;
 
-    while let Some(field) = multipart.next_field().await? {
+    while let Some(mut field) = multipart.next_field().await? {
         if field.name() == Some("file") {
             let file_name = field
                 .file_name()
@@ -92,30 +93,36 @@ pub async fn upload_task_attachment(
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| "application/octet-stream".to_string());
 
-            let data = field.bytes().await?;
-            let file_size = data.len() as i64;
-
-            // Calculate SHA256 hash
-            let mut hasher = Sha256::new();
-            hasher.update(&data);
-            let hash = format!("{:x}", hasher.finalize());
-
-            // Create unique file path
-            let extension = std::path::Path::new(&file_name)
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("");
-            let stored_name = if extension.is_empty() {
-                format!("{}", Uuid::new_v4())
-            } else {
-                format!("{}.{}", Uuid::new_v4(), extension)
-            };
-            let file_path = attachments_dir.join(&stored_name);
+            // Stream to disk, hashing on the way
+            let upload =
+                stream_to_file(&mut field, &attachments_dir, |_| Ok(MAX_FILE_SIZE as u64)).await?;
+            let file_size = upload.size as i64;
+            let hash = upload.sha256.clone();
 
-            // Write file to disk
-            let mut file = File::create(&file_path).await?;
-            file.write_all(&data).await?;
-            file.flush().await?;
+            // Reuse the stored file of an identical attachment, if it is still on disk
+            let existing = TaskAttachment::find_by_sha256(&deployment.db().pool, &hash)
+                .await?
+                .filter(|a| attachments_dir.join(&a.file_path).exists());
+
+            let stored_name = match existing {
+                // The streamed copy is removed when `upload` is dropped
+                Some(existing) => existing.file_path,
+                None => {
+                    // Content-addressed path; identical content may already be there
+                    let stored_name = attachment_layout::content_path(
+                        &hash,
+                        &attachment_layout::clean_extension(&file_name),
+                    );
+                    let dest = attachments_dir.join(&stored_name);
+                    if !dest.is_file() {
+                        if let Some(parent) = dest.parent() {
+                            fs::create_dir_all(parent).await?;
+                        }
+                        upload.persist(&dest).await?;
+                    }
+                    stored_name
+                }
+            };
 
             // Create database record
             let attachment = TaskAttachment::create(
@@ -140,6 +147,78 @@ pub async fn upload_task_attachment(
     Err(ApiError::BadRequest("No file field found in request".to_string()))
 }
 
+/// Attach a PM chat attachment of the task's project to the task. The file is
+/// linked or copied into the task attachments directory, so deleting either
+/// attachment leaves the other intact.
+pub async fn promote_pm_attachment(
+    Path((task_id, pm_attachment_id)): Path<(Uuid, Uuid)>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<TaskAttachmentResponse>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let task = Task::find_by_id(pool, task_id)
+        .await?
+        .ok_or(ApiError::Database(SqlxError::RowNotFound))?;
+    let pm_attachment = PmAttachment::find_by_id(pool, pm_attachment_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("PM attachment not found".to_string()))?;
+
+    if pm_attachment.project_id != task.project_id {
+        return Err(ApiError::Forbidden(
+            "PM attachment does not belong to the task's project".to_string(),
+        ));
+    }
+
+    let extension = attachment_layout::clean_extension(&pm_attachment.file_name);
+    let stored_name = match pm_attachment.sha256.as_deref() {
+        Some(sha256) => attachment_layout::content_path(sha256, &extension),
+        None if extension.is_empty() => format!("{}", Uuid::new_v4()),
+        None => format!("{}.{}", Uuid::new_v4(), extension),
+    };
+
+    let attachments_dir = get_attachments_dir();
+    if !attachments_dir.join(&stored_name).is_file() {
+        let source = attachment_layout::resolve(
+            &pm_attachments_dir(),
+            &pm_attachment.file_path,
+            pm_attachment.sha256.as_deref(),
+        );
+        let (Some(source_dir), Some(source_file)) = (source.parent(), source.file_name()) else {
+            return Err(ApiError::NotFound(
+                "PM attachment file not found".to_string(),
+            ));
+        };
+        pm_attachments::link_or_copy_file(
+            source_dir,
+            &source_file.to_string_lossy(),
+            &attachments_dir,
+            &stored_name,
+        )
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                ApiError::NotFound("PM attachment file not found".to_string())
+            }
+            _ => ApiError::Io(e),
+        })?;
+    }
+
+    let attachment = TaskAttachment::create(
+        pool,
+        &CreateTaskAttachment {
+            task_id,
+            file_name: pm_attachment.file_name,
+            file_path: stored_name,
+            mime_type: pm_attachment.mime_type,
+            file_size: pm_attachment.file_size,
+            sha256: pm_attachment.sha256,
+        },
+    )
+    .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        TaskAttachmentResponse::from_attachment(attachment),
+    )))
+}
+
 /// List all attachments for a task
 pub async fn list_task_attachments(
     Path(task_id): Path<Uuid>,
@@ -164,10 +243,16 @@ pub async fn download_task_attachment(
 
     // Verify the attachment belongs to this task
     if attachment.task_id != task_id {
-        return Err(ApiError::BadRequest("Attachment does not belong to this task".to_string()));
+        return Err(ApiError::Forbidden(
+            "Attachment does not belong to this task".to_string(),
+        ));
     }
 
-    let file_path = get_attachments_dir().join(&attachment.file_path);
+    let file_path = attachment_layout::resolve(
+        &get_attachments_dir(),
+        &attachment.file_path,
+        attachment.sha256.as_deref(),
+    );
 
     let file = File::open(&file_path).await?;
     let metadata = file.metadata().await?;
@@ -201,18 +286,26 @@ pub async fn delete_task_attachment(
 
     // Verify the attachment belongs to this task
     if attachment.task_id != task_id {
-        return Err(ApiError::BadRequest("Attachment does not belong to this task".to_string()));
-    }
-
-    // Delete file from disk
-    let file_path = get_attachments_dir().join(&attachment.file_path);
-    if file_path.exists() {
-        fs::remove_file(&file_path).await?;
+        return Err(ApiError::Forbidden(
+            "Attachment does not belong to this task".to_string(),
+        ));
     }
 
     // Delete from database
     TaskAttachment::delete(&deployment.db().pool, attachment_id).await?;
 
+    // Delete file from disk once no other attachment shares it
+    let remaining =
+        TaskAttachment::count_by_file_path(&deployment.db().pool, &attachment.file_path).await?;
+    let file_path = attachment_layout::resolve(
+        &get_attachments_dir(),
+        &attachment.file_path,
+        attachment.sha256.as_deref(),
+    );
+    if remaining == 0 && file_path.exists() {
+        fs::remove_file(&file_path).await?;
+    }
+
     Ok(ResponseJson(ApiResponse::success(())))
 }
 
@@ -231,4 +324,8 @@ pub fn routes() -> Router<DeploymentImpl> {
             "/{task_id}/attachments/{attachment_id}/file",
             get(download_task_attachment),
         )
+        .route(
+            "/{task_id}/attachments/from-pm/{pm_attachment_id}",
+            post(promote_pm_attachment),
+        )
 }
@@ -48,6 +48,7 @@ use services::services::{
     container::ContainerService,
     file_search::SearchQuery,
     git::{ConflictOp, GitCliError, GitServiceError},
+    webhooks,
     workspace_manager::WorkspaceManager,
 };
 use sqlx::Error as SqlxError;
@@ -485,7 +486,7 @@ pub async fn merge_task_attempt(
         &merge_commit_id,
     )
     .await?;
-    Task::update_status(pool, task.id, TaskStatus::Done).await?;
+    webhooks::update_task_status(pool, task.id, TaskStatus::Done).await?;
     if !workspace.pinned {
         Workspace::set_archived(pool, workspace.id, true).await?;
     }
@@ -4,7 +4,7 @@ use anyhow;
 use axum::{
     Extension, Json, Router,
     extract::{
-        Query, State,
+        Path, Query, State,
         ws::{WebSocket, WebSocketUpgrade},
     },
     http::StatusCode,
@@ -12,11 +12,18 @@ use axum::{
     response::{IntoResponse, Json as ResponseJson},
     routing::{delete, get, post, put},
 };
+use chrono::{DateTime, Utc};
 use db::models::{
     image::TaskImage,
-    label::{Label, TaskDependency},
+    label::{Label, TaskDependency, TaskDependencyError},
+    project::Project,
     repo::{Repo, RepoError},
-    task::{CreateTask, Task, TaskWithAttemptStatus, UpdateTask},
+    task::{
+        CreateTask, ProgressSummary, Task, TaskParentError, TaskPriority, TaskStatus,
+        TaskWithAttemptStatus, UpdateTask,
+    },
+    task_comment::{CreateTaskComment, TaskComment, TaskCommentAuthor},
+    task_review::{CreateTaskReview, TaskReview, TaskReviewSource},
     workspace::{CreateWorkspace, Workspace},
     workspace_repo::{CreateWorkspaceRepo, WorkspaceRepo},
 };
@@ -24,7 +31,13 @@ use deployment::Deployment;
 use executors::profile::ExecutorProfileId;
 use futures_util::{SinkExt, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
-use services::services::{container::ContainerService, workspace_manager::WorkspaceManager};
+use services::services::{
+    container::ContainerService,
+    events::BoardEvent,
+    task_inference::{self, ExecutorSuggestion, LabelSuggestion},
+    webhooks::{self, WebhookEvent},
+    workspace_manager::WorkspaceManager,
+};
 use sqlx::Error as SqlxError;
 use ts_rs::TS;
 use utils::response::ApiResponse;
@@ -40,13 +53,52 @@ pub struct TaskQuery {
     pub project_id: Uuid,
 }
 
+/// Filters for listing a project's tasks. Times are RFC3339; `*_after` bounds
+/// are inclusive and `created_before` is exclusive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListTasksQuery {
+    pub project_id: Uuid,
+    pub status: Option<TaskStatus>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+}
+
+impl ListTasksQuery {
+    /// Whether `task` passes every filter of the query
+    fn matches(&self, task: &Task) -> bool {
+        let status = self.status.as_ref().is_none_or(|s| &task.status == s);
+        let created = self.created_after.is_none_or(|t| task.created_at >= t)
+            && self.created_before.is_none_or(|t| task.created_at < t);
+        let updated = self.updated_after.is_none_or(|t| task.updated_at >= t);
+        status && created && updated
+    }
+}
+
+/// Tell the board subscribers of the task's project that it was created
+pub(crate) fn publish_task_created(deployment: &DeploymentImpl, task: &Task) {
+    deployment.events().board().publish(
+        task.project_id,
+        BoardEvent::TaskCreated { task_id: task.id },
+    );
+}
+
+/// Tell the board subscribers of the task's project that it changed
+fn publish_task_updated(deployment: &DeploymentImpl, task: &Task) {
+    deployment.events().board().publish(
+        task.project_id,
+        BoardEvent::TaskUpdated { task_id: task.id },
+    );
+}
+
 pub async fn get_tasks(
     State(deployment): State<DeploymentImpl>,
-    Query(query): Query<TaskQuery>,
+    Query(query): Query<ListTasksQuery>,
 ) -> Result<ResponseJson<ApiResponse<Vec<TaskWithAttemptStatus>>>, ApiError> {
-    let tasks =
+    let mut tasks =
         Task::find_by_project_id_with_attempt_status(&deployment.db().pool, query.project_id)
             .await?;
+    tasks.retain(|task| query.matches(&task.task));
 
     Ok(ResponseJson(ApiResponse::success(tasks)))
 }
@@ -117,6 +169,17 @@ pub async fn create_task(
         payload.project_id
     );
 
+    if let Some(parent_task_id) = payload.parent_task_id {
+        Task::validate_parent(
+            &deployment.db().pool,
+            id,
+            payload.project_id,
+            parent_task_id,
+        )
+        .await
+        .map_err(task_parent_error)?;
+    }
+
     let task = Task::create(&deployment.db().pool, &payload, id).await?;
 
     if let Some(image_ids) = &payload.image_ids {
@@ -139,10 +202,301 @@ pub async fn create_task(
             }),
         )
         .await;
+    publish_task_created(&deployment, &task);
+    webhooks::dispatch(
+        deployment.db().pool.clone(),
+        WebhookEvent::task_created(&task),
+    );
 
     Ok(ResponseJson(ApiResponse::success(task)))
 }
 
+#[derive(Debug, Deserialize, TS)]
+pub struct QuickTaskRequest {
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct QuickTaskResponse {
+    pub task: Task,
+    pub labels: Vec<Label>,
+    /// Whether priority was inferred from the title (otherwise the default was used)
+    pub auto_set_priority: bool,
+    /// Whether any labels were inferred from the title
+    pub auto_set_labels: bool,
+}
+
+/// Create a task from just a title, inferring priority and labels with keyword rules
+pub async fn create_quick_task(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<QuickTaskRequest>,
+) -> Result<ResponseJson<ApiResponse<QuickTaskResponse>>, ApiError> {
+    let title = payload.title.trim().to_string();
+    if title.is_empty() {
+        return Err(ApiError::BadRequest("Title is required".to_string()));
+    }
+
+    let pool = &deployment.db().pool;
+
+    let priority = task_inference::infer_priority(&title);
+    let project_labels = Label::find_by_project_id(pool, project.id).await?;
+    let labels: Vec<Label> = task_inference::suggest_labels(&title, &project_labels)
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let auto_set_priority = priority.is_some();
+    let auto_set_labels = !labels.is_empty();
+
+    let mut create = CreateTask::from_title_description(project.id, title, None);
+    create.priority = priority;
+
+    let task = Task::create(pool, &create, Uuid::new_v4()).await?;
+
+    if auto_set_labels {
+        let label_ids: Vec<Uuid> = labels.iter().map(|l| l.id).collect();
+        Label::set_task_labels(pool, task.id, &label_ids).await?;
+    }
+
+    deployment
+        .track_if_analytics_allowed(
+            "task_created",
+            serde_json::json!({
+                "task_id": task.id.to_string(),
+                "project_id": project.id,
+                "has_description": false,
+                "has_images": false,
+                "quick": true,
+                "auto_set_priority": auto_set_priority,
+                "auto_set_labels": auto_set_labels,
+            }),
+        )
+        .await;
+    publish_task_created(&deployment, &task);
+    webhooks::dispatch(pool.clone(), WebhookEvent::task_created(&task));
+
+    Ok(ResponseJson(ApiResponse::success(QuickTaskResponse {
+        task,
+        labels,
+        auto_set_priority,
+        auto_set_labels,
+    })))
+}
+
+/// Suggest labels of the project for a task from its title and description,
+/// using the same keyword rules as quick task creation. Strongest match first.
+pub async fn suggest_task_labels(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_project_id, task_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<Vec<LabelSuggestion>>>, ApiError> {
+    let pool = &deployment.db().pool;
+    let task = Task::find_by_id(pool, task_id)
+        .await?
+        .filter(|task| task.project_id == project.id)
+        .ok_or(ApiError::Database(SqlxError::RowNotFound))?;
+
+    let text = match &task.description {
+        Some(description) => format!("{}\n{}", task.title, description),
+        None => task.title.clone(),
+    };
+    let project_labels = Label::find_by_project_id(pool, project.id).await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        task_inference::rank_label_suggestions(&text, &project_labels),
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchTasksQuery {
+    /// Text to find in task titles and descriptions; all tasks match if empty
+    pub query: Option<String>,
+    pub status: Option<TaskStatus>,
+    /// Comma-separated label ids; matching tasks have every one of them
+    pub label_ids: Option<String>,
+    /// Maximum number of tasks to return (default: 50)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+pub struct SearchTasksResponse {
+    pub tasks: Vec<Task>,
+    /// Number of matching tasks before `limit` was applied
+    pub total: usize,
+}
+
+/// Search a project's tasks by text, status, and labels
+pub async fn search_tasks(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<SearchTasksQuery>,
+) -> Result<ResponseJson<ApiResponse<SearchTasksResponse>>, ApiError> {
+    let label_ids = query
+        .label_ids
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            Uuid::parse_str(id)
+                .map_err(|_| ApiError::BadRequest(format!("Invalid label id: {}", id)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tasks = Task::search(
+        &deployment.db().pool,
+        project.id,
+        query.query.as_deref().unwrap_or_default(),
+        query.status,
+        &label_ids,
+    )
+    .await?;
+    let total = tasks.len();
+    tasks.truncate(query.limit.unwrap_or(50));
+
+    Ok(ResponseJson(ApiResponse::success(SearchTasksResponse {
+        tasks,
+        total,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActionableTasksQuery {
+    /// Maximum number of tasks to return (default: 10, at most 100)
+    pub limit: Option<i64>,
+}
+
+/// Unfinished tasks whose dependencies are all done, most urgent first
+pub async fn get_actionable_tasks(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Query(query): Query<ActionableTasksQuery>,
+) -> Result<ResponseJson<ApiResponse<Vec<Task>>>, ApiError> {
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    let tasks = TaskDependency::find_actionable(&deployment.db().pool, project.id, limit).await?;
+
+    Ok(ResponseJson(ApiResponse::success(tasks)))
+}
+
+/// Task counts by status, blocked tasks, and completion percentage for a project
+pub async fn get_project_progress(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<ProgressSummary>>, ApiError> {
+    let summary = Task::progress_summary(&deployment.db().pool, project.id).await?;
+    Ok(ResponseJson(ApiResponse::success(summary)))
+}
+
+/// A task in dependency-safe execution order
+#[derive(Debug, Serialize, Deserialize, TS)]
+pub struct OrderedTaskSummary {
+    pub id: Uuid,
+    pub title: String,
+    pub status: TaskStatus,
+    pub priority: TaskPriority,
+    /// Tasks that must be completed before this one
+    pub depends_on: Vec<Uuid>,
+}
+
+/// Summaries of `tasks` in the order of `order`
+fn summarize_ordered_tasks(
+    order: Vec<Uuid>,
+    tasks: Vec<Task>,
+    dependencies: &[TaskDependency],
+) -> Vec<OrderedTaskSummary> {
+    let mut tasks_by_id: std::collections::HashMap<Uuid, Task> =
+        tasks.into_iter().map(|t| (t.id, t)).collect();
+    order
+        .into_iter()
+        .filter_map(|id| tasks_by_id.remove(&id))
+        .map(|task| OrderedTaskSummary {
+            depends_on: dependencies
+                .iter()
+                .filter(|d| d.task_id == task.id)
+                .map(|d| d.depends_on_task_id)
+                .collect(),
+            id: task.id,
+            title: task.title,
+            status: task.status,
+            priority: task.priority,
+        })
+        .collect()
+}
+
+fn task_dependency_error(e: TaskDependencyError) -> ApiError {
+    match e {
+        TaskDependencyError::Database(e) => ApiError::Database(e),
+        TaskDependencyError::Cycle(_) => ApiError::Conflict(e.to_string()),
+        TaskDependencyError::OutsideProject(_) => ApiError::BadRequest(e.to_string()),
+    }
+}
+
+fn task_parent_error(e: TaskParentError) -> ApiError {
+    match e {
+        TaskParentError::Database(e) => ApiError::Database(e),
+        TaskParentError::Cycle(_) => ApiError::Conflict(e.to_string()),
+        TaskParentError::SelfParent
+        | TaskParentError::ParentNotFound(_)
+        | TaskParentError::OutsideProject(_) => ApiError::BadRequest(e.to_string()),
+    }
+}
+
+/// Suggest an execution order for a project's tasks that respects dependencies
+pub async fn get_task_order(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<OrderedTaskSummary>>>, ApiError> {
+    let pool = &deployment.db().pool;
+
+    let tasks: Vec<Task> = Task::find_by_project_id_with_attempt_status(pool, project.id)
+        .await?
+        .into_iter()
+        .map(|t| t.task)
+        .collect();
+    let dependencies = TaskDependency::find_by_project_id(pool, project.id).await?;
+
+    let order =
+        TaskDependency::order_tasks(&tasks, &dependencies).map_err(task_dependency_error)?;
+
+    let summaries = summarize_ordered_tasks(order, tasks, &dependencies);
+
+    Ok(ResponseJson(ApiResponse::success(summaries)))
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+pub struct CriticalPathResponse {
+    /// Not-done tasks on the longest dependency chain, first task first
+    pub tasks: Vec<OrderedTaskSummary>,
+    /// Number of tasks on the chain
+    pub length: usize,
+}
+
+/// Longest chain of unfinished dependent tasks, i.e. the sequence gating project completion
+pub async fn get_critical_path(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<CriticalPathResponse>>, ApiError> {
+    let pool = &deployment.db().pool;
+
+    let tasks: Vec<Task> = Task::find_by_project_id_with_attempt_status(pool, project.id)
+        .await?
+        .into_iter()
+        .map(|t| t.task)
+        .collect();
+    let dependencies = TaskDependency::find_by_project_id(pool, project.id).await?;
+
+    let path =
+        TaskDependency::longest_chain(&tasks, &dependencies).map_err(task_dependency_error)?;
+    let tasks = summarize_ordered_tasks(path, tasks, &dependencies);
+
+    Ok(ResponseJson(ApiResponse::success(CriticalPathResponse {
+        length: tasks.len(),
+        tasks,
+    })))
+}
+
 #[derive(Debug, Deserialize, TS)]
 pub struct CreateAndStartTaskRequest {
     pub task: CreateTask,
@@ -163,6 +517,11 @@ pub async fn create_task_and_start(
     let pool = &deployment.db().pool;
 
     let task_id = Uuid::new_v4();
+    if let Some(parent_task_id) = payload.task.parent_task_id {
+        Task::validate_parent(pool, task_id, payload.task.project_id, parent_task_id)
+            .await
+            .map_err(task_parent_error)?;
+    }
     let task = Task::create(pool, &payload.task, task_id).await?;
 
     if let Some(image_ids) = &payload.task.image_ids {
@@ -180,6 +539,8 @@ pub async fn create_task_and_start(
             }),
         )
         .await;
+    publish_task_created(&deployment, &task);
+    webhooks::dispatch(pool.clone(), WebhookEvent::task_created(&task));
 
     let attempt_id = Uuid::new_v4();
     let git_branch_name = deployment
@@ -248,6 +609,8 @@ pub async fn create_task_and_start(
         has_in_progress_attempt: is_attempt_running,
         last_attempt_failed: false,
         executor: payload.executor_profile_id.executor.to_string(),
+        subtask_count: 0,
+        subtasks_done: 0,
     })))
 }
 
@@ -264,6 +627,7 @@ pub async fn update_task(
         Some(s) => Some(s),                     // Non-empty string = update description
         None => existing_task.description,      // Field omitted = keep existing
     };
+    let previous_status = existing_task.status.clone();
     let status = payload.status.unwrap_or(existing_task.status);
     let priority = payload.priority.unwrap_or(existing_task.priority);
     let position = payload.position.unwrap_or(existing_task.position);
@@ -294,6 +658,9 @@ pub async fn update_task(
         Label::set_task_labels(&deployment.db().pool, task.id, label_ids).await?;
     }
 
+    publish_task_updated(&deployment, &task);
+    webhooks::dispatch_status_change(&deployment.db().pool, &task, previous_status);
+
     Ok(ResponseJson(ApiResponse::success(task)))
 }
 
@@ -345,6 +712,7 @@ pub async fn delete_task(
 
     // Commit the transaction - if this fails, all changes are rolled back
     tx.commit().await?;
+    publish_task_updated(&deployment, &task);
 
     if total_children_affected > 0 {
         tracing::info!(
@@ -412,6 +780,91 @@ pub async fn get_task_labels(
     Ok(ResponseJson(ApiResponse::success(labels)))
 }
 
+/// Suggest the coding agent to run a task from the executors set on its
+/// labels; `null` when none of them specifies one
+pub async fn suggest_task_executor(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Option<ExecutorSuggestion>>>, ApiError> {
+    let labels = Label::find_by_task_id(&deployment.db().pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(
+        task_inference::suggest_executor(&labels),
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetTaskLabelsRequest {
+    pub label_ids: Vec<Uuid>,
+}
+
+/// Reject label ids that are not labels of the task's project
+async fn ensure_project_labels(
+    pool: &sqlx::SqlitePool,
+    task: &Task,
+    label_ids: &[Uuid],
+) -> Result<(), ApiError> {
+    let project_labels: std::collections::HashSet<Uuid> =
+        Label::find_by_project_id(pool, task.project_id)
+            .await?
+            .into_iter()
+            .map(|label| label.id)
+            .collect();
+    let outside_project: Vec<String> = label_ids
+        .iter()
+        .filter(|id| !project_labels.contains(id))
+        .map(|id| id.to_string())
+        .collect();
+    if outside_project.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!(
+            "Labels do not belong to the task's project: {}",
+            outside_project.join(", ")
+        )))
+    }
+}
+
+/// Set task labels (replaces existing), returning the resulting labels
+pub async fn set_task_labels(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<SetTaskLabelsRequest>,
+) -> Result<ResponseJson<ApiResponse<Vec<Label>>>, ApiError> {
+    let pool = &deployment.db().pool;
+    ensure_project_labels(pool, &task, &payload.label_ids).await?;
+    Label::set_task_labels(pool, task.id, &payload.label_ids).await?;
+    publish_task_updated(&deployment, &task);
+    let labels = Label::find_by_task_id(pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(labels)))
+}
+
+/// Add a label to a task, returning the resulting labels
+pub async fn add_task_label(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_task_id, label_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<Vec<Label>>>, ApiError> {
+    let pool = &deployment.db().pool;
+    ensure_project_labels(pool, &task, &[label_id]).await?;
+    Label::add_to_task(pool, task.id, label_id).await?;
+    publish_task_updated(&deployment, &task);
+    let labels = Label::find_by_task_id(pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(labels)))
+}
+
+/// Remove a label from a task, returning the remaining labels
+pub async fn remove_task_label(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+    Path((_task_id, label_id)): Path<(Uuid, Uuid)>,
+) -> Result<ResponseJson<ApiResponse<Vec<Label>>>, ApiError> {
+    let pool = &deployment.db().pool;
+    Label::remove_from_task(pool, task.id, label_id).await?;
+    publish_task_updated(&deployment, &task);
+    let labels = Label::find_by_task_id(pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(labels)))
+}
+
 /// Get task dependencies (tasks this task depends on)
 pub async fn get_task_dependencies(
     Extension(task): Extension<Task>,
@@ -421,6 +874,15 @@ pub async fn get_task_dependencies(
     Ok(ResponseJson(ApiResponse::success(dependencies)))
 }
 
+/// Get task dependents (tasks that depend on this task)
+pub async fn get_task_dependents(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<Uuid>>>, ApiError> {
+    let dependents = TaskDependency::find_dependents(&deployment.db().pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(dependents)))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SetDependenciesRequest {
     pub dependency_ids: Vec<Uuid>,
@@ -432,11 +894,126 @@ pub async fn set_task_dependencies(
     State(deployment): State<DeploymentImpl>,
     Json(payload): Json<SetDependenciesRequest>,
 ) -> Result<ResponseJson<ApiResponse<Vec<Uuid>>>, ApiError> {
-    TaskDependency::set_dependencies(&deployment.db().pool, task.id, &payload.dependency_ids)
-        .await?;
+    let pool = &deployment.db().pool;
+    TaskDependency::ensure_acyclic(pool, &task, &payload.dependency_ids)
+        .await
+        .map_err(task_dependency_error)?;
+    TaskDependency::set_dependencies(pool, task.id, &payload.dependency_ids).await?;
+    publish_task_updated(&deployment, &task);
     Ok(ResponseJson(ApiResponse::success(payload.dependency_ids)))
 }
 
+/// Get the direct subtasks of a task
+pub async fn get_subtasks(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<Task>>>, ApiError> {
+    let subtasks = Task::find_children(&deployment.db().pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(subtasks)))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct SetTaskParentRequest {
+    /// New parent task; `None` makes the task a top-level task
+    pub parent_task_id: Option<Uuid>,
+}
+
+/// Move a task under another task, or back to the top level
+pub async fn set_task_parent(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<SetTaskParentRequest>,
+) -> Result<ResponseJson<ApiResponse<Task>>, ApiError> {
+    let task = Task::set_parent(&deployment.db().pool, &task, payload.parent_task_id)
+        .await
+        .map_err(task_parent_error)?;
+    publish_task_updated(&deployment, &task);
+    Ok(ResponseJson(ApiResponse::success(task)))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct AddTaskCommentRequest {
+    pub content: String,
+    /// Defaults to `user`
+    #[serde(default)]
+    pub author: TaskCommentAuthor,
+}
+
+/// Get the comments on a task, oldest first
+pub async fn get_task_comments(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<TaskComment>>>, ApiError> {
+    let comments = TaskComment::find_by_task_id(&deployment.db().pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(comments)))
+}
+
+/// Add a comment to a task
+pub async fn add_task_comment(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<AddTaskCommentRequest>,
+) -> Result<ResponseJson<ApiResponse<TaskComment>>, ApiError> {
+    let content = payload.content.trim();
+    if content.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Comment content must not be empty".to_string(),
+        ));
+    }
+    let comment = TaskComment::create(
+        &deployment.db().pool,
+        &CreateTaskComment {
+            task_id: task.id,
+            author: payload.author,
+            content: content.to_string(),
+        },
+    )
+    .await?;
+    Ok(ResponseJson(ApiResponse::success(comment)))
+}
+
+#[derive(Debug, Deserialize, TS)]
+pub struct CreateTaskReviewRequest {
+    pub prompt: String,
+    pub checklist: Vec<String>,
+    /// Defaults to `user`
+    #[serde(default)]
+    pub source: TaskReviewSource,
+}
+
+/// Get the reviews requested for a task, newest first
+pub async fn get_task_reviews(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<TaskReview>>>, ApiError> {
+    let reviews = TaskReview::find_by_task_id(&deployment.db().pool, task.id).await?;
+    Ok(ResponseJson(ApiResponse::success(reviews)))
+}
+
+/// Record a review requested for a task
+pub async fn create_task_review(
+    Extension(task): Extension<Task>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<CreateTaskReviewRequest>,
+) -> Result<ResponseJson<ApiResponse<TaskReview>>, ApiError> {
+    if payload.prompt.trim().is_empty() {
+        return Err(ApiError::BadRequest(
+            "Review prompt must not be empty".to_string(),
+        ));
+    }
+    let review = TaskReview::create(
+        &deployment.db().pool,
+        &CreateTaskReview {
+            task_id: task.id,
+            prompt: payload.prompt,
+            checklist: payload.checklist,
+            source: payload.source,
+        },
+    )
+    .await?;
+    Ok(ResponseJson(ApiResponse::success(review)))
+}
+
 #[derive(Debug, Deserialize, TS)]
 pub struct TaskPositionUpdate {
     pub task_id: Uuid,
@@ -468,11 +1045,21 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
     let task_actions_router = Router::new()
         .route("/", put(update_task))
         .route("/", delete(delete_task))
-        .route("/labels", get(get_task_labels))
+        .route("/labels", get(get_task_labels).put(set_task_labels))
+        .route(
+            "/labels/{label_id}",
+            post(add_task_label).delete(remove_task_label),
+        )
         .route(
             "/dependencies",
             get(get_task_dependencies).put(set_task_dependencies),
-        );
+        )
+        .route("/suggest-executor", post(suggest_task_executor))
+        .route("/dependents", get(get_task_dependents))
+        .route("/subtasks", get(get_subtasks))
+        .route("/parent", put(set_task_parent))
+        .route("/comments", get(get_task_comments).post(add_task_comment))
+        .route("/reviews", get(get_task_reviews).post(create_task_review));
 
     let task_id_router = Router::new()
         .route("/", get(get_task))
@@ -489,3 +1076,122 @@ pub fn router(deployment: &DeploymentImpl) -> Router<DeploymentImpl> {
     // mount under /projects/:project_id/tasks
     Router::new().nest("/tasks", inner)
 }
+
+#[cfg(test)]
+mod tests {
+    use db::test_support::task;
+
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn task_at(title: &str, status: TaskStatus, created_at: &str, updated_at: &str) -> Task {
+        Task {
+            created_at: at(created_at),
+            updated_at: at(updated_at),
+            ..task(title, status)
+        }
+    }
+
+    fn query() -> ListTasksQuery {
+        ListTasksQuery {
+            project_id: Uuid::nil(),
+            status: None,
+            created_after: None,
+            created_before: None,
+            updated_after: None,
+        }
+    }
+
+    fn titles(query: &ListTasksQuery, tasks: &[Task]) -> Vec<String> {
+        tasks
+            .iter()
+            .filter(|task| query.matches(task))
+            .map(|task| task.title.clone())
+            .collect()
+    }
+
+    fn tasks() -> Vec<Task> {
+        vec![
+            task_at(
+                "old",
+                TaskStatus::Done,
+                "2025-03-01T09:00:00Z",
+                "2025-03-02T09:00:00Z",
+            ),
+            task_at(
+                "monday",
+                TaskStatus::Todo,
+                "2025-03-10T00:00:00Z",
+                "2025-03-10T00:00:00Z",
+            ),
+            task_at(
+                "touched",
+                TaskStatus::InProgress,
+                "2025-03-03T12:00:00Z",
+                "2025-03-12T08:30:00Z",
+            ),
+            task_at(
+                "friday",
+                TaskStatus::Todo,
+                "2025-03-14T17:00:00+02:00",
+                "2025-03-14T15:00:00Z",
+            ),
+            task_at(
+                "next week",
+                TaskStatus::Todo,
+                "2025-03-17T00:00:00Z",
+                "2025-03-17T00:00:00Z",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_list_tasks_query_without_filters_matches_all() {
+        assert_eq!(titles(&query(), &tasks()).len(), 5);
+    }
+
+    #[test]
+    fn test_list_tasks_query_filters_created_range() {
+        let this_week = ListTasksQuery {
+            created_after: Some(at("2025-03-10T00:00:00Z")),
+            created_before: Some(at("2025-03-17T00:00:00Z")),
+            ..query()
+        };
+
+        // The lower bound is inclusive, the upper bound exclusive
+        assert_eq!(titles(&this_week, &tasks()), vec!["monday", "friday"]);
+    }
+
+    #[test]
+    fn test_list_tasks_query_filters_updated_after() {
+        let since_yesterday = ListTasksQuery {
+            updated_after: Some(at("2025-03-12T00:00:00Z")),
+            ..query()
+        };
+
+        assert_eq!(
+            titles(&since_yesterday, &tasks()),
+            vec!["touched", "friday", "next week"]
+        );
+    }
+
+    #[test]
+    fn test_list_tasks_query_combines_with_status() {
+        let open_this_week = ListTasksQuery {
+            status: Some(TaskStatus::Todo),
+            created_after: Some(at("2025-03-10T00:00:00Z")),
+            updated_after: Some(at("2025-03-14T00:00:00Z")),
+            ..query()
+        };
+
+        assert_eq!(
+            titles(&open_this_week, &tasks()),
+            vec!["friday", "next week"]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use axum::{Extension, Json, Router, extract::State, response::Json as ResponseJson, routing::get};
+use db::models::{
+    project::Project,
+    project_webhook::{ProjectWebhook, WebhookDeadLetter},
+};
+use deployment::Deployment;
+use serde::Deserialize;
+use services::services::webhooks;
+use ts_rs::TS;
+use utils::response::ApiResponse;
+
+use crate::{DeploymentImpl, error::ApiError};
+
+#[derive(Debug, Deserialize, TS)]
+pub struct SetProjectWebhookRequest {
+    /// http(s) URL that receives the signed event POSTs
+    pub url: String,
+    /// Replace the signing secret; a new webhook always gets a fresh one
+    #[serde(default)]
+    pub regenerate_secret: bool,
+}
+
+fn validate_webhook_url(raw: &str) -> Result<(), ApiError> {
+    let url = url::Url::parse(raw)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid webhook URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ApiError::BadRequest(
+            "Webhook URL must use http or https".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Get the webhook of a project, if one is configured
+pub async fn get_webhook(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Option<ProjectWebhook>>>, ApiError> {
+    let webhook = ProjectWebhook::find_by_project_id(&deployment.db().pool, project.id).await?;
+    Ok(ResponseJson(ApiResponse::success(webhook)))
+}
+
+/// Create or update the webhook of a project
+pub async fn set_webhook(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+    Json(payload): Json<SetProjectWebhookRequest>,
+) -> Result<ResponseJson<ApiResponse<ProjectWebhook>>, ApiError> {
+    let url = payload.url.trim();
+    validate_webhook_url(url)?;
+
+    let pool = &deployment.db().pool;
+    let secret = match ProjectWebhook::find_by_project_id(pool, project.id).await? {
+        Some(existing) if !payload.regenerate_secret => existing.secret,
+        _ => webhooks::generate_secret(),
+    };
+    let webhook = ProjectWebhook::upsert(pool, project.id, url, &secret).await?;
+    Ok(ResponseJson(ApiResponse::success(webhook)))
+}
+
+/// Remove the webhook of a project
+pub async fn delete_webhook(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    if ProjectWebhook::delete(&deployment.db().pool, project.id).await? == 0 {
+        return Err(ApiError::NotFound(
+            "No webhook is configured for this project".to_string(),
+        ));
+    }
+    Ok(ResponseJson(ApiResponse::success(())))
+}
+
+/// Deliveries that still failed after all retries, newest first
+pub async fn get_dead_letters(
+    Extension(project): Extension<Project>,
+    State(deployment): State<DeploymentImpl>,
+) -> Result<ResponseJson<ApiResponse<Vec<WebhookDeadLetter>>>, ApiError> {
+    let dead_letters =
+        WebhookDeadLetter::find_by_project_id(&deployment.db().pool, project.id).await?;
+    Ok(ResponseJson(ApiResponse::success(dead_letters)))
+}
+
+pub fn router() -> Router<DeploymentImpl> {
+    Router::new()
+        .route(
+            "/",
+            get(get_webhook).put(set_webhook).delete(delete_webhook),
+        )
+        .route("/dead-letters", get(get_dead_letters))
+}
@@ -9,7 +9,7 @@ use std::{
 use dashmap::DashMap;
 use db::models::{
     execution_process::ExecutionProcess,
-    task::{Task, TaskStatus},
+    task::TaskStatus,
 };
 use executors::{
     approvals::ToolCallMetadata,
@@ -29,6 +29,8 @@ use utils::{
 };
 use uuid::Uuid;
 
+use crate::services::webhooks;
+
 #[derive(Debug)]
 struct PendingApproval {
     entry_index: usize,
@@ -177,7 +179,8 @@ impl Approvals {
             ) && let Ok(ctx) =
                 ExecutionProcess::load_context(pool, tool_ctx.execution_process_id).await
                 && ctx.task.status == TaskStatus::InReview
-                && let Err(e) = Task::update_status(pool, ctx.task.id, TaskStatus::InProgress).await
+                && let Err(e) =
+                    webhooks::update_task_status(pool, ctx.task.id, TaskStatus::InProgress).await
             {
                 tracing::warn!(
                     "Failed to update task status to InProgress after approval response: {}",
@@ -285,7 +288,8 @@ impl Approvals {
 pub(crate) async fn ensure_task_in_review(pool: &SqlitePool, execution_process_id: Uuid) {
     if let Ok(ctx) = ExecutionProcess::load_context(pool, execution_process_id).await
         && ctx.task.status == TaskStatus::InProgress
-        && let Err(e) = Task::update_status(pool, ctx.task.id, TaskStatus::InReview).await
+        && let Err(e) =
+            webhooks::update_task_status(pool, ctx.task.id, TaskStatus::InReview).await
     {
         tracing::warn!(
             "Failed to update task status to InReview for approval request: {}",
@@ -0,0 +1,260 @@
+//! Attachment garbage collection
+//!
+//! Removes files in the PM and task attachment directories that no
+//! attachment row references any more, e.g. left behind by a crash between
+//! writing a file and deleting its row. Rows whose file has gone missing are
+//! logged but left alone.
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use db::models::{pm_conversation::PmAttachment, task_attachment::TaskAttachment};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use thiserror::Error;
+use tracing::{info, warn};
+use ts_rs::TS;
+
+use super::{pm_attachments, pm_chat_retention::pm_attachments_dir};
+
+/// Directory (under the cache dir) where task attachments are stored
+const TASK_ATTACHMENTS_DIR: &str = "attachments";
+
+/// Files younger than this are never removed: uploads write the file before
+/// inserting its row
+const MIN_ORPHAN_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Get the task attachments directory
+pub fn task_attachments_dir() -> PathBuf {
+    utils::cache_dir().join(TASK_ATTACHMENTS_DIR)
+}
+
+#[derive(Debug, Error)]
+pub enum AttachmentGcError {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Outcome of an attachment garbage collection run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct AttachmentGcReport {
+    pub pm_files_removed: usize,
+    pub task_files_removed: usize,
+    /// Attachment rows whose file no longer exists on disk
+    pub missing_files: usize,
+}
+
+/// Whether `file_name` is referenced, directly or as a cached thumbnail of a referenced file
+fn is_referenced(file_name: &str, referenced: &HashSet<String>) -> bool {
+    referenced.contains(file_name)
+        || pm_attachments::thumbnail_source(file_name).is_some_and(|s| referenced.contains(s))
+}
+
+/// Files in `dir`, including its shard subdirectories, not referenced by any
+/// attachment and last modified more than `min_age` ago. A missing directory
+/// has no orphans.
+pub fn find_orphaned_files(
+    dir: &Path,
+    referenced: &HashSet<String>,
+    min_age: Duration,
+) -> io::Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+    let mut orphans = Vec::new();
+    // Directories still to scan, with their path relative to `dir` as stored in rows
+    let mut pending = vec![(dir.to_path_buf(), String::new())];
+
+    while let Some((current, prefix)) = pending.pop() {
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let file_name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if metadata.is_dir() {
+                pending.push((entry.path(), format!("{}/", file_name)));
+                continue;
+            }
+            if !metadata.is_file() || is_referenced(&file_name, referenced) {
+                continue;
+            }
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age >= min_age {
+                orphans.push(entry.path());
+            }
+        }
+    }
+
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// Referenced files that do not exist in `dir`
+pub fn find_missing_files(dir: &Path, referenced: &HashSet<String>) -> Vec<String> {
+    let mut missing: Vec<String> = referenced
+        .iter()
+        .filter(|file_path| !dir.join(file_path).is_file())
+        .cloned()
+        .collect();
+    missing.sort();
+    missing
+}
+
+/// Remove orphaned files from `dir`, returning how many were removed.
+/// Files that fail to be removed are logged and skipped.
+pub fn remove_orphaned_files(
+    dir: &Path,
+    referenced: &HashSet<String>,
+    min_age: Duration,
+) -> io::Result<usize> {
+    let mut removed = 0;
+    for path in find_orphaned_files(dir, referenced, min_age)? {
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove orphaned attachment {:?}: {}", path, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove unreferenced files from `dir` and log referenced files missing from it.
+/// Returns `(files removed, files missing)`.
+fn collect_dir(dir: &Path, referenced: &HashSet<String>, kind: &str) -> io::Result<(usize, usize)> {
+    let removed = remove_orphaned_files(dir, referenced, MIN_ORPHAN_AGE)?;
+    let missing = find_missing_files(dir, referenced);
+    for file_path in &missing {
+        warn!(
+            "{} attachment file {} is missing from {:?}",
+            kind, file_path, dir
+        );
+    }
+    Ok((removed, missing.len()))
+}
+
+/// Remove PM chat and task attachment files that no attachment row references
+pub async fn cleanup_orphaned_attachments(
+    pool: &SqlitePool,
+) -> Result<AttachmentGcReport, AttachmentGcError> {
+    let pm_referenced: HashSet<String> = PmAttachment::all_file_paths(pool)
+        .await?
+        .into_iter()
+        .collect();
+    let task_referenced: HashSet<String> = TaskAttachment::all_file_paths(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let (pm_files_removed, pm_missing) = collect_dir(&pm_attachments_dir(), &pm_referenced, "PM")?;
+    let (task_files_removed, task_missing) =
+        collect_dir(&task_attachments_dir(), &task_referenced, "Task")?;
+
+    let report = AttachmentGcReport {
+        pm_files_removed,
+        task_files_removed,
+        missing_files: pm_missing + task_missing,
+    };
+    info!(
+        "Attachment GC removed {} PM and {} task files ({} missing)",
+        report.pm_files_removed, report.task_files_removed, report.missing_files
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn referenced(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_removes_only_unreferenced_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "kept.png",
+            "kept.png.thumb-64x64.png",
+            "orphan.txt",
+            "orphan.png.thumb-64x64.png",
+        ] {
+            std::fs::write(dir.path().join(name), b"data").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("subdir")).unwrap();
+
+        let removed =
+            remove_orphaned_files(dir.path(), &referenced(&["kept.png"]), Duration::ZERO).unwrap();
+
+        assert_eq!(removed, 2);
+        assert!(dir.path().join("kept.png").is_file());
+        assert!(dir.path().join("kept.png.thumb-64x64.png").is_file());
+        assert!(!dir.path().join("orphan.txt").exists());
+        assert!(!dir.path().join("orphan.png.thumb-64x64.png").exists());
+        assert!(dir.path().join("subdir").is_dir());
+    }
+
+    #[test]
+    fn test_removes_unreferenced_files_in_shard_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("ab/cd")).unwrap();
+        for name in [
+            "ab/cd/abcd.png",
+            "ab/cd/abcd.png.thumb-64x64.png",
+            "ab/cd/abce.txt",
+        ] {
+            std::fs::write(dir.path().join(name), b"data").unwrap();
+        }
+
+        let removed =
+            remove_orphaned_files(dir.path(), &referenced(&["ab/cd/abcd.png"]), Duration::ZERO)
+                .unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(dir.path().join("ab/cd/abcd.png").is_file());
+        assert!(dir.path().join("ab/cd/abcd.png.thumb-64x64.png").is_file());
+        assert!(!dir.path().join("ab/cd/abce.txt").exists());
+    }
+
+    #[test]
+    fn test_keeps_recent_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("uploading.bin"), b"data").unwrap();
+
+        let orphans =
+            find_orphaned_files(dir.path(), &HashSet::new(), Duration::from_secs(60)).unwrap();
+
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn test_missing_directory_has_no_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let orphans =
+            find_orphaned_files(&dir.path().join("absent"), &HashSet::new(), Duration::ZERO)
+                .unwrap();
+
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn test_finds_missing_referenced_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("present.pdf"), b"data").unwrap();
+
+        let missing = find_missing_files(dir.path(), &referenced(&["present.pdf", "gone.pdf"]));
+
+        assert_eq!(missing, vec!["gone.pdf".to_string()]);
+    }
+}
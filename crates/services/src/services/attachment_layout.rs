@@ -0,0 +1,261 @@
+//! Content-addressed storage layout for attachment files
+//!
+//! PM chat and task attachment files are stored under a path derived from
+//! their SHA-256, sharded by the first bytes of the hash: `ab/cd/<sha256>.<ext>`.
+//! This keeps directories small and makes identical uploads land on the same
+//! file. Attachment rows store this path relative to the attachments directory.
+//!
+//! Older files were stored flat as `{uuid}_{name}.{ext}`. They are moved into
+//! the sharded layout once on startup ([`relocate_legacy_files`]); files that
+//! could not be moved keep being served from their legacy path.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use db::models::{pm_conversation::PmAttachment, task_attachment::TaskAttachment};
+use sqlx::SqlitePool;
+use tracing::{info, warn};
+
+use super::{attachment_gc::task_attachments_dir, pm_chat_retention::pm_attachments_dir};
+
+/// Longest file extension kept in a content-addressed path
+const MAX_EXTENSION_LEN: usize = 16;
+
+/// Whether `value` looks like a hex-encoded SHA-256
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Extension of `file_name`, lowercased, or an empty string if it has none or
+/// it isn't a plain alphanumeric extension
+pub fn clean_extension(file_name: &str) -> String {
+    Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.len() <= MAX_EXTENSION_LEN && e.bytes().all(|b| b.is_ascii_alphanumeric()))
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// Path, relative to the attachments directory, of a file with content hash
+/// `sha256`: `ab/cd/<sha256>.<extension>` (no extension if it is empty)
+pub fn content_path(sha256: &str, extension: &str) -> String {
+    let sha256 = sha256.to_ascii_lowercase();
+    let file_name = if extension.is_empty() {
+        sha256.clone()
+    } else {
+        format!("{}.{}", sha256, extension)
+    };
+    format!("{}/{}/{}", &sha256[..2], &sha256[2..4], file_name)
+}
+
+/// Whether `file_path` (as stored in an attachment row) uses the sharded layout
+pub fn is_content_path(file_path: &str) -> bool {
+    file_path.contains('/')
+}
+
+/// Location of an attachment file on disk. Rows still pointing at a legacy
+/// flat path resolve to the sharded location when the file has already been
+/// moved there, and to the legacy path otherwise.
+pub fn resolve(dir: &Path, file_path: &str, sha256: Option<&str>) -> PathBuf {
+    dir.join(resolve_relative(dir, file_path, sha256))
+}
+
+/// [`resolve`], as a path relative to `dir` like the ones stored in rows
+pub fn resolve_relative(dir: &Path, file_path: &str, sha256: Option<&str>) -> String {
+    if is_content_path(file_path) || dir.join(file_path).is_file() {
+        return file_path.to_string();
+    }
+    sha256
+        .filter(|sha256| is_sha256(sha256))
+        .map(|sha256| content_path(sha256, &clean_extension(file_path)))
+        .filter(|path| dir.join(path).is_file())
+        .unwrap_or_else(|| file_path.to_string())
+}
+
+/// Move the legacy file `file_path` in `dir` to its content-addressed path.
+/// Returns the new relative path, or `None` if the file is gone from both
+/// locations. A file already present at the new path is kept and the legacy
+/// copy removed.
+pub fn relocate_file(dir: &Path, file_path: &str, sha256: &str) -> io::Result<Option<String>> {
+    let new_path = content_path(sha256, &clean_extension(file_path));
+    let src = dir.join(file_path);
+    let dest = dir.join(&new_path);
+
+    if dest.is_file() {
+        if src.is_file() {
+            std::fs::remove_file(&src)?;
+        }
+        return Ok(Some(new_path));
+    }
+    if !src.is_file() {
+        return Ok(None);
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(&src, &dest)?;
+    Ok(Some(new_path))
+}
+
+/// Outcome of moving legacy attachment files into the sharded layout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelocationReport {
+    pub relocated: usize,
+    pub failed: usize,
+}
+
+/// Relocate every legacy file in `dir`, then point its rows at the new path.
+/// Files that fail to move are logged and left in place.
+async fn relocate_dir<F, Fut>(
+    dir: &Path,
+    files: Vec<(String, String)>,
+    update_file_path: F,
+) -> Result<RelocationReport, sqlx::Error>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<u64, sqlx::Error>>,
+{
+    let mut report = RelocationReport::default();
+    for (file_path, sha256) in files {
+        if !is_sha256(&sha256) {
+            continue;
+        }
+        match relocate_file(dir, &file_path, &sha256) {
+            Ok(Some(new_path)) => {
+                update_file_path(file_path, new_path).await?;
+                report.relocated += 1;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(
+                    "Failed to relocate attachment {} in {:?}: {}",
+                    file_path, dir, e
+                );
+                report.failed += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Move PM chat and task attachment files still stored in the flat legacy
+/// layout into the sharded layout. Safe to run on every startup: only rows
+/// with a legacy path are looked at.
+pub async fn relocate_legacy_files(pool: &SqlitePool) -> Result<RelocationReport, sqlx::Error> {
+    let pm = relocate_dir(
+        &pm_attachments_dir(),
+        PmAttachment::legacy_files(pool).await?,
+        |old, new| async move { PmAttachment::update_file_path(pool, &old, &new).await },
+    )
+    .await?;
+    let task = relocate_dir(
+        &task_attachments_dir(),
+        TaskAttachment::legacy_files(pool).await?,
+        |old, new| async move { TaskAttachment::update_file_path(pool, &old, &new).await },
+    )
+    .await?;
+
+    let report = RelocationReport {
+        relocated: pm.relocated + task.relocated,
+        failed: pm.failed + task.failed,
+    };
+    if report.relocated > 0 || report.failed > 0 {
+        info!(
+            "Relocated {} attachment files to the sharded layout ({} failed)",
+            report.relocated, report.failed
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA: &str = "abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789";
+
+    #[test]
+    fn test_content_path_is_sharded_by_hash() {
+        assert_eq!(content_path(SHA, "png"), format!("ab/cd/{}.png", SHA));
+        assert_eq!(content_path(SHA, ""), format!("ab/cd/{}", SHA));
+    }
+
+    #[test]
+    fn test_clean_extension_drops_unusual_extensions() {
+        assert_eq!(clean_extension("Photo.PNG"), "png");
+        assert_eq!(clean_extension("notes"), "");
+        assert_eq!(clean_extension("weird.p n g"), "");
+        assert_eq!(clean_extension("archive.tar.gz"), "gz");
+    }
+
+    #[test]
+    fn test_relocate_file_moves_into_sharded_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1234_photo.png"), b"png").unwrap();
+
+        let new_path = relocate_file(dir.path(), "1234_photo.png", SHA)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(new_path, content_path(SHA, "png"));
+        assert_eq!(std::fs::read(dir.path().join(&new_path)).unwrap(), b"png");
+        assert!(!dir.path().join("1234_photo.png").exists());
+    }
+
+    #[test]
+    fn test_relocate_file_reuses_existing_content_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join(content_path(SHA, "png"));
+        std::fs::create_dir_all(existing.parent().unwrap()).unwrap();
+        std::fs::write(&existing, b"png").unwrap();
+        std::fs::write(dir.path().join("5678_copy.png"), b"png").unwrap();
+
+        let new_path = relocate_file(dir.path(), "5678_copy.png", SHA)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(dir.path().join(new_path), existing);
+        assert!(!dir.path().join("5678_copy.png").exists());
+    }
+
+    #[test]
+    fn test_relocate_file_skips_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(relocate_file(dir.path(), "gone.png", SHA).unwrap(), None);
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_legacy_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1234_notes.txt"), b"txt").unwrap();
+
+        assert_eq!(
+            resolve(dir.path(), "1234_notes.txt", Some(SHA)),
+            dir.path().join("1234_notes.txt")
+        );
+    }
+
+    #[test]
+    fn test_resolve_finds_relocated_file_of_legacy_row() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1234_notes.txt"), b"txt").unwrap();
+        relocate_file(dir.path(), "1234_notes.txt", SHA).unwrap();
+
+        assert_eq!(
+            resolve(dir.path(), "1234_notes.txt", Some(SHA)),
+            dir.path().join(content_path(SHA, "txt"))
+        );
+        assert_eq!(
+            resolve_relative(dir.path(), "1234_notes.txt", Some(SHA)),
+            content_path(SHA, "txt")
+        );
+        assert_eq!(
+            resolve(dir.path(), "1234_notes.txt", None),
+            dir.path().join("1234_notes.txt")
+        );
+    }
+}
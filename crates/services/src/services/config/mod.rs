@@ -27,6 +27,8 @@ pub type GitHubConfig = versions::v8::GitHubConfig;
 pub type UiLanguage = versions::v8::UiLanguage;
 pub type ShowcaseState = versions::v8::ShowcaseState;
 pub type SendMessageShortcut = versions::v8::SendMessageShortcut;
+pub type PmAttachmentPolicy = versions::v8::PmAttachmentPolicy;
+pub type PmChatSettings = versions::v8::PmChatSettings;
 
 /// Will always return config, trying old schemas or eventually returning default
 pub async fn load_config_from_file(config_path: &PathBuf) -> Config {
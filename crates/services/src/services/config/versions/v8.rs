@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::Error;
 use executors::{executors::BaseCodingAgent, profile::ExecutorProfileId};
 use serde::{Deserialize, Serialize};
@@ -24,6 +26,78 @@ pub enum SendMessageShortcut {
     Enter,
 }
 
+/// Which files may be uploaded as PM chat attachments
+#[derive(Clone, Debug, Serialize, Deserialize, TS, PartialEq, Eq)]
+#[serde(default)]
+pub struct PmAttachmentPolicy {
+    /// Largest accepted upload in bytes
+    pub max_size_bytes: u64,
+    /// Accepted MIME type prefixes such as `image/`; empty accepts every type
+    pub allowed_mime_prefixes: Vec<String>,
+    /// Size limits that replace `max_size_bytes` for matching MIME type
+    /// prefixes; the longest matching prefix wins
+    pub max_size_bytes_by_mime_prefix: HashMap<String, u64>,
+    /// Largest total size in bytes of the files sent in one upload
+    pub max_upload_bytes: u64,
+    /// Extensions (without the dot) that are always rejected
+    pub blocked_extensions: Vec<String>,
+    /// Reject files starting with a `#!` shebang line whatever their
+    /// extension, e.g. scripts renamed to `.txt`
+    pub reject_shebang_scripts: bool,
+}
+
+impl Default for PmAttachmentPolicy {
+    fn default() -> Self {
+        const MB: u64 = 1024 * 1024;
+        Self {
+            max_size_bytes: 20 * MB,
+            allowed_mime_prefixes: [
+                "image/",
+                "text/",
+                "application/pdf",
+                "application/json",
+                "application/xml",
+                "application/msword",
+                "application/vnd.ms-",
+                "application/vnd.openxmlformats-officedocument.",
+            ]
+            .map(String::from)
+            .to_vec(),
+            max_size_bytes_by_mime_prefix: HashMap::from([
+                ("image/".to_string(), 10 * MB),
+                ("text/".to_string(), 5 * MB),
+            ]),
+            max_upload_bytes: 100 * MB,
+            blocked_extensions: [
+                "exe", "dll", "com", "bat", "cmd", "msi", "scr", "ps1", "vbs", "sh", "bash", "zsh",
+                "command", "app", "dmg", "pkg", "deb", "rpm", "apk", "jar", "so", "dylib",
+            ]
+            .map(String::from)
+            .to_vec(),
+            reject_shebang_scripts: false,
+        }
+    }
+}
+
+/// Limits on PM chat AI replies
+#[derive(Clone, Debug, Serialize, Deserialize, TS, PartialEq, Eq)]
+#[serde(default)]
+pub struct PmChatSettings {
+    /// Most AI replies streamed at once for one project
+    pub max_streams_per_project: u32,
+    /// Seconds an AI reply may run before the CLI is stopped
+    pub cli_timeout_secs: u32,
+}
+
+impl Default for PmChatSettings {
+    fn default() -> Self {
+        Self {
+            max_streams_per_project: 2,
+            cli_timeout_secs: 120,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, TS)]
 pub struct Config {
     pub config_version: String,
@@ -56,6 +130,13 @@ pub struct Config {
     pub commit_reminder: bool,
     #[serde(default)]
     pub send_message_shortcut: SendMessageShortcut,
+    #[serde(default)]
+    pub pm_attachment_policy: PmAttachmentPolicy,
+    #[serde(default)]
+    pub pm_chat: PmChatSettings,
+    /// Globs, relative to a repo's `docs/` folder, of docs left out of agent prompts
+    #[serde(default)]
+    pub docs_scan_ignore: Vec<String>,
 }
 
 impl Config {
@@ -85,6 +166,9 @@ impl Config {
             beta_workspaces_invitation_sent: false,
             commit_reminder: false,
             send_message_shortcut: SendMessageShortcut::default(),
+            pm_attachment_policy: PmAttachmentPolicy::default(),
+            pm_chat: PmChatSettings::default(),
+            docs_scan_ignore: Vec::new(),
         }
     }
 
@@ -139,6 +223,9 @@ impl Default for Config {
             beta_workspaces_invitation_sent: false,
             commit_reminder: false,
             send_message_shortcut: SendMessageShortcut::default(),
+            pm_attachment_policy: PmAttachmentPolicy::default(),
+            pm_chat: PmChatSettings::default(),
+            docs_scan_ignore: Vec::new(),
         }
     }
 }
@@ -20,7 +20,7 @@ use db::{
         },
         repo::Repo,
         session::{CreateSession, Session, SessionError},
-        task::{Task, TaskStatus},
+        task::TaskStatus,
         workspace::{Workspace, WorkspaceError},
         workspace_repo::WorkspaceRepo,
     },
@@ -52,9 +52,10 @@ use utils::{
 use uuid::Uuid;
 
 use crate::services::{
-    docs_scanner::get_docs_context_for_workspace,
+    docs_scanner::{ScanOptions, get_docs_context_for_workspace},
     git::{GitService, GitServiceError},
     notification::NotificationService,
+    webhooks,
     workspace_manager::WorkspaceError as WorkspaceManagerError,
     worktree_manager::WorktreeError,
 };
@@ -220,7 +221,7 @@ pub trait ContainerService {
     /// Finalize task execution by updating status to InReview and sending notifications
     async fn finalize_task(&self, ctx: &ExecutionContext) {
         if let Err(e) =
-            Task::update_status(&self.db().pool, ctx.task.id, TaskStatus::InReview).await
+            webhooks::update_task_status(&self.db().pool, ctx.task.id, TaskStatus::InReview).await
         {
             tracing::error!("Failed to update task status to InReview: {e}");
         }
@@ -315,7 +316,8 @@ pub trait ContainerService {
                     Workspace::find_by_id(&self.db().pool, session.workspace_id).await
                 && let Ok(Some(task)) = workspace.parent_task(&self.db().pool).await
                 && let Err(e) =
-                    Task::update_status(&self.db().pool, task.id, TaskStatus::InReview).await
+                    webhooks::update_task_status(&self.db().pool, task.id, TaskStatus::InReview)
+                        .await
             {
                 tracing::error!(
                     "Failed to update task status to InReview for orphaned session: {}",
@@ -589,6 +591,9 @@ pub trait ContainerService {
 
     async fn git_branch_prefix(&self) -> String;
 
+    /// Globs of docs to leave out of the docs context in agent prompts
+    async fn docs_scan_ignore(&self) -> Vec<String>;
+
     async fn git_branch_from_workspace(&self, workspace_id: &Uuid, task_title: &str) -> String {
         let task_title_id = git_branch_id(task_title);
         let prefix = self.git_branch_prefix().await;
@@ -932,7 +937,13 @@ pub trait ContainerService {
         let task_prompt = task.to_prompt();
         let prompt = if let Some(container_ref) = &workspace.container_ref {
             let workspace_path = PathBuf::from(container_ref);
-            if let Some(docs_context) = get_docs_context_for_workspace(&workspace_path).await {
+            let scan_options = ScanOptions {
+                ignore_globs: self.docs_scan_ignore().await,
+                ..Default::default()
+            };
+            if let Some(docs_context) =
+                get_docs_context_for_workspace(&workspace_path, &scan_options).await
+            {
                 format!("{}\n\n# Task\n\n{}", docs_context, task_prompt)
             } else {
                 task_prompt
@@ -1015,7 +1026,7 @@ pub trait ContainerService {
         if task.status != TaskStatus::InProgress
             && run_reason != &ExecutionProcessRunReason::DevServer
         {
-            Task::update_status(&self.db().pool, task.id, TaskStatus::InProgress).await?;
+            webhooks::update_task_status(&self.db().pool, task.id, TaskStatus::InProgress).await?;
         }
         // Create new execution process record
         // Capture current HEAD per repository as the "before" commit for this execution
@@ -1106,7 +1117,7 @@ pub trait ContainerService {
                     update_error
                 );
             }
-            Task::update_status(&self.db().pool, task.id, TaskStatus::InReview).await?;
+            webhooks::update_task_status(&self.db().pool, task.id, TaskStatus::InReview).await?;
 
             // Emit stderr error message
             let log_message = LogMsg::Stderr(format!("Failed to start execution: {start_error}"));
@@ -1,10 +1,29 @@
 //! Documentation scanner for workspace docs folder
 //!
 //! Scans the `docs/` folder in a workspace and builds a context string
-//! to be included in coding agent prompts.
-
-use std::path::Path;
-
+//! to be included in coding agent prompts. Hidden entries, paths matched by a
+//! `.gitignore` or `.vkignore` inside the docs folder, and paths matched by
+//! the configured ignore globs are skipped.
+//!
+//! A leading YAML front-matter block (`---` ... `---`) is parsed into
+//! [`ScannedDoc::metadata`] and removed from the content.
+//!
+//! File contents are cached in memory and only read again once a file's
+//! modification time or size changes. The docs folder itself is walked on
+//! every scan so added, removed and newly ignored files are picked up.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::SystemTime,
+};
+
+use ignore::{WalkBuilder, overrides::OverrideBuilder};
+use serde_json::{Map, Value};
 use tokio::fs;
 use tracing;
 
@@ -14,6 +33,9 @@ const MAX_TOTAL_DOCS_SIZE: usize = 100_000; // ~100KB
 /// Maximum size of a single document (in bytes)
 const MAX_SINGLE_DOC_SIZE: usize = 50_000; // ~50KB
 
+/// Ignore file read in every directory of the docs folder, alongside `.gitignore`
+const VK_IGNORE_FILE: &str = ".vkignore";
+
 /// Supported document extensions
 const SUPPORTED_EXTENSIONS: &[&str] = &["md", "txt", "rst"];
 
@@ -28,22 +50,112 @@ const PRIORITY_DOCS: &[&str] = &[
     "readme",
 ];
 
+/// What `scan_docs_folder` includes. The defaults are the built-in extensions and size limits.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// File extensions to include, without the dot (case-insensitive)
+    pub extensions: Vec<String>,
+    /// Maximum total size of docs content to include (in bytes)
+    pub max_total: usize,
+    /// Maximum size of a single document (in bytes)
+    pub max_single: usize,
+    /// Globs of paths to skip, relative to the docs folder (gitignore syntax)
+    pub ignore_globs: Vec<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            extensions: SUPPORTED_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            max_total: MAX_TOTAL_DOCS_SIZE,
+            max_single: MAX_SINGLE_DOC_SIZE,
+            ignore_globs: Vec::new(),
+        }
+    }
+}
+
+/// Front-matter `status` values that push a document to the lowest priority
+const INACTIVE_DOC_STATUSES: &[&str] =
+    &["draft", "deprecated", "archived", "obsolete", "superseded"];
+
 /// A scanned document with its content
 #[derive(Debug, Clone)]
 pub struct ScannedDoc {
     pub relative_path: String,
+    /// Content without the front-matter block
     pub content: String,
     pub priority: usize,
+    /// `title` from the front matter
+    pub title: Option<String>,
+    /// Front-matter fields; an empty object if the document has none
+    pub metadata: Value,
+    /// Size of the whole file in bytes
+    pub size_bytes: usize,
+    /// True if `content` is only the first `max_single` bytes of the file
+    pub truncated: bool,
+}
+
+/// The result of scanning a docs folder
+#[derive(Debug, Clone, Default)]
+pub struct DocsScan {
+    /// Included documents, highest priority first
+    pub docs: Vec<ScannedDoc>,
+    /// Relative paths of documents left out because of the total size limit
+    pub skipped: Vec<String>,
 }
 
 impl ScannedDoc {
     fn new(relative_path: String, content: String) -> Self {
-        let priority = Self::calculate_priority(&relative_path);
+        let size_bytes = content.len();
+        let (metadata, body) = parse_front_matter(&content);
+        let priority = Self::front_matter_priority(&metadata)
+            .unwrap_or_else(|| Self::calculate_priority(&relative_path));
+        let title = metadata
+            .get("title")
+            .and_then(|t| t.as_str())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+        let content = body.to_string();
         Self {
             relative_path,
             content,
             priority,
+            title,
+            metadata: Value::Object(metadata),
+            size_bytes,
+            truncated: false,
+        }
+    }
+
+    /// A document holding only the first `max_bytes` of `content`
+    fn truncated(relative_path: String, content: &str, max_bytes: usize) -> Self {
+        let mut doc = Self::new(
+            relative_path,
+            truncate_at_char_boundary(content, max_bytes).to_string(),
+        );
+        doc.size_bytes = content.len();
+        doc.truncated = true;
+        doc
+    }
+
+    /// Priority set by the front matter: an explicit `priority:` (a number, or
+    /// high/medium/low), otherwise the lowest priority for inactive `status:` values
+    fn front_matter_priority(metadata: &Map<String, Value>) -> Option<usize> {
+        match metadata.get("priority") {
+            Some(Value::Number(n)) => return n.as_u64().map(|n| n as usize),
+            Some(Value::String(p)) => match p.trim().to_lowercase().as_str() {
+                "urgent" | "critical" | "high" => return Some(PRIORITY_DOCS.len() + 1),
+                "medium" | "normal" => return Some(PRIORITY_DOCS.len() / 2),
+                "low" => return Some(0),
+                _ => {}
+            },
+            _ => {}
         }
+
+        let status = metadata.get("status")?.as_str()?.trim().to_lowercase();
+        INACTIVE_DOC_STATUSES
+            .contains(&status.as_str())
+            .then_some(0)
     }
 
     fn calculate_priority(path: &str) -> usize {
@@ -57,118 +169,277 @@ impl ScannedDoc {
     }
 }
 
+/// The longest prefix of `text` that is at most `max_bytes` long and ends on a
+/// char boundary
+fn truncate_at_char_boundary(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Split a leading YAML front-matter block from `content`. Returns the parsed
+/// fields and the remaining content, or an empty map and `content` unchanged if
+/// there is no front matter or it is not a YAML mapping.
+pub fn parse_front_matter(content: &str) -> (Map<String, Value>, &str) {
+    let Some((yaml, body)) = split_front_matter(content) else {
+        return (Map::new(), content);
+    };
+    match serde_yaml::from_str::<Value>(yaml) {
+        Ok(Value::Object(fields)) => (fields, body),
+        // An empty block
+        Ok(Value::Null) => (Map::new(), body),
+        _ => (Map::new(), content),
+    }
+}
+
+/// The YAML between the opening `---` line and the closing `---` or `...`
+/// line, and the content after it
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---")?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let delimiter = line.trim_end_matches(['\r', '\n']);
+        if delimiter == "---" || delimiter == "..." {
+            let body = &rest[offset + line.len()..];
+            return Some((&rest[..offset], body.trim_start_matches(['\r', '\n'])));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Docs contents shared by all scans in this process
+static DOCS_CACHE: LazyLock<DocsCache> = LazyLock::new(DocsCache::default);
+
+/// A doc file's content as of its last read
+#[derive(Debug, Clone)]
+struct CachedFile {
+    modified: SystemTime,
+    len: u64,
+    content: String,
+}
+
+/// Contents of previously read doc files, keyed by path. An entry is reused
+/// while the file's modification time and size are unchanged.
+#[derive(Debug, Default)]
+pub struct DocsCache {
+    files: Mutex<HashMap<PathBuf, CachedFile>>,
+    reads: AtomicUsize,
+}
+
+impl DocsCache {
+    /// Number of files read from disk through this cache
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    /// Drop the cached files under `dir`
+    pub fn invalidate(&self, dir: &Path) {
+        self.lock().retain(|path, _| !path.starts_with(dir));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, CachedFile>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Read `path`, reusing the cached content if the file is unchanged
+    async fn read(&self, path: &Path) -> std::io::Result<String> {
+        let metadata = fs::metadata(path).await?;
+        let modified = metadata.modified()?;
+        let len = metadata.len();
+        if let Some(cached) = self.lock().get(path)
+            && cached.modified == modified
+            && cached.len == len
+        {
+            return Ok(cached.content.clone());
+        }
+
+        let content = fs::read_to_string(path).await?;
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(
+            path.to_path_buf(),
+            CachedFile {
+                modified,
+                len,
+                content: content.clone(),
+            },
+        );
+        Ok(content)
+    }
+
+    /// Forget files under `dir` that are no longer part of it
+    fn retain_listed(&self, dir: &Path, listed: &[PathBuf]) {
+        let listed: HashSet<&PathBuf> = listed.iter().collect();
+        self.lock()
+            .retain(|path, _| !path.starts_with(dir) || listed.contains(path));
+    }
+}
+
+/// Drop the cached docs of a workspace so the next scan reads every file again
+pub fn invalidate_docs_cache(workspace_path: &Path) {
+    DOCS_CACHE.invalidate(&workspace_path.join("docs"));
+}
+
 /// Scan the docs folder in a workspace and return a list of documents
-pub async fn scan_docs_folder(workspace_path: &Path) -> Vec<ScannedDoc> {
+pub async fn scan_docs_folder(workspace_path: &Path, options: &ScanOptions) -> DocsScan {
+    scan_docs_folder_with_cache(workspace_path, options, &DOCS_CACHE).await
+}
+
+/// [`scan_docs_folder`] with an explicit content cache
+pub async fn scan_docs_folder_with_cache(
+    workspace_path: &Path,
+    options: &ScanOptions,
+    cache: &DocsCache,
+) -> DocsScan {
     let docs_path = workspace_path.join("docs");
 
     if !docs_path.exists() {
         tracing::debug!("No docs folder found at {:?}", docs_path);
-        return Vec::new();
+        return DocsScan::default();
     }
 
+    let walk_root = docs_path.clone();
+    let walk_options = options.clone();
+    let files = match tokio::task::spawn_blocking(move || list_doc_files(&walk_root, &walk_options))
+        .await
+    {
+        Ok(Ok(files)) => files,
+        Ok(Err(e)) => {
+            tracing::warn!("Error scanning docs folder: {}", e);
+            return DocsScan::default();
+        }
+        Err(e) => {
+            tracing::warn!("Docs folder scan failed: {}", e);
+            return DocsScan::default();
+        }
+    };
+
+    cache.retain_listed(&docs_path, &files);
+
     let mut docs = Vec::new();
+    let mut skipped = Vec::new();
     let mut total_size: usize = 0;
 
-    if let Err(e) =
-        scan_directory_recursive(&docs_path, &docs_path, &mut docs, &mut total_size).await
-    {
-        tracing::warn!("Error scanning docs folder: {}", e);
+    for path in files {
+        let relative_path = path
+            .strip_prefix(&docs_path)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+
+        // Once the total size is reached, remaining files are only reported
+        if total_size >= options.max_total {
+            skipped.push(relative_path);
+            continue;
+        }
+
+        // Read file content
+        match cache.read(&path).await {
+            Ok(content) => {
+                // Include only the first `max_single` bytes of large files
+                let doc = if content.len() > options.max_single {
+                    tracing::debug!(
+                        "Truncating {:?}: file too large ({} bytes)",
+                        path,
+                        content.len()
+                    );
+                    ScannedDoc::truncated(relative_path, &content, options.max_single)
+                } else {
+                    ScannedDoc::new(relative_path, content)
+                };
+
+                // Skip if would exceed total size
+                let included_size = doc.size_bytes.min(options.max_single);
+                if total_size + included_size > options.max_total {
+                    tracing::debug!("Skipping {:?}: would exceed total size limit", path);
+                    skipped.push(doc.relative_path);
+                    continue;
+                }
+
+                total_size += included_size;
+                docs.push(doc);
+            }
+            Err(e) => {
+                tracing::debug!("Failed to read {:?}: {}", path, e);
+            }
+        }
     }
 
     // Sort by priority (highest first)
     docs.sort_by(|a, b| b.priority.cmp(&a.priority));
 
     tracing::info!(
-        "Scanned {} docs from {:?} (total size: {} bytes)",
+        "Scanned {} docs from {:?} (total size: {} bytes, {} skipped)",
         docs.len(),
         docs_path,
-        total_size
+        total_size,
+        skipped.len()
     );
 
-    docs
+    DocsScan { docs, skipped }
 }
 
-async fn scan_directory_recursive(
-    base_path: &Path,
-    current_path: &Path,
-    docs: &mut Vec<ScannedDoc>,
-    total_size: &mut usize,
-) -> Result<(), std::io::Error> {
-    let mut entries = fs::read_dir(current_path).await?;
-
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
-
-        if path.is_dir() {
-            // Skip hidden directories
-            if path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .map(|n| n.starts_with('.'))
-                .unwrap_or(false)
-            {
-                continue;
-            }
-            Box::pin(scan_directory_recursive(base_path, &path, docs, total_size)).await?;
-        } else if path.is_file() {
-            // Check if we've exceeded total size
-            if *total_size >= MAX_TOTAL_DOCS_SIZE {
-                tracing::debug!("Reached max total docs size, stopping scan");
-                break;
-            }
-
-            // Check extension
-            let extension = path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|e| e.to_lowercase());
+/// List the files with one of the `options` extensions under `docs_path`,
+/// honoring hidden entries, ignore files and the ignore globs
+fn list_doc_files(docs_path: &Path, options: &ScanOptions) -> Result<Vec<PathBuf>, ignore::Error> {
+    let mut overrides = OverrideBuilder::new(docs_path);
+    for glob in &options.ignore_globs {
+        // Override globs whitelist by default; a leading `!` makes them ignore
+        overrides.add(&format!("!{}", glob.trim()))?;
+    }
 
-            if let Some(ext) = extension {
-                if !SUPPORTED_EXTENSIONS.contains(&ext.as_str()) {
-                    continue;
-                }
-            } else {
+    let walker = WalkBuilder::new(docs_path)
+        .follow_links(false)
+        .hidden(true)
+        .parents(false)
+        .git_ignore(true)
+        .git_global(false)
+        .git_exclude(false)
+        .require_git(false)
+        .add_custom_ignore_filename(VK_IGNORE_FILE)
+        .overrides(overrides.build()?)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!("Skipping docs entry: {}", e);
                 continue;
             }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
 
-            // Read file content
-            match fs::read_to_string(&path).await {
-                Ok(content) => {
-                    let content_size = content.len();
-
-                    // Skip if single file is too large
-                    if content_size > MAX_SINGLE_DOC_SIZE {
-                        tracing::debug!(
-                            "Skipping {:?}: file too large ({} bytes)",
-                            path,
-                            content_size
-                        );
-                        continue;
-                    }
-
-                    // Skip if would exceed total size
-                    if *total_size + content_size > MAX_TOTAL_DOCS_SIZE {
-                        tracing::debug!("Skipping {:?}: would exceed total size limit", path);
-                        continue;
-                    }
-
-                    let relative_path = path
-                        .strip_prefix(base_path)
-                        .unwrap_or(&path)
-                        .to_string_lossy()
-                        .to_string();
-
-                    *total_size += content_size;
-                    docs.push(ScannedDoc::new(relative_path, content));
-                }
-                Err(e) => {
-                    tracing::debug!("Failed to read {:?}: {}", path, e);
-                }
-            }
+        // Check extension
+        let supported = entry
+            .path()
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| {
+                options
+                    .extensions
+                    .iter()
+                    .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(ext))
+            });
+        if supported {
+            files.push(entry.into_path());
         }
     }
 
-    Ok(())
+    Ok(files)
 }
 
 /// Build a context string from scanned documents
@@ -186,6 +457,12 @@ pub fn build_docs_context(docs: &[ScannedDoc]) -> Option<String> {
 
     for doc in docs {
         context.push_str(&format!("## docs/{}\n\n", doc.relative_path));
+        if doc.truncated {
+            context.push_str(&format!(
+                "(Truncated: only the first part of this {} byte file is included)\n\n",
+                doc.size_bytes
+            ));
+        }
         context.push_str(&doc.content);
         context.push_str("\n\n---\n\n");
     }
@@ -194,9 +471,12 @@ pub fn build_docs_context(docs: &[ScannedDoc]) -> Option<String> {
 }
 
 /// Scan docs folder and build a context string for the coding agent prompt
-pub async fn get_docs_context_for_workspace(workspace_path: &Path) -> Option<String> {
-    let docs = scan_docs_folder(workspace_path).await;
-    build_docs_context(&docs)
+pub async fn get_docs_context_for_workspace(
+    workspace_path: &Path,
+    options: &ScanOptions,
+) -> Option<String> {
+    let scan = scan_docs_folder(workspace_path, options).await;
+    build_docs_context(&scan.docs)
 }
 
 #[cfg(test)]
@@ -211,7 +491,9 @@ mod tests {
         let docs_path = temp_dir.path().join("docs");
         fs::create_dir(&docs_path).await.unwrap();
 
-        let docs = scan_docs_folder(temp_dir.path()).await;
+        let docs = scan_docs_folder(temp_dir.path(), &ScanOptions::default())
+            .await
+            .docs;
         assert!(docs.is_empty());
     }
 
@@ -231,7 +513,9 @@ mod tests {
             .await
             .unwrap();
 
-        let docs = scan_docs_folder(temp_dir.path()).await;
+        let docs = scan_docs_folder(temp_dir.path(), &ScanOptions::default())
+            .await
+            .docs;
         assert_eq!(docs.len(), 2);
 
         // Requirements should be first (higher priority)
@@ -241,10 +525,181 @@ mod tests {
     #[tokio::test]
     async fn test_no_docs_folder() {
         let temp_dir = TempDir::new().unwrap();
-        let docs = scan_docs_folder(temp_dir.path()).await;
+        let docs = scan_docs_folder(temp_dir.path(), &ScanOptions::default())
+            .await
+            .docs;
         assert!(docs.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_scan_skips_ignored_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs_path = temp_dir.path().join("docs");
+        fs::create_dir_all(docs_path.join("generated"))
+            .await
+            .unwrap();
+        fs::create_dir_all(docs_path.join("vendor")).await.unwrap();
+        fs::create_dir_all(docs_path.join("drafts")).await.unwrap();
+
+        fs::write(docs_path.join(".gitignore"), "generated/\n")
+            .await
+            .unwrap();
+        fs::write(docs_path.join(".vkignore"), "drafts/\n")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("design.md"), "# Design")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("generated/api.md"), "# Generated API")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("vendor/lib.md"), "# Vendored")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("drafts/idea.md"), "# Draft")
+            .await
+            .unwrap();
+
+        let options = ScanOptions {
+            ignore_globs: vec!["vendor/**".to_string()],
+            ..Default::default()
+        };
+        let docs = scan_docs_folder(temp_dir.path(), &options).await.docs;
+
+        let paths: Vec<&str> = docs.iter().map(|d| d.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["design.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_scan_with_custom_extensions() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs_path = temp_dir.path().join("docs");
+        fs::create_dir(&docs_path).await.unwrap();
+
+        fs::write(docs_path.join("guide.adoc"), "= Guide")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("intro.MDX"), "# Intro")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("notes.md"), "# Notes")
+            .await
+            .unwrap();
+
+        let options = ScanOptions {
+            extensions: vec!["adoc".to_string(), ".mdx".to_string()],
+            ..Default::default()
+        };
+        let docs = scan_docs_folder(temp_dir.path(), &options).await.docs;
+
+        let paths: Vec<&str> = docs.iter().map(|d| d.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["guide.adoc", "intro.MDX"]);
+    }
+
+    #[tokio::test]
+    async fn test_scan_with_custom_size_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs_path = temp_dir.path().join("docs");
+        fs::create_dir(&docs_path).await.unwrap();
+
+        fs::write(docs_path.join("a.md"), "x".repeat(10))
+            .await
+            .unwrap();
+        fs::write(docs_path.join("b.md"), "x".repeat(30))
+            .await
+            .unwrap();
+        fs::write(docs_path.join("c.md"), "x".repeat(10))
+            .await
+            .unwrap();
+        fs::write(docs_path.join("d.md"), "x".repeat(10))
+            .await
+            .unwrap();
+
+        let options = ScanOptions {
+            max_total: 25,
+            max_single: 20,
+            ..Default::default()
+        };
+        let scan = scan_docs_folder(temp_dir.path(), &options).await;
+
+        // b.md truncated to 20 bytes and d.md would both exceed the total
+        let paths: Vec<&str> = scan.docs.iter().map(|d| d.relative_path.as_str()).collect();
+        assert_eq!(paths, vec!["a.md", "c.md"]);
+        assert_eq!(scan.skipped, vec!["b.md", "d.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_doc_is_truncated() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs_path = temp_dir.path().join("docs");
+        fs::create_dir(&docs_path).await.unwrap();
+
+        // 'é' is two bytes, so the cap falls inside a character
+        fs::write(docs_path.join("big.md"), "aé".repeat(10))
+            .await
+            .unwrap();
+        fs::write(docs_path.join("small.md"), "ok").await.unwrap();
+
+        let options = ScanOptions {
+            max_single: 8,
+            ..Default::default()
+        };
+        let scan = scan_docs_folder(temp_dir.path(), &options).await;
+
+        assert!(scan.skipped.is_empty());
+        let big = &scan.docs[0];
+        assert_eq!(big.relative_path, "big.md");
+        assert!(big.truncated);
+        assert_eq!(big.size_bytes, 30);
+        assert_eq!(big.content, "aéaéa");
+
+        let small = &scan.docs[1];
+        assert!(!small.truncated);
+        assert_eq!(small.size_bytes, 2);
+    }
+
+    #[tokio::test]
+    async fn test_rescan_reuses_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs_path = temp_dir.path().join("docs");
+        fs::create_dir(&docs_path).await.unwrap();
+        fs::write(docs_path.join("design.md"), "# Design")
+            .await
+            .unwrap();
+        fs::write(docs_path.join("spec.md"), "# Spec")
+            .await
+            .unwrap();
+
+        let cache = DocsCache::default();
+        let options = ScanOptions::default();
+
+        let first = scan_docs_folder_with_cache(temp_dir.path(), &options, &cache)
+            .await
+            .docs;
+        assert_eq!(first.len(), 2);
+        assert_eq!(cache.reads(), 2);
+
+        let second = scan_docs_folder_with_cache(temp_dir.path(), &options, &cache)
+            .await
+            .docs;
+        assert_eq!(second.len(), 2);
+        assert_eq!(cache.reads(), 2);
+
+        // Only the changed file is read again
+        fs::write(docs_path.join("spec.md"), "# Spec v2")
+            .await
+            .unwrap();
+        let third = scan_docs_folder_with_cache(temp_dir.path(), &options, &cache)
+            .await
+            .docs;
+        assert!(third.iter().any(|d| d.content == "# Spec v2"));
+        assert_eq!(cache.reads(), 3);
+
+        cache.invalidate(&docs_path);
+        scan_docs_folder_with_cache(temp_dir.path(), &options, &cache).await;
+        assert_eq!(cache.reads(), 5);
+    }
+
     #[test]
     fn test_priority_calculation() {
         assert!(ScannedDoc::calculate_priority("requirements.md") > 0);
@@ -258,6 +713,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_front_matter_is_parsed_and_stripped() {
+        let doc = ScannedDoc::new(
+            "notes.md".to_string(),
+            "---\ntitle: Checkout PRD\nstatus: approved\nowner: alice\n---\n\n# Checkout\n"
+                .to_string(),
+        );
+
+        assert_eq!(doc.title.as_deref(), Some("Checkout PRD"));
+        assert_eq!(doc.metadata["owner"], "alice");
+        assert_eq!(doc.content, "# Checkout\n");
+        assert_eq!(doc.priority, 0);
+    }
+
+    #[test]
+    fn test_doc_without_front_matter_is_unchanged() {
+        let content = "# Design\n\n---\n\nA horizontal rule, not front matter\n";
+        let doc = ScannedDoc::new("design.md".to_string(), content.to_string());
+
+        assert_eq!(doc.title, None);
+        assert_eq!(doc.metadata, serde_json::json!({}));
+        assert_eq!(doc.content, content);
+        assert_eq!(doc.priority, ScannedDoc::calculate_priority("design.md"));
+    }
+
+    #[test]
+    fn test_unterminated_or_invalid_front_matter_is_kept() {
+        for content in ["---\ntitle: Open\n# Body", "---\n- a\n- b\n---\nBody"] {
+            let (metadata, body) = parse_front_matter(content);
+            assert!(metadata.is_empty());
+            assert_eq!(body, content);
+        }
+    }
+
+    #[test]
+    fn test_front_matter_priority_and_status() {
+        let priority = |front_matter: &str| {
+            ScannedDoc::new(
+                "requirements.md".to_string(),
+                format!("---\n{front_matter}\n---\nBody"),
+            )
+            .priority
+        };
+        let filename_priority = ScannedDoc::calculate_priority("requirements.md");
+
+        assert!(priority("priority: high") > filename_priority);
+        assert_eq!(priority("priority: low"), 0);
+        assert_eq!(priority("priority: 3"), 3);
+        assert_eq!(priority("status: draft"), 0);
+        assert_eq!(
+            priority("status: Draft\npriority: high"),
+            PRIORITY_DOCS.len() + 1
+        );
+        assert_eq!(priority("status: approved"), filename_priority);
+    }
+
     #[test]
     fn test_build_docs_context_empty() {
         let docs: Vec<ScannedDoc> = vec![];
@@ -13,6 +13,8 @@ use tokio::sync::RwLock;
 use utils::msg_store::MsgStore;
 use uuid::Uuid;
 
+#[path = "events/board.rs"]
+pub mod board;
 #[path = "events/patches.rs"]
 pub mod patches;
 #[path = "events/streams.rs"]
@@ -23,6 +25,7 @@ pub mod types;
 pub use patches::{
     execution_process_patch, project_patch, scratch_patch, task_patch, workspace_patch,
 };
+pub use board::{BoardEvent, BoardEvents};
 pub use types::{EventError, EventPatch, EventPatchInner, HookTables, RecordTypes};
 
 #[derive(Clone)]
@@ -31,6 +34,7 @@ pub struct EventService {
     db: DBService,
     #[allow(dead_code)]
     entry_count: Arc<RwLock<usize>>,
+    board: BoardEvents,
 }
 
 impl EventService {
@@ -40,9 +44,15 @@ impl EventService {
             msg_store,
             db,
             entry_count,
+            board: BoardEvents::default(),
         }
     }
 
+    /// Task board changes published by the task and label handlers
+    pub fn board(&self) -> &BoardEvents {
+        &self.board
+    }
+
     async fn push_task_update_for_task(
         pool: &SqlitePool,
         msg_store: Arc<MsgStore>,
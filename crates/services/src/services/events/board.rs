@@ -0,0 +1,124 @@
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Events buffered per subscriber before slow subscribers start missing some
+const BOARD_EVENTS_CAPACITY: usize = 1024;
+
+/// Change to a project's task board, published by the task and label handlers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BoardEvent {
+    TaskCreated {
+        task_id: Uuid,
+    },
+    /// Fields, labels, dependencies or position of a task changed, or it was deleted
+    TaskUpdated {
+        task_id: Uuid,
+    },
+    /// A label was created, updated, merged or deleted
+    LabelChanged {
+        label_id: Uuid,
+    },
+}
+
+impl BoardEvent {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoardEvent::TaskCreated { .. } => "task_created",
+            BoardEvent::TaskUpdated { .. } => "task_updated",
+            BoardEvent::LabelChanged { .. } => "label_changed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ProjectBoardEvent {
+    project_id: Uuid,
+    event: BoardEvent,
+}
+
+/// Broadcasts board events to the subscribers of each project
+#[derive(Clone)]
+pub struct BoardEvents {
+    sender: broadcast::Sender<ProjectBoardEvent>,
+}
+
+impl Default for BoardEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(BOARD_EVENTS_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl BoardEvents {
+    /// Publish `event` to the subscribers of `project_id`. Without subscribers
+    /// the event is dropped.
+    pub fn publish(&self, project_id: Uuid, event: BoardEvent) {
+        let _ = self.sender.send(ProjectBoardEvent { project_id, event });
+    }
+
+    /// Events of `project_id` published from now on. Events missed by a lagging
+    /// subscriber are skipped.
+    pub fn subscribe(&self, project_id: Uuid) -> impl Stream<Item = BoardEvent> + use<> {
+        futures::stream::unfold(self.sender.subscribe(), move |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(e) if e.project_id == project_id => return Some((e.event, receiver)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "Board event subscriber for project {} skipped {} events",
+                            project_id,
+                            skipped
+                        );
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_only_receive_their_project() {
+        let board = BoardEvents::default();
+        let (project_id, other_project_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (task_id, label_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut events = Box::pin(board.subscribe(project_id));
+
+        board.publish(other_project_id, BoardEvent::TaskCreated { task_id });
+        board.publish(project_id, BoardEvent::TaskUpdated { task_id });
+        board.publish(project_id, BoardEvent::LabelChanged { label_id });
+
+        assert_eq!(
+            events.next().await,
+            Some(BoardEvent::TaskUpdated { task_id })
+        );
+        assert_eq!(
+            events.next().await,
+            Some(BoardEvent::LabelChanged { label_id })
+        );
+    }
+
+    #[test]
+    fn test_event_serializes_with_type_tag() {
+        let task_id = Uuid::new_v4();
+        let event = BoardEvent::TaskCreated { task_id };
+
+        assert_eq!(event.name(), "task_created");
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "type": "task_created", "task_id": task_id })
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub mod analytics;
 pub mod approvals;
+pub mod attachment_gc;
+pub mod attachment_layout;
 pub mod auth;
 pub mod config;
 pub mod container;
@@ -15,6 +17,8 @@ pub mod git_host;
 pub mod image;
 pub mod notification;
 pub mod oauth_credentials;
+pub mod pm_attachments;
+pub mod pm_chat_retention;
 pub mod pr_monitor;
 pub mod project;
 #[cfg(feature = "qa-mode")]
@@ -22,5 +26,7 @@ pub mod qa_repos;
 pub mod queued_message;
 pub mod remote_client;
 pub mod repo;
+pub mod task_inference;
+pub mod webhooks;
 pub mod workspace_manager;
 pub mod worktree_manager;
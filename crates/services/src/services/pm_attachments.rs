@@ -0,0 +1,632 @@
+//! PM chat attachment files
+//!
+//! Uploads with identical content (same SHA-256) share one file on disk. Every
+//! upload still gets its own `pm_attachments` row, and a file is only removed
+//! once the last row referencing it has been deleted.
+//!
+//! Uploads are checked against the [`PmAttachmentPolicy`] from the user config
+//! before anything is stored. Uploads are streamed to a temporary file in the
+//! attachments directory first, so the policy is checked in two steps: the
+//! file type and size limit from the leading bytes, then the size as it grows.
+//!
+//! Thumbnails of image attachments are generated on first request and cached
+//! next to the original file, one file per requested size. The pixel size of
+//! images is recorded on upload so clients can lay them out before loading.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use image::{ImageFormat, ImageReader};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::services::config::PmAttachmentPolicy;
+
+/// Leading bytes of native executables, rejected whatever their extension.
+/// Windows executables are detected by [`is_pe_executable`].
+const EXECUTABLE_SIGNATURES: &[&[u8]] = &[
+    b"\x7fELF",          // Linux/BSD
+    b"\xfe\xed\xfa\xce", // Mach-O 32-bit
+    b"\xfe\xed\xfa\xcf", // Mach-O 64-bit
+    b"\xce\xfa\xed\xfe", // Mach-O 32-bit, little endian
+    b"\xcf\xfa\xed\xfe", // Mach-O 64-bit, little endian
+    b"\xca\xfe\xba\xbe", // Mach-O universal
+];
+
+/// Offset of `e_lfanew` in the MS-DOS header, the offset of the PE header
+const PE_HEADER_OFFSET_FIELD: usize = 0x3c;
+
+/// Whether `head` starts a Windows PE executable: an MS-DOS header ("MZ")
+/// whose `e_lfanew` field points at the `PE\0\0` signature. Checking "MZ"
+/// alone would reject text that happens to start with those letters.
+fn is_pe_executable(head: &[u8]) -> bool {
+    let Some(field) = head.get(PE_HEADER_OFFSET_FIELD..PE_HEADER_OFFSET_FIELD + 4) else {
+        return false;
+    };
+    if !head.starts_with(b"MZ") {
+        return false;
+    }
+    let offset = u32::from_le_bytes([field[0], field[1], field[2], field[3]]) as usize;
+    head.get(offset..offset.saturating_add(4)) == Some(&b"PE\0\0"[..])
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AttachmentPolicyError {
+    #[error("Files with the .{0} extension are not allowed")]
+    BlockedExtension(String),
+    #[error("Executable files are not allowed")]
+    Executable,
+    #[error("File type {0} is not allowed")]
+    MimeTypeNotAllowed(String),
+    #[error("File too large: {size} bytes (max for {mime_type}: {max} bytes)")]
+    TooLarge {
+        size: u64,
+        max: u64,
+        mime_type: String,
+    },
+}
+
+/// Check an upload against the attachment policy.
+/// `mime_type` is the type detected for the upload, not the one the client sent.
+pub fn check_policy(
+    policy: &PmAttachmentPolicy,
+    file_name: &str,
+    mime_type: &str,
+    data: &[u8],
+) -> Result<(), AttachmentPolicyError> {
+    let max = upload_size_limit(policy, file_name, mime_type, data)?;
+    let size = data.len() as u64;
+    if size > max {
+        return Err(AttachmentPolicyError::TooLarge {
+            size,
+            max,
+            mime_type: mime_type.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Check everything but the size of an upload against the attachment policy,
+/// given its leading bytes, and return the size limit that applies to it.
+pub fn upload_size_limit(
+    policy: &PmAttachmentPolicy,
+    file_name: &str,
+    mime_type: &str,
+    head: &[u8],
+) -> Result<u64, AttachmentPolicyError> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    if let Some(extension) = extension
+        && policy.blocked_extensions.iter().any(|blocked| {
+            blocked
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(&extension)
+        })
+    {
+        return Err(AttachmentPolicyError::BlockedExtension(extension));
+    }
+
+    if EXECUTABLE_SIGNATURES
+        .iter()
+        .any(|sig| head.starts_with(sig))
+        || is_pe_executable(head)
+        || (policy.reject_shebang_scripts && head.starts_with(b"#!"))
+    {
+        return Err(AttachmentPolicyError::Executable);
+    }
+
+    if !policy.allowed_mime_prefixes.is_empty()
+        && !policy
+            .allowed_mime_prefixes
+            .iter()
+            .any(|prefix| mime_type.starts_with(prefix.as_str()))
+    {
+        return Err(AttachmentPolicyError::MimeTypeNotAllowed(
+            mime_type.to_string(),
+        ));
+    }
+
+    Ok(policy
+        .max_size_bytes_by_mime_prefix
+        .iter()
+        .filter(|(prefix, _)| mime_type.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, max)| *max)
+        .unwrap_or(policy.max_size_bytes))
+}
+
+/// Store an `uploaded` file (already written to a temporary file in `dir`),
+/// reusing `existing_file` (a file already holding the same content) when it is
+/// still on disk, in which case the upload is removed. Otherwise the upload is
+/// moved to `new_file_name` (a content-addressed path, see
+/// [`attachment_layout`](super::attachment_layout)), unless a file with the
+/// same content is already stored there.
+/// Returns the file name, relative to `dir`, the attachment record should point at.
+pub fn store_file(
+    dir: &Path,
+    existing_file: Option<&str>,
+    new_file_name: String,
+    uploaded: &Path,
+) -> io::Result<String> {
+    if let Some(existing) = existing_file
+        && dir.join(existing).is_file()
+    {
+        remove_duplicate_upload(uploaded);
+        return Ok(existing.to_string());
+    }
+
+    let dest = dir.join(&new_file_name);
+    if dest.is_file() {
+        remove_duplicate_upload(uploaded);
+        return Ok(new_file_name);
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(uploaded, dest)?;
+    Ok(new_file_name)
+}
+
+fn remove_duplicate_upload(uploaded: &Path) {
+    if let Err(e) = std::fs::remove_file(uploaded) {
+        warn!("Failed to remove duplicate upload {:?}: {}", uploaded, e);
+    }
+}
+
+/// Put a copy of `src_dir/src_file` at `dest_dir/dest_file`, e.g. to turn a PM
+/// attachment into a task attachment. The file is hard-linked when possible
+/// (attachment files are never modified in place) and copied otherwise, e.g.
+/// across file systems. The directory `dest_file` goes in is created if needed.
+pub fn link_or_copy_file(
+    src_dir: &Path,
+    src_file: &str,
+    dest_dir: &Path,
+    dest_file: &str,
+) -> io::Result<()> {
+    let src = src_dir.join(src_file);
+    let dest = dest_dir.join(dest_file);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::hard_link(&src, &dest).is_err() {
+        std::fs::copy(&src, &dest)?;
+    }
+    Ok(())
+}
+
+/// Remove an attachment file, and its cached thumbnails, once no attachment
+/// record references it. Returns true if the file was removed.
+pub fn release_file(dir: &Path, file_path: &str, remaining_references: i64) -> bool {
+    if remaining_references > 0 {
+        return false;
+    }
+
+    remove_thumbnails(dir, file_path);
+    let path = dir.join(file_path);
+    if !path.exists() {
+        return false;
+    }
+    match std::fs::remove_file(&path) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("Failed to remove PM attachment {:?}: {}", path, e);
+            false
+        }
+    }
+}
+
+/// Pixel size of an uploaded image file, read from its header without decoding
+/// the pixel data. `None` for non-images and formats that can't be probed.
+pub fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Default thumbnail bounding box when the request doesn't give one
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+const MIN_THUMBNAIL_SIZE: u32 = 16;
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+#[derive(Debug, Error)]
+pub enum ThumbnailError {
+    #[error("Thumbnails are only available for PNG and JPEG images, not {0}")]
+    UnsupportedType(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Failed to generate thumbnail: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Image types thumbnails can be generated for, with the format they are stored in
+fn thumbnail_format(mime_type: &str) -> Option<ImageFormat> {
+    match mime_type {
+        "image/png" => Some(ImageFormat::Png),
+        "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+        _ => None,
+    }
+}
+
+/// Prefix shared by all cached thumbnails of `file_path`
+fn thumbnail_prefix(file_path: &str) -> String {
+    format!("{}.thumb-", file_path)
+}
+
+/// Attachment file a cached thumbnail was generated from, if `file_name` is a thumbnail
+pub fn thumbnail_source(file_name: &str) -> Option<&str> {
+    file_name.rfind(".thumb-").map(|i| &file_name[..i])
+}
+
+/// Get the thumbnail of an image attachment that fits in `width` x `height`
+/// (clamped to 16..=1024), keeping the aspect ratio. The thumbnail is
+/// generated on the first request for a size and reused afterwards.
+/// Returns the thumbnail path and its MIME type.
+pub fn thumbnail(
+    dir: &Path,
+    file_path: &str,
+    mime_type: &str,
+    width: u32,
+    height: u32,
+) -> Result<(PathBuf, &'static str), ThumbnailError> {
+    let format = thumbnail_format(mime_type)
+        .ok_or_else(|| ThumbnailError::UnsupportedType(mime_type.to_string()))?;
+    let width = width.clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+    let height = height.clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+    let extension = format.extensions_str()[0];
+    let path = dir.join(format!(
+        "{}{}x{}.{}",
+        thumbnail_prefix(file_path),
+        width,
+        height,
+        extension
+    ));
+
+    if !path.is_file() {
+        let image = ImageReader::open(dir.join(file_path))?
+            .with_guessed_format()?
+            .decode()?;
+        // Write to a temporary name first so a concurrent request never reads a partial file
+        let partial = path.with_extension(format!("{}.partial", extension));
+        image
+            .thumbnail(width, height)
+            .save_with_format(&partial, format)?;
+        std::fs::rename(&partial, &path)?;
+    }
+
+    Ok((path, format.to_mime_type()))
+}
+
+/// Remove the cached thumbnails of an attachment file
+fn remove_thumbnails(dir: &Path, file_path: &str) {
+    // Thumbnails sit next to their file, which may be in a shard subdirectory
+    let path = dir.join(file_path);
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    let prefix = thumbnail_prefix(&file_name.to_string_lossy());
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix)
+            && let Err(e) = std::fs::remove_file(entry.path())
+        {
+            warn!(
+                "Failed to remove PM attachment thumbnail {:?}: {}",
+                entry.path(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_rejects_oversized_image() {
+        let policy = PmAttachmentPolicy::default();
+        let image_max = policy.max_size_bytes_by_mime_prefix["image/"];
+        let data = vec![0u8; image_max as usize + 1];
+
+        assert_eq!(
+            check_policy(&policy, "photo.png", "image/png", &data),
+            Err(AttachmentPolicyError::TooLarge {
+                size: image_max + 1,
+                max: image_max,
+                mime_type: "image/png".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_policy_rejects_blocked_extension() {
+        let policy = PmAttachmentPolicy::default();
+
+        assert_eq!(
+            check_policy(&policy, "deploy.SH", "text/plain", b"echo hi"),
+            Err(AttachmentPolicyError::BlockedExtension("sh".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_policy_rejects_renamed_script_when_shebangs_are_rejected() {
+        let policy = PmAttachmentPolicy {
+            reject_shebang_scripts: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_policy(&policy, "notes.txt", "text/plain", b"#!/bin/sh\nrm -rf ~"),
+            Err(AttachmentPolicyError::Executable)
+        );
+    }
+
+    #[test]
+    fn test_policy_allows_text_starting_with_shebang_by_default() {
+        let policy = PmAttachmentPolicy::default();
+
+        assert_eq!(
+            check_policy(
+                &policy,
+                "setup.md",
+                "text/markdown",
+                b"#!/usr/bin/env bash is the first line of every script"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_policy_allows_text_starting_with_mz() {
+        let policy = PmAttachmentPolicy::default();
+
+        let padded = [&b"MZ notes"[..], &[b' '; 300]].concat();
+
+        for data in [&b"MZ"[..], b"MZ-4 migration notes", &padded] {
+            assert_eq!(
+                check_policy(&policy, "notes.txt", "text/plain", data),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_policy_rejects_pe_executable_whatever_its_name() {
+        let policy = PmAttachmentPolicy::default();
+        let mut data = vec![0u8; 0x100];
+        data[..2].copy_from_slice(b"MZ");
+        data[PE_HEADER_OFFSET_FIELD..PE_HEADER_OFFSET_FIELD + 4]
+            .copy_from_slice(&0x80u32.to_le_bytes());
+        data[0x80..0x84].copy_from_slice(b"PE\0\0");
+
+        assert_eq!(
+            check_policy(&policy, "report.pdf", "application/pdf", &data),
+            Err(AttachmentPolicyError::Executable)
+        );
+    }
+
+    #[test]
+    fn test_policy_rejects_mime_type_outside_allow_list() {
+        let policy = PmAttachmentPolicy::default();
+
+        assert_eq!(
+            check_policy(&policy, "blob", "application/octet-stream", b"data"),
+            Err(AttachmentPolicyError::MimeTypeNotAllowed(
+                "application/octet-stream".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_policy_allows_file_within_limits() {
+        let policy = PmAttachmentPolicy::default();
+
+        assert_eq!(
+            check_policy(&policy, "spec.pdf", "application/pdf", b"%PDF-1.7"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_upload_size_limit_uses_most_specific_prefix() {
+        let policy = PmAttachmentPolicy::default();
+
+        assert_eq!(
+            upload_size_limit(&policy, "photo.png", "image/png", b"\x89PNG"),
+            Ok(policy.max_size_bytes_by_mime_prefix["image/"])
+        );
+        assert_eq!(
+            upload_size_limit(&policy, "spec.pdf", "application/pdf", b"%PDF-1.7"),
+            Ok(policy.max_size_bytes)
+        );
+    }
+
+    /// Write `data` to a temporary upload file in `dir`
+    fn upload(dir: &Path, data: &[u8]) -> PathBuf {
+        let path = dir.join(format!(".upload-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_store_file_reuses_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploaded = upload(dir.path(), b"png");
+        let first = store_file(dir.path(), None, "a_image.png".to_string(), &uploaded).unwrap();
+        assert_eq!(first, "a_image.png");
+        assert!(!uploaded.exists());
+
+        let uploaded = upload(dir.path(), b"png");
+        let second = store_file(
+            dir.path(),
+            Some(&first),
+            "b_image.png".to_string(),
+            &uploaded,
+        )
+        .unwrap();
+
+        assert_eq!(second, first);
+        assert!(!dir.path().join("b_image.png").exists());
+        assert!(!uploaded.exists());
+    }
+
+    #[test]
+    fn test_store_file_creates_shard_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploaded = upload(dir.path(), b"png");
+        let stored = store_file(dir.path(), None, "ab/cd/abcd.png".to_string(), &uploaded).unwrap();
+        assert_eq!(std::fs::read(dir.path().join(&stored)).unwrap(), b"png");
+
+        // Same content uploaded again lands on the same file
+        let uploaded = upload(dir.path(), b"png");
+        let again = store_file(dir.path(), None, stored.clone(), &uploaded).unwrap();
+        assert_eq!(again, stored);
+        assert!(!uploaded.exists());
+    }
+
+    #[test]
+    fn test_store_file_writes_new_file_when_existing_is_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploaded = upload(dir.path(), b"png");
+        let stored = store_file(
+            dir.path(),
+            Some("gone.png"),
+            "new.png".to_string(),
+            &uploaded,
+        )
+        .unwrap();
+
+        assert_eq!(stored, "new.png");
+        assert_eq!(std::fs::read(dir.path().join("new.png")).unwrap(), b"png");
+    }
+
+    #[test]
+    fn test_link_or_copy_file_copies_into_other_store() {
+        let pm_dir = tempfile::tempdir().unwrap();
+        let task_root = tempfile::tempdir().unwrap();
+        let task_dir = task_root.path().join("attachments");
+        let uploaded = upload(pm_dir.path(), b"pdf");
+        let stored = store_file(pm_dir.path(), None, "spec.pdf".to_string(), &uploaded).unwrap();
+
+        link_or_copy_file(pm_dir.path(), &stored, &task_dir, "copy.pdf").unwrap();
+        assert_eq!(std::fs::read(task_dir.join("copy.pdf")).unwrap(), b"pdf");
+
+        // Removing the copy leaves the PM attachment file alone
+        std::fs::remove_file(task_dir.join("copy.pdf")).unwrap();
+        assert_eq!(std::fs::read(pm_dir.path().join(&stored)).unwrap(), b"pdf");
+    }
+
+    #[test]
+    fn test_link_or_copy_file_fails_for_missing_source() {
+        let pm_dir = tempfile::tempdir().unwrap();
+        let task_dir = tempfile::tempdir().unwrap();
+
+        let error =
+            link_or_copy_file(pm_dir.path(), "gone.pdf", task_dir.path(), "copy.pdf").unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(!task_dir.path().join("copy.pdf").exists());
+    }
+
+    #[test]
+    fn test_release_file_only_removes_last_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploaded = upload(dir.path(), b"png");
+        let stored = store_file(dir.path(), None, "shared.png".to_string(), &uploaded).unwrap();
+
+        assert!(!release_file(dir.path(), &stored, 1));
+        assert!(dir.path().join(&stored).exists());
+
+        assert!(release_file(dir.path(), &stored, 0));
+        assert!(!dir.path().join(&stored).exists());
+    }
+
+    fn write_png(dir: &Path, name: &str, width: u32, height: u32) {
+        image::RgbImage::new(width, height)
+            .save_with_format(dir.join(name), ImageFormat::Png)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_image_dimensions_reads_png_header() {
+        let dir = tempfile::tempdir().unwrap();
+        write_png(dir.path(), "photo.png", 640, 480);
+
+        assert_eq!(
+            image_dimensions(&dir.path().join("photo.png")),
+            Some((640, 480))
+        );
+    }
+
+    #[test]
+    fn test_image_dimensions_ignores_non_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = upload(dir.path(), b"%PDF-1.7");
+
+        assert_eq!(image_dimensions(&pdf), None);
+        assert_eq!(image_dimensions(&upload(dir.path(), b"")), None);
+        assert_eq!(image_dimensions(&dir.path().join("missing.png")), None);
+    }
+
+    #[test]
+    fn test_thumbnail_fits_bounds_and_is_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        write_png(dir.path(), "photo.png", 400, 200);
+
+        let (path, mime_type) = thumbnail(dir.path(), "photo.png", "image/png", 100, 100).unwrap();
+        assert_eq!(mime_type, "image/png");
+        assert_eq!(image::image_dimensions(&path).unwrap(), (100, 50));
+
+        // A second request reuses the cached file
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let (again, _) = thumbnail(dir.path(), "photo.png", "image/png", 100, 100).unwrap();
+        assert_eq!(again, path);
+        assert_eq!(
+            std::fs::metadata(&again).unwrap().modified().unwrap(),
+            modified
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_rejects_non_images() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("spec.pdf"), b"%PDF-1.7").unwrap();
+
+        assert!(matches!(
+            thumbnail(dir.path(), "spec.pdf", "application/pdf", 100, 100),
+            Err(ThumbnailError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn test_release_file_removes_thumbnails_in_shard_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("ab/cd")).unwrap();
+        write_png(dir.path(), "ab/cd/abcd.png", 40, 40);
+        let (thumb, _) = thumbnail(dir.path(), "ab/cd/abcd.png", "image/png", 20, 20).unwrap();
+        assert_eq!(thumb.parent(), Some(dir.path().join("ab/cd").as_path()));
+
+        assert!(release_file(dir.path(), "ab/cd/abcd.png", 0));
+        assert!(!thumb.exists());
+    }
+
+    #[test]
+    fn test_release_file_removes_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        write_png(dir.path(), "photo.png", 40, 40);
+        let (thumb, _) = thumbnail(dir.path(), "photo.png", "image/png", 20, 20).unwrap();
+
+        assert!(release_file(dir.path(), "photo.png", 0));
+        assert!(!thumb.exists());
+    }
+}
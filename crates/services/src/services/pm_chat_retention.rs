@@ -0,0 +1,339 @@
+//! PM chat retention
+//!
+//! Prunes PM conversation messages older than a project's `chat_retention_days`,
+//! archived or not, keeping pinned messages and removing attachment files of pruned messages.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use db::{
+    DBService,
+    models::{
+        pm_conversation::{PmAttachment, PmConversation},
+        project::Project,
+    },
+};
+use sqlx::SqlitePool;
+use tokio::time::interval;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use super::{attachment_layout, pm_attachments};
+
+/// Directory (under the cache dir) where PM chat attachments are stored
+const PM_ATTACHMENTS_DIR: &str = "pm-attachments";
+
+/// Get the PM attachments directory
+pub fn pm_attachments_dir() -> PathBuf {
+    let cache_dir = utils::cache_dir().join(PM_ATTACHMENTS_DIR);
+    std::fs::create_dir_all(&cache_dir).ok();
+    cache_dir
+}
+
+/// Oldest creation time a message may have and still be kept
+pub fn retention_cutoff(now: DateTime<Utc>, retention_days: i32) -> DateTime<Utc> {
+    now - chrono::Duration::days(retention_days.max(0) as i64)
+}
+
+/// IDs of unpinned messages created before `cutoff`
+pub fn select_prunable(messages: &[PmConversation], cutoff: DateTime<Utc>) -> Vec<Uuid> {
+    messages
+        .iter()
+        .filter(|m| !m.pinned && m.created_at < cutoff)
+        .map(|m| m.id)
+        .collect()
+}
+
+/// Delete unpinned messages of a project older than `retention_days`, archived
+/// ones included. Attachment rows are removed by the `ON DELETE CASCADE` on
+/// `pm_attachments`; their files are removed from disk unless another
+/// attachment still shares them. Returns the number of messages removed.
+pub async fn prune_project_messages(
+    pool: &SqlitePool,
+    project_id: Uuid,
+    retention_days: i32,
+) -> Result<u64, sqlx::Error> {
+    prune_messages_in(pool, &pm_attachments_dir(), project_id, retention_days).await
+}
+
+/// [`prune_project_messages`] with attachment files in `attachments_dir`
+async fn prune_messages_in(
+    pool: &SqlitePool,
+    attachments_dir: &Path,
+    project_id: Uuid,
+    retention_days: i32,
+) -> Result<u64, sqlx::Error> {
+    let messages = PmConversation::find_all_for_retention(pool, project_id).await?;
+    let prunable = select_prunable(&messages, retention_cutoff(Utc::now(), retention_days));
+
+    delete_messages_in(pool, attachments_dir, &prunable).await
+}
+
+/// Permanently delete messages together with their attachments.
+/// Attachment files are removed from disk unless another attachment still
+/// shares them. Returns the number of messages removed.
+pub async fn delete_messages(pool: &SqlitePool, message_ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    delete_messages_in(pool, &pm_attachments_dir(), message_ids).await
+}
+
+/// [`delete_messages`] with attachment files in `attachments_dir`
+async fn delete_messages_in(
+    pool: &SqlitePool,
+    attachments_dir: &Path,
+    message_ids: &[Uuid],
+) -> Result<u64, sqlx::Error> {
+    if message_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+
+    for &message_id in message_ids {
+        let attachments = PmAttachment::find_by_conversation_id(pool, message_id).await?;
+        removed += PmConversation::delete(pool, message_id).await?;
+        for attachment in attachments {
+            let sha256 = attachment.sha256.as_deref();
+            let file_path =
+                attachment_layout::resolve_relative(attachments_dir, &attachment.file_path, sha256);
+            let remaining = PmAttachment::count_sharing_file(pool, &file_path, sha256).await?;
+            pm_attachments::release_file(attachments_dir, &file_path, remaining);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Background service that periodically applies each project's chat retention
+pub struct PmChatRetentionService {
+    db: DBService,
+    poll_interval: Duration,
+}
+
+impl PmChatRetentionService {
+    pub async fn spawn(db: DBService) -> tokio::task::JoinHandle<()> {
+        let service = Self {
+            db,
+            poll_interval: Duration::from_secs(60 * 60), // Check every hour
+        };
+        tokio::spawn(async move {
+            service.start().await;
+        })
+    }
+
+    async fn start(&self) {
+        info!(
+            "Starting PM chat retention service with interval {:?}",
+            self.poll_interval
+        );
+
+        let mut interval = interval(self.poll_interval);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.prune_all_projects().await {
+                error!("Error pruning PM chat messages: {}", e);
+            }
+        }
+    }
+
+    async fn prune_all_projects(&self) -> Result<(), sqlx::Error> {
+        let projects = Project::find_with_chat_retention(&self.db.pool).await?;
+
+        for project in projects {
+            let Some(retention_days) = project.chat_retention_days else {
+                continue;
+            };
+            let removed = prune_project_messages(&self.db.pool, project.id, retention_days).await?;
+            if removed > 0 {
+                info!(
+                    "Pruned {} PM chat messages older than {} days from project {}",
+                    removed, retention_days, project.id
+                );
+            } else {
+                debug!("No PM chat messages to prune for project {}", project.id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use db::test_support::{insert_project, test_pool};
+
+    use super::*;
+
+    fn message(days_old: i64, pinned: bool, now: DateTime<Utc>) -> PmConversation {
+        let created_at = now - chrono::Duration::days(days_old);
+        PmConversation {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            role: "user".to_string(),
+            content: format!("{} days old", days_old),
+            model: None,
+            pinned,
+            edited: false,
+            input_tokens: None,
+            output_tokens: None,
+            archived_at: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    #[test]
+    fn test_prunes_old_unpinned_keeps_pinned_and_recent() {
+        let now = Utc::now();
+        let old = message(40, false, now);
+        let old_pinned = message(40, true, now);
+        let recent = message(2, false, now);
+        let messages = vec![old.clone(), old_pinned, recent];
+
+        let prunable = select_prunable(&messages, retention_cutoff(now, 30));
+        assert_eq!(prunable, vec![old.id]);
+    }
+
+    #[test]
+    fn test_nothing_pruned_within_window() {
+        let now = Utc::now();
+        let messages = vec![message(1, false, now), message(29, false, now)];
+        assert!(select_prunable(&messages, retention_cutoff(now, 30)).is_empty());
+    }
+
+    #[test]
+    fn test_retention_cutoff_clamps_negative_days() {
+        let now = Utc::now();
+        assert_eq!(retention_cutoff(now, -5), now);
+    }
+
+    /// Insert a message created `days_old` days ago
+    async fn insert_message(
+        pool: &SqlitePool,
+        project_id: Uuid,
+        days_old: i64,
+        pinned: bool,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO pm_conversations (id, project_id, role, content, pinned, created_at)
+             VALUES ($1, $2, 'user', 'Message', $3, $4)",
+        )
+        .bind(id)
+        .bind(project_id)
+        .bind(pinned)
+        .bind(Utc::now() - chrono::Duration::days(days_old))
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_prune_removes_old_archived_messages() {
+        let pool = test_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let project_id = insert_project(&pool).await;
+        let old = insert_message(&pool, project_id, 40, false).await;
+        let recent = insert_message(&pool, project_id, 2, false).await;
+        PmConversation::archive_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+
+        let removed = prune_messages_in(&pool, dir.path(), project_id, 30)
+            .await
+            .unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(
+            PmConversation::find_by_id(&pool, old)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            PmConversation::find_by_id(&pool, recent)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    /// Attach the file `file_path` (relative to the attachments directory) to a message
+    async fn attach(pool: &SqlitePool, message_id: Uuid, project_id: Uuid, file_path: &str) {
+        use db::models::pm_conversation::CreatePmAttachment;
+
+        PmAttachment::create(
+            pool,
+            &CreatePmAttachment {
+                conversation_id: message_id,
+                project_id,
+                file_name: "notes.txt".to_string(),
+                file_path: file_path.to_string(),
+                mime_type: "text/plain".to_string(),
+                file_size: 5,
+                sha256: None,
+                width: None,
+                height: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_pinned_and_recent_and_releases_unshared_files() {
+        let pool = test_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let project_id = insert_project(&pool).await;
+        let old = insert_message(&pool, project_id, 40, false).await;
+        let old_pinned = insert_message(&pool, project_id, 40, true).await;
+        let recent = insert_message(&pool, project_id, 2, false).await;
+
+        let shared = "aa/bb/shared.txt";
+        let own = "cc/dd/own.txt";
+        for file in [shared, own] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"notes").unwrap();
+        }
+        attach(&pool, old, project_id, shared).await;
+        attach(&pool, old, project_id, own).await;
+        attach(&pool, recent, project_id, shared).await;
+
+        let removed = prune_messages_in(&pool, dir.path(), project_id, 30)
+            .await
+            .unwrap();
+
+        assert_eq!(removed, 1);
+        let mut kept: Vec<Uuid> = PmConversation::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        kept.sort();
+        let mut expected = vec![old_pinned, recent];
+        expected.sort();
+        assert_eq!(kept, expected);
+        // The pruned message's attachment rows went with it
+        assert!(
+            PmAttachment::find_by_conversation_id(&pool, old)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        // A file still attached to a kept message stays on disk
+        assert!(dir.path().join(shared).is_file());
+        assert!(!dir.path().join(own).exists());
+
+        // Deleting its last message releases the shared file too
+        delete_messages_in(&pool, dir.path(), &[recent])
+            .await
+            .unwrap();
+        assert!(!dir.path().join(shared).exists());
+    }
+}
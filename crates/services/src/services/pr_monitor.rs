@@ -17,6 +17,7 @@ use tracing::{debug, error, info};
 use crate::services::{
     analytics::AnalyticsContext,
     git_host::{self, GitHostError, GitHostProvider},
+    webhooks,
 };
 
 #[derive(Debug, Error)]
@@ -119,7 +120,8 @@ impl PrMonitorService {
                     "PR #{} was merged, updating task {} to done and archiving workspace",
                     pr_merge.pr_info.number, workspace.task_id
                 );
-                Task::update_status(&self.db.pool, workspace.task_id, TaskStatus::Done).await?;
+                webhooks::update_task_status(&self.db.pool, workspace.task_id, TaskStatus::Done)
+                    .await?;
 
                 // Archive workspace unless pinned
                 if !workspace.pinned {
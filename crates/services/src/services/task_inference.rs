@@ -0,0 +1,408 @@
+//! Deterministic task field inference
+//!
+//! Infers task priority and matching labels from free text using keyword
+//! rules only (no LLM), so results are predictable and cheap to compute.
+//! The coding agent for a task is suggested from the executors of its labels.
+
+use std::str::FromStr;
+
+use db::models::{label::Label, task::TaskPriority};
+use executors::executors::BaseCodingAgent;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Keywords that bump a task to high priority
+const HIGH_PRIORITY_KEYWORDS: &[&str] = &[
+    "urgent",
+    "asap",
+    "critical",
+    "blocker",
+    "hotfix",
+    "immediately",
+    "緊急",
+    "至急",
+];
+
+/// Keywords that mark a task as low priority
+const LOW_PRIORITY_KEYWORDS: &[&str] = &["someday", "nice to have", "minor", "low priority"];
+
+/// Keyword groups used to match common label names.
+/// If a label name contains the first element, any of the keywords selects it.
+const LABEL_KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "bug",
+        &[
+            "bug",
+            "fix",
+            "error",
+            "crash",
+            "broken",
+            "regression",
+            "バグ",
+            "不具合",
+        ],
+    ),
+    (
+        "feature",
+        &[
+            "feature",
+            "add",
+            "implement",
+            "support",
+            "new",
+            "機能",
+            "追加",
+        ],
+    ),
+    (
+        "frontend",
+        &[
+            "ui",
+            "frontend",
+            "css",
+            "component",
+            "page",
+            "button",
+            "画面",
+        ],
+    ),
+    (
+        "backend",
+        &[
+            "api",
+            "backend",
+            "server",
+            "endpoint",
+            "database",
+            "db",
+            "migration",
+        ],
+    ),
+    (
+        "design",
+        &["design", "mockup", "figma", "layout", "デザイン"],
+    ),
+    (
+        "doc",
+        &["doc", "docs", "documentation", "readme", "ドキュメント"],
+    ),
+    ("test", &["test", "tests", "testing", "coverage", "テスト"]),
+    (
+        "refactor",
+        &["refactor", "cleanup", "clean up", "リファクタ"],
+    ),
+];
+
+/// Split text into lowercase word tokens
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// Whether `keyword` appears in `text` as a whole word (or as a substring for
+/// multi-word and non-ASCII keywords, which don't tokenize on spaces)
+fn contains_keyword(text_lower: &str, tokens: &[String], keyword: &str) -> bool {
+    if keyword.contains(' ') || !keyword.is_ascii() {
+        text_lower.contains(keyword)
+    } else {
+        tokens.iter().any(|t| t == keyword)
+    }
+}
+
+/// Infer a task priority from its text. Returns `None` when no keyword matches.
+pub fn infer_priority(text: &str) -> Option<TaskPriority> {
+    let text_lower = text.to_lowercase();
+    let tokens = tokenize(text);
+
+    if HIGH_PRIORITY_KEYWORDS
+        .iter()
+        .any(|k| contains_keyword(&text_lower, &tokens, k))
+    {
+        return Some(TaskPriority::High);
+    }
+    if LOW_PRIORITY_KEYWORDS
+        .iter()
+        .any(|k| contains_keyword(&text_lower, &tokens, k))
+    {
+        return Some(TaskPriority::Low);
+    }
+    None
+}
+
+/// Score for a label whose own name appears in the text
+const LABEL_NAME_SCORE: u32 = 2;
+
+/// A label suggested for a task, with the keywords that selected it
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct LabelSuggestion {
+    pub label_id: Uuid,
+    pub name: String,
+    /// Higher is a stronger match: the label name counts 2, each group keyword 1
+    pub score: u32,
+    /// Keywords found in the text, the label name first when it matched
+    pub matched_keywords: Vec<String>,
+}
+
+/// Score a label against the text; 0 means it does not match
+fn match_label(label: &Label, text_lower: &str, tokens: &[String]) -> (u32, Vec<String>) {
+    let name = label.name.to_lowercase();
+    let name = name.trim();
+    let mut score = 0;
+    let mut matched = Vec::new();
+
+    if !name.is_empty() && contains_keyword(text_lower, tokens, name) {
+        score += LABEL_NAME_SCORE;
+        matched.push(name.to_string());
+    }
+    for (group, keywords) in LABEL_KEYWORDS {
+        if !name.contains(group) {
+            continue;
+        }
+        for keyword in keywords.iter() {
+            if *keyword != name
+                && !matched.iter().any(|m| m == keyword)
+                && contains_keyword(text_lower, tokens, keyword)
+            {
+                score += 1;
+                matched.push(keyword.to_string());
+            }
+        }
+    }
+    (score, matched)
+}
+
+/// Suggest labels for a task from its text.
+/// A label matches when its name appears in the text, or when it belongs to a
+/// known keyword group (e.g. "bug", "frontend") and one of the group's keywords appears.
+/// Results keep the order of `labels`.
+pub fn suggest_labels<'a>(text: &str, labels: &'a [Label]) -> Vec<&'a Label> {
+    let text_lower = text.to_lowercase();
+    let tokens = tokenize(text);
+
+    labels
+        .iter()
+        .filter(|label| match_label(label, &text_lower, &tokens).0 > 0)
+        .collect()
+}
+
+/// Suggest labels for a task from its text like [`suggest_labels`], strongest
+/// match first. Labels with equal scores keep the order of `labels`.
+pub fn rank_label_suggestions(text: &str, labels: &[Label]) -> Vec<LabelSuggestion> {
+    let text_lower = text.to_lowercase();
+    let tokens = tokenize(text);
+
+    let mut suggestions: Vec<LabelSuggestion> = labels
+        .iter()
+        .filter_map(|label| {
+            let (score, matched_keywords) = match_label(label, &text_lower, &tokens);
+            (score > 0).then(|| LabelSuggestion {
+                label_id: label.id,
+                name: label.name.clone(),
+                score,
+                matched_keywords,
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.score.cmp(&a.score));
+    suggestions
+}
+
+/// Convenience wrapper returning only the IDs of suggested labels
+pub fn suggest_label_ids(text: &str, labels: &[Label]) -> Vec<Uuid> {
+    suggest_labels(text, labels).iter().map(|l| l.id).collect()
+}
+
+/// Coding agent recommended for a task by the executors set on its labels
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ExecutorSuggestion {
+    pub executor: BaseCodingAgent,
+    /// Labels of the task that specify `executor`, by name
+    pub label_ids: Vec<Uuid>,
+    pub label_names: Vec<String>,
+    /// Other executors specified by labels of the task, best first
+    pub alternatives: Vec<BaseCodingAgent>,
+}
+
+/// Suggest the coding agent for a task from the executors of its `labels`.
+/// The executor set on the most labels wins; on a tie, the executor of the
+/// label whose name sorts first. `None` if no label has a known executor.
+pub fn suggest_executor(labels: &[Label]) -> Option<ExecutorSuggestion> {
+    let mut labels: Vec<(&Label, BaseCodingAgent)> = labels
+        .iter()
+        .filter_map(|label| {
+            let executor = label.executor.as_deref()?;
+            BaseCodingAgent::from_str(executor)
+                .ok()
+                .map(|agent| (label, agent))
+        })
+        .collect();
+    labels.sort_by_key(|(label, _)| label.name.to_lowercase());
+
+    // Executors in order of their first label, with the labels specifying them
+    let mut groups: Vec<(BaseCodingAgent, Vec<&Label>)> = Vec::new();
+    for (label, agent) in labels {
+        match groups.iter_mut().find(|(executor, _)| *executor == agent) {
+            Some((_, group)) => group.push(label),
+            None => groups.push((agent, vec![label])),
+        }
+    }
+    // Stable sort keeps the first-label order among equally common executors
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+
+    let mut groups = groups.into_iter();
+    let (executor, labels) = groups.next()?;
+    Some(ExecutorSuggestion {
+        executor,
+        label_ids: labels.iter().map(|label| label.id).collect(),
+        label_names: labels.iter().map(|label| label.name.clone()).collect(),
+        alternatives: groups.map(|(executor, _)| executor).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use db::test_support::label;
+
+    use super::*;
+
+    #[test]
+    fn test_infer_priority_high_keywords() {
+        assert_eq!(
+            infer_priority("URGENT: login is down"),
+            Some(TaskPriority::High)
+        );
+        assert_eq!(
+            infer_priority("Fix payment bug asap"),
+            Some(TaskPriority::High)
+        );
+        assert_eq!(
+            infer_priority("緊急 ログイン不可"),
+            Some(TaskPriority::High)
+        );
+    }
+
+    #[test]
+    fn test_infer_priority_low_keywords() {
+        assert_eq!(
+            infer_priority("Nice to have: dark mode toggle"),
+            Some(TaskPriority::Low)
+        );
+    }
+
+    #[test]
+    fn test_infer_priority_requires_whole_words() {
+        // "asap" inside another word must not match
+        assert_eq!(infer_priority("Update asapi client"), None);
+        assert_eq!(infer_priority("Add settings page"), None);
+    }
+
+    #[test]
+    fn test_suggest_labels_by_name_and_keyword_group() {
+        let labels = vec![label("bug"), label("Frontend"), label("backend")];
+
+        let suggested = suggest_labels("Fix crash on settings page", &labels);
+        let names: Vec<_> = suggested.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["bug", "Frontend"]);
+
+        let suggested = suggest_labels("Add backend endpoint for exports", &labels);
+        let names: Vec<_> = suggested.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["backend"]);
+    }
+
+    #[test]
+    fn test_rank_label_suggestions_orders_by_score() {
+        let labels = vec![label("frontend"), label("bug"), label("backend")];
+
+        let ranked = rank_label_suggestions("Fix crash and error on the API endpoint", &labels);
+        let names: Vec<_> = ranked.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["bug", "backend"]);
+        assert_eq!(ranked[0].score, 3);
+        assert_eq!(ranked[0].matched_keywords, vec!["fix", "error", "crash"]);
+    }
+
+    #[test]
+    fn test_rank_label_suggestions_name_match_scores_higher() {
+        let labels = vec![label("feature"), label("bug")];
+
+        let ranked = rank_label_suggestions("Add export; bug in import", &labels);
+        assert_eq!(ranked[0].name, "bug");
+        assert_eq!(ranked[0].score, 2);
+        assert_eq!(ranked[0].matched_keywords, vec!["bug"]);
+        assert_eq!(ranked[1].name, "feature");
+        assert_eq!(ranked[1].score, 1);
+    }
+
+    #[test]
+    fn test_rank_label_suggestions_japanese_keywords() {
+        let labels = vec![label("bug"), label("frontend"), label("feature")];
+
+        let ranked = rank_label_suggestions("ログイン画面の不具合を修正", &labels);
+        let names: Vec<_> = ranked.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["bug", "frontend"]);
+        assert_eq!(ranked[0].matched_keywords, vec!["不具合"]);
+        assert_eq!(ranked[1].matched_keywords, vec!["画面"]);
+
+        let ranked = rank_label_suggestions("エクスポート機能を追加", &labels);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].name, "feature");
+        assert_eq!(ranked[0].score, 2);
+    }
+
+    #[test]
+    fn test_suggest_labels_no_match() {
+        let labels = vec![label("bug"), label("design")];
+        assert!(suggest_label_ids("Write quarterly report", &labels).is_empty());
+    }
+
+    fn executor_label(name: &str, executor: Option<&str>) -> Label {
+        Label {
+            executor: executor.map(String::from),
+            ..label(name)
+        }
+    }
+
+    #[test]
+    fn test_suggest_executor_none_without_executors() {
+        let labels = vec![
+            executor_label("bug", None),
+            executor_label("legacy", Some("NOT_AN_AGENT")),
+        ];
+
+        assert_eq!(suggest_executor(&labels), None);
+        assert_eq!(suggest_executor(&[]), None);
+    }
+
+    #[test]
+    fn test_suggest_executor_prefers_most_labels() {
+        let labels = vec![
+            executor_label("frontend", Some("CODEX")),
+            executor_label("backend", Some("CLAUDE_CODE")),
+            executor_label("api", Some("CLAUDE_CODE")),
+            executor_label("docs", None),
+        ];
+
+        let suggestion = suggest_executor(&labels).unwrap();
+
+        assert_eq!(suggestion.executor, BaseCodingAgent::ClaudeCode);
+        assert_eq!(suggestion.label_names, vec!["api", "backend"]);
+        assert_eq!(suggestion.label_ids, vec![labels[2].id, labels[1].id]);
+        assert_eq!(suggestion.alternatives, vec![BaseCodingAgent::Codex]);
+    }
+
+    #[test]
+    fn test_suggest_executor_breaks_ties_by_label_name() {
+        let labels = vec![
+            executor_label("UI", Some("GEMINI")),
+            executor_label("backend", Some("CODEX")),
+        ];
+
+        let suggestion = suggest_executor(&labels).unwrap();
+
+        assert_eq!(suggestion.executor, BaseCodingAgent::Codex);
+        assert_eq!(suggestion.alternatives, vec![BaseCodingAgent::Gemini]);
+    }
+}
@@ -0,0 +1,426 @@
+//! Outbound project webhooks
+//!
+//! Task lifecycle events are POSTed as JSON to the webhook configured for the
+//! task's project. Each body is signed with the webhook secret (HMAC-SHA256,
+//! sent as `X-Vibe-Signature-256: sha256=<hex>`). Failed deliveries are retried
+//! with exponential backoff and end up in the dead-letter log once retries run out.
+
+use std::{
+    sync::{
+        LazyLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use backon::{ExponentialBuilder, Retryable};
+use chrono::{DateTime, Utc};
+use db::models::{
+    project_webhook::{CreateWebhookDeadLetter, ProjectWebhook, WebhookDeadLetter},
+    task::{Task, TaskStatus},
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use strum_macros::{Display, EnumString};
+use thiserror::Error;
+use tracing::{debug, warn};
+use ts_rs::TS;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying the `sha256=<hex>` signature of the request body
+pub const SIGNATURE_HEADER: &str = "X-Vibe-Signature-256";
+/// Header carrying the event type
+pub const EVENT_HEADER: &str = "X-Vibe-Event";
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS, EnumString, Display)]
+pub enum WebhookEventType {
+    #[serde(rename = "task.created")]
+    #[strum(serialize = "task.created")]
+    TaskCreated,
+    #[serde(rename = "task.status_changed")]
+    #[strum(serialize = "task.status_changed")]
+    TaskStatusChanged,
+}
+
+/// JSON body of a webhook delivery
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct WebhookEvent {
+    pub event: WebhookEventType,
+    pub project_id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub status: TaskStatus,
+    /// Status before the change, for `task.status_changed`
+    pub previous_status: Option<TaskStatus>,
+    #[ts(type = "Date")]
+    pub timestamp: DateTime<Utc>,
+}
+
+impl WebhookEvent {
+    pub fn task_created(task: &Task) -> Self {
+        Self::new(WebhookEventType::TaskCreated, task, None)
+    }
+
+    pub fn task_status_changed(task: &Task, previous_status: TaskStatus) -> Self {
+        Self::new(
+            WebhookEventType::TaskStatusChanged,
+            task,
+            Some(previous_status),
+        )
+    }
+
+    fn new(event: WebhookEventType, task: &Task, previous_status: Option<TaskStatus>) -> Self {
+        Self {
+            event,
+            project_id: task.project_id,
+            task_id: task.id,
+            task_title: task.title.clone(),
+            status: task.status.clone(),
+            previous_status,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// How often and how fast failed deliveries are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: usize,
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            min_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("Webhook endpoint responded with {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// Signature of `body` under `secret`, in the `sha256=<hex>` header format
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Random secret for a new webhook
+pub fn generate_secret() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+async fn post_event(
+    webhook: &ProjectWebhook,
+    event_type: WebhookEventType,
+    body: &str,
+) -> Result<(), WebhookError> {
+    let response = CLIENT
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, event_type.to_string())
+        .header(SIGNATURE_HEADER, sign(&webhook.secret, body.as_bytes()))
+        .body(body.to_string())
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(WebhookError::Status(response.status()));
+    }
+    Ok(())
+}
+
+/// Deliver `event` to `webhook`, retrying with backoff. A delivery that still
+/// fails is recorded as a dead letter, which is returned.
+pub async fn deliver(
+    pool: &SqlitePool,
+    webhook: &ProjectWebhook,
+    event: &WebhookEvent,
+    policy: &RetryPolicy,
+) -> Result<Option<WebhookDeadLetter>, sqlx::Error> {
+    let body = serde_json::to_string(event).expect("webhook events serialize to JSON");
+    let attempts = AtomicUsize::new(0);
+
+    let result = (|| async {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        post_event(webhook, event.event, &body).await
+    })
+    .retry(
+        &ExponentialBuilder::default()
+            .with_min_delay(policy.min_delay)
+            .with_max_delay(policy.max_delay)
+            .with_max_times(policy.max_retries)
+            .with_jitter(),
+    )
+    .notify(|err: &WebhookError, dur: Duration| {
+        debug!(
+            "Webhook delivery to {} failed, retrying after {:.2}s: {}",
+            webhook.url,
+            dur.as_secs_f64(),
+            err
+        );
+    })
+    .await;
+
+    let Err(err) = result else {
+        return Ok(None);
+    };
+    let attempts = attempts.load(Ordering::Relaxed);
+    warn!(
+        "Webhook {} for task {} failed after {} attempts: {}",
+        event.event, event.task_id, attempts, err
+    );
+    let dead_letter = WebhookDeadLetter::create(
+        pool,
+        &CreateWebhookDeadLetter {
+            project_id: webhook.project_id,
+            event_type: event.event.to_string(),
+            url: webhook.url.clone(),
+            payload: body,
+            error: err.to_string(),
+            attempts: attempts as i64,
+        },
+    )
+    .await?;
+    Ok(Some(dead_letter))
+}
+
+/// Send `event` to its project's webhook in the background, if one is configured
+pub fn dispatch(pool: SqlitePool, event: WebhookEvent) {
+    tokio::spawn(async move {
+        let webhook = match ProjectWebhook::find_by_project_id(&pool, event.project_id).await {
+            Ok(Some(webhook)) => webhook,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(
+                    "Failed to load webhook for project {}: {}",
+                    event.project_id, e
+                );
+                return;
+            }
+        };
+        if let Err(e) = deliver(&pool, &webhook, &event, &RetryPolicy::default()).await {
+            warn!("Failed to record dead-lettered webhook: {}", e);
+        }
+    });
+}
+
+/// Send `task.status_changed` for `task` if its status differs from `previous_status`
+pub fn dispatch_status_change(pool: &SqlitePool, task: &Task, previous_status: TaskStatus) {
+    if task.status != previous_status {
+        dispatch(
+            pool.clone(),
+            WebhookEvent::task_status_changed(task, previous_status),
+        );
+    }
+}
+
+/// Set the status of a task and notify its project's webhook of the change.
+/// Status changes go through here rather than [`Task::update_status`], so
+/// that none (e.g. a merge marking the task done) is missed by webhooks.
+pub async fn update_task_status(
+    pool: &SqlitePool,
+    task_id: Uuid,
+    status: TaskStatus,
+) -> Result<(), sqlx::Error> {
+    let previous = Task::find_by_id(pool, task_id).await?;
+    Task::update_status(pool, task_id, status.clone()).await?;
+    if let Some(previous) = previous {
+        let previous_status = previous.status.clone();
+        let task = Task { status, ..previous };
+        dispatch_status_change(pool, &task, previous_status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Router,
+        body::Bytes,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+    };
+    use db::test_support::{insert_project, insert_task, test_pool};
+
+    use super::*;
+
+    /// Serve `status` on a local port, recording each request's headers and body
+    async fn serve(status: StatusCode) -> (String, Arc<Mutex<Vec<(HeaderMap, Bytes)>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let recorder = received.clone();
+        let app = Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: Bytes| {
+                let recorder = recorder.clone();
+                async move {
+                    recorder.lock().unwrap().push((headers, body));
+                    status
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    fn webhook(project_id: Uuid, url: String) -> ProjectWebhook {
+        ProjectWebhook {
+            project_id,
+            url,
+            secret: "secret".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn event(project_id: Uuid) -> WebhookEvent {
+        WebhookEvent {
+            event: WebhookEventType::TaskStatusChanged,
+            project_id,
+            task_id: Uuid::new_v4(),
+            task_title: "Ship it".to_string(),
+            status: TaskStatus::Done,
+            previous_status: Some(TaskStatus::InReview),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn fast_retries(max_retries: usize) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn test_sign_matches_known_hmac() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_event_serializes_with_dotted_type() {
+        let value = serde_json::to_value(event(Uuid::new_v4())).unwrap();
+        assert_eq!(value["event"], "task.status_changed");
+        assert_eq!(value["status"], "done");
+        assert_eq!(value["previous_status"], "inreview");
+    }
+
+    #[tokio::test]
+    async fn test_deliver_sends_signed_event() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let (url, received) = serve(StatusCode::OK).await;
+
+        let dead_letter = deliver(
+            &pool,
+            &webhook(project_id, url),
+            &event(project_id),
+            &fast_retries(2),
+        )
+        .await
+        .unwrap();
+
+        assert!(dead_letter.is_none());
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        assert_eq!(headers[EVENT_HEADER], "task.status_changed");
+        assert_eq!(headers[SIGNATURE_HEADER], sign("secret", body).as_str());
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried_then_dead_lettered() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let (url, received) = serve(StatusCode::INTERNAL_SERVER_ERROR).await;
+
+        let dead_letter = deliver(
+            &pool,
+            &webhook(project_id, url),
+            &event(project_id),
+            &fast_retries(2),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(received.lock().unwrap().len(), 3);
+        assert_eq!(dead_letter.attempts, 3);
+        assert_eq!(dead_letter.event_type, "task.status_changed");
+        let stored = WebhookDeadLetter::find_by_project_id(&pool, project_id)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, dead_letter.id);
+    }
+
+    #[tokio::test]
+    async fn test_update_task_status_notifies_webhook_of_changes() {
+        let pool = test_pool().await;
+        let project_id = insert_project(&pool).await;
+        let task_id = insert_task(&pool, project_id, "Ship it").await;
+        let (url, received) = serve(StatusCode::OK).await;
+        ProjectWebhook::upsert(&pool, project_id, &url, "secret")
+            .await
+            .unwrap();
+
+        // Setting the current status again is not a change
+        update_task_status(&pool, task_id, TaskStatus::Todo)
+            .await
+            .unwrap();
+        update_task_status(&pool, task_id, TaskStatus::Done)
+            .await
+            .unwrap();
+
+        // Deliveries run in the background
+        let body = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some((_, body)) = received.lock().unwrap().first().cloned() {
+                    break body;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["event"], "task.status_changed");
+        assert_eq!(value["status"], "done");
+        assert_eq!(value["previous_status"], "todo");
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        let task = Task::find_by_id(&pool, task_id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Done);
+    }
+}
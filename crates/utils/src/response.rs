@@ -7,6 +7,9 @@ pub struct ApiResponse<T, E = T> {
     data: Option<T>,
     error_data: Option<E>,
     message: Option<String>,
+    /// Stable machine-readable error code, e.g. `NotFoundError`
+    #[serde(default)]
+    code: Option<String>,
 }
 
 impl<T, E> ApiResponse<T, E> {
@@ -17,6 +20,7 @@ impl<T, E> ApiResponse<T, E> {
             data: Some(data),
             message: None,
             error_data: None,
+            code: None,
         }
     }
 
@@ -27,6 +31,18 @@ impl<T, E> ApiResponse<T, E> {
             data: None,
             message: Some(message.to_string()),
             error_data: None,
+            code: None,
+        }
+    }
+
+    /// Creates an error response, with `message`, a stable error `code` and no data.
+    pub fn error_with_code(message: &str, code: &str) -> Self {
+        ApiResponse {
+            success: false,
+            data: None,
+            message: Some(message.to_string()),
+            error_data: None,
+            code: Some(code.to_string()),
         }
     }
     /// Creates an error response, with no `data`, no `message`, but with arbitrary `error_data`.
@@ -36,6 +52,7 @@ impl<T, E> ApiResponse<T, E> {
             data: None,
             error_data: Some(data),
             message: None,
+            code: None,
         }
     }
 
@@ -53,4 +70,9 @@ impl<T, E> ApiResponse<T, E> {
     pub fn message(&self) -> Option<&str> {
         self.message.as_deref()
     }
+
+    /// Returns the error code if present.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
 }
@@ -255,6 +255,7 @@ const TaskFormDialogImpl = NiceModal.create<TaskFormDialogProps>((props) => {
         position: null,
         parent_workspace_id:
           mode === 'subtask' ? props.parentTaskAttemptId : null,
+        parent_task_id: null,
         image_ids: imageIds,
         label_ids: value.labelIds.length > 0 ? value.labelIds : null,
       };
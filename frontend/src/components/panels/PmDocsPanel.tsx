@@ -1,5 +1,10 @@
 import { useState, useRef, useEffect, useCallback, useMemo } from 'react';
-import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query';
+import {
+  useInfiniteQuery,
+  useQuery,
+  useMutation,
+  useQueryClient,
+} from '@tanstack/react-query';
 import { useTranslation } from 'react-i18next';
 import {
   ChevronLeft,
@@ -47,9 +52,16 @@ import {
   DialogHeader,
   DialogTitle,
 } from '@/components/ui/dialog';
-import type { PmConversation, PmAttachment, PmChatAgent } from 'shared/types';
+import type {
+  PmChatMessage,
+  PmAttachment,
+  PmChatAgent,
+} from 'shared/types';
 import { usePmChat } from '@/contexts/PmChatContext';
 
+/** Number of PM chat messages loaded per page */
+const PM_CHAT_PAGE_SIZE = 50;
+
 interface PmDocsPanelProps {
   projectId?: string;
   className?: string;
@@ -76,15 +88,26 @@ function AttachmentPreview({
 }) {
   const isImage = isImageMimeType(attachment.mime_type);
   const fileUrl = pmChatApi.getAttachmentUrl(projectId, attachment.id);
+  // The server only generates thumbnails for PNG and JPEG images
+  const previewUrl = ['image/png', 'image/jpeg'].includes(attachment.mime_type)
+    ? pmChatApi.getAttachmentThumbnailUrl(projectId, attachment.id, 320)
+    : fileUrl;
 
   return (
     <div className="group relative inline-block">
       {isImage ? (
         <a href={fileUrl} target="_blank" rel="noopener noreferrer">
           <img
-            src={fileUrl}
+            src={previewUrl}
             alt={attachment.file_name}
-            className="max-w-full max-h-40 rounded border hover:opacity-90 transition-opacity"
+            // Reserve the image's space before it loads
+            width={
+              attachment.width != null ? Number(attachment.width) : undefined
+            }
+            height={
+              attachment.height != null ? Number(attachment.height) : undefined
+            }
+            className="max-w-full max-h-40 w-auto h-auto rounded border hover:opacity-90 transition-opacity"
           />
         </a>
       ) : (
@@ -125,22 +148,19 @@ function AttachmentPreview({
 
 function ChatMessage({
   message,
-  attachments,
   projectId,
   onDelete,
   onDeleteAttachment,
 }: {
-  message: PmConversation;
-  attachments: PmAttachment[];
+  message: PmChatMessage;
   projectId: string;
   onDelete?: () => void;
   onDeleteAttachment?: (attachmentId: string) => void;
 }) {
+  const { t } = useTranslation('tasks');
   const isUser = message.role === 'user';
   const isSystem = message.role === 'system';
-  const messageAttachments = attachments.filter(
-    (a) => a.conversation_id === message.id
-  );
+  const messageAttachments = message.attachments;
 
   // Check if this is an attachment-only message
   const isAttachmentMessage = message.content.startsWith('[Attachment:');
@@ -200,6 +220,7 @@ function ChatMessage({
       )}
       <span className="text-[10px] text-muted-foreground">
         {new Date(message.created_at).toLocaleTimeString()}
+        {message.edited && ` · ${t('pmDocs.edited', 'edited')}`}
       </span>
     </div>
   );
@@ -210,6 +231,10 @@ interface WorkspaceDoc {
   path: string;
   repo_name: string;
   content: string;
+  title: string | null;
+  metadata: Record<string, unknown>;
+  size_bytes: number;
+  truncated: boolean;
 }
 
 export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
@@ -249,52 +274,57 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
   const { settings: autoReviewSettings, updateSettings } =
     useAutoReviewSettings(projectId);
 
-  // Available AI models per agent (memoized to avoid dependency issues)
-  // Updated 2025-01: Latest models for each CLI
-  const modelsByAgent = useMemo(
+  // Models for the selected agent (Ollama reports its installed models)
+  const { data: agentModels } = useQuery({
+    queryKey: ['pm-chat-agent-models', projectId, selectedAgent],
+    queryFn: () => pmChatApi.getAgentModels(projectId!, selectedAgent!),
+    enabled: !!projectId && !!selectedAgent,
+    staleTime: 5 * 60 * 1000,
+  });
+  const aiModels = useMemo(
     () =>
-      ({
-        CLAUDE_CLI: [
-          { value: 'sonnet', label: 'Sonnet (4.5)' },
-          { value: 'opus', label: 'Opus (4.5)' },
-          { value: 'haiku', label: 'Haiku (3.5)' },
-        ],
-        CODEX_CLI: [
-          { value: 'codex-1', label: 'Codex-1 (o3 optimized)' },
-          { value: 'codex-mini-latest', label: 'Codex Mini' },
-          { value: 'gpt-5.2-codex', label: 'GPT-5.2 Codex' },
-          { value: 'o3', label: 'o3' },
-          { value: 'o4-mini', label: 'o4-mini' },
-          { value: 'gpt-4.1', label: 'GPT-4.1' },
-        ],
-        GEMINI_CLI: [
-          { value: 'gemini-3-flash', label: 'Gemini 3 Flash' },
-          { value: 'gemini-2.5-pro', label: 'Gemini 2.5 Pro' },
-          { value: 'gemini-2.5-flash', label: 'Gemini 2.5 Flash' },
-        ],
-        OPENCODE_CLI: [{ value: 'default', label: 'Default' }],
-      }) as Record<string, { value: string; label: string }[]>,
-    []
+      agentModels?.models.map((model) => ({
+        value: model.id,
+        label: model.display_name,
+      })) ?? [],
+    [agentModels]
   );
 
-  // Get models for currently selected agent
-  const aiModels = selectedAgent ? modelsByAgent[selectedAgent] ?? [] : [];
-
+  // The first page holds the newest messages (oldest first); older pages are
+  // fetched with the previous page's cursor and come back newest first
   const {
-    data: chatData,
+    data: chatPages,
     isLoading,
     error,
-  } = useQuery({
+    fetchNextPage: fetchOlderMessages,
+    hasNextPage: hasOlderMessages,
+    isFetchingNextPage: isFetchingOlderMessages,
+  } = useInfiniteQuery({
     queryKey: ['pm-chat', projectId],
-    queryFn: () => (projectId ? pmChatApi.getChat(projectId) : null),
+    queryFn: ({ pageParam }) =>
+      pmChatApi.getChat(projectId!, {
+        limit: PM_CHAT_PAGE_SIZE,
+        before: pageParam,
+      }),
+    initialPageParam: undefined as string | undefined,
+    getNextPageParam: (lastPage) => lastPage.next_cursor ?? undefined,
     enabled: !!projectId,
   });
 
-  const { data: attachments = [] } = useQuery({
-    queryKey: ['pm-chat-attachments', projectId],
-    queryFn: () => (projectId ? pmChatApi.getAttachments(projectId) : []),
-    enabled: !!projectId,
-  });
+  const messages = useMemo(() => {
+    if (!chatPages) return undefined;
+    const [newest, ...older] = chatPages.pages;
+    const all = [
+      ...older.reverse().flatMap((page) => [...page.messages].reverse()),
+      ...newest.messages,
+    ];
+    // The reply being streamed is saved as it arrives; show it only as the
+    // streaming response until it is done
+    if (isAiResponding && all[all.length - 1]?.role === 'assistant') {
+      all.pop();
+    }
+    return all;
+  }, [chatPages, isAiResponding]);
 
   // Query for workspace docs (files in docs/ folder)
   const { data: workspaceDocs, isLoading: isLoadingDocs } = useQuery({
@@ -323,13 +353,13 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
 
   // Reset model when agent changes
   useEffect(() => {
-    if (selectedAgent && modelsByAgent[selectedAgent]) {
-      const models = modelsByAgent[selectedAgent];
-      if (models.length > 0 && !models.find((m) => m.value === selectedModel)) {
-        setSelectedModel(models[0].value);
-      }
+    if (
+      aiModels.length > 0 &&
+      !aiModels.find((m) => m.value === selectedModel)
+    ) {
+      setSelectedModel(aiModels[0].value);
     }
-  }, [selectedAgent, selectedModel, modelsByAgent]);
+  }, [selectedModel, aiModels]);
 
   const deleteMessageMutation = useMutation({
     mutationFn: (messageId: string) =>
@@ -343,20 +373,14 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
     mutationFn: () => pmChatApi.clearChat(projectId!),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['pm-chat', projectId] });
-      queryClient.invalidateQueries({
-        queryKey: ['pm-chat-attachments', projectId],
-      });
       setShowClearDialog(false);
     },
   });
 
   const uploadAttachmentMutation = useMutation({
-    mutationFn: (file: File) => pmChatApi.uploadAttachment(projectId!, file),
+    mutationFn: (file: File) => pmChatApi.uploadAttachment(projectId!, [file]),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['pm-chat', projectId] });
-      queryClient.invalidateQueries({
-        queryKey: ['pm-chat-attachments', projectId],
-      });
     },
   });
 
@@ -364,16 +388,17 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
     mutationFn: (attachmentId: string) =>
       pmChatApi.deleteAttachment(projectId!, attachmentId),
     onSuccess: () => {
-      queryClient.invalidateQueries({
-        queryKey: ['pm-chat-attachments', projectId],
-      });
+      queryClient.invalidateQueries({ queryKey: ['pm-chat', projectId] });
     },
   });
 
-  // Auto-scroll to bottom when new messages arrive
+  // Auto-scroll to bottom when new messages arrive (not when older ones are loaded)
+  const newestMessage = messages?.[messages.length - 1];
+  const newestMessageId = newestMessage?.id;
+  const newestAttachmentCount = newestMessage?.attachments.length;
   useEffect(() => {
     messagesEndRef.current?.scrollIntoView({ behavior: 'smooth' });
-  }, [chatData?.messages, attachments]);
+  }, [newestMessageId, newestAttachmentCount]);
 
   // Note: Stream cleanup is now handled by PmChatContext which persists across route changes
 
@@ -427,7 +452,11 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
 
     // First, send the user message and show it immediately
     try {
-      await pmChatApi.sendMessage(projectId, { content, role: 'user' });
+      await pmChatApi.sendMessage(projectId, {
+        content,
+        role: 'user',
+        client_message_id: crypto.randomUUID(),
+      });
       // Refresh to show the user message right away
       await queryClient.invalidateQueries({ queryKey: ['pm-chat', projectId] });
     } catch (error) {
@@ -599,7 +628,7 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
                       variant="ghost"
                       size="sm"
                       className="h-6 w-6 p-0"
-                      disabled={!chatData?.messages?.length}
+                      disabled={!messages?.length}
                       onClick={() => setShowClearDialog(true)}
                     >
                       <Trash2 size={14} className="text-muted-foreground" />
@@ -619,7 +648,7 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
                     <DialogDescription>
                       {t(
                         'tasks:pmDocs.clearChatDescription',
-                        'This will permanently delete all chat messages. This action cannot be undone.'
+                        'Chat messages will be archived. You can restore them until the archive is purged.'
                       )}
                     </DialogDescription>
                   </DialogHeader>
@@ -760,7 +789,7 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
 
                   {/* Messages */}
                   <div className="flex-1 overflow-y-auto p-2 space-y-2">
-                    {chatData?.messages?.length === 0 ? (
+                    {messages?.length === 0 ? (
                       <div className="text-sm text-muted-foreground italic text-center py-4">
                         {t(
                           'tasks:pmDocs.noMessages',
@@ -769,11 +798,32 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
                       </div>
                     ) : (
                       <>
-                        {chatData?.messages?.map((message) => (
+                        {hasOlderMessages && (
+                          <div className="flex justify-center">
+                            <Button
+                              variant="ghost"
+                              size="sm"
+                              className="h-6 text-xs"
+                              onClick={() => fetchOlderMessages()}
+                              disabled={isFetchingOlderMessages}
+                            >
+                              {isFetchingOlderMessages && (
+                                <Loader2
+                                  size={12}
+                                  className="animate-spin mr-1"
+                                />
+                              )}
+                              {t(
+                                'tasks:pmDocs.loadOlderMessages',
+                                'Load earlier messages'
+                              )}
+                            </Button>
+                          </div>
+                        )}
+                        {messages?.map((message) => (
                           <ChatMessage
                             key={message.id}
                             message={message}
-                            attachments={attachments}
                             projectId={projectId}
                             onDelete={() =>
                               deleteMessageMutation.mutate(message.id)
@@ -927,6 +977,21 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
                                     className="text-xs"
                                   >
                                     {agent.display_name}
+                                    {agent.version && (
+                                      <span className="ml-1 text-muted-foreground">
+                                        v{agent.version}
+                                      </span>
+                                    )}
+                                    {!agent.tools_configured && (
+                                      <span className="ml-1 text-muted-foreground">
+                                        (
+                                        {t(
+                                          'tasks:pmDocs.noTools',
+                                          'no tools'
+                                        )}
+                                        )
+                                      </span>
+                                    )}
                                   </SelectItem>
                                 ))}
                               </SelectContent>
@@ -990,7 +1055,7 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
                           ← {t('common:actions.back', 'Back')}
                         </button>
                         <span className="text-xs font-medium truncate flex-1">
-                          {selectedDoc.path}
+                          {selectedDoc.title ?? selectedDoc.path}
                         </span>
                       </div>
                       <div className="flex-1 overflow-y-auto p-2">
@@ -1018,15 +1083,36 @@ export function PmDocsPanel({ projectId, className }: PmDocsPanelProps) {
                             <FileText size={14} className="text-muted-foreground shrink-0" />
                             <div className="flex-1 min-w-0">
                               <div className="text-sm font-medium truncate group-hover:text-primary">
-                                {doc.path}
+                                {doc.title ?? doc.path}
                               </div>
-                              <div className="text-[10px] text-muted-foreground">
-                                {doc.repo_name}
+                              <div className="text-[10px] text-muted-foreground truncate">
+                                {doc.title
+                                  ? `${doc.repo_name} · ${doc.path}`
+                                  : doc.repo_name}
+                                {doc.truncated &&
+                                  ` · ${t('tasks:pmDocs.truncatedDoc', 'truncated ({{size}} KB)', {
+                                    size: Math.ceil(doc.size_bytes / 1024),
+                                  })}`}
                               </div>
                             </div>
                           </div>
                         </button>
                       ))}
+                      {workspaceDocs.skipped.length > 0 && (
+                        <div className="p-2 text-[10px] text-muted-foreground">
+                          <div className="font-medium">
+                            {t(
+                              'tasks:pmDocs.skippedDocs',
+                              'Not shown (size limit reached):'
+                            )}
+                          </div>
+                          {workspaceDocs.skipped.map((path) => (
+                            <div key={path} className="truncate">
+                              {path}
+                            </div>
+                          ))}
+                        </div>
+                      )}
                     </div>
                   ) : (
                     <div className="text-sm text-muted-foreground italic p-4 text-center">
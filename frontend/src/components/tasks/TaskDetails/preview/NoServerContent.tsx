@@ -71,6 +71,7 @@ export function NoServerContent({
         priority: null,
         position: null,
         parent_workspace_id: null,
+        parent_task_id: null,
         image_ids: null,
         label_ids: null,
       },
@@ -829,6 +829,8 @@ export const Actions = {
           has_in_progress_attempt: false,
           last_attempt_failed: false,
           executor: '',
+          subtask_count: 0n,
+          subtasks_done: 0n,
         },
         repoId,
         targetBranch: repo?.target_branch,
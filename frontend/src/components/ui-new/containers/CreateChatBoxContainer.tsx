@@ -186,6 +186,7 @@ export function CreateChatBoxContainer() {
         priority: null,
         position: null,
         parent_workspace_id: null,
+        parent_task_id: null,
         image_ids: getImageIds(),
         label_ids: null,
       },
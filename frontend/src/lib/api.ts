@@ -18,6 +18,11 @@ import {
   ExecutionProcessRepoState,
   GitBranch,
   Label,
+  LabelWithCount,
+  LabelSuggestion,
+  ExecutorSuggestion,
+  LabelMergeResult,
+  MergeLabelsRequest,
   Project,
   Repo,
   RepoWithTargetBranch,
@@ -93,12 +98,33 @@ import {
   StartReviewRequest,
   ReviewError,
   PmChatResponse,
+  PmChatSearchResult,
+  PmChatUsageResponse,
+  PmChatStats,
+  PmChatContextPreview,
+  PmChatMessage,
+  RestoreChatResponse,
+  PurgeChatResponse,
   PmConversation,
   SendMessageRequest,
+  UpdateMessageRequest,
+  MessageToTaskRequest,
   UpdatePmDocsRequest,
+  SetTaskParentRequest,
+  AddTaskCommentRequest,
+  TaskComment,
+  TaskReview,
+  ProjectWebhook,
+  SetProjectWebhookRequest,
+  WebhookDeadLetter,
+  UpdatePmDocsResponse,
+  RenderedPmDocs,
   PmAttachment,
+  PmDocsVersion,
   PmChatAgent,
   AvailablePmChatAgentsResponse,
+  AvailableModelsResponse,
+  TaskSummaryLocale,
 } from 'shared/types';
 import type { WorkspaceWithSession } from '@/types/attempt';
 import { createWorkspaceWithSession } from '@/types/attempt';
@@ -106,17 +132,21 @@ import { createWorkspaceWithSession } from '@/types/attempt';
 export class ApiError<E = unknown> extends Error {
   public status?: number;
   public error_data?: E;
+  /** Stable error code from the server, e.g. 'NotFoundError' */
+  public code?: string;
 
   constructor(
     message: string,
     public statusCode?: number,
     public response?: Response,
-    error_data?: E
+    error_data?: E,
+    code?: string
   ) {
     super(message);
     this.name = 'ApiError';
     this.status = statusCode;
     this.error_data = error_data;
+    this.code = code;
   }
 }
 
@@ -180,12 +210,14 @@ export const handleApiResponse = async <T, E = T>(
 ): Promise<T> => {
   if (!response.ok) {
     let errorMessage = `Request failed with status ${response.status}`;
+    let errorCode: string | undefined;
 
     try {
       const errorData = await response.json();
       if (errorData.message) {
         errorMessage = errorData.message;
       }
+      errorCode = errorData.code ?? undefined;
     } catch {
       // Fallback to status text if JSON parsing fails
       errorMessage = response.statusText || errorMessage;
@@ -193,12 +225,19 @@ export const handleApiResponse = async <T, E = T>(
 
     console.error('[API Error]', {
       message: errorMessage,
+      code: errorCode,
       status: response.status,
       response,
       endpoint: response.url,
       timestamp: new Date().toISOString(),
     });
-    throw new ApiError<E>(errorMessage, response.status, response);
+    throw new ApiError<E>(
+      errorMessage,
+      response.status,
+      response,
+      undefined,
+      errorCode
+    );
   }
 
   if (response.status === 204) {
@@ -223,7 +262,8 @@ export const handleApiResponse = async <T, E = T>(
         result.message || 'API request failed',
         response.status,
         response,
-        result.error_data
+        result.error_data,
+        result.code ?? undefined
       );
     }
 
@@ -237,7 +277,9 @@ export const handleApiResponse = async <T, E = T>(
     throw new ApiError<E>(
       result.message || 'API request failed',
       response.status,
-      response
+      response,
+      undefined,
+      result.code ?? undefined
     );
   }
 
@@ -368,6 +410,38 @@ export const projectsApi = {
     );
     return handleApiResponse<void>(response);
   },
+
+  getWebhook: async (projectId: string): Promise<ProjectWebhook | null> => {
+    const response = await makeRequest(`/api/projects/${projectId}/webhook`);
+    return handleApiResponse<ProjectWebhook | null>(response);
+  },
+
+  setWebhook: async (
+    projectId: string,
+    data: SetProjectWebhookRequest
+  ): Promise<ProjectWebhook> => {
+    const response = await makeRequest(`/api/projects/${projectId}/webhook`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    });
+    return handleApiResponse<ProjectWebhook>(response);
+  },
+
+  deleteWebhook: async (projectId: string): Promise<void> => {
+    const response = await makeRequest(`/api/projects/${projectId}/webhook`, {
+      method: 'DELETE',
+    });
+    return handleApiResponse<void>(response);
+  },
+
+  getWebhookDeadLetters: async (
+    projectId: string
+  ): Promise<WebhookDeadLetter[]> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/webhook/dead-letters`
+    );
+    return handleApiResponse<WebhookDeadLetter[]>(response);
+  },
 };
 
 // Labels API (nested under projects)
@@ -377,6 +451,13 @@ export const labelsApi = {
     return handleApiResponse<Label[]>(response);
   },
 
+  listWithCounts: async (projectId: string): Promise<LabelWithCount[]> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/labels/with-counts`
+    );
+    return handleApiResponse<LabelWithCount[]>(response);
+  },
+
   getById: async (projectId: string, labelId: string): Promise<Label> => {
     const response = await makeRequest(
       `/api/projects/${projectId}/labels/${labelId}`
@@ -407,9 +488,17 @@ export const labelsApi = {
     return handleApiResponse<Label>(response);
   },
 
-  delete: async (projectId: string, labelId: string): Promise<void> => {
+  // Delete a label, optionally moving its tasks to another label first
+  delete: async (
+    projectId: string,
+    labelId: string,
+    reassignTo?: string
+  ): Promise<void> => {
+    const query = reassignTo
+      ? `?reassign_to=${encodeURIComponent(reassignTo)}`
+      : '';
     const response = await makeRequest(
-      `/api/projects/${projectId}/labels/${labelId}`,
+      `/api/projects/${projectId}/labels/${labelId}${query}`,
       {
         method: 'DELETE',
       }
@@ -417,21 +506,114 @@ export const labelsApi = {
     return handleApiResponse<void>(response);
   },
 
+  // Merge labels into a target label
+  merge: async (
+    projectId: string,
+    data: MergeLabelsRequest
+  ): Promise<LabelMergeResult> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/labels/merge`,
+      {
+        method: 'POST',
+        body: JSON.stringify(data),
+      }
+    );
+    return handleApiResponse<LabelMergeResult>(response);
+  },
+
+  // Keyword-based label suggestions for a task, strongest match first
+  suggestForTask: async (
+    projectId: string,
+    taskId: string
+  ): Promise<LabelSuggestion[]> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/tasks/${taskId}/suggest-labels`,
+      {
+        method: 'POST',
+      }
+    );
+    return handleApiResponse<LabelSuggestion[]>(response);
+  },
+
   // Get labels for a specific task
   getTaskLabels: async (taskId: string): Promise<Label[]> => {
     const response = await makeRequest(`/api/tasks/${taskId}/labels`);
     return handleApiResponse<Label[]>(response);
   },
+
+  // Coding agent suggested by the executors of a task's labels, if any
+  suggestExecutor: async (
+    taskId: string
+  ): Promise<ExecutorSuggestion | null> => {
+    const response = await makeRequest(
+      `/api/tasks/${taskId}/suggest-executor`,
+      {
+        method: 'POST',
+      }
+    );
+    return handleApiResponse<ExecutorSuggestion | null>(response);
+  },
 };
 
 // PM Chat API (nested under projects)
 export const pmChatApi = {
-  // Get all PM chat messages and pm_docs for a project
-  getChat: async (projectId: string): Promise<PmChatResponse> => {
-    const response = await makeRequest(`/api/projects/${projectId}/pm-chat`);
+  // Get PM chat messages and pm_docs for a project.
+  // With `limit` only the newest page is returned; pass `next_cursor` as
+  // `before` to fetch older messages (returned newest first).
+  getChat: async (
+    projectId: string,
+    params?: { limit?: number; before?: string }
+  ): Promise<PmChatResponse> => {
+    const query = new URLSearchParams();
+    if (params?.limit !== undefined) query.set('limit', String(params.limit));
+    if (params?.before) query.set('before', params.before);
+    const qs = query.toString();
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat${qs ? `?${qs}` : ''}`
+    );
     return handleApiResponse<PmChatResponse>(response);
   },
 
+  // Search messages (newest first) with highlighted snippets
+  searchChat: async (
+    projectId: string,
+    q: string,
+    limit?: number
+  ): Promise<PmChatSearchResult[]> => {
+    const query = new URLSearchParams({ q });
+    if (limit !== undefined) query.set('limit', String(limit));
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/search?${query.toString()}`
+    );
+    return handleApiResponse<PmChatSearchResult[]>(response);
+  },
+
+  // Get total token usage, by model
+  getUsage: async (projectId: string): Promise<PmChatUsageResponse> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/usage`
+    );
+    return handleApiResponse<PmChatUsageResponse>(response);
+  },
+
+  // Get message, attachment and model summary
+  getStats: async (projectId: string): Promise<PmChatStats> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/stats`
+    );
+    return handleApiResponse<PmChatStats>(response);
+  },
+
+  // Get the system prompt the next AI chat message would be sent with
+  getContextPreview: async (
+    projectId: string
+  ): Promise<PmChatContextPreview> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/context-preview`
+    );
+    return handleApiResponse<PmChatContextPreview>(response);
+  },
+
   // Send a new message
   sendMessage: async (
     projectId: string,
@@ -444,7 +626,7 @@ export const pmChatApi = {
     return handleApiResponse<PmConversation>(response);
   },
 
-  // Clear all messages
+  // Clear all messages (they are archived and can be restored)
   clearChat: async (projectId: string): Promise<void> => {
     const response = await makeRequest(`/api/projects/${projectId}/pm-chat`, {
       method: 'DELETE',
@@ -452,6 +634,68 @@ export const pmChatApi = {
     return handleApiResponse<void>(response);
   },
 
+  // Get archived messages
+  getArchived: async (projectId: string): Promise<PmChatMessage[]> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/archived`
+    );
+    return handleApiResponse<PmChatMessage[]>(response);
+  },
+
+  // Restore all archived messages
+  restoreArchived: async (projectId: string): Promise<RestoreChatResponse> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/archived/restore`,
+      {
+        method: 'POST',
+      }
+    );
+    return handleApiResponse<RestoreChatResponse>(response);
+  },
+
+  // Permanently delete archived messages
+  purgeArchived: async (projectId: string): Promise<PurgeChatResponse> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/archived`,
+      {
+        method: 'DELETE',
+      }
+    );
+    return handleApiResponse<PurgeChatResponse>(response);
+  },
+
+  // Edit the content of a message
+  updateMessage: async (
+    projectId: string,
+    messageId: string,
+    data: UpdateMessageRequest
+  ): Promise<PmConversation> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/messages/${messageId}`,
+      {
+        method: 'PATCH',
+        body: JSON.stringify(data),
+      }
+    );
+    return handleApiResponse<PmConversation>(response);
+  },
+
+  // Create a task from a message, linked back to it
+  createTaskFromMessage: async (
+    projectId: string,
+    messageId: string,
+    data: MessageToTaskRequest
+  ): Promise<Task> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/messages/${messageId}/to-task`,
+      {
+        method: 'POST',
+        body: JSON.stringify(data),
+      }
+    );
+    return handleApiResponse<Task>(response);
+  },
+
   // Delete a specific message
   deleteMessage: async (
     projectId: string,
@@ -474,11 +718,23 @@ export const pmChatApi = {
     return handleApiResponse<string | null>(response);
   },
 
+  // Get PM docs (or one `##` section) rendered to sanitized HTML
+  renderPmDocs: async (
+    projectId: string,
+    section?: string
+  ): Promise<RenderedPmDocs> => {
+    const query = section ? `?section=${encodeURIComponent(section)}` : '';
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/docs/render${query}`
+    );
+    return handleApiResponse<RenderedPmDocs>(response);
+  },
+
   // Update PM docs
   updatePmDocs: async (
     projectId: string,
     data: UpdatePmDocsRequest
-  ): Promise<Project> => {
+  ): Promise<UpdatePmDocsResponse> => {
     const response = await makeRequest(
       `/api/projects/${projectId}/pm-chat/docs`,
       {
@@ -486,6 +742,26 @@ export const pmChatApi = {
         body: JSON.stringify(data),
       }
     );
+    return handleApiResponse<UpdatePmDocsResponse>(response);
+  },
+
+  // Get previous versions of the PM docs, newest first
+  getPmDocsHistory: async (projectId: string): Promise<PmDocsVersion[]> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/docs/history`
+    );
+    return handleApiResponse<PmDocsVersion[]>(response);
+  },
+
+  // Restore a previous version of the PM docs
+  restorePmDocsVersion: async (
+    projectId: string,
+    versionId: string
+  ): Promise<Project> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/docs/history/${versionId}/restore`,
+      { method: 'POST' }
+    );
     return handleApiResponse<Project>(response);
   },
 
@@ -500,10 +776,12 @@ export const pmChatApi = {
   // Upload an attachment
   uploadAttachment: async (
     projectId: string,
-    file: File
-  ): Promise<PmAttachment> => {
+    files: File[],
+    conversationId?: string
+  ): Promise<PmAttachment[]> => {
     const formData = new FormData();
-    formData.append('file', file);
+    for (const file of files) formData.append('file', file);
+    if (conversationId) formData.append('conversation_id', conversationId);
 
     // Note: Don't use makeRequest here as it sets Content-Type to application/json
     // For multipart/form-data, browser must set the content-type with boundary
@@ -545,9 +823,24 @@ export const pmChatApi = {
     return `/api/projects/${projectId}/pm-chat/attachments/${attachmentId}/file`;
   },
 
+  // Get URL of a thumbnail (PNG and JPEG attachments only) fitting in size x size
+  getAttachmentThumbnailUrl: (
+    projectId: string,
+    attachmentId: string,
+    size: number
+  ): string => {
+    return `/api/projects/${projectId}/pm-chat/attachments/${attachmentId}/thumbnail?w=${size}&h=${size}`;
+  },
+
+  // Get URL of a zip archive with all attachments
+  getAttachmentsArchiveUrl: (projectId: string): string => {
+    return `/api/projects/${projectId}/pm-chat/attachments/archive`;
+  },
+
   // Get task summary with dependencies
   getTaskSummary: async (
-    projectId: string
+    projectId: string,
+    locale?: TaskSummaryLocale
   ): Promise<{
     tasks: Array<{
       id: string;
@@ -560,16 +853,21 @@ export const pmChatApi = {
     }>;
     summary_text: string;
   }> => {
+    const query = locale ? `?locale=${locale}` : '';
     const response = await makeRequest(
-      `/api/projects/${projectId}/pm-chat/task-summary`
+      `/api/projects/${projectId}/pm-chat/task-summary${query}`
     );
     return handleApiResponse(response);
   },
 
   // Sync task summary to PM docs
-  syncTaskSummary: async (projectId: string): Promise<Project> => {
+  syncTaskSummary: async (
+    projectId: string,
+    locale?: TaskSummaryLocale
+  ): Promise<Project> => {
+    const query = locale ? `?locale=${locale}` : '';
     const response = await makeRequest(
-      `/api/projects/${projectId}/pm-chat/task-summary`,
+      `/api/projects/${projectId}/pm-chat/task-summary${query}`,
       {
         method: 'POST',
       }
@@ -585,7 +883,12 @@ export const pmChatApi = {
       path: string;
       repo_name: string;
       content: string;
+      title: string | null;
+      metadata: Record<string, unknown>;
+      size_bytes: number;
+      truncated: boolean;
     }>;
+    skipped: string[];
   }> => {
     const response = await makeRequest(
       `/api/projects/${projectId}/pm-chat/workspace-docs`
@@ -602,108 +905,151 @@ export const pmChatApi = {
     onDone: () => void = () => {},
     onError: (error: string) => void = () => {},
     onTaskCreated?: (taskId: string, taskTitle: string) => void,
-    onDocsUpdated?: () => void,
+    onDocsUpdated?: (diff?: string, docsLength?: number) => void,
     onToolUse?: (toolName: string) => void,
     agent?: PmChatAgent
   ): { abort: () => void } => {
     const abortController = new AbortController();
-
-    const fetchStream = async () => {
-      try {
-        const response = await fetch(
-          `/api/projects/${projectId}/pm-chat/ai-chat`,
+    // Resume state: the stream id from the first event and the last SSE id seen
+    let streamId: string | null = null;
+    let lastEventId: string | null = null;
+    const maxReconnects = 3;
+
+    const openStream = (): Promise<Response> => {
+      if (streamId) {
+        return fetch(
+          `/api/projects/${projectId}/pm-chat/ai-chat/${streamId}/events`,
           {
-            method: 'POST',
-            headers: {
-              'Content-Type': 'application/json',
-            },
-            body: JSON.stringify({ content, model, agent }),
+            headers: lastEventId ? { 'Last-Event-ID': lastEventId } : {},
             signal: abortController.signal,
           }
         );
+      }
+      return fetch(`/api/projects/${projectId}/pm-chat/ai-chat`, {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ content, model, agent }),
+        signal: abortController.signal,
+      });
+    };
 
-        if (!response.ok) {
-          const error = await response
-            .json()
-            .catch(() => ({ message: 'AI chat failed' }));
-          onError(error.message || 'Failed to get AI response');
-          return;
-        }
-
-        const reader = response.body?.getReader();
-        if (!reader) {
-          onError('Failed to get response stream');
-          return;
-        }
-
-        const decoder = new TextDecoder();
-        let buffer = '';
-        let isReading = true;
+    // Returns true once the stream has ended with a done or error event
+    const readStream = async (response: Response): Promise<boolean> => {
+      const reader = response.body?.getReader();
+      if (!reader) {
+        onError('Failed to get response stream');
+        return true;
+      }
 
-        while (isReading) {
-          const { done, value } = await reader.read();
-          if (done) {
-            isReading = false;
-            continue;
-          }
+      const decoder = new TextDecoder();
+      let buffer = '';
+      let isReading = true;
 
-          buffer += decoder.decode(value, { stream: true });
-          const lines = buffer.split('\n');
-          buffer = lines.pop() || '';
+      while (isReading) {
+        const { done, value } = await reader.read();
+        if (done) {
+          isReading = false;
+          continue;
+        }
 
-          for (const line of lines) {
-            if (line.startsWith('data: ')) {
-              const data = line.slice(6);
-              if (data === '[DONE]') {
-                onDone();
-                return;
+        buffer += decoder.decode(value, { stream: true });
+        const lines = buffer.split('\n');
+        buffer = lines.pop() || '';
+
+        for (const line of lines) {
+          if (line.startsWith('id: ')) {
+            lastEventId = line.slice(4);
+          } else if (line.startsWith('data: ')) {
+            const data = line.slice(6);
+            if (data === '[DONE]') {
+              onDone();
+              return true;
+            }
+            try {
+              const event = JSON.parse(data);
+              if (event.stream_id) {
+                streamId = event.stream_id;
               }
-              try {
-                const event = JSON.parse(data);
-                if (event.type === 'content' && event.content) {
-                  // Append newline for line-based streaming
-                  onContent(event.content + '\n');
-                } else if (event.type === 'thinking') {
-                  // Thinking indicator - can be shown to user or just logged
-                  console.log('AI thinking:', event.content);
-                } else if (event.type === 'tool_use' && event.content) {
-                  // Tool is being used - show indicator
-                  onToolUse?.(event.content);
-                  onContent(event.content + '\n');
-                } else if (event.type === 'task_created') {
-                  // Task was created - refresh task list
-                  if (event.content) {
-                    onContent('\n' + event.content + '\n');
-                  }
-                  if (event.task_id && event.task_title) {
-                    onTaskCreated?.(event.task_id, event.task_title);
-                  }
-                } else if (event.type === 'docs_updated') {
-                  // Docs were updated - refresh docs
-                  if (event.content) {
-                    onContent('\n' + event.content + '\n');
-                  }
-                  onDocsUpdated?.();
-                } else if (event.type === 'done') {
-                  onDone();
-                  return;
-                } else if (event.type === 'error') {
-                  onError(event.error || 'Unknown error');
-                  return;
+              if (event.type === 'content' && event.content) {
+                // Append newline for line-based streaming
+                onContent(event.content + '\n');
+              } else if (event.type === 'thinking') {
+                // Thinking indicator - can be shown to user or just logged
+                console.log('AI thinking:', event.content);
+              } else if (event.type === 'debug') {
+                // CLI stderr, only useful when troubleshooting
+                console.debug('AI chat CLI:', event.content);
+              } else if (event.type === 'tool_use' && event.content) {
+                // Tool is being used - show indicator
+                onToolUse?.(event.content);
+                onContent(event.content + '\n');
+              } else if (event.type === 'task_created') {
+                // Task was created - refresh task list
+                if (event.content) {
+                  onContent('\n' + event.content + '\n');
+                }
+                if (event.task_id && event.task_title) {
+                  onTaskCreated?.(event.task_id, event.task_title);
                 }
-              } catch {
-                // Skip invalid JSON
+              } else if (event.type === 'docs_updated') {
+                // Docs were updated - refresh docs; content is the diff
+                onDocsUpdated?.(
+                  event.content || undefined,
+                  event.docs_length ?? undefined
+                );
+              } else if (event.type === 'done') {
+                onDone();
+                return true;
+              } else if (event.type === 'error') {
+                onError(event.error || 'Unknown error');
+                return true;
               }
+            } catch {
+              // Skip invalid JSON
             }
           }
         }
+      }
+
+      return false;
+    };
+
+    const fetchStream = async () => {
+      let reconnects = 0;
+      for (;;) {
+        try {
+          const response = await openStream();
+
+          if (!response.ok) {
+            const error = await response
+              .json()
+              .catch(() => ({ message: 'AI chat failed' }));
+            onError(error.message || 'Failed to get AI response');
+            return;
+          }
 
-        onDone();
-      } catch (error) {
-        if ((error as Error).name === 'AbortError') {
+          if (await readStream(response)) {
+            return;
+          }
+          // The stream closed before a done event; resume below
+        } catch (error) {
+          if ((error as Error).name === 'AbortError') {
+            return;
+          }
+          // The connection dropped; resume after the last event we saw
+          if (!streamId || reconnects >= maxReconnects) {
+            onError((error as Error).message || 'AI chat failed');
+            return;
+          }
+        }
+
+        if (!streamId || reconnects >= maxReconnects) {
+          onDone();
           return;
         }
-        onError((error as Error).message || 'AI chat failed');
+        reconnects += 1;
       }
     };
 
@@ -719,6 +1065,17 @@ export const pmChatApi = {
     const response = await makeRequest('/api/pm-chat/ai-agents');
     return handleApiResponse<AvailablePmChatAgentsResponse>(response);
   },
+
+  // Get the models available for an agent
+  getAgentModels: async (
+    projectId: string,
+    agent: PmChatAgent
+  ): Promise<AvailableModelsResponse> => {
+    const response = await makeRequest(
+      `/api/projects/${projectId}/pm-chat/ai-agents/${agent}/models`
+    );
+    return handleApiResponse<AvailableModelsResponse>(response);
+  },
 };
 
 // Task Management APIs
@@ -786,6 +1143,44 @@ export const tasksApi = {
     return handleApiResponse<string[]>(response);
   },
 
+  // Get the direct subtasks of a task
+  getSubtasks: async (taskId: string): Promise<Task[]> => {
+    const response = await makeRequest(`/api/tasks/${taskId}/subtasks`);
+    return handleApiResponse<Task[]>(response);
+  },
+
+  // Move a task under another task, or back to the top level with null
+  setParent: async (
+    taskId: string,
+    parentTaskId: string | null
+  ): Promise<Task> => {
+    const body: SetTaskParentRequest = { parent_task_id: parentTaskId };
+    const response = await makeRequest(`/api/tasks/${taskId}/parent`, {
+      method: 'PUT',
+      body: JSON.stringify(body),
+    });
+    return handleApiResponse<Task>(response);
+  },
+
+  getComments: async (taskId: string): Promise<TaskComment[]> => {
+    const response = await makeRequest(`/api/tasks/${taskId}/comments`);
+    return handleApiResponse<TaskComment[]>(response);
+  },
+
+  addComment: async (taskId: string, content: string): Promise<TaskComment> => {
+    const body: AddTaskCommentRequest = { content, author: 'user' };
+    const response = await makeRequest(`/api/tasks/${taskId}/comments`, {
+      method: 'POST',
+      body: JSON.stringify(body),
+    });
+    return handleApiResponse<TaskComment>(response);
+  },
+
+  getReviews: async (taskId: string): Promise<TaskReview[]> => {
+    const response = await makeRequest(`/api/tasks/${taskId}/reviews`);
+    return handleApiResponse<TaskReview[]>(response);
+  },
+
   // Batch update task positions (for drag-and-drop reordering)
   batchUpdatePositions: async (
     updates: Array<{ task_id: string; position: number }>
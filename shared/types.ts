@@ -13,7 +13,11 @@ pm_task_id: string | null,
 /**
  * PM documentation/specifications in Markdown format, generated from PM chat
  */
-pm_docs: string | null, created_at: Date, updated_at: Date, };
+pm_docs: string | null, 
+/**
+ * Number of days to keep PM chat messages (None = keep forever)
+ */
+chat_retention_days: number | null, created_at: Date, updated_at: Date, };
 
 export type CreateProject = { name: string, repositories: Array<CreateProjectRepo>, };
 
@@ -36,6 +40,22 @@ score: bigint, };
 
 export type SearchMatchType = "FileName" | "DirectoryName" | "FullPath";
 
+export type ProjectWebhook = { project_id: string, url: string, 
+/**
+ * Key used to sign deliveries with HMAC-SHA256
+ */
+secret: string, created_at: Date, updated_at: Date, };
+
+export type WebhookDeadLetter = { id: string, project_id: string, event_type: string, url: string, 
+/**
+ * JSON body that could not be delivered
+ */
+payload: string, 
+/**
+ * Error of the last attempt
+ */
+error: string, attempts: bigint, created_at: Date, };
+
 export type Repo = { id: string, path: string, name: string, display_name: string, setup_script: string | null, cleanup_script: string | null, copy_files: string | null, parallel_setup_script: boolean, dev_server_script: string | null, default_target_branch: string | null, created_at: Date, updated_at: Date, };
 
 export type UpdateRepo = { display_name?: string | null, setup_script?: string | null, cleanup_script?: string | null, copy_files?: string | null, parallel_setup_script?: boolean | null, dev_server_script?: string | null, default_target_branch?: string | null, };
@@ -60,13 +80,43 @@ export type TaskStatus = "todo" | "inprogress" | "inreview" | "done" | "cancelle
 
 export type TaskPriority = "low" | "medium" | "high" | "urgent";
 
-export type Task = { id: string, project_id: string, title: string, description: string | null, status: TaskStatus, priority: TaskPriority, position: number, parent_workspace_id: string | null, created_at: string, updated_at: string, };
+export type Task = { id: string, project_id: string, title: string, description: string | null, status: TaskStatus, priority: TaskPriority, position: number, parent_workspace_id: string | null, 
+/**
+ * Task this is a subtask of
+ */
+parent_task_id: string | null, created_at: string, updated_at: string, };
 
-export type TaskWithAttemptStatus = { has_in_progress_attempt: boolean, last_attempt_failed: boolean, executor: string, id: string, project_id: string, title: string, description: string | null, status: TaskStatus, priority: TaskPriority, position: number, parent_workspace_id: string | null, created_at: string, updated_at: string, };
+export type TaskWithAttemptStatus = { has_in_progress_attempt: boolean, last_attempt_failed: boolean, executor: string, 
+/**
+ * Number of direct subtasks
+ */
+subtask_count: bigint, 
+/**
+ * Number of direct subtasks that are done
+ */
+subtasks_done: bigint, id: string, project_id: string, title: string, description: string | null, status: TaskStatus, priority: TaskPriority, position: number, parent_workspace_id: string | null, 
+/**
+ * Task this is a subtask of
+ */
+parent_task_id: string | null, created_at: string, updated_at: string, };
+
+export type ProgressSummary = { total_tasks: bigint, todo: bigint, in_progress: bigint, in_review: bigint, done: bigint, cancelled: bigint, 
+/**
+ * Tasks that are not done and depend on at least one task that is not done
+ */
+blocked: bigint, 
+/**
+ * Share of tasks that are done (0-100)
+ */
+progress_percent: number, };
 
 export type TaskRelationships = { parent_task: Task | null, current_workspace: Workspace, children: Array<Task>, };
 
-export type CreateTask = { project_id: string, title: string, description: string | null, status: TaskStatus | null, priority: TaskPriority | null, position: number | null, parent_workspace_id: string | null, image_ids: Array<string> | null, label_ids: Array<string> | null, };
+export type CreateTask = { project_id: string, title: string, description: string | null, status: TaskStatus | null, priority: TaskPriority | null, position: number | null, parent_workspace_id: string | null, 
+/**
+ * Create the task as a subtask of this task
+ */
+parent_task_id: string | null, image_ids: Array<string> | null, label_ids: Array<string> | null, };
 
 export type UpdateTask = { title: string | null, description: string | null, status: TaskStatus | null, priority: TaskPriority | null, position: number | null, parent_workspace_id: string | null, image_ids: Array<string> | null, label_ids: Array<string> | null, };
 
@@ -76,35 +126,324 @@ export type CreateLabel = { project_id: string, name: string, color: string | nu
 
 export type UpdateLabel = { name: string | null, color: string | null, executor: string | null, };
 
+export type LabelWithCount = { task_count: bigint, id: string, project_id: string, name: string, color: string, executor: string | null, created_at: string, updated_at: string, };
+
+export type LabelMergeResult = { 
+/**
+ * Tasks that gained the target label
+ */
+reassigned: bigint, 
+/**
+ * Source labels that were deleted
+ */
+deleted: bigint, };
+
 export type TaskLabel = { task_id: string, label_id: string, created_at: string, };
 
 export type TaskDependency = { task_id: string, depends_on_task_id: string, created_at: string, };
 
 export type CreateTaskDependency = { task_id: string, depends_on_task_id: string, };
 
-export type PmConversation = { id: string, project_id: string, role: string, content: string, model: string | null, created_at: Date, updated_at: Date, };
+export type PmConversation = { id: string, project_id: string, role: string, content: string, model: string | null, 
+/**
+ * Pinned messages are kept when old messages are pruned
+ */
+pinned: boolean, 
+/**
+ * Whether the content was edited after the message was sent
+ */
+edited: boolean, 
+/**
+ * Prompt tokens of the assistant turn, when the agent CLI reports them
+ */
+input_tokens: bigint | null, 
+/**
+ * Generated tokens of the assistant turn, when the agent CLI reports them
+ */
+output_tokens: bigint | null, 
+/**
+ * When the message was archived by clearing the chat; archived messages
+ * are hidden from the conversation until restored
+ */
+archived_at: Date | null, created_at: Date, updated_at: Date, };
 
-export type PmAttachment = { id: string, conversation_id: string, project_id: string, file_name: string, file_path: string, mime_type: string, file_size: bigint, sha256: string | null, created_at: Date, };
+export type PmAttachment = { id: string, conversation_id: string, project_id: string, file_name: string, file_path: string, mime_type: string, file_size: bigint, sha256: string | null, 
+/**
+ * Pixel width of image attachments
+ */
+width: bigint | null, 
+/**
+ * Pixel height of image attachments
+ */
+height: bigint | null, created_at: Date, };
 
 export type PmMessageRole = "user" | "assistant" | "system";
 
-export type CreatePmConversation = { project_id: string, role: PmMessageRole, content: string, model: string | null, };
+export type CreatePmConversation = { project_id: string, role: PmMessageRole, content: string, model: string | null, input_tokens: bigint | null, output_tokens: bigint | null, };
+
+export type CreatePmAttachment = { conversation_id: string, project_id: string, file_name: string, file_path: string, mime_type: string, file_size: bigint, sha256: string | null, width: bigint | null, height: bigint | null, };
+
+export type PmModelUsage = { model: string | null, 
+/**
+ * Number of assistant messages with reported usage
+ */
+messages: bigint, input_tokens: bigint, output_tokens: bigint, };
+
+export type PmRoleCount = { role: string, count: bigint, };
+
+export type PmDocsSource = "user" | "agent" | "task_summary" | "restore";
+
+export type PmDocsVersion = { id: string, project_id: string, content: string, 
+/**
+ * What replaced this version
+ */
+source: PmDocsSource, 
+/**
+ * When this version was replaced
+ */
+created_at: Date, };
+
+export type PmMessageTask = { task_id: string, message_id: string, created_at: Date, };
+
+export type TaskCommentAuthor = "user" | "agent";
+
+export type TaskComment = { id: string, task_id: string, author: TaskCommentAuthor, content: string, created_at: Date, };
+
+export type TaskReviewSource = "user" | "agent";
+
+export type TaskReview = { id: string, task_id: string, prompt: string, checklist: Array<string>, source: TaskReviewSource, created_at: Date, };
 
-export type CreatePmAttachment = { conversation_id: string, project_id: string, file_name: string, file_path: string, mime_type: string, file_size: bigint, sha256: string | null, };
+export type SendMessageRequest = { content: string, role: string | null, 
+/**
+ * Client-generated id of the message; sending again with the same id
+ * returns the message stored the first time instead of a duplicate
+ */
+client_message_id: string | null, };
 
-export type SendMessageRequest = { content: string, role: string | null, };
+export type PmChatMessage = { id: string, project_id: string, role: string, content: string, model: string | null, 
+/**
+ * Pinned messages are kept when old messages are pruned
+ */
+pinned: boolean, 
+/**
+ * Whether the content was edited after the message was sent
+ */
+edited: boolean, 
+/**
+ * Prompt tokens of the assistant turn, when the agent CLI reports them
+ */
+input_tokens: bigint | null, 
+/**
+ * Generated tokens of the assistant turn, when the agent CLI reports them
+ */
+output_tokens: bigint | null, created_at: Date, updated_at: Date, attachments: Array<PmAttachment>, };
 
-export type PmChatResponse = { messages: Array<PmConversation>, pm_docs: string | null, };
+export type PmChatResponse = { messages: Array<PmChatMessage>, 
+/**
+ * Attachments of the returned messages, also nested under each message
+ */
+attachments: Array<PmAttachment>, pm_docs: string | null, 
+/**
+ * Cursor for the next (older) page when paginating with `limit`
+ */
+next_cursor: string | null, };
 
-export type UpdatePmDocsRequest = { pm_docs: string | null, };
+export type UpdatePmDocsRequest = { pm_docs: string | null, 
+/**
+ * Who is making the change, recorded in the docs history (defaults to `user`)
+ */
+source: PmDocsSource | null, 
+/**
+ * Include a unified diff of the change in the response
+ */
+return_diff: boolean | null, 
+/**
+ * `updated_at` of the project the edit is based on. If the project has
+ * been updated since, nothing is saved and `409 Conflict` is returned.
+ */
+expected_updated_at: Date | null, };
 
-export type PmChatAgent = "CLAUDE_CLI" | "CODEX_CLI" | "GEMINI_CLI" | "OPENCODE_CLI";
+export type PmDocsConflict = { 
+/**
+ * Current PM docs, for the client to merge its edit into
+ */
+pm_docs: string | null, updated_at: Date, };
 
-export type AiChatRequest = { content: string, model: string | null, agent: PmChatAgent | null, };
+export type UpdatePmDocsResponse = { id: string, name: string, default_agent_working_dir: string | null, remote_project_id: string | null, 
+/**
+ * The PM task for this project - contains project specs and serves as PM AI context
+ * @deprecated Use pm_docs instead - kept for backward compatibility
+ */
+pm_task_id: string | null, 
+/**
+ * PM documentation/specifications in Markdown format, generated from PM chat
+ */
+pm_docs: string | null, 
+/**
+ * Number of days to keep PM chat messages (None = keep forever)
+ */
+chat_retention_days: number | null, created_at: Date, updated_at: Date, 
+/**
+ * Unified diff from the previous docs; `None` if not requested or unchanged
+ */
+diff: string | null, };
+
+export type RenderedPmDocs = { html: string, 
+/**
+ * The rendered section, if only one was requested
+ */
+section: string | null, };
+
+export type PmChatAgent = "CLAUDE_CLI" | "CODEX_CLI" | "GEMINI_CLI" | "OPENCODE_CLI" | "OLLAMA_CLI";
+
+export type AiChatRequest = { content: string, model: string | null, agent: PmChatAgent | null, backend_url: string | null, 
+/**
+ * Fail instead of chatting without tools when the agent cannot call MCP tools
+ */
+require_tools: boolean | null, };
+
+export type RegenerateAiChatRequest = { 
+/**
+ * Model for the new reply; defaults to the model of the replaced reply
+ */
+model: string | null, agent: PmChatAgent | null, backend_url: string | null, };
 
 export type AvailablePmChatAgentsResponse = { agents: Array<PmChatAgentInfo>, };
 
-export type PmChatAgentInfo = { agent: PmChatAgent, display_name: string, available: boolean, supports_streaming: boolean, };
+export type PmChatAgentInfo = { agent: PmChatAgent, display_name: string, available: boolean, supports_streaming: boolean, 
+/**
+ * Version reported by `<cli> --version`, if the CLI runs
+ */
+version: string | null, 
+/**
+ * False for agents that chat without the vibe_kanban MCP tools
+ */
+supports_tools: boolean, mcp_config_mode: McpConfigMode, 
+/**
+ * Whether the tools are actually wired up; false for a pre-configured
+ * agent whose MCP config lacks the vibe_kanban server
+ */
+tools_configured: boolean, };
+
+export type McpConfigMode = "PER_RUN" | "PRECONFIGURED" | "UNSUPPORTED";
+
+export type ModelInfo = { 
+/**
+ * Value to send as `AiChatRequest.model`
+ */
+id: string, display_name: string, };
+
+export type AvailableModelsResponse = { agent: PmChatAgent, models: Array<ModelInfo>, };
+
+export type UpdateMessageRequest = { content: string, };
+
+export type SetMessagePinnedRequest = { pinned: boolean, };
+
+export type MessageToTaskRequest = { 
+/**
+ * Title of the task; derived from the first line of the message if omitted
+ */
+title: string | null, 
+/**
+ * Priority of the task; inferred from the title if omitted
+ */
+priority: TaskPriority | null, };
+
+export type UpdateChatRetentionRequest = { 
+/**
+ * Number of days to keep messages; `null` keeps them forever
+ */
+chat_retention_days: number | null, };
+
+export type PruneChatResponse = { removed: bigint, retention_days: number, };
+
+export type RestoreChatResponse = { restored: bigint, };
+
+export type PurgeChatResponse = { removed: bigint, };
+
+export type TextRange = { start: number, end: number, };
+
+export type PmChatSearchResult = { message_id: string, role: string, created_at: Date, 
+/**
+ * Excerpt of the message around the first match
+ */
+snippet: string, 
+/**
+ * Matches within `snippet`
+ */
+highlights: Array<TextRange>, 
+/**
+ * Number of matches in the whole message
+ */
+match_count: number, };
+
+export type PmChatUsageResponse = { 
+/**
+ * Usage per model, counting assistant messages that reported it
+ */
+models: Array<PmModelUsage>, input_tokens: bigint, output_tokens: bigint, };
+
+export type PmChatStats = { message_count: bigint, messages_by_role: Array<PmRoleCount>, attachment_count: bigint, 
+/**
+ * Total size of all attachments
+ */
+attachment_bytes: bigint, 
+/**
+ * `None` while the chat has no messages
+ */
+first_message_at: Date | null, last_message_at: Date | null, 
+/**
+ * Models that replied in the chat, sorted by name
+ */
+models: Array<string>, };
+
+export type PmChatContextPreview = { system_prompt: string, 
+/**
+ * Length of `system_prompt` in characters
+ */
+characters: number, 
+/**
+ * Rough token count of `system_prompt`, see [`estimate_tokens`]
+ */
+estimated_tokens: number, };
+
+export type AgentSelftestReport = { agent: PmChatAgent, 
+/**
+ * Whether the CLI process was started
+ */
+spawned: boolean, 
+/**
+ * Whether the CLI produced any assistant output
+ */
+streamed_output: boolean, 
+/**
+ * Whether the agent invoked the `list_projects` MCP tool
+ */
+tool_call_observed: boolean, 
+/**
+ * Whether the `list_projects` tool call returned successfully
+ */
+tool_call_succeeded: boolean, exit_code: number | null, exit_success: boolean, timed_out: boolean, duration_ms: bigint, 
+/**
+ * Overall result: spawned, exited cleanly and the tool call succeeded
+ */
+passed: boolean, error: string | null, 
+/**
+ * Last lines written to stderr, useful when the CLI fails to start MCP
+ */
+stderr_tail: Array<string>, };
+
+export type CancelAiChatRequest = { stream_id: string, };
+
+export type CancelAiChatResponse = { 
+/**
+ * False if the stream had already finished or does not exist
+ */
+cancelled: boolean, };
+
+export type AiChatSession = { stream_id: string, project_id: string, agent: PmChatAgent, model: string, started_at: string, };
+
+export type TaskSummaryLocale = "en" | "ja";
 
 export type DraftFollowUpData = { message: string, executor_profile_id: ExecutorProfileId, };
 
@@ -178,7 +517,11 @@ additions: number | null, deletions: number | null, repoId: string | null, };
 
 export type DiffChangeKind = "added" | "deleted" | "modified" | "renamed" | "copied" | "permissionChange";
 
-export type ApiResponse<T, E = T> = { success: boolean, data: T | null, error_data: E | null, message: string | null, };
+export type ApiResponse<T, E = T> = { success: boolean, data: T | null, error_data: E | null, message: string | null, 
+/**
+ * Stable machine-readable error code, e.g. `NotFoundError`
+ */
+code: string | null, };
 
 export type LoginStatus = { "status": "loggedout" } | { "status": "loggedin", profile: ProfileResponse, };
 
@@ -236,6 +579,8 @@ export type ListProjectsResponse = { projects: Array<RemoteProject>, };
 
 export type RemoteProjectMembersResponse = { organization_id: string, members: Array<OrganizationMemberWithProfile>, };
 
+export type MergeLabelsRequest = { source_ids: Array<string>, target_id: string, };
+
 export type CreateRemoteProjectRequest = { organization_id: string, name: string, };
 
 export type LinkToExistingRequest = { remote_project_id: string, };
@@ -294,6 +639,104 @@ export type OpenEditorResponse = { url: string | null, };
 
 export type CreateAndStartTaskRequest = { task: CreateTask, executor_profile_id: ExecutorProfileId, repos: Array<WorkspaceRepoInput>, };
 
+export type QuickTaskRequest = { title: string, };
+
+export type QuickTaskResponse = { task: Task, labels: Array<Label>, 
+/**
+ * Whether priority was inferred from the title (otherwise the default was used)
+ */
+auto_set_priority: boolean, 
+/**
+ * Whether any labels were inferred from the title
+ */
+auto_set_labels: boolean, };
+
+export type LabelSuggestion = { label_id: string, name: string, 
+/**
+ * Higher is a stronger match: the label name counts 2, each group keyword 1
+ */
+score: number, 
+/**
+ * Keywords found in the text, the label name first when it matched
+ */
+matched_keywords: Array<string>, };
+
+export type ExecutorSuggestion = { executor: BaseCodingAgent, 
+/**
+ * Labels of the task that specify `executor`, by name
+ */
+label_ids: Array<string>, label_names: Array<string>, 
+/**
+ * Other executors specified by labels of the task, best first
+ */
+alternatives: Array<BaseCodingAgent>, };
+
+export type AttachmentGcReport = { pm_files_removed: number, task_files_removed: number, 
+/**
+ * Attachment rows whose file no longer exists on disk
+ */
+missing_files: number, };
+
+export type WebhookEventType = "task.created" | "task.status_changed";
+
+export type WebhookEvent = { event: WebhookEventType, project_id: string, task_id: string, task_title: string, status: TaskStatus, 
+/**
+ * Status before the change, for `task.status_changed`
+ */
+previous_status: TaskStatus | null, timestamp: Date, };
+
+export type BoardEvent = { "type": "task_created", task_id: string, } | { "type": "task_updated", task_id: string, } | { "type": "label_changed", label_id: string, };
+
+export type SetProjectWebhookRequest = { 
+/**
+ * http(s) URL that receives the signed event POSTs
+ */
+url: string, 
+/**
+ * Replace the signing secret; a new webhook always gets a fresh one
+ */
+regenerate_secret: boolean, };
+
+export type SearchTasksResponse = { tasks: Array<Task>, 
+/**
+ * Number of matching tasks before `limit` was applied
+ */
+total: number, };
+
+export type OrderedTaskSummary = { id: string, title: string, status: TaskStatus, priority: TaskPriority, 
+/**
+ * Tasks that must be completed before this one
+ */
+depends_on: Array<string>, };
+
+export type CriticalPathResponse = { 
+/**
+ * Not-done tasks on the longest dependency chain, first task first
+ */
+tasks: Array<OrderedTaskSummary>, 
+/**
+ * Number of tasks on the chain
+ */
+length: number, };
+
+export type SetTaskParentRequest = { 
+/**
+ * New parent task; `None` makes the task a top-level task
+ */
+parent_task_id: string | null, };
+
+export type AddTaskCommentRequest = { content: string, 
+/**
+ * Defaults to `user`
+ */
+author: TaskCommentAuthor, };
+
+export type CreateTaskReviewRequest = { prompt: string, checklist: Array<string>, 
+/**
+ * Defaults to `user`
+ */
+source: TaskReviewSource, };
+
 export type TaskPositionUpdate = { task_id: string, position: number, };
 
 export type BatchUpdatePositionsRequest = { updates: Array<TaskPositionUpdate>, };
@@ -414,7 +857,11 @@ export type DirectoryListResponse = { entries: Array<DirectoryEntry>, current_pa
 
 export type SearchMode = "taskform" | "settings";
 
-export type Config = { config_version: string, theme: ThemeMode, executor_profile: ExecutorProfileId, disclaimer_acknowledged: boolean, onboarding_acknowledged: boolean, notifications: NotificationConfig, editor: EditorConfig, github: GitHubConfig, analytics_enabled: boolean, workspace_dir: string | null, last_app_version: string | null, show_release_notes: boolean, language: UiLanguage, git_branch_prefix: string, showcases: ShowcaseState, pr_auto_description_enabled: boolean, pr_auto_description_prompt: string | null, beta_workspaces: boolean, beta_workspaces_invitation_sent: boolean, commit_reminder: boolean, send_message_shortcut: SendMessageShortcut, };
+export type Config = { config_version: string, theme: ThemeMode, executor_profile: ExecutorProfileId, disclaimer_acknowledged: boolean, onboarding_acknowledged: boolean, notifications: NotificationConfig, editor: EditorConfig, github: GitHubConfig, analytics_enabled: boolean, workspace_dir: string | null, last_app_version: string | null, show_release_notes: boolean, language: UiLanguage, git_branch_prefix: string, showcases: ShowcaseState, pr_auto_description_enabled: boolean, pr_auto_description_prompt: string | null, beta_workspaces: boolean, beta_workspaces_invitation_sent: boolean, commit_reminder: boolean, send_message_shortcut: SendMessageShortcut, pm_attachment_policy: PmAttachmentPolicy, pm_chat: PmChatSettings, 
+/**
+ * Globs, relative to a repo's `docs/` folder, of docs left out of agent prompts
+ */
+docs_scan_ignore: Array<string>, };
 
 export type NotificationConfig = { sound_enabled: boolean, push_enabled: boolean, sound_file: SoundFile, };
 
@@ -436,6 +883,44 @@ export type ShowcaseState = { seen_features: Array<string>, };
 
 export type SendMessageShortcut = "ModifierEnter" | "Enter";
 
+export type PmAttachmentPolicy = { 
+/**
+ * Largest accepted upload in bytes
+ */
+max_size_bytes: bigint, 
+/**
+ * Accepted MIME type prefixes such as `image/`; empty accepts every type
+ */
+allowed_mime_prefixes: Array<string>, 
+/**
+ * Size limits that replace `max_size_bytes` for matching MIME type
+ * prefixes; the longest matching prefix wins
+ */
+max_size_bytes_by_mime_prefix: { [key in string]?: bigint }, 
+/**
+ * Largest total size in bytes of the files sent in one upload
+ */
+max_upload_bytes: bigint, 
+/**
+ * Extensions (without the dot) that are always rejected
+ */
+blocked_extensions: Array<string>, 
+/**
+ * Reject files starting with a `#!` shebang line whatever their
+ * extension, e.g. scripts renamed to `.txt`
+ */
+reject_shebang_scripts: boolean, };
+
+export type PmChatSettings = { 
+/**
+ * Most AI replies streamed at once for one project
+ */
+max_streams_per_project: number, 
+/**
+ * Seconds an AI reply may run before the CLI is stopped
+ */
+cli_timeout_secs: number, };
+
 export type GitBranch = { name: string, is_current: boolean, is_remote: boolean, last_commit_date: Date, };
 
 export type QueuedMessage = { 